@@ -1,39 +1,8 @@
 CREATE DATABASE test;
 \c test;
 
--- a single table is used for all events in the cqrs system
-CREATE TABLE events
-(
-    aggregate_type text                         NOT NULL,
-    aggregate_id   text                         NOT NULL,
-    sequence       bigint CHECK (sequence >= 0) NOT NULL,
-    payload        jsonb                        NOT NULL,
-    metadata       jsonb                        NOT NULL,
-    timestamp      timestamp with time zone DEFAULT (CURRENT_TIMESTAMP),
-    PRIMARY KEY (aggregate_type, aggregate_id, sequence)
-);
-
--- this table is only needed if snapshotting is employed
-CREATE TABLE snapshots
-(
-    aggregate_type text                                        NOT NULL,
-    aggregate_id   text                                        NOT NULL,
-    version        bigint                 CHECK (version >= 0) NOT NULL,
-    payload        jsonb                                       NOT NULL,
-    timestamp      timestamp with time zone DEFAULT (CURRENT_TIMESTAMP),
-    PRIMARY KEY (aggregate_type, aggregate_id)
-);
-
--- a single table is used for all queries in the cqrs system
-CREATE TABLE queries
-(
-    aggregate_type text                        NOT NULL,
-    aggregate_id   text                        NOT NULL,
-    query_type     text                        NOT NULL,
-    version        bigint CHECK (version >= 0) NOT NULL,
-    payload        jsonb                       NOT NULL,
-    PRIMARY KEY (aggregate_type, aggregate_id, query_type)
-);
+-- the tables are created and upgraded by the stores, see the
+-- migrations of the postgres store
 
 CREATE
     USER
@@ -45,9 +14,7 @@ ENCRYPTED PASSWORD
 
 GRANT
     ALL PRIVILEGES
-ON TABLE
-    events,
-    snapshots,
-    queries
+ON SCHEMA
+    public
 TO
     test_user;
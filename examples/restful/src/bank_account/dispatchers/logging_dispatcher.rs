@@ -24,7 +24,7 @@ impl IEventDispatcher<BankAccountCommand, BankAccountEvent>
     for LoggingDispatcher
 {
     fn dispatch(
-        &mut self,
+        &self,
         aggregate_id: &str,
         events: &Vec<
             EventContext<BankAccountCommand, BankAccountEvent>,
@@ -1,8 +1,5 @@
 use log::debug;
-use std::{
-    collections::HashMap,
-    io::Read,
-};
+use std::io::Read;
 
 use iron::{
     status,
@@ -14,12 +11,13 @@ use router::Router;
 
 use cqrs_es2::Error;
 
-use super::super::stores::get_event_store;
+use super::super::stores::ThisRepository;
 
 use super::common::std_headers;
 
 pub fn bank_account_command(
-    req: &mut Request
+    req: &mut Request,
+    repository: &ThisRepository,
 ) -> IronResult<Response> {
     debug!("Received command '{:?}'", &req);
 
@@ -41,16 +39,36 @@ pub fn bank_account_command(
 
     let result = match command_type {
         "openBankAccount" => {
-            process_command("OpenBankAccount", aggregate_id, payload)
+            process_command(
+                repository,
+                "OpenBankAccount",
+                aggregate_id,
+                payload,
+            )
         },
         "depositMoney" => {
-            process_command("DepositMoney", aggregate_id, payload)
+            process_command(
+                repository,
+                "DepositMoney",
+                aggregate_id,
+                payload,
+            )
         },
         "withdrawMoney" => {
-            process_command("WithdrawMoney", aggregate_id, payload)
+            process_command(
+                repository,
+                "WithdrawMoney",
+                aggregate_id,
+                payload,
+            )
         },
         "writeCheck" => {
-            process_command("WriteCheck", aggregate_id, payload)
+            process_command(
+                repository,
+                "WriteCheck",
+                aggregate_id,
+                payload,
+            )
         },
         _ => return Ok(Response::with(status::NotFound)),
     };
@@ -72,6 +90,7 @@ pub fn bank_account_command(
 }
 
 fn process_command(
+    repository: &ThisRepository,
     payload_type: &str,
     aggregate_id: &str,
     payload: String,
@@ -85,13 +104,6 @@ fn process_command(
         },
     };
 
-    let mut event_store = get_event_store().unwrap();
-
-    let mut metadata = HashMap::new();
-    metadata.insert(
-        "time".to_string(),
-        chrono::Utc::now().to_rfc3339(),
-    );
-
-    event_store.execute_with_metadata(aggregate_id, payload, metadata)
+    // the stores record the commit time of the events
+    repository.execute(aggregate_id, payload)
 }
@@ -10,11 +10,14 @@ use router::Router;
 
 use cqrs_es2_store::IQueryStore;
 
-use super::super::stores::get_query_store;
+use super::super::stores::ThisQueryStore;
 
 use super::common::std_headers;
 
-pub fn bank_account_query(req: &mut Request) -> IronResult<Response> {
+pub fn bank_account_query(
+    req: &mut Request,
+    query_store: &ThisQueryStore,
+) -> IronResult<Response> {
     debug!("Received query '{:?}'", &req);
 
     let query_id = req
@@ -25,8 +28,6 @@ pub fn bank_account_query(req: &mut Request) -> IronResult<Response> {
         .unwrap_or("")
         .to_string();
 
-    let mut query_store = get_query_store().unwrap();
-
     let query = match query_store.load_query(&query_id) {
         Err(_e) => {
             return Ok(Response::with(status::BadRequest));
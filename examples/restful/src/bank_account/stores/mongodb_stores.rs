@@ -1,4 +1,5 @@
 use cqrs_es2::Error;
+use mongodb::sync::Client;
 
 use cqrs_es2_store::{
     mongodb_store::{
@@ -8,8 +9,6 @@ use cqrs_es2_store::{
     Repository,
 };
 
-use crate::cqrs::db_connection;
-
 use super::super::{
     aggregate::BankAccount,
     commands::BankAccountCommand,
@@ -18,36 +17,38 @@ use super::super::{
     queries::BankAccountQuery,
 };
 
-type ThisEventStore =
+pub type ThisEventStore =
     EventStore<BankAccountCommand, BankAccountEvent, BankAccount>;
 
-type ThisQueryStore = QueryStore<
+pub type ThisQueryStore = QueryStore<
     BankAccountCommand,
     BankAccountEvent,
     BankAccount,
     BankAccountQuery,
 >;
 
-type ThisRepository = Repository<
+pub type ThisRepository = Repository<
     BankAccountCommand,
     BankAccountEvent,
     BankAccount,
     ThisEventStore,
 >;
 
-pub fn get_event_store() -> Result<ThisRepository, Error> {
+pub fn get_event_store(
+    client: &Client
+) -> Result<ThisRepository, Error> {
     Ok(ThisRepository::new(
-        ThisEventStore::new(db_connection().unwrap()),
+        ThisEventStore::new(client, "test"),
         vec![
-            Box::new(get_query_store().unwrap()),
+            Box::new(get_query_store(client)?),
             Box::new(LoggingDispatcher::new()),
         ],
         true,
     ))
 }
 
-pub fn get_query_store() -> Result<ThisQueryStore, Error> {
-    Ok(ThisQueryStore::new(
-        db_connection().unwrap(),
-    ))
+pub fn get_query_store(
+    client: &Client
+) -> Result<ThisQueryStore, Error> {
+    Ok(ThisQueryStore::new(client.database("test")))
 }
@@ -3,13 +3,12 @@ use cqrs_es2::Error;
 use cqrs_es2_store::{
     redis_store::{
         EventStore,
+        RedisPool,
         QueryStore,
     },
     Repository,
 };
 
-use crate::cqrs::db_connection;
-
 use super::super::{
     aggregate::BankAccount,
     commands::BankAccountCommand,
@@ -18,36 +17,38 @@ use super::super::{
     queries::BankAccountQuery,
 };
 
-type ThisEventStore =
+pub type ThisEventStore =
     EventStore<BankAccountCommand, BankAccountEvent, BankAccount>;
 
-type ThisQueryStore = QueryStore<
+pub type ThisQueryStore = QueryStore<
     BankAccountCommand,
     BankAccountEvent,
     BankAccount,
     BankAccountQuery,
 >;
 
-type ThisRepository = Repository<
+pub type ThisRepository = Repository<
     BankAccountCommand,
     BankAccountEvent,
     BankAccount,
     ThisEventStore,
 >;
 
-pub fn get_event_store() -> Result<ThisRepository, Error> {
+pub fn get_event_store(
+    pool: &RedisPool
+) -> Result<ThisRepository, Error> {
     Ok(ThisRepository::new(
-        ThisEventStore::new(db_connection().unwrap()),
+        ThisEventStore::new(pool.clone()),
         vec![
-            Box::new(get_query_store().unwrap()),
+            Box::new(get_query_store(pool)?),
             Box::new(LoggingDispatcher::new()),
         ],
         true,
     ))
 }
 
-pub fn get_query_store() -> Result<ThisQueryStore, Error> {
-    Ok(ThisQueryStore::new(
-        db_connection().unwrap(),
-    ))
+pub fn get_query_store(
+    pool: &RedisPool
+) -> Result<ThisQueryStore, Error> {
+    Ok(ThisQueryStore::new(pool.clone()))
 }
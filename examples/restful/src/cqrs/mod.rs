@@ -1,20 +1,20 @@
 #[cfg(feature = "with-postgres")]
-pub use postgres_db::db_connection;
+pub use postgres_db::db_pool;
 
 #[cfg(feature = "with-mysql")]
-pub use mysql_db::db_connection;
+pub use mysql_db::db_pool;
 
 #[cfg(feature = "with-mariadb")]
-pub use mariadb_db::db_connection;
+pub use mariadb_db::db_pool;
 
 #[cfg(feature = "with-sqlite")]
-pub use sqlite_db::db_connection;
+pub use sqlite_db::db_pool;
 
 #[cfg(feature = "with-mongodb")]
-pub use mongodb_db::db_connection;
+pub use mongodb_db::db_pool;
 
 #[cfg(feature = "with-redis")]
-pub use redis_db::db_connection;
+pub use redis_db::db_pool;
 
 #[cfg(feature = "with-postgres")]
 mod postgres_db;
@@ -1,13 +1,12 @@
-use redis::{
-    Client,
-    Connection,
-    RedisError,
+use cqrs_es2_store::redis_store::RedisPool;
+use r2d2::{
+    Error,
+    Pool,
 };
+use redis::Client;
 
-pub fn db_connection() -> Result<Connection, RedisError> {
+pub fn db_pool() -> Result<RedisPool, Error> {
     let client = Client::open("redis://localhost:9086/").unwrap();
 
-    let conn = client.get_connection().unwrap();
-
-    Ok(conn)
+    Pool::new(client)
 }
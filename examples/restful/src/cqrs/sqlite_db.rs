@@ -1,7 +1,10 @@
-use rusqlite::Connection;
+use cqrs_es2_store::sqlite_store::SqlitePool;
+use r2d2::{
+    Error,
+    Pool,
+};
+use r2d2_sqlite::SqliteConnectionManager;
 
-pub fn db_connection() -> Result<Connection, rusqlite::Error> {
-    let conn = Connection::open("test.db").unwrap();
-
-    Ok(conn)
+pub fn db_pool() -> Result<SqlitePool, Error> {
+    Pool::new(SqliteConnectionManager::file("test.db"))
 }
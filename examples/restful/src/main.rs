@@ -1,7 +1,12 @@
 #![forbid(unsafe_code)]
 #![deny(clippy::all)]
 
-use iron::Iron;
+use std::sync::Arc;
+
+use iron::{
+    Iron,
+    Request,
+};
 use router::Router;
 
 use log::info;
@@ -12,6 +17,8 @@ mod cqrs;
 use bank_account::{
     bank_account_command,
     bank_account_query,
+    get_event_store,
+    get_query_store,
 };
 
 fn setup_logger() -> Result<(), fern::InitError> {
@@ -35,16 +42,27 @@ fn setup_logger() -> Result<(), fern::InitError> {
 fn main() {
     setup_logger().unwrap();
 
+    // the stores share a single pool, the requests are served from
+    // several threads
+    let pool = cqrs::db_pool().unwrap();
+
+    let repository = Arc::new(get_event_store(&pool).unwrap());
+    let query_store = Arc::new(get_query_store(&pool).unwrap());
+
     let mut router = Router::new();
     router.get(
         "/account/:query_id",
-        bank_account_query,
+        move |req: &mut Request| {
+            bank_account_query(req, &query_store)
+        },
         "account_query",
     );
 
     router.post(
         "/account/:command_type/:aggregate_id",
-        bank_account_command,
+        move |req: &mut Request| {
+            bank_account_command(req, &repository)
+        },
         "account_command",
     );
 
@@ -0,0 +1,158 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use cqrs_es2::{
+    Error,
+    EventContext,
+    IAggregate,
+    ICommand,
+    ICommandHandler,
+    IEvent,
+    IEventConsumer,
+    IEventHandler,
+    IQuery,
+};
+
+/// The commands of the `Account` aggregate
+#[derive(Debug, PartialEq, Clone)]
+pub enum AccountCommand {
+    /// Opens the account of an owner
+    OpenAccount {
+        /// The owner of the account
+        owner: String,
+    },
+    /// Deposits a positive amount
+    Deposit {
+        /// The amount deposited
+        amount: i64,
+    },
+}
+
+impl ICommand for AccountCommand {}
+
+/// The events of the `Account` aggregate
+#[derive(
+    Debug,
+    PartialEq,
+    Clone,
+    Serialize,
+    Deserialize
+)]
+pub enum AccountEvent {
+    /// The account was opened
+    AccountOpened {
+        /// The owner of the account
+        owner: String,
+    },
+    /// An amount was deposited
+    MoneyDeposited {
+        /// The amount deposited
+        amount: i64,
+    },
+}
+
+impl IEvent for AccountEvent {}
+
+/// A second aggregate type, checking that the streams of different
+/// aggregate types sharing an id are kept apart
+#[derive(
+    Debug,
+    PartialEq,
+    Default,
+    Clone,
+    Serialize,
+    Deserialize
+)]
+pub struct Account {
+    /// The owner of the account
+    pub owner: String,
+    /// The sum of the deposits
+    pub balance: i64,
+}
+
+impl IAggregate<AccountCommand, AccountEvent> for Account {
+    fn aggregate_type() -> &'static str {
+        "conformance_account"
+    }
+}
+
+impl ICommandHandler<AccountCommand, AccountEvent> for Account {
+    fn handle(
+        &self,
+        command: AccountCommand,
+    ) -> Result<Vec<AccountEvent>, Error> {
+        match command {
+            AccountCommand::OpenAccount { owner } => {
+                if !self.owner.is_empty() {
+                    return Err(Error::new(
+                        "the account is already open",
+                    ));
+                }
+
+                Ok(vec![AccountEvent::AccountOpened { owner }])
+            },
+            AccountCommand::Deposit { amount } => {
+                if amount <= 0 {
+                    return Err(Error::new(
+                        "the amount must be positive",
+                    ));
+                }
+
+                Ok(vec![AccountEvent::MoneyDeposited { amount }])
+            },
+        }
+    }
+}
+
+impl IEventHandler<AccountEvent> for Account {
+    fn apply(
+        &mut self,
+        event: &AccountEvent,
+    ) {
+        match event {
+            AccountEvent::AccountOpened { owner } => {
+                self.owner = owner.clone();
+            },
+            AccountEvent::MoneyDeposited { amount } => {
+                self.balance += amount;
+            },
+        }
+    }
+}
+
+/// The query of the `Account` aggregate
+#[derive(
+    Debug,
+    PartialEq,
+    Default,
+    Clone,
+    Serialize,
+    Deserialize
+)]
+pub struct AccountBalanceQuery {
+    /// The sum of the deposits
+    pub balance: i64,
+}
+
+impl IQuery<AccountCommand, AccountEvent> for AccountBalanceQuery {
+    fn query_type() -> &'static str {
+        "conformance_account_balance_query"
+    }
+}
+
+impl IEventConsumer<AccountCommand, AccountEvent>
+    for AccountBalanceQuery
+{
+    fn update(
+        &mut self,
+        event: &EventContext<AccountCommand, AccountEvent>,
+    ) {
+        if let AccountEvent::MoneyDeposited { amount } =
+            &event.payload
+        {
+            self.balance += amount;
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use cqrs_es2::{
+    example_impl::{
+        CustomerCommand,
+        CustomerEvent,
+    },
+    EventContext,
+    IEventConsumer,
+    IQuery,
+};
+
+/// A second query type of the `Customer` aggregate, only used by
+/// the suites so that they may delete all of its queries
+#[derive(
+    Debug,
+    PartialEq,
+    Default,
+    Clone,
+    Serialize,
+    Deserialize
+)]
+pub struct CustomerNameQuery {
+    /// The latest name of the customer
+    pub name: String,
+}
+
+impl IQuery<CustomerCommand, CustomerEvent> for CustomerNameQuery {
+    fn query_type() -> &'static str {
+        "conformance_customer_name_query"
+    }
+}
+
+impl IEventConsumer<CustomerCommand, CustomerEvent>
+    for CustomerNameQuery
+{
+    fn update(
+        &mut self,
+        event: &EventContext<CustomerCommand, CustomerEvent>,
+    ) {
+        if let CustomerEvent::NameAdded(payload) = &event.payload {
+            self.name = payload.changed_name.clone();
+        }
+    }
+}
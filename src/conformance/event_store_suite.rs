@@ -0,0 +1,706 @@
+use std::{
+    collections::HashMap,
+    time::{
+        Duration,
+        SystemTime,
+    },
+};
+
+use cqrs_es2::{
+    example_impl::{
+        Customer,
+        CustomerCommand,
+        CustomerEvent,
+        EmailUpdated,
+        NameAdded,
+    },
+    AggregateContext,
+    EventContext,
+    IEventHandler,
+};
+
+use crate::repository::IEventStore;
+
+use super::{
+    accounts::{
+        Account,
+        AccountCommand,
+        AccountEvent,
+    },
+    fixtures::{
+        large_text,
+        unique_id,
+    },
+    i_store_factory::IStoreFactory,
+};
+
+type Customers =
+    Box<dyn IEventStore<CustomerCommand, CustomerEvent, Customer>>;
+
+type Accounts =
+    Box<dyn IEventStore<AccountCommand, AccountEvent, Account>>;
+
+fn customers(factory: &mut impl IStoreFactory) -> Customers {
+    factory.event_store::<CustomerCommand, CustomerEvent, Customer>()
+}
+
+fn accounts(factory: &mut impl IStoreFactory) -> Accounts {
+    factory.event_store::<AccountCommand, AccountEvent, Account>()
+}
+
+fn get_metadata() -> HashMap<String, String> {
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "time".to_string(),
+        "2021-03-18T12:32:45.930Z".to_string(),
+    );
+    metadata
+}
+
+fn customer_events(
+    aggregate_id: &str,
+    from_sequence: i64,
+    to_sequence: i64,
+) -> Vec<EventContext<CustomerCommand, CustomerEvent>> {
+    (from_sequence..=to_sequence)
+        .map(|x| {
+            EventContext::new(
+                aggregate_id.to_string(),
+                x,
+                CustomerEvent::EmailUpdated(EmailUpdated {
+                    new_email: format!("email_{}@example.com", x),
+                }),
+                get_metadata(),
+            )
+        })
+        .collect()
+}
+
+/// Run all checks of the event store suite
+pub fn check_event_store(factory: &mut impl IStoreFactory) {
+    check_empty_streams(factory);
+    check_event_ordering(factory);
+    check_read_all(factory);
+    check_timestamps(factory);
+    check_stream_metadata(factory);
+    check_event_conflicts(factory);
+    check_snapshots(factory);
+    check_delete_stream(factory);
+    check_deleted_positions(factory);
+    check_unicode_aggregate_ids(factory);
+    check_large_events(factory);
+    check_aggregate_types(factory);
+}
+
+/// Check that the streams without events load empty
+pub fn check_empty_streams(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id = unique_id("empty");
+
+    assert!(
+        store.load_events(&id).unwrap().is_empty(),
+        "events loaded for an empty stream"
+    );
+    assert!(store
+        .load_events_range(&id, 2, Some(5))
+        .unwrap()
+        .is_empty());
+    assert!(!store.stream_exists(&id).unwrap());
+    assert_eq!(store.stream_metadata(&id).unwrap(), None);
+    assert!(!store.is_tombstoned(&id).unwrap());
+
+    assert_eq!(
+        store
+            .load_aggregate_from_snapshot(&id)
+            .unwrap(),
+        AggregateContext::new(id.clone(), 0, Customer::default()),
+        "the snapshot of an empty stream is not the default"
+    );
+
+    store.save_events(&Vec::new()).unwrap();
+
+    assert!(!store.stream_exists(&id).unwrap());
+}
+
+/// Check that the events load in sequence order, whatever the
+/// batches they were committed in
+pub fn check_event_ordering(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id = unique_id("ordering");
+
+    let contexts = customer_events(&id, 1, 6);
+
+    store
+        .save_events(&contexts[..1].to_vec())
+        .unwrap();
+    store
+        .save_events(&contexts[1..5].to_vec())
+        .unwrap();
+    store
+        .save_events(&contexts[5..].to_vec())
+        .unwrap();
+
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+    assert_eq!(
+        store
+            .load_events_range(&id, 2, Some(4))
+            .unwrap(),
+        contexts[1..4]
+    );
+    assert_eq!(
+        store
+            .load_events_range(&id, 5, None)
+            .unwrap(),
+        contexts[4..]
+    );
+    assert_eq!(
+        store
+            .load_events_range(&id, 0, Some(1))
+            .unwrap(),
+        contexts[..1]
+    );
+    assert!(store
+        .load_events_range(&id, 7, None)
+        .unwrap()
+        .is_empty());
+
+    let events: Vec<_> = store
+        .read_all(0, 1_000_000)
+        .unwrap()
+        .into_iter()
+        .filter(|x| x.context.aggregate_id == id)
+        .collect();
+
+    assert_eq!(
+        events
+            .iter()
+            .map(|x| x.context.clone())
+            .collect::<Vec<_>>(),
+        contexts,
+        "read_all does not return the events in commit order"
+    );
+
+    for x in events.windows(2) {
+        assert!(
+            x[0].position < x[1].position,
+            "the global positions are not ascending"
+        );
+    }
+
+    let metadata = store
+        .stream_metadata(&id)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(metadata.aggregate_id, id);
+    assert_eq!(metadata.version, 6);
+    assert_eq!(metadata.event_count, 6);
+}
+
+/// Check that `read_all` interleaves the streams in commit order
+/// and reads on from a position
+pub fn check_read_all(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id_a = unique_id("read_all");
+    let id_b = unique_id("read_all");
+
+    let a = customer_events(&id_a, 1, 3);
+    let b = customer_events(&id_b, 1, 1);
+
+    store.save_events(&a[..2].to_vec()).unwrap();
+    store.save_events(&b).unwrap();
+    store.save_events(&a[2..].to_vec()).unwrap();
+
+    let contexts =
+        [a[0].clone(), a[1].clone(), b[0].clone(), a[2].clone()];
+
+    let events: Vec<_> = store
+        .read_all(0, 1_000_000)
+        .unwrap()
+        .into_iter()
+        .filter(|x| {
+            x.context.aggregate_id == id_a ||
+                x.context.aggregate_id == id_b
+        })
+        .collect();
+
+    assert_eq!(
+        events
+            .iter()
+            .map(|x| x.context.clone())
+            .collect::<Vec<_>>(),
+        contexts,
+        "read_all does not interleave the streams in commit order"
+    );
+
+    for x in events.windows(2) {
+        assert!(
+            x[0].position < x[1].position,
+            "the global positions are not ascending"
+        );
+    }
+
+    let events = store
+        .read_all(events[2].position, 1)
+        .unwrap();
+
+    assert_eq!(events.len(), 1, "read_all ignores its limit");
+    assert_eq!(
+        events[0].context, contexts[2],
+        "read_all does not start at its position"
+    );
+}
+
+/// Check that the events load with their commit times, allowing
+/// for the clock of a database
+pub fn check_timestamps(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id = unique_id("timestamps");
+
+    let contexts = customer_events(&id, 1, 2);
+
+    let before = SystemTime::now() - Duration::from_secs(1);
+    store.save_events(&contexts).unwrap();
+    let after = SystemTime::now() + Duration::from_secs(1);
+
+    let events = store
+        .load_timestamped_events_range(&id, 0, None)
+        .unwrap();
+
+    assert_eq!(
+        events
+            .iter()
+            .map(|x| x.context.clone())
+            .collect::<Vec<_>>(),
+        contexts
+    );
+
+    for x in &events {
+        assert!(
+            x.timestamp >= before && x.timestamp <= after,
+            "the commit time is not the time of the commit"
+        );
+    }
+
+    let positioned: Vec<_> = store
+        .read_all(0, 1_000_000)
+        .unwrap()
+        .into_iter()
+        .filter(|x| x.context.aggregate_id == id)
+        .map(|x| x.timestamp)
+        .collect();
+
+    assert_eq!(
+        positioned,
+        events
+            .iter()
+            .map(|x| x.timestamp)
+            .collect::<Vec<_>>(),
+        "read_all does not load the commit times"
+    );
+}
+
+/// Check the metadata of the streams and the listing of their ids
+pub fn check_stream_metadata(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+
+    let mut ids = [unique_id("metadata"), unique_id("metadata")];
+    ids.sort();
+
+    let before = SystemTime::now() - Duration::from_secs(1);
+
+    store
+        .save_events(&customer_events(&ids[0], 1, 2))
+        .unwrap();
+    store
+        .save_events(&customer_events(&ids[1], 1, 1))
+        .unwrap();
+
+    let after = SystemTime::now() + Duration::from_secs(1);
+
+    assert!(store.stream_exists(&ids[0]).unwrap());
+
+    let metadata = store
+        .stream_metadata(&ids[0])
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(metadata.aggregate_id, ids[0]);
+    assert_eq!(metadata.version, 2);
+    assert_eq!(metadata.event_count, 2);
+    assert_eq!(metadata.snapshot_version, None);
+    assert!(metadata.first_timestamp >= before);
+    assert!(metadata.first_timestamp <= metadata.last_timestamp);
+    assert!(metadata.last_timestamp <= after);
+
+    store
+        .save_aggregate_snapshot(AggregateContext::new(
+            ids[0].clone(),
+            2,
+            Customer::default(),
+        ))
+        .unwrap();
+
+    assert_eq!(
+        store
+            .stream_metadata(&ids[0])
+            .unwrap()
+            .unwrap()
+            .snapshot_version,
+        Some(2)
+    );
+
+    let listed = store
+        .list_aggregate_ids(None, 1_000_000)
+        .unwrap();
+
+    assert!(listed.contains(&ids[0]));
+    assert!(listed.contains(&ids[1]));
+    assert!(
+        listed.windows(2).all(|x| x[0] < x[1]),
+        "the aggregate ids are not listed in order"
+    );
+
+    let listed = store
+        .list_aggregate_ids(Some(&ids[0]), 1_000_000)
+        .unwrap();
+
+    assert!(!listed.contains(&ids[0]));
+    assert!(listed.contains(&ids[1]));
+
+    assert_eq!(
+        store
+            .list_aggregate_ids(None, 1)
+            .unwrap()
+            .len(),
+        1,
+        "list_aggregate_ids ignores its limit"
+    );
+}
+
+/// Check that committing an already committed sequence is rejected
+/// with a conflict, leaving the stream untouched
+pub fn check_event_conflicts(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id = unique_id("conflicts");
+
+    let contexts = customer_events(&id, 1, 2);
+
+    store.save_events(&contexts).unwrap();
+
+    let err = store
+        .save_events(&customer_events(&id, 2, 2))
+        .unwrap_err();
+
+    assert!(err.is_conflict(), "not a conflict: {}", err);
+
+    // the batches are committed atomically
+    let mut batch = customer_events(&id, 3, 4);
+    batch.extend(customer_events(&id, 1, 1));
+
+    assert!(store.save_events(&batch).is_err());
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+
+    // a concurrent writer commits the same sequence first
+    let other = customers(factory);
+
+    other
+        .save_events(&customer_events(&id, 3, 3))
+        .unwrap();
+
+    let err = store
+        .save_events(&customer_events(&id, 3, 4))
+        .unwrap_err();
+
+    assert!(err.is_conflict(), "not a conflict: {}", err);
+    assert_eq!(
+        store.load_events(&id).unwrap(),
+        customer_events(&id, 1, 3)
+    );
+}
+
+/// Check that the snapshots are saved, overwritten and deleted
+pub fn check_snapshots(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id = unique_id("snapshots");
+
+    let mut customer = Customer::default();
+
+    for x in &customer_events(&id, 1, 2) {
+        customer.apply(&x.payload);
+    }
+
+    let context =
+        AggregateContext::new(id.clone(), 2, customer.clone());
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    assert_eq!(
+        store
+            .load_aggregate_from_snapshot(&id)
+            .unwrap(),
+        context
+    );
+
+    customer.name = "John Doe".to_string();
+
+    let context = AggregateContext::new(id.clone(), 5, customer);
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    assert_eq!(
+        store
+            .load_aggregate_from_snapshot(&id)
+            .unwrap(),
+        context,
+        "the snapshot was not overwritten"
+    );
+
+    store
+        .delete_aggregate_snapshot(&id)
+        .unwrap();
+
+    assert_eq!(
+        store
+            .load_aggregate_from_snapshot(&id)
+            .unwrap(),
+        AggregateContext::new(id.clone(), 0, Customer::default()),
+        "the snapshot was not deleted"
+    );
+}
+
+/// Check that the streams are deleted along with their snapshots
+/// and that their tombstones outlive them
+pub fn check_delete_stream(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id = unique_id("delete");
+
+    store
+        .save_events(&customer_events(&id, 1, 2))
+        .unwrap();
+    store
+        .save_aggregate_snapshot(AggregateContext::new(
+            id.clone(),
+            2,
+            Customer::default(),
+        ))
+        .unwrap();
+
+    store.delete_stream(&id).unwrap();
+
+    assert!(store.load_events(&id).unwrap().is_empty());
+    assert_eq!(store.stream_metadata(&id).unwrap(), None);
+    assert_eq!(
+        store
+            .load_aggregate_from_snapshot(&id)
+            .unwrap()
+            .version,
+        0,
+        "the snapshot of a deleted stream was loaded"
+    );
+
+    assert!(!store.is_tombstoned(&id).unwrap());
+
+    store.tombstone_stream(&id).unwrap();
+    store.tombstone_stream(&id).unwrap();
+
+    assert!(store.is_tombstoned(&id).unwrap());
+
+    store.delete_stream(&id).unwrap();
+
+    assert!(
+        store.is_tombstoned(&id).unwrap(),
+        "the tombstone was deleted with the stream"
+    );
+}
+
+/// Check that the positions of deleted events are not taken again,
+/// so that a reader past them does not skip the new events
+pub fn check_deleted_positions(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id = unique_id("deleted_positions");
+    let other_id = unique_id("deleted_positions");
+
+    store
+        .save_events(&customer_events(&id, 1, 2))
+        .unwrap();
+
+    let checkpoint = store
+        .read_all(0, 1_000_000)
+        .unwrap()
+        .into_iter()
+        .filter(|x| x.context.aggregate_id == id)
+        .map(|x| x.position)
+        .max()
+        .unwrap();
+
+    store.delete_stream(&id).unwrap();
+
+    let contexts = customer_events(&other_id, 1, 2);
+
+    store.save_events(&contexts).unwrap();
+
+    let events: Vec<_> = store
+        .read_all(checkpoint + 1, 1_000_000)
+        .unwrap()
+        .into_iter()
+        .filter(|x| x.context.aggregate_id == other_id)
+        .map(|x| x.context)
+        .collect();
+
+    assert_eq!(
+        events, contexts,
+        "the positions of the deleted events were taken again"
+    );
+}
+
+/// Check that the aggregate ids are not restricted to ASCII
+pub fn check_unicode_aggregate_ids(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id = unique_id("ünïcödé-漢字-🚀");
+
+    let contexts = vec![EventContext::new(
+        id.clone(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "Jöhn Dœ 漢字 🚀".to_string(),
+        }),
+        get_metadata(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+    assert!(store.stream_exists(&id).unwrap());
+
+    let context = AggregateContext::new(id.clone(), 1, Customer {
+        name: "Jöhn Dœ 漢字 🚀".to_string(),
+        ..Customer::default()
+    });
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    assert_eq!(
+        store
+            .load_aggregate_from_snapshot(&id)
+            .unwrap(),
+        context
+    );
+}
+
+/// Check that the large events and snapshots load unchanged
+pub fn check_large_events(factory: &mut impl IStoreFactory) {
+    let store = customers(factory);
+    let id = unique_id("large");
+
+    let contexts = vec![EventContext::new(
+        id.clone(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: large_text(),
+        }),
+        get_metadata(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+
+    let context = AggregateContext::new(id.clone(), 1, Customer {
+        name: large_text(),
+        ..Customer::default()
+    });
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    assert_eq!(
+        store
+            .load_aggregate_from_snapshot(&id)
+            .unwrap(),
+        context
+    );
+}
+
+/// Check that the streams of different aggregate types sharing an
+/// id are kept apart
+pub fn check_aggregate_types(factory: &mut impl IStoreFactory) {
+    let customers = customers(factory);
+    let accounts = accounts(factory);
+    let id = unique_id("types");
+
+    let customer_contexts = customer_events(&id, 1, 2);
+
+    let account_contexts = vec![
+        EventContext::new(
+            id.clone(),
+            1,
+            AccountEvent::AccountOpened {
+                owner: "John Doe".to_string(),
+            },
+            get_metadata(),
+        ),
+        EventContext::new(
+            id.clone(),
+            2,
+            AccountEvent::MoneyDeposited { amount: 42 },
+            get_metadata(),
+        ),
+    ];
+
+    customers
+        .save_events(&customer_contexts)
+        .unwrap();
+    accounts
+        .save_events(&account_contexts)
+        .unwrap();
+
+    assert_eq!(
+        customers.load_events(&id).unwrap(),
+        customer_contexts
+    );
+    assert_eq!(
+        accounts.load_events(&id).unwrap(),
+        account_contexts
+    );
+
+    let account = AggregateContext::new(id.clone(), 2, Account {
+        owner: "John Doe".to_string(),
+        balance: 42,
+    });
+
+    accounts
+        .save_aggregate_snapshot(account.clone())
+        .unwrap();
+
+    assert_eq!(
+        customers
+            .load_aggregate_from_snapshot(&id)
+            .unwrap()
+            .version,
+        0,
+        "a snapshot of another aggregate type was loaded"
+    );
+
+    customers.delete_stream(&id).unwrap();
+
+    assert!(!customers.stream_exists(&id).unwrap());
+    assert_eq!(
+        accounts.load_events(&id).unwrap(),
+        account_contexts,
+        "the stream of another aggregate type was deleted"
+    );
+    assert_eq!(
+        accounts
+            .load_aggregate_from_snapshot(&id)
+            .unwrap(),
+        account
+    );
+}
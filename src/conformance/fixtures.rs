@@ -0,0 +1,35 @@
+use std::{
+    sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The payloads of the large payload checks, under the 64 KiB of the
+/// MySQL `TEXT` columns once serialized
+pub(super) fn large_text() -> String {
+    "ünïcödé 漢字 🚀 large payload "
+        .repeat(1_600)
+}
+
+/// An aggregate id not used by any previous run, so that the suites
+/// may run on databases shared with other tests
+pub(super) fn unique_id(prefix: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+
+    format!(
+        "conformance_{}_{}_{}",
+        prefix,
+        nanos,
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    )
+}
@@ -0,0 +1,36 @@
+use cqrs_es2::{
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+};
+
+use crate::repository::{
+    IEventStore,
+    IQueryStore,
+};
+
+/// Creates the stores checked by the conformance suites.
+///
+/// The stores created by a factory must share the same database, so
+/// that a store sees the events committed by another one.
+pub trait IStoreFactory {
+    /// Create an event store of a particular aggregate type
+    fn event_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IEventStore<C, E, A>>;
+
+    /// Create a query store of a particular query type
+    fn query_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+        Q: IQuery<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IQueryStore<C, E, A, Q>>;
+}
@@ -0,0 +1,62 @@
+//! Conformance suites checking that an `IEventStore` or an
+//! `IQueryStore` implementation behaves like the stores of this
+//! crate.
+//!
+//! The suites create their stores with an `IStoreFactory` and panic
+//! on the first failed check, e.g. in a test of a custom store:
+//!
+//! ```ignore
+//! #[test]
+//! fn test_conformance() {
+//!     let mut factory = MyStoreFactory::new();
+//!
+//!     check_event_store(&mut factory);
+//!     check_query_store(&mut factory);
+//! }
+//! ```
+//!
+//! The stores are checked with the `Customer` aggregate of
+//! `cqrs_es2::example_impl` along with the `Account` aggregate and
+//! the `CustomerNameQuery` of this module. The suites use fresh
+//! aggregate ids on every run, so they may share a database with
+//! other tests.
+
+pub use accounts::{
+    Account,
+    AccountBalanceQuery,
+    AccountCommand,
+    AccountEvent,
+};
+pub use customer_name_query::CustomerNameQuery;
+pub use event_store_suite::{
+    check_aggregate_types,
+    check_delete_stream,
+    check_deleted_positions,
+    check_empty_streams,
+    check_event_conflicts,
+    check_event_ordering,
+    check_event_store,
+    check_large_events,
+    check_read_all,
+    check_snapshots,
+    check_stream_metadata,
+    check_timestamps,
+    check_unicode_aggregate_ids,
+};
+pub use i_store_factory::IStoreFactory;
+pub use query_store_suite::{
+    check_delete_query,
+    check_empty_queries,
+    check_large_queries,
+    check_query_store,
+    check_query_types,
+    check_save_load_queries,
+    check_unicode_query_ids,
+};
+
+mod accounts;
+mod customer_name_query;
+mod event_store_suite;
+mod fixtures;
+mod i_store_factory;
+mod query_store_suite;
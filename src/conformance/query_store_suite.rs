@@ -0,0 +1,203 @@
+use cqrs_es2::{
+    example_impl::{
+        Customer,
+        CustomerCommand,
+        CustomerContactQuery,
+        CustomerEvent,
+    },
+    QueryContext,
+};
+
+use crate::repository::IQueryStore;
+
+use super::{
+    accounts::{
+        Account,
+        AccountBalanceQuery,
+        AccountCommand,
+        AccountEvent,
+    },
+    customer_name_query::CustomerNameQuery,
+    fixtures::{
+        large_text,
+        unique_id,
+    },
+    i_store_factory::IStoreFactory,
+};
+
+type Contacts = Box<
+    dyn IQueryStore<
+        CustomerCommand,
+        CustomerEvent,
+        Customer,
+        CustomerContactQuery,
+    >,
+>;
+
+fn contacts(factory: &mut impl IStoreFactory) -> Contacts {
+    factory.query_store::<
+        CustomerCommand,
+        CustomerEvent,
+        Customer,
+        CustomerContactQuery,
+    >()
+}
+
+fn get_query(name: &str) -> CustomerContactQuery {
+    CustomerContactQuery {
+        name: name.to_string(),
+        email: "john@example.com".to_string(),
+        latest_address: "1 Main St".to_string(),
+    }
+}
+
+/// Run all checks of the query store suite
+pub fn check_query_store(factory: &mut impl IStoreFactory) {
+    check_empty_queries(factory);
+    check_save_load_queries(factory);
+    check_delete_query(factory);
+    check_unicode_query_ids(factory);
+    check_large_queries(factory);
+    check_query_types(factory);
+}
+
+/// Check that the queries never saved load as the default
+pub fn check_empty_queries(factory: &mut impl IStoreFactory) {
+    let store = contacts(factory);
+    let id = unique_id("empty_query");
+
+    assert_eq!(
+        store.load_query(&id).unwrap(),
+        QueryContext::new(
+            id.clone(),
+            0,
+            CustomerContactQuery::default()
+        ),
+        "a query never saved is not the default"
+    );
+}
+
+/// Check that the queries are saved at version `1` and overwritten
+/// at the later versions
+pub fn check_save_load_queries(factory: &mut impl IStoreFactory) {
+    let store = contacts(factory);
+    let id = unique_id("save_query");
+
+    let context = QueryContext::new(id.clone(), 1, get_query("A"));
+
+    store.save_query(context.clone()).unwrap();
+
+    assert_eq!(store.load_query(&id).unwrap(), context);
+
+    let context = QueryContext::new(id.clone(), 4, get_query("B"));
+
+    store.save_query(context.clone()).unwrap();
+
+    assert_eq!(
+        store.load_query(&id).unwrap(),
+        context,
+        "the query was not overwritten"
+    );
+}
+
+/// Check that deleting a query keeps the others
+pub fn check_delete_query(factory: &mut impl IStoreFactory) {
+    let store = contacts(factory);
+    let id = unique_id("delete_query");
+    let other_id = unique_id("delete_query");
+
+    let context = QueryContext::new(id.clone(), 1, get_query("A"));
+    let other =
+        QueryContext::new(other_id.clone(), 1, get_query("B"));
+
+    store.save_query(context).unwrap();
+    store.save_query(other.clone()).unwrap();
+
+    store.delete_query(&id).unwrap();
+
+    assert_eq!(store.load_query(&id).unwrap().version, 0);
+    assert_eq!(
+        store.load_query(&other_id).unwrap(),
+        other,
+        "another query was deleted"
+    );
+}
+
+/// Check that the aggregate ids of the queries are not restricted to
+/// ASCII
+pub fn check_unicode_query_ids(factory: &mut impl IStoreFactory) {
+    let store = contacts(factory);
+    let id = unique_id("ünïcödé-漢字-🚀");
+
+    let context =
+        QueryContext::new(id.clone(), 1, get_query("Jöhn Dœ 漢字 🚀"));
+
+    store.save_query(context.clone()).unwrap();
+
+    assert_eq!(store.load_query(&id).unwrap(), context);
+}
+
+/// Check that the large queries load unchanged
+pub fn check_large_queries(factory: &mut impl IStoreFactory) {
+    let store = contacts(factory);
+    let id = unique_id("large_query");
+
+    let context =
+        QueryContext::new(id.clone(), 1, get_query(&large_text()));
+
+    store.save_query(context.clone()).unwrap();
+
+    assert_eq!(store.load_query(&id).unwrap(), context);
+}
+
+/// Check that the queries of different query and aggregate types
+/// sharing an id are kept apart
+pub fn check_query_types(factory: &mut impl IStoreFactory) {
+    let contacts = contacts(factory);
+    let names = factory.query_store::<
+        CustomerCommand,
+        CustomerEvent,
+        Customer,
+        CustomerNameQuery,
+    >();
+    let balances = factory.query_store::<
+        AccountCommand,
+        AccountEvent,
+        Account,
+        AccountBalanceQuery,
+    >();
+    let id = unique_id("query_types");
+
+    let contact = QueryContext::new(id.clone(), 1, get_query("A"));
+
+    let name = QueryContext::new(id.clone(), 1, CustomerNameQuery {
+        name: "B".to_string(),
+    });
+
+    let balance =
+        QueryContext::new(id.clone(), 1, AccountBalanceQuery {
+            balance: 42,
+        });
+
+    contacts.save_query(contact.clone()).unwrap();
+    names.save_query(name.clone()).unwrap();
+    balances.save_query(balance.clone()).unwrap();
+
+    assert_eq!(contacts.load_query(&id).unwrap(), contact);
+    assert_eq!(names.load_query(&id).unwrap(), name);
+    assert_eq!(balances.load_query(&id).unwrap(), balance);
+
+    names.delete_queries().unwrap();
+
+    assert_eq!(names.load_query(&id).unwrap().version, 0);
+    assert_eq!(
+        contacts.load_query(&id).unwrap(),
+        contact,
+        "the queries of another query type were deleted"
+    );
+    assert_eq!(
+        balances.load_query(&id).unwrap(),
+        balance,
+        "the queries of another aggregate type were deleted"
+    );
+}
@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    fmt::{
+        Display,
+        Formatter,
+        Result as fmtResult,
+    },
+};
+
+use cqrs_es2::{
+    Error,
+    UserError,
+};
+
+/// Raised by the event stores as `StoreError::Conflict` when a
+/// commit is rejected because another writer has already persisted
+/// events for the same aggregate at the expected sequence.
+///
+/// It is converted into a `cqrs_es2::Error::UserError` carrying
+/// `ConflictError::CODE` so that callers can tell it apart from
+/// other failures with `ConflictError::is_conflict`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflictError {
+    /// The aggregate type of the rejected commit
+    pub aggregate_type: String,
+
+    /// The aggregate id of the rejected commit
+    pub aggregate_id: String,
+
+    /// The sequence found already committed by another writer
+    pub sequence: i64,
+}
+
+impl ConflictError {
+    /// The user error code used for concurrency conflicts
+    pub const CODE: &'static str = "concurrency_conflict";
+
+    /// Constructor
+    pub fn new(
+        aggregate_type: &str,
+        aggregate_id: &str,
+        sequence: i64,
+    ) -> Self {
+        Self {
+            aggregate_type: aggregate_type.to_string(),
+            aggregate_id: aggregate_id.to_string(),
+            sequence,
+        }
+    }
+
+    /// Checks if an error was generated from a concurrency conflict
+    pub fn is_conflict(error: &Error) -> bool {
+        match error {
+            Error::UserError(e) => {
+                e.code.as_deref() == Some(Self::CODE)
+            },
+            Error::TechnicalError(_) => false,
+        }
+    }
+}
+
+impl Display for ConflictError {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> fmtResult {
+        write!(
+            f,
+            "concurrency conflict: sequence {} was already \
+             committed for aggregate '{}' with id '{}'",
+            self.sequence, self.aggregate_type, self.aggregate_id
+        )
+    }
+}
+
+impl From<ConflictError> for Error {
+    fn from(e: ConflictError) -> Self {
+        let message = e.to_string();
+
+        let mut params = HashMap::new();
+        params.insert(
+            "aggregate_type".to_string(),
+            e.aggregate_type,
+        );
+        params.insert("aggregate_id".to_string(), e.aggregate_id);
+        params.insert("sequence".to_string(), e.sequence.to_string());
+
+        Error::UserError(UserError {
+            code: Some(ConflictError::CODE.to_string()),
+            message: Some(message),
+            params: Some(params),
+        })
+    }
+}
@@ -0,0 +1,10 @@
+pub use conflict_error::ConflictError;
+pub use store_error::StoreError;
+pub use tombstoned_error::TombstonedError;
+
+mod conflict_error;
+mod store_error;
+mod tombstoned_error;
+
+#[cfg(test)]
+mod test;
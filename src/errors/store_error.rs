@@ -0,0 +1,198 @@
+use std::{
+    error,
+    fmt::{
+        Display,
+        Formatter,
+        Result as fmtResult,
+    },
+};
+
+use cqrs_es2::Error;
+
+use super::conflict_error::ConflictError;
+
+type BoxedError = Box<dyn error::Error + Send + Sync>;
+
+/// The failures of the stores.
+///
+/// Apart from `Conflict`, every variant carries a message describing
+/// the failed operation and the underlying error, if any, as its
+/// `source`. It is converted into a `cqrs_es2::Error` whose message
+/// includes the whole source chain.
+#[derive(Debug)]
+pub enum StoreError {
+    /// The database could not be reached or the connection was lost
+    Connection {
+        /// The failed operation
+        message: String,
+        /// The underlying error
+        source: Option<BoxedError>,
+    },
+
+    /// A payload or metadata could not be serialized
+    Serialization {
+        /// The failed operation
+        message: String,
+        /// The underlying error
+        source: Option<BoxedError>,
+    },
+
+    /// A stored payload or metadata could not be deserialized
+    Deserialization {
+        /// The failed operation
+        message: String,
+        /// The underlying error
+        source: Option<BoxedError>,
+    },
+
+    /// A commit was rejected because another writer committed the
+    /// same sequence first
+    Conflict(ConflictError),
+
+    /// A table, collection or entry does not exist
+    NotFound {
+        /// The failed operation
+        message: String,
+        /// The underlying error
+        source: Option<BoxedError>,
+    },
+
+    /// Any other failure reported by the database
+    Backend {
+        /// The failed operation
+        message: String,
+        /// The underlying error
+        source: Option<BoxedError>,
+    },
+}
+
+impl StoreError {
+    /// Constructor of a `Connection` error
+    pub fn connection(
+        message: &str,
+        source: impl Into<BoxedError>,
+    ) -> Self {
+        Self::Connection {
+            message: message.to_string(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Constructor of a `Serialization` error
+    pub fn serialization(
+        message: &str,
+        source: impl Into<BoxedError>,
+    ) -> Self {
+        Self::Serialization {
+            message: message.to_string(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Constructor of a `Deserialization` error
+    pub fn deserialization(
+        message: &str,
+        source: impl Into<BoxedError>,
+    ) -> Self {
+        Self::Deserialization {
+            message: message.to_string(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Constructor of a `NotFound` error without underlying error
+    pub fn not_found(message: &str) -> Self {
+        Self::NotFound {
+            message: message.to_string(),
+            source: None,
+        }
+    }
+
+    /// Constructor of a `Backend` error
+    pub fn backend(
+        message: &str,
+        source: impl Into<BoxedError>,
+    ) -> Self {
+        Self::Backend {
+            message: message.to_string(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// Checks if the error was raised by a concurrency conflict
+    pub fn is_conflict(&self) -> bool {
+        matches!(self, Self::Conflict(_))
+    }
+}
+
+impl Display for StoreError {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> fmtResult {
+        match self {
+            Self::Connection { message, .. } => {
+                write!(f, "connection error: {}", message)
+            },
+            Self::Serialization { message, .. } => {
+                write!(f, "serialization error: {}", message)
+            },
+            Self::Deserialization { message, .. } => {
+                write!(f, "deserialization error: {}", message)
+            },
+            Self::Conflict(e) => write!(f, "{}", e),
+            Self::NotFound { message, .. } => {
+                write!(f, "not found: {}", message)
+            },
+            Self::Backend { message, .. } => {
+                write!(f, "backend error: {}", message)
+            },
+        }
+    }
+}
+
+impl error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Connection { source, .. } |
+            Self::Serialization { source, .. } |
+            Self::Deserialization { source, .. } |
+            Self::NotFound { source, .. } |
+            Self::Backend { source, .. } => {
+                match source {
+                    Some(x) => Some(x.as_ref()),
+                    None => None,
+                }
+            },
+            Self::Conflict(_) => None,
+        }
+    }
+}
+
+impl From<ConflictError> for StoreError {
+    fn from(e: ConflictError) -> Self {
+        Self::Conflict(e)
+    }
+}
+
+impl From<StoreError> for Error {
+    fn from(e: StoreError) -> Self {
+        let e = match e {
+            StoreError::Conflict(x) => {
+                return x.into();
+            },
+            x => x,
+        };
+
+        let mut message = e.to_string();
+
+        let mut source = error::Error::source(&e);
+
+        while let Some(x) = source {
+            message = format!("{}: {}", message, x);
+            source = x.source();
+        }
+
+        Error::TechnicalError(message)
+    }
+}
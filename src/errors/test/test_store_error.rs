@@ -0,0 +1,76 @@
+use std::error::Error as StdError;
+
+use cqrs_es2::Error;
+
+use crate::{
+    ConflictError,
+    StoreError,
+    TombstonedError,
+};
+
+#[test]
+fn test_source_chain() {
+    let json = serde_json::from_str::<i64>("{").unwrap_err();
+    let json_message = json.to_string();
+
+    let e = StoreError::deserialization(
+        "bad payload found in events table",
+        json,
+    );
+
+    assert_eq!(
+        e.to_string(),
+        "deserialization error: bad payload found in events table"
+    );
+    assert_eq!(e.source().unwrap().to_string(), json_message);
+
+    match Error::from(e) {
+        Error::TechnicalError(x) => {
+            assert_eq!(
+                x,
+                format!(
+                    "deserialization error: bad payload found in \
+                     events table: {}",
+                    json_message
+                )
+            );
+        },
+        x => panic!("unexpected error {:?}", x),
+    }
+}
+
+#[test]
+fn test_not_found_without_source() {
+    let e = StoreError::not_found("missing event 3");
+
+    assert!(e.source().is_none());
+    assert!(!e.is_conflict());
+
+    assert_eq!(
+        Error::from(e),
+        Error::TechnicalError("not found: missing event 3".to_string())
+    );
+}
+
+#[test]
+fn test_conflict() {
+    let e: StoreError =
+        ConflictError::new("Customer", "test_id_A", 2).into();
+
+    assert!(e.is_conflict());
+    assert!(ConflictError::is_conflict(&e.into()));
+}
+
+#[test]
+fn test_tombstoned() {
+    let e: Error =
+        TombstonedError::new("Customer", "test_id_A").into();
+
+    assert!(TombstonedError::is_tombstoned(&e));
+    assert!(!ConflictError::is_conflict(&e));
+
+    let e: Error =
+        ConflictError::new("Customer", "test_id_A", 2).into();
+
+    assert!(!TombstonedError::is_tombstoned(&e));
+}
@@ -0,0 +1,88 @@
+use std::{
+    collections::HashMap,
+    fmt::{
+        Display,
+        Formatter,
+        Result as fmtResult,
+    },
+};
+
+use cqrs_es2::{
+    Error,
+    UserError,
+};
+
+/// Returned by the `Repository` when a command is sent to an
+/// aggregate whose stream was tombstoned with
+/// `IEventStore::tombstone_stream`.
+///
+/// It is converted into a `cqrs_es2::Error::UserError` carrying
+/// `TombstonedError::CODE` so that callers can tell it apart from
+/// other failures with `TombstonedError::is_tombstoned`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TombstonedError {
+    /// The aggregate type of the refused command
+    pub aggregate_type: String,
+
+    /// The aggregate id of the refused command
+    pub aggregate_id: String,
+}
+
+impl TombstonedError {
+    /// The user error code used for tombstoned aggregates
+    pub const CODE: &'static str = "aggregate_tombstoned";
+
+    /// Constructor
+    pub fn new(
+        aggregate_type: &str,
+        aggregate_id: &str,
+    ) -> Self {
+        Self {
+            aggregate_type: aggregate_type.to_string(),
+            aggregate_id: aggregate_id.to_string(),
+        }
+    }
+
+    /// Checks if an error was generated for a tombstoned aggregate
+    pub fn is_tombstoned(error: &Error) -> bool {
+        match error {
+            Error::UserError(e) => {
+                e.code.as_deref() == Some(Self::CODE)
+            },
+            Error::TechnicalError(_) => false,
+        }
+    }
+}
+
+impl Display for TombstonedError {
+    fn fmt(
+        &self,
+        f: &mut Formatter<'_>,
+    ) -> fmtResult {
+        write!(
+            f,
+            "aggregate '{}' with id '{}' was tombstoned and accepts \
+             no more commands",
+            self.aggregate_type, self.aggregate_id
+        )
+    }
+}
+
+impl From<TombstonedError> for Error {
+    fn from(e: TombstonedError) -> Self {
+        let message = e.to_string();
+
+        let mut params = HashMap::new();
+        params.insert(
+            "aggregate_type".to_string(),
+            e.aggregate_type,
+        );
+        params.insert("aggregate_id".to_string(), e.aggregate_id);
+
+        Error::UserError(UserError {
+            code: Some(TombstonedError::CODE.to_string()),
+            message: Some(message),
+            params: Some(params),
+        })
+    }
+}
@@ -0,0 +1,94 @@
+use log::{
+    debug,
+    trace,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        RwLock,
+    },
+};
+
+use crate::{
+    errors::StoreError,
+    repository::ICheckpointStore,
+};
+
+type LockedCheckpointMap = RwLock<HashMap<String, i64>>;
+
+/// Sync memory checkpoint store useful for testing purposes only
+pub struct CheckpointStore {
+    checkpoints: Arc<LockedCheckpointMap>,
+}
+
+impl CheckpointStore {
+    /// Constructor
+    pub fn new(checkpoints: Arc<LockedCheckpointMap>) -> Self {
+        let x = Self { checkpoints };
+
+        trace!(
+            "Created new sync memory checkpoint store from passed \
+             Arcs"
+        );
+
+        x
+    }
+}
+
+impl Default for CheckpointStore {
+    fn default() -> Self {
+        let x = Self {
+            checkpoints: Default::default(),
+        };
+
+        trace!("Created default sync memory checkpoint store");
+
+        x
+    }
+}
+
+impl ICheckpointStore for CheckpointStore {
+    /// Save the last position processed by `subscription`
+    fn save_checkpoint(
+        &self,
+        subscription: &str,
+        position: i64,
+    ) -> Result<(), StoreError> {
+        debug!(
+            "storing checkpoint {} for subscription '{}'",
+            position, subscription
+        );
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        let mut map = self.checkpoints.write().unwrap();
+        map.insert(subscription.to_string(), position);
+
+        Ok(())
+    }
+
+    /// Load the last position processed by `subscription`
+    fn load_checkpoint(
+        &self,
+        subscription: &str,
+    ) -> Result<i64, StoreError> {
+        trace!(
+            "loading checkpoint for subscription '{}'",
+            subscription
+        );
+
+        // uninteresting unwrap: this will not be used in production,
+        // for tests only
+
+        match self
+            .checkpoints
+            .read()
+            .unwrap()
+            .get(subscription)
+        {
+            None => Ok(0),
+            Some(x) => Ok(*x),
+        }
+    }
+}
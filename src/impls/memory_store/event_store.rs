@@ -3,34 +3,68 @@ use log::{
     trace,
 };
 use std::{
-    collections::HashMap,
+    collections::{
+        HashMap,
+        HashSet,
+    },
     sync::{
+        atomic::{
+            AtomicI64,
+            Ordering,
+        },
         Arc,
         RwLock,
     },
+    time::SystemTime,
 };
 
 use cqrs_es2::{
     AggregateContext,
-    Error,
     EventContext,
     IAggregate,
     ICommand,
     IEvent,
 };
 
-use crate::repository::IEventStore;
+use crate::{
+    errors::{
+        ConflictError,
+        StoreError,
+    },
+    repository::{
+        IEventStore,
+        PositionedEvent,
+        StreamMetadata,
+        TimestampedEvent,
+    },
+};
+
+/// The aggregate type and aggregate id of a stream
+type StreamKey = (String, String);
 
 type LockedEventContextMap<C, E> =
-    RwLock<HashMap<String, Vec<EventContext<C, E>>>>;
+    RwLock<HashMap<StreamKey, Vec<PositionedEvent<C, E>>>>;
 
 type LockedAggregateContextMap<C, E, A> =
-    RwLock<HashMap<String, AggregateContext<C, E, A>>>;
+    RwLock<HashMap<StreamKey, AggregateContext<C, E, A>>>;
+
+type LockedTombstoneSet = RwLock<HashSet<StreamKey>>;
 
-/// Sync memory event store useful for testing purposes only
+/// Sync memory event store useful for testing purposes only.
+///
+/// The events, snapshots and tombstones are keyed by aggregate type
+/// and id like in the other stores, so that the stores of several
+/// aggregate types may share the same maps, along with the next
+/// global position. The sequences of a stream are consecutive, a
+/// commit must start right after the last committed sequence.
+///
+/// The events are kept typed, they are neither serialized nor
+/// upcasted. An `EncryptingEventStore` encrypts their string fields.
 pub struct EventStore<C: ICommand, E: IEvent, A: IAggregate<C, E>> {
     events: Arc<LockedEventContextMap<C, E>>,
     snapshots: Arc<LockedAggregateContextMap<C, E, A>>,
+    tombstones: Arc<LockedTombstoneSet>,
+    next_position: Arc<AtomicI64>,
 }
 
 impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
@@ -40,8 +74,15 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
     pub fn new(
         events: Arc<LockedEventContextMap<C, E>>,
         snapshots: Arc<LockedAggregateContextMap<C, E, A>>,
+        tombstones: Arc<LockedTombstoneSet>,
+        next_position: Arc<AtomicI64>,
     ) -> Self {
-        let x = Self { events, snapshots };
+        let x = Self {
+            events,
+            snapshots,
+            tombstones,
+            next_position,
+        };
 
         trace!(
             "Created new sync memory event store from passed Arcs"
@@ -49,6 +90,14 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
 
         x
     }
+
+    /// The key of the stream of a particular `aggregate_id`
+    fn key(aggregate_id: &str) -> StreamKey {
+        (
+            A::aggregate_type().to_string(),
+            aggregate_id.to_string(),
+        )
+    }
 }
 
 impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> Default
@@ -58,6 +107,8 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> Default
         let x = Self {
             events: Default::default(),
             snapshots: Default::default(),
+            tombstones: Default::default(),
+            next_position: Default::default(),
         };
 
         trace!("Created default sync memory event store");
@@ -71,9 +122,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 {
     /// Save new events
     fn save_events(
-        &mut self,
+        &self,
         contexts: &Vec<EventContext<C, E>>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         if contexts.len() == 0 {
             trace!("Skip saving zero contexts");
             return Ok(());
@@ -91,25 +142,74 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             &aggregate_id
         );
 
-        let mut new_contexts = self.load_events(&aggregate_id)?;
-
-        contexts
-            .iter()
-            .for_each(|x| new_contexts.push(x.clone()));
-
         // uninteresting unwrap: this is not a struct for production
         // use
         let mut map = self.events.write().unwrap();
-        map.insert(aggregate_id, new_contexts);
+
+        // the next position is only ever raised, the positions of
+        // deleted events are not taken again
+        let first_position = self
+            .next_position
+            .load(Ordering::SeqCst)
+            .max(1);
+
+        // the sequences of a stream are consecutive, within the batch
+        // too
+        let mut sequences = HashMap::new();
+
+        for x in contexts {
+            let key = Self::key(&x.aggregate_id);
+
+            let current_sequence = match sequences.get(&key) {
+                Some(x) => *x,
+                None => {
+                    match map.get(&key).and_then(|x| x.last()) {
+                        Some(x) => x.context.sequence,
+                        None => 0,
+                    }
+                },
+            };
+
+            if x.sequence != current_sequence + 1 {
+                return Err(ConflictError::new(
+                    A::aggregate_type(),
+                    &x.aggregate_id,
+                    x.sequence,
+                )
+                .into());
+            }
+
+            sequences.insert(key, x.sequence);
+        }
+
+        let timestamp = SystemTime::now();
+
+        for (i, x) in contexts.iter().enumerate() {
+            map.entry(Self::key(&x.aggregate_id))
+                .or_default()
+                .push(PositionedEvent::new(
+                    first_position + i as i64,
+                    timestamp,
+                    x.clone(),
+                ));
+        }
+
+        self.next_position.store(
+            first_position + contexts.len() as i64,
+            Ordering::SeqCst,
+        );
 
         Ok(())
     }
 
-    /// Load all events for a particular `aggregate_id`
-    fn load_events(
-        &mut self,
+    /// Load the events for a particular `aggregate_id` within a
+    /// range of sequences along with their commit timestamps
+    fn load_timestamped_events_range(
+        &self,
         aggregate_id: &str,
-    ) -> Result<Vec<EventContext<C, E>>, Error> {
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
         trace!(
             "loading events for aggregate id '{}'",
             aggregate_id
@@ -122,38 +222,242 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             .events
             .read()
             .unwrap()
-            .get(aggregate_id)
+            .get(&Self::key(aggregate_id))
         {
             None => Ok(Vec::new()),
-            Some(x) => Ok(x.clone()),
+            Some(x) => {
+                Ok(x.iter()
+                    .filter(|x| {
+                        x.context.sequence >= from_sequence &&
+                            x.context.sequence <=
+                                to_sequence.unwrap_or(i64::MAX)
+                    })
+                    .map(|x| {
+                        TimestampedEvent::new(
+                            x.timestamp,
+                            x.context.clone(),
+                        )
+                    })
+                    .collect())
+            },
         }
     }
 
+    /// Load up to `limit` events of all aggregates of this type in
+    /// commit order, starting at the global position
+    /// `from_position`
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError> {
+        trace!(
+            "reading up to '{}' events from position {}",
+            limit,
+            from_position
+        );
+
+        // uninteresting unwrap: this will not be used in production,
+        // for tests only
+
+        let mut result: Vec<_> = self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(k, _)| k.0 == A::aggregate_type())
+            .flat_map(|(_, v)| v)
+            .filter(|x| x.position >= from_position)
+            .cloned()
+            .collect();
+
+        result.sort_by_key(|x| x.position);
+        result.truncate(limit);
+
+        Ok(result)
+    }
+
+    /// List up to `limit` ids of the aggregates of this type in
+    /// ascending order, starting after `after_aggregate_id`
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        trace!(
+            "listing up to '{}' aggregate ids after '{:?}'",
+            limit,
+            after_aggregate_id
+        );
+
+        // uninteresting unwrap: this will not be used in production,
+        // for tests only
+
+        let mut result: Vec<_> = self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(k, v)| {
+                k.0 == A::aggregate_type() &&
+                    !v.is_empty() &&
+                    match after_aggregate_id {
+                        Some(x) => k.1.as_str() > x,
+                        None => true,
+                    }
+            })
+            .map(|(k, _)| k.1.clone())
+            .collect();
+
+        result.sort();
+        result.truncate(limit);
+
+        Ok(result)
+    }
+
+    /// Load the metadata of the event stream of a particular
+    /// `aggregate_id`
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        trace!(
+            "loading stream metadata for aggregate id '{}'",
+            aggregate_id
+        );
+
+        // uninteresting unwrap: this will not be used in production,
+        // for tests only
+
+        let key = Self::key(aggregate_id);
+
+        let snapshot_version = self
+            .snapshots
+            .read()
+            .unwrap()
+            .get(&key)
+            .map(|x| x.version);
+
+        let map = self.events.read().unwrap();
+
+        let events = match map.get(&key) {
+            Some(x) if !x.is_empty() => x,
+            _ => {
+                return Ok(None);
+            },
+        };
+
+        let first = events.first().unwrap();
+        let last = events.last().unwrap();
+
+        Ok(Some(StreamMetadata::new(
+            aggregate_id,
+            last.context.sequence,
+            events.len() as i64,
+            first.timestamp,
+            last.timestamp,
+            snapshot_version,
+        )))
+    }
+
+    /// Delete all events of a particular `aggregate_id` along with
+    /// its snapshot
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        debug!(
+            "deleting events and snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        self.events
+            .write()
+            .unwrap()
+            .remove(&Self::key(aggregate_id));
+
+        self.delete_aggregate_snapshot(aggregate_id)
+    }
+
+    /// Mark the stream of a particular `aggregate_id` as tombstoned
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        debug!("tombstoning aggregate id '{}'", aggregate_id);
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        self.tombstones
+            .write()
+            .unwrap()
+            .insert(Self::key(aggregate_id));
+
+        Ok(())
+    }
+
+    /// Check whether the stream of a particular `aggregate_id` was
+    /// tombstoned
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        // uninteresting unwrap: this will not be used in production,
+        // for tests only
+        Ok(self
+            .tombstones
+            .read()
+            .unwrap()
+            .contains(&Self::key(aggregate_id)))
+    }
+
     /// save a new aggregate snapshot
     fn save_aggregate_snapshot(
-        &mut self,
+        &self,
         context: AggregateContext<C, E, A>,
-    ) -> Result<(), Error> {
-        let aggregate_id = context.aggregate_id.clone();
+    ) -> Result<(), StoreError> {
+        let key = Self::key(&context.aggregate_id);
 
         debug!(
             "storing a new snapshot for aggregate id '{}'",
-            &aggregate_id
+            &key.1
         );
 
         // uninteresting unwrap: this is not a struct for production
         // use
         let mut map = self.snapshots.write().unwrap();
-        map.insert(aggregate_id, context);
+        map.insert(key, context);
+
+        Ok(())
+    }
+
+    /// Delete the snapshot of a particular `aggregate_id`
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        debug!(
+            "deleting the snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        self.snapshots
+            .write()
+            .unwrap()
+            .remove(&Self::key(aggregate_id));
 
         Ok(())
     }
 
     /// Load aggregate at current state from snapshots
     fn load_aggregate_from_snapshot(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<AggregateContext<C, E, A>, Error> {
+    ) -> Result<AggregateContext<C, E, A>, StoreError> {
         trace!(
             "loading snapshot for aggregate id '{}'",
             aggregate_id
@@ -166,7 +470,7 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             .snapshots
             .read()
             .unwrap()
-            .get(aggregate_id)
+            .get(&Self::key(aggregate_id))
         {
             None => {
                 Ok(AggregateContext::new(
@@ -0,0 +1,130 @@
+use log::{
+    debug,
+    trace,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        RwLock,
+    },
+};
+
+use crate::{
+    errors::StoreError,
+    repository::IKeyStore,
+};
+
+type LockedKeyMap = RwLock<HashMap<String, Vec<u8>>>;
+
+/// Sync memory key store useful for testing purposes only.
+///
+/// The keys are lost when the process exits, which leaves every
+/// encrypted field unreadable as if all aggregates were shredded.
+pub struct KeyStore {
+    keys: Arc<LockedKeyMap>,
+}
+
+impl KeyStore {
+    /// Constructor
+    pub fn new(keys: Arc<LockedKeyMap>) -> Self {
+        let x = Self { keys };
+
+        trace!("Created new sync memory key store from passed Arcs");
+
+        x
+    }
+}
+
+impl Default for KeyStore {
+    fn default() -> Self {
+        let x = Self {
+            keys: Default::default(),
+        };
+
+        trace!("Created default sync memory key store");
+
+        x
+    }
+}
+
+impl IKeyStore for KeyStore {
+    /// Save the key of a particular `aggregate_id`
+    fn save_key(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        key: &[u8],
+    ) -> Result<(), StoreError> {
+        debug!(
+            "storing a new key for aggregate id '{}'",
+            aggregate_id
+        );
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        let mut map = self.keys.write().unwrap();
+        map.insert(
+            format!("{};{}", aggregate_type, aggregate_id),
+            key.to_vec(),
+        );
+
+        Ok(())
+    }
+
+    /// Save the key of a particular `aggregate_id` unless it already
+    /// has one
+    fn save_key_if_absent(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        key: &[u8],
+    ) -> Result<Vec<u8>, StoreError> {
+        trace!(
+            "storing a new key for aggregate id '{}' if missing",
+            aggregate_id
+        );
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        let mut map = self.keys.write().unwrap();
+
+        Ok(map
+            .entry(format!("{};{}", aggregate_type, aggregate_id))
+            .or_insert_with(|| key.to_vec())
+            .clone())
+    }
+
+    /// Load the key of a particular `aggregate_id`
+    fn load_key(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+    ) -> Result<Option<Vec<u8>>, StoreError> {
+        trace!("loading key for aggregate id '{}'", aggregate_id);
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        let map = self.keys.read().unwrap();
+
+        Ok(map
+            .get(&format!("{};{}", aggregate_type, aggregate_id))
+            .cloned())
+    }
+
+    /// Delete the key of a particular `aggregate_id`
+    fn delete_key(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        debug!("deleting the key of aggregate id '{}'", aggregate_id);
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        let mut map = self.keys.write().unwrap();
+        map.remove(&format!("{};{}", aggregate_type, aggregate_id));
+
+        Ok(())
+    }
+}
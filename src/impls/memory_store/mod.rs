@@ -1,9 +1,15 @@
 //!
 //! A simple memory store for testing purposes only
 
+pub use checkpoint_store::CheckpointStore;
 pub use event_store::EventStore;
+#[cfg(feature = "with-encryption")]
+pub use key_store::KeyStore;
 pub use query_store::QueryStore;
 
+mod checkpoint_store;
 mod event_store;
+#[cfg(feature = "with-encryption")]
+mod key_store;
 mod query_store;
 mod test;
@@ -21,15 +21,25 @@ use cqrs_es2::{
     QueryContext,
 };
 
-use crate::repository::{
-    IEventDispatcher,
-    IQueryStore,
+use crate::{
+    errors::StoreError,
+    repository::{
+        IEventDispatcher,
+        IQueryStore,
+    },
 };
 
+/// The aggregate type, aggregate id and query type of a query
+type QueryKey = (String, String, String);
+
 type LockedQueryContextMap<C, E, Q> =
-    RwLock<HashMap<String, QueryContext<C, E, Q>>>;
+    RwLock<HashMap<QueryKey, QueryContext<C, E, Q>>>;
 
-/// Sync memory query store useful for testing purposes only
+/// Sync memory query store useful for testing purposes only.
+///
+/// The queries are keyed by aggregate type, aggregate id and query
+/// type like in the other stores, so that the stores of several
+/// aggregate types may share the same map.
 pub struct QueryStore<
     C: ICommand,
     E: IEvent,
@@ -60,6 +70,15 @@ impl<
 
         x
     }
+
+    /// The key of the query of a particular `aggregate_id`
+    fn key(aggregate_id: &str) -> QueryKey {
+        (
+            A::aggregate_type().to_string(),
+            aggregate_id.to_string(),
+            Q::query_type().to_string(),
+        )
+    }
 }
 
 impl<
@@ -90,9 +109,9 @@ impl<
 {
     /// saves the updated query
     fn save_query(
-        &mut self,
+        &self,
         context: QueryContext<C, E, Q>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let query_type = Q::query_type();
 
         let aggregate_id = context.aggregate_id.clone();
@@ -105,16 +124,16 @@ impl<
         // uninteresting unwrap: this is not a struct for production
         // use
         let mut map = self.queries.write().unwrap();
-        map.insert(aggregate_id, context);
+        map.insert(Self::key(&aggregate_id), context);
 
         Ok(())
     }
 
     /// loads the most recent query
     fn load_query(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<QueryContext<C, E, Q>, Error> {
+    ) -> Result<QueryContext<C, E, Q>, StoreError> {
         let query_type = Q::query_type();
 
         trace!(
@@ -130,7 +149,7 @@ impl<
             .queries
             .read()
             .unwrap()
-            .get(aggregate_id)
+            .get(&Self::key(aggregate_id))
         {
             None => {
                 Ok(QueryContext::new(
@@ -142,6 +161,43 @@ impl<
             Some(x) => Ok(x.clone()),
         }
     }
+
+    /// deletes the query of a particular `aggregate_id`
+    fn delete_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let query_type = Q::query_type();
+
+        debug!(
+            "deleting query '{}' for aggregate id '{}'",
+            query_type, aggregate_id
+        );
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        self.queries
+            .write()
+            .unwrap()
+            .remove(&Self::key(aggregate_id));
+
+        Ok(())
+    }
+
+    /// deletes all queries of the query type
+    fn delete_queries(&self) -> Result<(), StoreError> {
+        let query_type = Q::query_type();
+
+        debug!("deleting all queries '{}'", query_type);
+
+        // uninteresting unwrap: this is not a struct for production
+        // use
+        self.queries.write().unwrap().retain(|k, _| {
+            k.0 != A::aggregate_type() || k.2 != query_type
+        });
+
+        Ok(())
+    }
 }
 
 impl<
@@ -152,10 +208,10 @@ impl<
     > IEventDispatcher<C, E> for QueryStore<C, E, A, Q>
 {
     fn dispatch(
-        &mut self,
+        &self,
         aggregate_id: &str,
         events: &Vec<EventContext<C, E>>,
     ) -> Result<(), Error> {
-        self.dispatch_events(aggregate_id, events)
+        Ok(self.dispatch_events(aggregate_id, events)?)
     }
 }
@@ -1,5 +1,14 @@
+#[cfg(test)]
+mod test_checkpoint_store;
+
+#[cfg(test)]
+mod test_conformance;
+
 #[cfg(test)]
 mod test_event_store;
 
 #[cfg(test)]
 mod test_query_store;
+
+#[cfg(test)]
+mod vip_customer;
@@ -0,0 +1,106 @@
+use std::{
+    any::{
+        Any,
+        TypeId,
+    },
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    sync::{
+        atomic::AtomicI64,
+        Arc,
+        RwLock,
+    },
+};
+
+use cqrs_es2::{
+    AggregateContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+    QueryContext,
+};
+
+use crate::{
+    conformance::*,
+    memory_store::{
+        EventStore,
+        QueryStore,
+    },
+    IEventStore,
+    IQueryStore,
+    PositionedEvent,
+};
+
+type StreamKey = (String, String);
+
+type QueryKey = (String, String, String);
+
+type EventMaps<C, E, A> = (
+    Arc<RwLock<HashMap<StreamKey, Vec<PositionedEvent<C, E>>>>>,
+    Arc<RwLock<HashMap<StreamKey, AggregateContext<C, E, A>>>>,
+    Arc<RwLock<HashSet<StreamKey>>>,
+    Arc<AtomicI64>,
+);
+
+type QueryMap<C, E, Q> =
+    Arc<RwLock<HashMap<QueryKey, QueryContext<C, E, Q>>>>;
+
+/// Shares the maps of the stores of the same type, like a database
+#[derive(Default)]
+struct StoreFactory {
+    maps: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl StoreFactory {
+    fn maps<T: Any + Clone + Default>(&mut self) -> T {
+        self.maps
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_ref::<T>()
+            .unwrap()
+            .clone()
+    }
+}
+
+impl IStoreFactory for StoreFactory {
+    fn event_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IEventStore<C, E, A>> {
+        let (events, snapshots, tombstones, positions) =
+            self.maps::<EventMaps<C, E, A>>();
+
+        Box::new(EventStore::new(
+            events, snapshots, tombstones, positions,
+        ))
+    }
+
+    fn query_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+        Q: IQuery<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IQueryStore<C, E, A, Q>> {
+        let queries = self.maps::<QueryMap<C, E, Q>>();
+
+        Box::new(QueryStore::<C, E, A, Q>::new(queries))
+    }
+}
+
+#[test]
+fn test_event_store_conformance() {
+    check_event_store(&mut StoreFactory::default());
+}
+
+#[test]
+fn test_query_store_conformance() {
+    check_query_store(&mut StoreFactory::default());
+}
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
 
 use cqrs_es2::{
     example_impl::*,
@@ -11,9 +14,14 @@ use crate::{
     IEventStore,
 };
 
+use super::vip_customer::VipCustomer;
+
 type ThisEventStore =
     EventStore<CustomerCommand, CustomerEvent, Customer>;
 
+type VipEventStore =
+    EventStore<CustomerCommand, CustomerEvent, VipCustomer>;
+
 fn get_metadata() -> HashMap<String, String> {
     let now = "2021-03-18T12:32:45.930Z".to_string();
     let mut metadata = HashMap::new();
@@ -23,11 +31,11 @@ fn get_metadata() -> HashMap<String, String> {
 
 #[test]
 fn test_save_load_events() {
-    let mut store = ThisEventStore::default();
+    let store = ThisEventStore::default();
 
     let id = "test_id_A";
 
-    let stored_events = store.load_events(&id).unwrap();
+    let stored_events = store.load_events(id).unwrap();
     assert_eq!(0, stored_events.len());
 
     let metadata = get_metadata();
@@ -43,7 +51,7 @@ fn test_save_load_events() {
 
     store.save_events(&contexts_0).unwrap();
 
-    let stored_events = store.load_events(&id).unwrap();
+    let stored_events = store.load_events(id).unwrap();
     assert_eq!(stored_events, contexts_0);
 
     let metadata = get_metadata();
@@ -77,20 +85,58 @@ fn test_save_load_events() {
     ];
 
     store.save_events(&contexts_1).unwrap();
-    let stored_events = store.load_events(&id).unwrap();
+    let stored_events = store.load_events(id).unwrap();
 
     contexts_0.append(&mut contexts_1);
     assert_eq!(stored_events, contexts_0);
 }
 
+#[test]
+fn test_save_conflicting_events() {
+    let store = ThisEventStore::default();
+
+    let id = "test_id_A";
+
+    let metadata = get_metadata();
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        metadata.clone(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    let conflicting_contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::EmailUpdated(EmailUpdated {
+            new_email: "test A".to_string(),
+        }),
+        metadata,
+    )];
+
+    let err = store
+        .save_events(&conflicting_contexts)
+        .unwrap_err();
+
+    assert!(err.is_conflict());
+
+    let stored_events = store.load_events(id).unwrap();
+    assert_eq!(stored_events, contexts);
+}
+
 #[test]
 fn test_save_load_snapshots() {
-    let mut store = ThisEventStore::default();
+    let store = ThisEventStore::default();
 
     let id = "test_id_A";
 
     let stored_context = store
-        .load_aggregate_from_snapshot(&id)
+        .load_aggregate_from_snapshot(id)
         .unwrap();
 
     assert_eq!(
@@ -114,7 +160,7 @@ fn test_save_load_snapshots() {
         .unwrap();
 
     let stored_context = store
-        .load_aggregate_from_snapshot(&id)
+        .load_aggregate_from_snapshot(id)
         .unwrap();
 
     assert_eq!(stored_context, context);
@@ -138,8 +184,155 @@ fn test_save_load_snapshots() {
         .unwrap();
 
     let stored_context = store
-        .load_aggregate_from_snapshot(&id)
+        .load_aggregate_from_snapshot(id)
         .unwrap();
 
     assert_eq!(stored_context, context);
 }
+
+#[test]
+fn test_save_consecutive_sequences() {
+    let store = ThisEventStore::default();
+
+    let id = "test_id_A";
+
+    let contexts: Vec<_> = (1..=3)
+        .map(|x| {
+            EventContext::new(
+                id.to_string(),
+                x,
+                CustomerEvent::EmailUpdated(EmailUpdated {
+                    new_email: format!("test {}", x),
+                }),
+                get_metadata(),
+            )
+        })
+        .collect();
+
+    // the sequences of a batch are unique
+    let err = store
+        .save_events(&vec![
+            contexts[0].clone(),
+            contexts[0].clone(),
+        ])
+        .unwrap_err();
+
+    assert!(err.is_conflict());
+    assert!(!store.stream_exists(id).unwrap());
+
+    // the sequences of a stream have no gaps
+    let err = store
+        .save_events(&vec![
+            contexts[0].clone(),
+            contexts[2].clone(),
+        ])
+        .unwrap_err();
+
+    assert!(err.is_conflict());
+    assert!(!store.stream_exists(id).unwrap());
+
+    store
+        .save_events(&vec![contexts[1].clone()])
+        .unwrap_err();
+
+    store
+        .save_events(&contexts[..2].to_vec())
+        .unwrap();
+    store
+        .save_events(&contexts[2..].to_vec())
+        .unwrap();
+
+    assert_eq!(store.load_events(id).unwrap(), contexts);
+
+    let err = store
+        .save_events(&vec![contexts[1].clone()])
+        .unwrap_err();
+
+    assert!(err.is_conflict());
+    assert_eq!(store.load_events(id).unwrap(), contexts);
+}
+
+#[test]
+fn test_share_maps_between_aggregate_types() {
+    let events = Arc::default();
+    let tombstones = Arc::default();
+    let positions = Arc::default();
+
+    let store = ThisEventStore::new(
+        Arc::clone(&events),
+        Arc::default(),
+        Arc::clone(&tombstones),
+        Arc::clone(&positions),
+    );
+    let vip_store = VipEventStore::new(
+        Arc::clone(&events),
+        Arc::default(),
+        Arc::clone(&tombstones),
+        Arc::clone(&positions),
+    );
+
+    let id = "test_id_A";
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        get_metadata(),
+    )];
+
+    let vip_contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_B".to_string(),
+        }),
+        get_metadata(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+    vip_store
+        .save_events(&vip_contexts)
+        .unwrap();
+
+    assert_eq!(store.load_events(id).unwrap(), contexts);
+    assert_eq!(vip_store.load_events(id).unwrap(), vip_contexts);
+
+    // the positions are shared like the events
+    let stored_positions: Vec<_> = store
+        .read_all(0, 100)
+        .unwrap()
+        .into_iter()
+        .chain(vip_store.read_all(0, 100).unwrap())
+        .map(|x| x.position)
+        .collect();
+
+    assert_eq!(stored_positions, [1, 2]);
+    assert_eq!(
+        vip_store
+            .list_aggregate_ids(None, 100)
+            .unwrap(),
+        vec![id.to_string()]
+    );
+
+    store.delete_stream(id).unwrap();
+
+    assert!(store.load_events(id).unwrap().is_empty());
+    assert_eq!(vip_store.load_events(id).unwrap(), vip_contexts);
+
+    store.tombstone_stream(id).unwrap();
+
+    assert!(store.is_tombstoned(id).unwrap());
+    assert!(!vip_store.is_tombstoned(id).unwrap());
+
+    // the tombstones are shared like the events
+    let other_store = ThisEventStore::new(
+        Arc::clone(&events),
+        Arc::default(),
+        Arc::clone(&tombstones),
+        Arc::clone(&positions),
+    );
+
+    assert!(other_store.is_tombstoned(id).unwrap());
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use cqrs_es2::{
     example_impl::*,
     QueryContext,
@@ -8,6 +10,8 @@ use crate::{
     IQueryStore,
 };
 
+use super::vip_customer::VipCustomer;
+
 type ThisQueryStore = QueryStore<
     CustomerCommand,
     CustomerEvent,
@@ -15,13 +19,20 @@ type ThisQueryStore = QueryStore<
     CustomerContactQuery,
 >;
 
+type VipQueryStore = QueryStore<
+    CustomerCommand,
+    CustomerEvent,
+    VipCustomer,
+    CustomerContactQuery,
+>;
+
 #[test]
 fn test_save_load_queries() {
-    let mut store = ThisQueryStore::default();
+    let store = ThisQueryStore::default();
 
     let id = "test_id_A";
 
-    let stored_context = store.load_query(&id).unwrap();
+    let stored_context = store.load_query(id).unwrap();
 
     assert_eq!(
         stored_context,
@@ -42,7 +53,7 @@ fn test_save_load_queries() {
         .save_query(context.clone())
         .unwrap();
 
-    let stored_context = store.load_query(&id).unwrap();
+    let stored_context = store.load_query(id).unwrap();
 
     assert_eq!(stored_context, context);
 
@@ -60,7 +71,113 @@ fn test_save_load_queries() {
         .save_query(context.clone())
         .unwrap();
 
-    let stored_context = store.load_query(&id).unwrap();
+    let stored_context = store.load_query(id).unwrap();
 
     assert_eq!(stored_context, context);
 }
+
+#[test]
+fn test_delete_queries() {
+    let store = ThisQueryStore::default();
+
+    let id = "test_id_A";
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    store.delete_queries().unwrap();
+
+    let stored_context = store.load_query(id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+}
+
+#[test]
+fn test_delete_query() {
+    let store = ThisQueryStore::default();
+
+    let id = "test_id_A";
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    store.delete_query(id).unwrap();
+
+    let stored_context = store.load_query(id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+}
+
+#[test]
+fn test_share_maps_between_aggregate_types() {
+    let queries = Arc::default();
+
+    let store = ThisQueryStore::new(Arc::clone(&queries));
+    let vip_store = VipQueryStore::new(Arc::clone(&queries));
+
+    let id = "test_id_A";
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            ..Default::default()
+        },
+    );
+
+    let vip_context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test vip name".to_string(),
+            ..Default::default()
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+    vip_store
+        .save_query(vip_context.clone())
+        .unwrap();
+
+    assert_eq!(store.load_query(id).unwrap(), context);
+    assert_eq!(vip_store.load_query(id).unwrap(), vip_context);
+
+    store.delete_queries().unwrap();
+
+    assert_eq!(
+        store.load_query(id).unwrap(),
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+    assert_eq!(vip_store.load_query(id).unwrap(), vip_context);
+}
@@ -0,0 +1,50 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    Error,
+    IAggregate,
+    ICommandHandler,
+    IEventHandler,
+};
+
+/// A second aggregate type with the commands and events of the
+/// `Customer`, so that both share the same maps
+#[derive(
+    Debug,
+    PartialEq,
+    Default,
+    Clone,
+    Serialize,
+    Deserialize
+)]
+pub struct VipCustomer {
+    pub customer: Customer,
+}
+
+impl IAggregate<CustomerCommand, CustomerEvent> for VipCustomer {
+    fn aggregate_type() -> &'static str {
+        "vip_customer"
+    }
+}
+
+impl ICommandHandler<CustomerCommand, CustomerEvent> for VipCustomer {
+    fn handle(
+        &self,
+        command: CustomerCommand,
+    ) -> Result<Vec<CustomerEvent>, Error> {
+        self.customer.handle(command)
+    }
+}
+
+impl IEventHandler<CustomerEvent> for VipCustomer {
+    fn apply(
+        &mut self,
+        event: &CustomerEvent,
+    ) {
+        self.customer.apply(event);
+    }
+}
@@ -0,0 +1,19 @@
+use mongodb::bson::{
+    spec::BinarySubtype,
+    Binary,
+};
+
+/// Wraps the binary value of a document
+pub(super) fn to_binary(bytes: Option<Vec<u8>>) -> Option<Binary> {
+    bytes.map(|x| {
+        Binary {
+            subtype: BinarySubtype::Generic,
+            bytes: x,
+        }
+    })
+}
+
+/// Unwraps the binary value of a document
+pub(super) fn from_binary(binary: Option<Binary>) -> Option<Vec<u8>> {
+    binary.map(|x| x.bytes)
+}
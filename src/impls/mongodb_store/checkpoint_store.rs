@@ -0,0 +1,130 @@
+use log::{
+    debug,
+    trace,
+};
+
+use mongodb::{
+    bson::{
+        doc,
+        Document,
+    },
+    options::UpdateOptions,
+    sync::{
+        Collection,
+        Database,
+    },
+};
+
+use crate::{
+    errors::StoreError,
+    repository::ICheckpointStore,
+};
+
+use super::driver_error::driver_error;
+
+/// Sync MongoDB checkpoint store
+pub struct CheckpointStore {
+    db: Database,
+}
+
+impl CheckpointStore {
+    /// Constructor
+    pub fn new(db: Database) -> Self {
+        let x = Self { db };
+
+        trace!("Created new  MongoDB checkpoint store");
+
+        x
+    }
+
+    fn get_checkpoints_collection(&self) -> Collection<Document> {
+        self.db
+            .collection::<Document>("checkpoints")
+    }
+}
+
+impl ICheckpointStore for CheckpointStore {
+    /// Save the last position processed by `subscription`
+    fn save_checkpoint(
+        &self,
+        subscription: &str,
+        position: i64,
+    ) -> Result<(), StoreError> {
+        debug!(
+            "storing checkpoint {} for subscription '{}'",
+            position, subscription
+        );
+
+        match self.get_checkpoints_collection().update_one(
+            doc! { "_id": subscription },
+            doc! { "$set": { "position": position } },
+            UpdateOptions::builder()
+                .upsert(true)
+                .build(),
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert/update checkpoint for \
+                         subscription '{}'",
+                        subscription
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Load the last position processed by `subscription`
+    fn load_checkpoint(
+        &self,
+        subscription: &str,
+    ) -> Result<i64, StoreError> {
+        trace!(
+            "loading checkpoint for subscription '{}'",
+            subscription
+        );
+
+        let entry = match self
+            .get_checkpoints_collection()
+            .find_one(doc! { "_id": subscription }, None)
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load checkpoints table for \
+                         subscription '{}'",
+                        subscription
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        match entry {
+            None => Ok(0),
+            Some(d) => {
+                match d.get_i64("position") {
+                    Ok(x) => Ok(x),
+                    Err(e) => {
+                        Err(StoreError::deserialization(
+                            format!(
+                                "bad position found in checkpoints \
+                                 table for subscription '{}'",
+                                subscription
+                            )
+                            .as_str(),
+                            e,
+                        ))
+                    },
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use mongodb::error::{
+    Error,
+    ErrorKind,
+};
+
+use crate::errors::StoreError;
+
+/// Classifies a MongoDB error into a `StoreError`
+pub(super) fn driver_error(
+    message: &str,
+    e: Error,
+) -> StoreError {
+    match *e.kind {
+        ErrorKind::Io(_) |
+        ErrorKind::ConnectionPoolCleared { .. } |
+        ErrorKind::ServerSelection { .. } => {
+            StoreError::connection(message, e)
+        },
+        _ => StoreError::backend(message, e),
+    }
+}
@@ -1,3 +1,8 @@
+use mongodb::bson::{
+    oid::ObjectId,
+    Binary,
+    DateTime,
+};
 use serde::{
     Deserialize,
     Serialize,
@@ -5,13 +10,48 @@ use serde::{
 use std::{
     collections::HashMap,
     fmt::Debug,
+    time::SystemTime,
 };
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EventDocument {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
     pub aggregate_type: String,
     pub aggregate_id: String,
     pub sequence: i64,
     pub payload: String,
     pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub pending: bool,
+    #[serde(default)]
+    pub position: i64,
+    #[serde(default)]
+    pub event_type: String,
+    #[serde(default = "first_event_version")]
+    pub event_version: i64,
+    #[serde(default)]
+    pub timestamp: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_bytes: Option<Binary>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_bytes: Option<Binary>,
+}
+
+impl EventDocument {
+    /// The commit time of the event, the documents written before
+    /// the timestamps were recorded fall back to the creation time
+    /// of their id
+    pub fn commit_time(&self) -> SystemTime {
+        match self.timestamp {
+            Some(x) => x.to_system_time(),
+            None => self.id.timestamp().to_system_time(),
+        }
+    }
+}
+
+/// The documents written before the schema versions were recorded
+/// hold the first version of their event type
+fn first_event_version() -> i64 {
+    1
 }
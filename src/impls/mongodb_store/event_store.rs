@@ -2,49 +2,124 @@ use log::{
     debug,
     trace,
 };
-use std::marker::PhantomData;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::atomic::{
+        AtomicBool,
+        Ordering,
+    },
+};
 
 use mongodb::{
     bson::{
         doc,
-        Bson,
+        oid::ObjectId,
+        DateTime,
+        Document,
+    },
+    error::{
+        BulkWriteFailure,
+        Error,
+        ErrorKind,
+        WriteFailure,
+    },
+    options::{
+        FindOneAndUpdateOptions,
+        FindOptions,
+        IndexOptions,
+        ReturnDocument,
+        UpdateOptions,
     },
-    options::FindOptions,
     sync::{
+        Client,
+        ClientSession,
         Collection,
         Database,
     },
+    IndexModel,
 };
 
 use cqrs_es2::{
     AggregateContext,
-    Error,
     EventContext,
     IAggregate,
     ICommand,
     IEvent,
 };
 
-use crate::repository::IEventStore;
+use crate::{
+    errors::{
+        ConflictError,
+        StoreError,
+    },
+    repository::{
+        event_type,
+        EncryptionScope,
+        EventUpcasters,
+        IEventStore,
+        IOutboxStore,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        PositionedEvent,
+        StoreSerializer,
+        StreamMetadata,
+        TimestampedEvent,
+    },
+};
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
 
 use super::{
+    binary::{
+        from_binary,
+        to_binary,
+    },
+    driver_error::driver_error,
     event_document::EventDocument,
     snapshot_document::SnapshotDocument,
 };
 
+/// MongoDB error code for duplicate keys
+static DUPLICATE_KEY: i32 = 11000;
+
 /// Sync MongoDB event store
+///
+/// The events of a commit are inserted in a multi-document
+/// transaction, the store needs a replica set or a sharded cluster,
+/// a single node replica set is enough. The outbox is kept as a
+/// `pending` flag on the event documents.
 pub struct EventStore<C: ICommand, E: IEvent, A: IAggregate<C, E>> {
+    client: Client,
     db: Database,
+    has_events_index: AtomicBool,
+    has_outbox_index: AtomicBool,
+    with_outbox: bool,
+    upcasters: EventUpcasters,
+    event_type: Option<fn(&E) -> String>,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A)>,
 }
 
 impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
     EventStore<C, E, A>
 {
-    /// Constructor
-    pub fn new(db: Database) -> Self {
+    /// Constructor over the database `db_name` of `client`, the
+    /// stores created from the same `Client` share its connection
+    /// pool
+    pub fn new(
+        client: &Client,
+        db_name: &str,
+    ) -> Self {
         let x = Self {
-            db,
+            client: client.clone(),
+            db: client.database(db_name),
+            has_events_index: AtomicBool::new(false),
+            has_outbox_index: AtomicBool::new(false),
+            with_outbox: false,
+            upcasters: EventUpcasters::default(),
+            event_type: None,
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
         };
 
@@ -53,6 +128,129 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
         x
     }
 
+    /// Enables the transactional outbox, new events are flagged as
+    /// pending in the same document for an `OutboxRelay` to
+    /// deliver
+    pub fn with_outbox(mut self) -> Self {
+        self.with_outbox = true;
+        self
+    }
+
+    /// Upcasts the stored payloads with `upcasters` before they are
+    /// deserialized
+    pub fn with_upcasters(
+        mut self,
+        upcasters: EventUpcasters,
+    ) -> Self {
+        self.upcasters = upcasters;
+        self
+    }
+
+    /// Names the event types stored with the events with
+    /// `event_type` instead of their enum variants
+    pub fn with_event_type(
+        mut self,
+        event_type: fn(&E) -> String,
+    ) -> Self {
+        self.event_type = Some(event_type);
+        self
+    }
+
+    /// Stores the payloads, metadata and snapshots with
+    /// `serializer`, the binary formats use binary fields next to
+    /// the JSON ones
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
+
+    /// Compresses the payloads, metadata and snapshots longer than
+    /// `threshold` bytes with `compressor`, the uncompressed ones
+    /// keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
+
+    /// Encrypts the event payloads and the snapshots with
+    /// `encryption`, the events record the id of their key in their
+    /// metadata
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
+    }
+
+    /// Loads the payload and metadata of an event document, its
+    /// binary fields win over the JSON ones
+    fn deserialize_event(
+        &self,
+        d: &mut EventDocument,
+    ) -> Result<(E, HashMap<String, String>), StoreError> {
+        let scope = EncryptionScope::Event {
+            aggregate_type: &d.aggregate_type,
+            aggregate_id: &d.aggregate_id,
+            sequence: d.sequence,
+        };
+
+        let payload = match self.serializer.deserialize_text_event(
+            &scope,
+            &self.upcasters,
+            &d.event_type,
+            std::mem::take(&mut d.payload),
+            from_binary(d.payload_bytes.take()),
+            d.event_version,
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "bad payload found in events table for \
+                         aggregate id '{}'",
+                        &d.aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let metadata = match from_binary(d.metadata_bytes.take()) {
+            Some(x) => {
+                match self
+                    .serializer
+                    .deserialize_json(None, Some(x))
+                {
+                    Ok(x) => x,
+                    Err(e) => {
+                        return Err(StoreError::deserialization(
+                            format!(
+                                "bad metadata found in events table \
+                                 for aggregate id '{}'",
+                                &d.aggregate_id
+                            )
+                            .as_str(),
+                            e,
+                        ));
+                    },
+                }
+            },
+            None => std::mem::take(&mut d.metadata),
+        };
+
+        Ok((payload, metadata))
+    }
+
     fn get_events_collection(&self) -> Collection<EventDocument> {
         self.db
             .collection::<EventDocument>("events")
@@ -64,6 +262,160 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
         self.db
             .collection::<SnapshotDocument>("snapshots")
     }
+
+    fn get_tombstones_collection(&self) -> Collection<Document> {
+        self.db
+            .collection::<Document>("tombstones")
+    }
+
+    /// Makes sure the events collection rejects duplicate sequences
+    fn create_events_index(&self) -> Result<(), StoreError> {
+        if self.has_events_index.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let indexes = vec![
+            IndexModel::builder()
+                .keys(doc! {
+                    "aggregate_type": 1,
+                    "aggregate_id": 1,
+                    "sequence": 1,
+                })
+                .options(
+                    IndexOptions::builder()
+                        .unique(true)
+                        .build(),
+                )
+                .build(),
+            IndexModel::builder()
+                .keys(doc! {
+                    "aggregate_type": 1,
+                    "position": 1,
+                })
+                .build(),
+        ];
+
+        match self
+            .get_events_collection()
+            .create_indexes(indexes, None)
+        {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to create events index",
+                    e,
+                ));
+            },
+        };
+
+        debug!("Created events index");
+
+        self.has_events_index
+            .store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Makes sure the pending events are found without scanning
+    /// the events collection
+    fn create_outbox_index(&self) -> Result<(), StoreError> {
+        if self.has_outbox_index.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let index = IndexModel::builder()
+            .keys(doc! {
+                "aggregate_type": 1,
+                "_id": 1,
+            })
+            .options(
+                IndexOptions::builder()
+                    .name("outbox".to_string())
+                    .partial_filter_expression(doc! { "pending": true })
+                    .build(),
+            )
+            .build();
+
+        match self
+            .get_events_collection()
+            .create_index(index, None)
+        {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to create outbox index",
+                    e,
+                ));
+            },
+        };
+
+        debug!("Created outbox index");
+
+        self.has_outbox_index
+            .store(true, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Reserves `count` consecutive global positions within the
+    /// transaction of `session` and returns the first one
+    fn reserve_positions(
+        &self,
+        count: usize,
+        session: &mut ClientSession,
+    ) -> Result<i64, Error> {
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+
+        let res = match self
+            .db
+            .collection::<Document>("counters")
+            .find_one_and_update_with_session(
+                doc! { "_id": "events" },
+                doc! { "$inc": { "position": count as i64 } },
+                options,
+                session,
+            ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(e);
+            },
+        };
+
+        match res.as_ref().and_then(|x| x.get_i64("position").ok()) {
+            Some(x) => Ok(x - count as i64 + 1),
+            None => {
+                Err(Error::custom(
+                    "bad event positions counter document"
+                        .to_string(),
+                ))
+            },
+        }
+    }
+}
+
+/// Returns the position of the first document rejected for a
+/// duplicate key, if any
+fn find_duplicate_key(kind: &ErrorKind) -> Option<usize> {
+    match kind {
+        ErrorKind::BulkWrite(BulkWriteFailure {
+            write_errors: Some(errors),
+            ..
+        }) => {
+            errors
+                .iter()
+                .find(|x| x.code == DUPLICATE_KEY)
+                .map(|x| x.index)
+        },
+        ErrorKind::Write(WriteFailure::WriteError(e))
+            if e.code == DUPLICATE_KEY =>
+        {
+            Some(0)
+        },
+        _ => None,
+    }
 }
 
 impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
@@ -71,9 +423,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 {
     /// Save new events
     fn save_events(
-        &mut self,
+        &self,
         contexts: &Vec<EventContext<C, E>>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         if contexts.len() == 0 {
             trace!("Skip saving zero contexts");
             return Ok(());
@@ -93,58 +445,163 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             &aggregate_id
         );
 
+        self.create_events_index()?;
+
+        let timestamp = DateTime::now();
+
         let mut all_docs = Vec::new();
-        for context in contexts {
+        for context in contexts.iter() {
             let payload =
-                match serde_json::to_string(&context.payload) {
+                match serde_json::to_value(&context.payload) {
                     Ok(x) => x,
                     Err(e) => {
-                        return Err(Error::new(
+                        return Err(StoreError::serialization(
                             format!(
                                 "unable to serialize the event \
-                                 payload for aggregate id '{}' with \
-                                 error: {}",
-                                &aggregate_id, e
+                                 payload for aggregate id '{}'",
+                                &aggregate_id
                             )
                             .as_str(),
+                            e,
                         ));
                     },
                 };
 
+            let event_type = match self.event_type {
+                Some(x) => x(&context.payload),
+                None => event_type(&payload),
+            };
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &aggregate_id,
+                sequence: context.sequence,
+            };
+
+            let (payload, metadata) = match self
+                .serializer
+                .encrypt_event(&scope, payload, &context.metadata)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to encrypt the event payload for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let payload = match self.serializer.split_text(payload) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to serialize the event payload \
+                             for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            // the JSON metadata stay a document
+            let metadata = match self.serializer.to_json(&metadata) {
+                Ok((_, Some(x))) => (HashMap::new(), Some(x)),
+                Ok(_) => (metadata, None),
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to serialize the event metadata \
+                             for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
             all_docs.push(EventDocument {
+                id: ObjectId::new(),
                 aggregate_type: aggregate_type.to_string(),
                 aggregate_id: aggregate_id.to_string(),
                 sequence: context.sequence,
-                payload,
-                metadata: context.metadata.clone(),
+                event_version: self
+                    .upcasters
+                    .event_version(&event_type),
+                event_type,
+                payload: payload.0,
+                metadata: metadata.0,
+                pending: self.with_outbox,
+                // assigned in the transaction
+                position: 0,
+                timestamp: Some(timestamp),
+                payload_bytes: to_binary(payload.1),
+                metadata_bytes: to_binary(metadata.1),
             });
         }
 
-        match self
-            .get_events_collection()
-            .insert_many(all_docs, None)
-        {
-            Ok(x) => {
-                if x.inserted_ids.len() != contexts.len() {
-                    return Err(Error::new(
-                        format!(
-                            "documents size mismatch, expected {} \
-                             only inserted {}",
-                            contexts.len(),
-                            x.inserted_ids.len()
-                        )
-                        .as_str(),
-                    ));
+        // all events are inserted or none, the positions counter
+        // is updated in the same transaction so concurrent commits
+        // conflict on it and are retried one after the other, the
+        // positions become visible in order
+        let mut session = match self.client.start_session(None) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to start a session",
+                    e,
+                ));
+            },
+        };
+
+        let events = self.get_events_collection();
+
+        let res = session.with_transaction(
+            |session| {
+                let first_position = self
+                    .reserve_positions(all_docs.len(), session)?;
+
+                for (i, x) in all_docs.iter_mut().enumerate() {
+                    x.position = first_position + i as i64;
                 }
+
+                events.insert_many_with_session(
+                    &all_docs,
+                    None,
+                    session,
+                )
             },
+            None,
+        );
+
+        match res {
+            Ok(_) => {},
             Err(e) => {
-                return Err(Error::new(
+                if let Some(i) = find_duplicate_key(&e.kind) {
+                    return Err(ConflictError::new(
+                        aggregate_type,
+                        &aggregate_id,
+                        contexts[i].sequence,
+                    )
+                    .into());
+                }
+
+                return Err(driver_error(
                     format!(
                         "unable to insert new events for aggregate \
-                         id '{}' with error: {}",
-                        &aggregate_id, e
+                         id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -152,11 +609,14 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         Ok(())
     }
 
-    /// Load all events for a particular `aggregate_id`
-    fn load_events(
-        &mut self,
+    /// Load the events for a particular `aggregate_id` within a
+    /// range of sequences along with their commit timestamps
+    fn load_timestamped_events_range(
+        &self,
         aggregate_id: &str,
-    ) -> Result<Vec<EventContext<C, E>>, Error> {
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -172,18 +632,23 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             doc! {
                 "aggregate_type": aggregate_type,
                 "aggregate_id": aggregate_id,
+                "sequence": {
+                    "$gte": from_sequence,
+                    "$lte": to_sequence.unwrap_or(i64::MAX),
+                },
             },
             find_options,
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load events table for aggregate \
-                         id '{}' with error: {}",
-                        aggregate_id, e
+                         id '{}'",
+                        aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -191,53 +656,414 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         let mut result = Vec::new();
 
         for row in cursor {
-            let d = match row {
+            let mut d = match row {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(driver_error(
                         format!(
                             "unable to load next entry from events \
-                             table for aggregate id '{}' with \
-                             error: {}",
-                            aggregate_id, e
+                             table for aggregate id '{}'",
+                            aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-            let payload =
-                match serde_json::from_str(d.payload.as_str()) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        return Err(Error::new(
-                            format!(
-                                "bad payload found in events table \
-                                 for aggregate id '{}' with error: \
-                                 {}",
-                                aggregate_id, e
-                            )
-                            .as_str(),
-                        ));
-                    },
-                };
+            let (payload, metadata) = self.deserialize_event(&mut d)?;
 
-            result.push(EventContext::new(
-                aggregate_id.to_string(),
-                d.sequence,
-                payload,
-                d.metadata,
+            result.push(TimestampedEvent::new(
+                d.commit_time(),
+                EventContext::new(
+                    aggregate_id.to_string(),
+                    d.sequence,
+                    payload,
+                    metadata,
+                ),
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Load up to `limit` events of all aggregates of this type in
+    /// commit order, starting at the global position
+    /// `from_position`
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "reading up to '{}' events from position {}",
+            limit,
+            from_position
+        );
+
+        let find_options = FindOptions::builder()
+            .sort(doc! { "position": 1 })
+            .limit(limit as i64)
+            .build();
+
+        let cursor = match self.get_events_collection().find(
+            doc! {
+                "aggregate_type": aggregate_type,
+                "position": { "$gte": from_position },
+            },
+            find_options,
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load events table from position \
+                         {}",
+                        from_position
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let mut result = Vec::new();
+
+        for row in cursor {
+            let mut d = match row {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(driver_error(
+                        "unable to load next entry from eventstable",
+                        e,
+                    ));
+                },
+            };
+
+            let (payload, metadata) = self.deserialize_event(&mut d)?;
+
+            result.push(PositionedEvent::new(
+                d.position,
+                d.commit_time(),
+                EventContext::new(
+                    d.aggregate_id,
+                    d.sequence,
+                    payload,
+                    metadata,
+                ),
             ));
         }
 
         Ok(result)
     }
 
+    /// List up to `limit` ids of the aggregates of this type in
+    /// ascending order, starting after `after_aggregate_id`
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "listing up to '{}' aggregate ids after '{:?}'",
+            limit,
+            after_aggregate_id
+        );
+
+        let pipeline = vec![
+            doc! {
+                "$match": {
+                    "aggregate_type": aggregate_type,
+                    "aggregate_id": {
+                        "$gt": after_aggregate_id.unwrap_or(""),
+                    },
+                },
+            },
+            doc! { "$group": { "_id": "$aggregate_id" } },
+            doc! { "$sort": { "_id": 1 } },
+            doc! { "$limit": limit as i64 },
+        ];
+
+        let cursor = match self
+            .get_events_collection()
+            .aggregate(pipeline, None)
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to list aggregate ids from events table",
+                    e,
+                ));
+            },
+        };
+
+        let mut result = Vec::new();
+
+        for row in cursor {
+            let d = match row {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(driver_error(
+                        "unable to load next entry from events table",
+                        e,
+                    ));
+                },
+            };
+
+            match d.get_str("_id") {
+                Ok(x) => result.push(x.to_string()),
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        "bad aggregate id found in events table",
+                        e,
+                    ));
+                },
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Load the metadata of the event stream of a particular
+    /// `aggregate_id`
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "loading stream metadata for aggregate id '{}'",
+            aggregate_id
+        );
+
+        // the documents written before the timestamps were recorded
+        // fall back to the creation time of their id
+        let timestamp = doc! {
+            "$ifNull": ["$timestamp", { "$toDate": "$_id" }],
+        };
+
+        let pipeline = vec![
+            doc! {
+                "$match": {
+                    "aggregate_type": aggregate_type,
+                    "aggregate_id": aggregate_id,
+                },
+            },
+            doc! {
+                "$group": {
+                    "_id": null,
+                    "version": { "$max": "$sequence" },
+                    "event_count": { "$sum": 1_i64 },
+                    "first_timestamp": { "$min": timestamp.clone() },
+                    "last_timestamp": { "$max": timestamp },
+                },
+            },
+        ];
+
+        let mut cursor = match self
+            .get_events_collection()
+            .aggregate(pipeline, None)
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load stream metadata for \
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let d = match cursor.next() {
+            Some(Ok(x)) => x,
+            Some(Err(e)) => {
+                return Err(driver_error(
+                    "unable to load next entry from events table",
+                    e,
+                ));
+            },
+            None => {
+                return Ok(None);
+            },
+        };
+
+        let (version, event_count, first_timestamp, last_timestamp) =
+            match (
+                d.get_i64("version"),
+                d.get_i64("event_count"),
+                d.get_datetime("first_timestamp"),
+                d.get_datetime("last_timestamp"),
+            ) {
+                (Ok(v), Ok(n), Ok(f), Ok(l)) => (v, n, *f, *l),
+                _ => {
+                    return Err(StoreError::Deserialization {
+                        message: format!(
+                            "bad stream metadata found in events \
+                             table for aggregate id '{}'",
+                            &aggregate_id
+                        ),
+                        source: None,
+                    });
+                },
+            };
+
+        let snapshot = match self
+            .get_snapshots_collection()
+            .find_one(
+                doc! {
+                    "aggregate_type": aggregate_type.to_string(),
+                    "aggregate_id": aggregate_id.to_string(),
+                },
+                None,
+            ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to check snapshots table for \
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(Some(StreamMetadata::new(
+            aggregate_id,
+            version,
+            event_count,
+            first_timestamp.to_system_time(),
+            last_timestamp.to_system_time(),
+            snapshot.map(|x| x.version),
+        )))
+    }
+
+    /// Delete all events of a particular `aggregate_id` along with
+    /// its snapshot, the outbox entries are flags on the events
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting events and snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        match self.get_events_collection().delete_many(
+            doc! {
+                "aggregate_type": aggregate_type,
+                "aggregate_id": aggregate_id,
+            },
+            None,
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete the events of aggregate id \
+                         '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        self.delete_aggregate_snapshot(aggregate_id)
+    }
+
+    /// Mark the stream of a particular `aggregate_id` as tombstoned
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!("tombstoning aggregate id '{}'", aggregate_id);
+
+        match self.get_tombstones_collection().update_one(
+            doc! {
+                "aggregate_type": aggregate_type,
+                "aggregate_id": aggregate_id,
+            },
+            doc! {
+                "$setOnInsert": { "timestamp": DateTime::now() },
+            },
+            UpdateOptions::builder()
+                .upsert(true)
+                .build(),
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert tombstone for aggregate id \
+                         '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Check whether the stream of a particular `aggregate_id` was
+    /// tombstoned
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "checking tombstone of aggregate id '{}'",
+            aggregate_id
+        );
+
+        match self.get_tombstones_collection().find_one(
+            doc! {
+                "aggregate_type": aggregate_type,
+                "aggregate_id": aggregate_id,
+            },
+            None,
+        ) {
+            Ok(x) => Ok(x.is_some()),
+            Err(e) => {
+                Err(driver_error(
+                    format!(
+                        "unable to load tombstones table for \
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ))
+            },
+        }
+    }
+
     /// save a new aggregate snapshot
     fn save_aggregate_snapshot(
-        &mut self,
+        &self,
         context: AggregateContext<C, E, A>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
 
         let aggregate_id = context.aggregate_id;
@@ -247,97 +1073,92 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             &aggregate_id
         );
 
-        let payload = match serde_json::to_string(&context.payload) {
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id: &aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .encrypt_to_text(&scope, &context.payload)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize aggregate snapshot for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        let col = self.get_snapshots_collection();
+        match self.get_snapshots_collection().update_one(
+            doc! {
+                "aggregate_type": aggregate_type.to_string(),
+                "aggregate_id": aggregate_id.to_string(),
+            },
+            doc! {
+                "$set": {
+                    "version": context.version,
+                    "payload": payload.0,
+                    "payload_bytes": to_binary(payload.1),
+                }
+            },
+            UpdateOptions::builder()
+                .upsert(true)
+                .build(),
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert/update snapshot for \
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
 
-        match context.version {
-            1 => {
-                match col.insert_one(
-                    SnapshotDocument {
-                        aggregate_type: aggregate_type.to_string(),
-                        aggregate_id: aggregate_id.to_string(),
-                        version: 1,
-                        payload,
-                    },
-                    None,
-                ) {
-                    Ok(x) => {
-                        match x.inserted_id {
-                            Bson::ObjectId(id) => {
-                                if id.to_string().is_empty() {
-                                    return Err(Error::new(
-                                        format!(
-                                            "insert snapshot got \
-                                             empty document id",
-                                        )
-                                        .as_str(),
-                                    ));
-                                }
-                            },
-                            _ => {
-                                return Err(Error::new(
-                                    format!(
-                                        "unexpected return value \
-                                         from insert snapshot {:?}",
-                                        x.inserted_id
-                                    )
-                                    .as_str(),
-                                ));
-                            },
-                        };
-                    },
-                    Err(e) => {
-                        return Err(Error::new(
-                            format!(
-                                "unable to insert/update snapshot \
-                                 for aggregate id '{}' with error: \
-                                 {}",
-                                &aggregate_id, e
-                            )
-                            .as_str(),
-                        ));
-                    },
-                };
+        Ok(())
+    }
+
+    /// Delete the snapshot of a particular `aggregate_id`
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting the snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        match self.get_snapshots_collection().delete_one(
+            doc! {
+                "aggregate_type": aggregate_type,
+                "aggregate_id": aggregate_id,
             },
-            _ => {
-                match col.update_one(
-                    doc! {
-                        "aggregate_type": aggregate_type.to_string(),
-                        "aggregate_id": aggregate_id.to_string(),
-                    },
-                    doc! {
-                        "$set": {
-                            "version": context.version,
-                            "payload": payload,
-                        }
-                    },
-                    None,
-                ) {
-                    Ok(_) => {},
-                    Err(e) => {
-                        return Err(Error::new(
-                            format!(
-                                "unable to update snapshot for \
-                                 aggregate id '{}' with error: {}",
-                                &aggregate_id, e
-                            )
-                            .as_str(),
-                        ));
-                    },
-                };
+            None,
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete snapshot for aggregate id \
+                         '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
             },
         };
 
@@ -346,9 +1167,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 
     /// Load aggregate at current state from snapshots
     fn load_aggregate_from_snapshot(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<AggregateContext<C, E, A>, Error> {
+    ) -> Result<AggregateContext<C, E, A>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -367,13 +1188,14 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to check snapshots table for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -395,16 +1217,29 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             },
         };
 
-        let payload = match serde_json::from_str(d.payload.as_str()) {
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .decrypt_text(
+                &scope,
+                d.payload,
+                from_binary(d.payload_bytes),
+            )
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in snapshots table for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -416,3 +1251,111 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         ))
     }
 }
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IOutboxStore<C, E, A>
+    for EventStore<C, E, A>
+{
+    /// Load up to `limit` pending events in commit order
+    fn load_pending_events(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!("loading up to '{}' pending outbox events", limit);
+
+        self.create_outbox_index()?;
+
+        // ids are created in commit order by the writing client
+        let find_options = FindOptions::builder()
+            .sort(doc! { "_id": 1 })
+            .limit(limit as i64)
+            .build();
+
+        let cursor = match self.get_events_collection().find(
+            doc! {
+                "aggregate_type": aggregate_type,
+                "pending": true,
+            },
+            find_options,
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to load pending events",
+                    e,
+                ));
+            },
+        };
+
+        let mut result = Vec::new();
+
+        for row in cursor {
+            let mut d = match row {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(driver_error(
+                        "unable to load next pending event",
+                        e,
+                    ));
+                },
+            };
+
+            let (payload, metadata) = self.deserialize_event(&mut d)?;
+
+            result.push(EventContext::new(
+                d.aggregate_id,
+                d.sequence,
+                payload,
+                metadata,
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Mark events as delivered so that they are not loaded again
+    fn mark_events_delivered(
+        &self,
+        contexts: &[EventContext<C, E>],
+    ) -> Result<(), StoreError> {
+        if contexts.is_empty() {
+            return Ok(());
+        }
+
+        let aggregate_type = A::aggregate_type();
+
+        debug!("marking '{}' outbox events delivered", contexts.len());
+
+        let events: Vec<Document> = contexts
+            .iter()
+            .map(|x| {
+                doc! {
+                    "aggregate_id": x.aggregate_id.clone(),
+                    "sequence": x.sequence,
+                }
+            })
+            .collect();
+
+        match self.get_events_collection().update_many(
+            doc! {
+                "aggregate_type": aggregate_type,
+                "$or": events,
+            },
+            doc! {
+                "$set": { "pending": false },
+            },
+            None,
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to mark pending events delivered",
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+}
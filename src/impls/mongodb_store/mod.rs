@@ -1,10 +1,14 @@
 //!
 //! MongoDB store
 
+pub use checkpoint_store::CheckpointStore;
 pub use event_store::EventStore;
 pub use query_store::QueryStore;
 
+mod binary;
 mod event_document;
+mod checkpoint_store;
+mod driver_error;
 mod event_store;
 mod query_document;
 mod query_store;
@@ -1,3 +1,4 @@
+use mongodb::bson::Binary;
 use serde::{
     Deserialize,
     Serialize,
@@ -11,4 +12,6 @@ pub struct QueryDocument {
     pub query_type: String,
     pub version: i64,
     pub payload: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_bytes: Option<Binary>,
 }
@@ -25,12 +25,28 @@ use cqrs_es2::{
     QueryContext,
 };
 
-use crate::repository::{
-    IEventDispatcher,
-    IQueryStore,
+use crate::{
+    errors::StoreError,
+    repository::{
+        EncryptionScope,
+        IEventDispatcher,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        IQueryStore,
+        StoreSerializer,
+    },
 };
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
 
-use super::query_document::QueryDocument;
+use super::{
+    binary::{
+        from_binary,
+        to_binary,
+    },
+    driver_error::driver_error,
+    query_document::QueryDocument,
+};
 
 /// Sync MongoDB query store
 pub struct QueryStore<
@@ -40,6 +56,8 @@ pub struct QueryStore<
     Q: IQuery<C, E>,
 > {
     db: Database,
+    query_type: String,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A, Q)>,
 }
 
@@ -54,6 +72,8 @@ impl<
     pub fn new(db: Database) -> Self {
         let x = Self {
             db,
+            query_type: Q::query_type().to_string(),
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
         };
 
@@ -62,6 +82,48 @@ impl<
         x
     }
 
+    /// Stores the queries under a versioned query type, e.g. to
+    /// rebuild a new version of the queries next to the one in use
+    pub fn with_query_version(
+        mut self,
+        version: &str,
+    ) -> Self {
+        self.query_type = format!("{}_{}", Q::query_type(), version);
+        self
+    }
+
+    /// Stores the queries with `serializer`, the binary formats use
+    /// a binary field next to the JSON one
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
+
+    /// Compresses the queries longer than `threshold` bytes
+    /// with `compressor`, the uncompressed ones keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
+
+    /// Encrypts the queries with `encryption`, the queries stored
+    /// in clear keep loading
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
+    }
+
     fn get_queries_collection(&self) -> Collection<QueryDocument> {
         self.db
             .collection::<QueryDocument>("queries")
@@ -77,11 +139,11 @@ impl<
 {
     /// saves the updated query
     fn save_query(
-        &mut self,
+        &self,
         context: QueryContext<C, E, Q>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         let aggregate_id = context.aggregate_id;
 
@@ -90,16 +152,26 @@ impl<
             query_type, &aggregate_id
         );
 
-        let payload = match serde_json::to_string(&context.payload) {
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id: &aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .encrypt_to_text(&scope, &context.payload)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize the payload of query \
-                         '{}' with aggregate id '{}', error: {}",
-                        &query_type, &aggregate_id, e,
+                         '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -114,7 +186,8 @@ impl<
                         aggregate_id: aggregate_id.clone(),
                         query_type: query_type.to_string(),
                         version: context.version,
-                        payload,
+                        payload: payload.0,
+                        payload_bytes: to_binary(payload.1),
                     },
                     None,
                 ) {
@@ -122,35 +195,35 @@ impl<
                         match x.inserted_id {
                             Bson::ObjectId(id) => {
                                 if id.to_string().is_empty() {
-                                    return Err(Error::new(
-                                        format!(
-                                            "insert query got empty \
-                                             document id ",
-                                        )
-                                        .as_str(),
-                                    ));
+                                    return Err(StoreError::Backend {
+                                        message: "insert query got \
+                                                  empty document id"
+                                            .to_string(),
+                                        source: None,
+                                    });
                                 }
                             },
                             _ => {
-                                return Err(Error::new(
-                                    format!(
+                                return Err(StoreError::Backend {
+                                    message: format!(
                                         "unexpected return value \
                                          from insert query {:?}",
                                         x.inserted_id
-                                    )
-                                    .as_str(),
-                                ));
+                                    ),
+                                    source: None,
+                                });
                             },
                         };
                     },
                     Err(e) => {
-                        return Err(Error::new(
+                        return Err(driver_error(
                             format!(
                                 "unable to insert new query for \
-                                 aggregate id '{}' with error: {}",
-                                &aggregate_id, e
+                                 aggregate id '{}'",
+                                &aggregate_id
                             )
                             .as_str(),
+                            e,
                         ));
                     },
                 };
@@ -165,20 +238,22 @@ impl<
                     doc! {
                         "$set": {
                             "version": context.version,
-                            "payload": payload,
+                            "payload": payload.0,
+                            "payload_bytes": to_binary(payload.1),
                         }
                     },
                     None,
                 ) {
                     Ok(_) => {},
                     Err(e) => {
-                        return Err(Error::new(
+                        return Err(driver_error(
                             format!(
                                 "unable to update query {} for \
-                                 aggregate id '{}' with error: {}",
-                                &query_type, &aggregate_id, e
+                                 aggregate id '{}'",
+                                &query_type, &aggregate_id
                             )
                             .as_str(),
+                            e,
                         ));
                     },
                 };
@@ -190,11 +265,11 @@ impl<
 
     /// loads the most recent query
     fn load_query(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<QueryContext<C, E, Q>, Error> {
+    ) -> Result<QueryContext<C, E, Q>, StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         trace!(
             "loading query '{}' for aggregate id '{}'",
@@ -212,13 +287,14 @@ impl<
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load queries table for query \
-                         '{}' and aggregate id '{}' with error: {}",
-                        query_type, aggregate_id, e
+                         '{}' and aggregate id '{}'",
+                        query_type, aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -241,17 +317,30 @@ impl<
             },
         };
 
-        let payload = match serde_json::from_str(d.payload.as_str()) {
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .decrypt_text(
+                &scope,
+                d.payload,
+                from_binary(d.payload_bytes),
+            )
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in queries table for \
-                         query '{}' for aggregate id '{}' with \
-                         error: {}",
-                        query_type, aggregate_id, e
+                         query '{}' for aggregate id '{}'",
+                        query_type, aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -262,6 +351,73 @@ impl<
             payload,
         ))
     }
+    /// deletes the query of a particular `aggregate_id`
+    fn delete_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!(
+            "deleting query '{}' for aggregate id '{}'",
+            query_type, aggregate_id
+        );
+
+        match self.get_queries_collection().delete_one(
+            doc! {
+                "aggregate_type": aggregate_type.to_string(),
+                "aggregate_id": aggregate_id.to_string(),
+                "query_type": query_type.clone(),
+            },
+            None,
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete query '{}' for aggregate \
+                         id '{}'",
+                        &query_type, aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// deletes all queries of the query type
+    fn delete_queries(&self) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!("deleting all queries '{}'", query_type);
+
+        match self.get_queries_collection().delete_many(
+            doc! {
+                "aggregate_type": aggregate_type.to_string(),
+                "query_type": query_type.clone(),
+            },
+            None,
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete queries '{}'",
+                        &query_type
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
 }
 
 impl<
@@ -272,10 +428,10 @@ impl<
     > IEventDispatcher<C, E> for QueryStore<C, E, A, Q>
 {
     fn dispatch(
-        &mut self,
+        &self,
         aggregate_id: &str,
         events: &Vec<EventContext<C, E>>,
     ) -> Result<(), Error> {
-        self.dispatch_events(aggregate_id, events)
+        Ok(self.dispatch_events(aggregate_id, events)?)
     }
 }
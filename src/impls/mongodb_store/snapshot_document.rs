@@ -1,3 +1,4 @@
+use mongodb::bson::Binary;
 use serde::{
     Deserialize,
     Serialize,
@@ -10,4 +11,6 @@ pub struct SnapshotDocument {
     pub aggregate_id: String,
     pub version: i64,
     pub payload: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_bytes: Option<Binary>,
 }
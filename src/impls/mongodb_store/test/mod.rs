@@ -1,6 +1,12 @@
 #[cfg(test)]
 mod common;
 
+#[cfg(test)]
+mod test_checkpoint_store;
+
+#[cfg(test)]
+mod test_conformance;
+
 #[cfg(test)]
 mod test_event_store;
 
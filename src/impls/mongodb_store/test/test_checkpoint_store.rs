@@ -0,0 +1,37 @@
+use mongodb::{
+    options::ClientOptions,
+    sync::Client,
+};
+
+use crate::{
+    mongodb_store::CheckpointStore,
+    ICheckpointStore,
+};
+
+use super::common::*;
+
+#[test]
+fn test_save_load_checkpoints() {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    let client = Client::with_options(client_options).unwrap();
+
+    let db = client.database("test");
+
+    let store = CheckpointStore::new(db);
+
+    let subscription = uuid::Uuid::new_v4().to_string();
+
+    assert_eq!(store.load_checkpoint(&subscription).unwrap(), 0);
+
+    store
+        .save_checkpoint(&subscription, 5).unwrap();
+    assert_eq!(store.load_checkpoint(&subscription).unwrap(), 5);
+
+    store
+        .save_checkpoint(&subscription, 7).unwrap();
+    assert_eq!(store.load_checkpoint(&subscription).unwrap(), 7);
+}
@@ -0,0 +1,69 @@
+use mongodb::{
+    options::ClientOptions,
+    sync::Client,
+};
+
+use cqrs_es2::{
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+};
+
+use crate::{
+    conformance::*,
+    mongodb_store::{
+        EventStore,
+        QueryStore,
+    },
+    IEventStore,
+    IQueryStore,
+};
+
+use super::common::*;
+
+struct StoreFactory;
+
+fn get_client() -> Client {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    Client::with_options(client_options).unwrap()
+}
+
+impl IStoreFactory for StoreFactory {
+    fn event_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IEventStore<C, E, A>> {
+        Box::new(EventStore::new(&get_client(), "test"))
+    }
+
+    fn query_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+        Q: IQuery<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IQueryStore<C, E, A, Q>> {
+        Box::new(QueryStore::<C, E, A, Q>::new(
+            get_client().database("test"),
+        ))
+    }
+}
+
+#[test]
+fn test_event_store_conformance() {
+    check_event_store(&mut StoreFactory);
+}
+
+#[test]
+fn test_query_store_conformance() {
+    check_query_store(&mut StoreFactory);
+}
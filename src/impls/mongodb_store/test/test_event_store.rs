@@ -14,6 +14,7 @@ use cqrs_es2::{
 use crate::{
     mongodb_store::EventStore,
     IEventStore,
+    IOutboxStore,
 };
 
 use super::common::*;
@@ -37,9 +38,7 @@ fn test_save_load_events() {
 
     let client = Client::with_options(client_options).unwrap();
 
-    let db = client.database("test");
-
-    let mut store = ThisEventStore::new(db);
+    let store = ThisEventStore::new(&client, "test");
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -100,7 +99,52 @@ fn test_save_load_events() {
 }
 
 #[test]
-fn test_save_load_snapshots() {
+fn test_save_conflicting_events() {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    let client = Client::with_options(client_options).unwrap();
+
+    let store = ThisEventStore::new(&client, "test");
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        metadata.clone(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    let conflicting_contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::EmailUpdated(EmailUpdated {
+            new_email: "test A".to_string(),
+        }),
+        metadata,
+    )];
+
+    let err = store
+        .save_events(&conflicting_contexts)
+        .unwrap_err();
+
+    assert!(err.is_conflict());
+
+    let stored_events = store.load_events(&id).unwrap();
+    assert_eq!(stored_events, contexts);
+}
+
+#[test]
+fn test_save_events_atomically() {
     let mut client_options =
         ClientOptions::parse(CONNECTION_STRING).unwrap();
 
@@ -108,9 +152,69 @@ fn test_save_load_snapshots() {
 
     let client = Client::with_options(client_options).unwrap();
 
-    let db = client.database("test");
+    let store = ThisEventStore::new(&client, "test");
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        metadata.clone(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    let failing_contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            3,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test B".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::AddressUpdated(AddressUpdated {
+                new_address: "something else happening here"
+                    .to_string(),
+            }),
+            metadata,
+        ),
+    ];
+
+    store
+        .save_events(&failing_contexts)
+        .unwrap_err();
+
+    let stored_events = store.load_events(&id).unwrap();
+    assert_eq!(stored_events, contexts);
+}
+
+#[test]
+fn test_save_load_snapshots() {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    let client = Client::with_options(client_options).unwrap();
 
-    let mut store = ThisEventStore::new(db);
+    let store = ThisEventStore::new(&client, "test");
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -168,3 +272,216 @@ fn test_save_load_snapshots() {
 
     assert_eq!(stored_context, context);
 }
+
+#[test]
+fn test_save_load_late_snapshots() {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    let client = Client::with_options(client_options).unwrap();
+
+    let store = ThisEventStore::new(&client, "test");
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = AggregateContext::new(
+        id.to_string(),
+        5,
+        Customer {
+            customer_id: "customer 1".to_string(),
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            addresses: vec!["initial address".to_string()],
+        },
+    );
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    let stored_context = store
+        .load_aggregate_from_snapshot(&id)
+        .unwrap();
+
+    assert_eq!(stored_context, context);
+}
+
+#[test]
+fn test_outbox() {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    let client = Client::with_options(client_options).unwrap();
+
+    let store = ThisEventStore::new(&client, "test").with_outbox();
+    let other_store = ThisEventStore::new(&client, "test");
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+    ];
+
+    store.save_events(&contexts).unwrap();
+
+    let other_id = uuid::Uuid::new_v4().to_string();
+
+    other_store
+        .save_events(&vec![EventContext::new(
+            other_id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_B".to_string(),
+            }),
+            metadata,
+        )]).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id || x.aggregate_id == other_id)
+        .collect();
+
+    assert_eq!(pending, contexts);
+
+    store
+        .mark_events_delivered(&pending[..1]).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id)
+        .collect();
+
+    assert_eq!(pending, contexts[1..]);
+
+    // the pending events are deleted with their stream
+    store.delete_stream(&id).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id)
+        .collect();
+
+    assert_eq!(0, pending.len());
+}
+
+// the binary documents are kept in their own database, the stores
+// reading them must use the same format
+#[cfg(feature = "with-msgpack")]
+#[test]
+fn test_binary_payloads() {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    let client = Client::with_options(client_options).unwrap();
+
+    let db_name =
+        format!("test_{}", uuid::Uuid::new_v4().to_simple());
+
+    let json_store = ThisEventStore::new(&client, &db_name);
+
+    let store = ThisEventStore::new(&client, &db_name)
+        .with_outbox()
+        .with_serializer(crate::MsgPackSerializer);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata,
+        ),
+    ];
+
+    json_store
+        .save_events(&contexts[..1].to_vec())
+        .unwrap();
+    store
+        .save_events(&contexts[1..].to_vec())
+        .unwrap();
+
+    // the events written as JSON still load
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+    assert_eq!(
+        store
+            .read_all(0, 1_000_000)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.context)
+            .filter(|x| x.aggregate_id == id)
+            .collect::<Vec<_>>(),
+        contexts
+    );
+    assert_eq!(
+        store
+            .load_pending_events(1_000_000)
+            .unwrap()
+            .into_iter()
+            .filter(|x| x.aggregate_id == id)
+            .collect::<Vec<_>>(),
+        contexts[1..]
+    );
+
+    let context = AggregateContext::new(
+        id.to_string(),
+        2,
+        Customer {
+            customer_id: id.to_string(),
+            name: "test_event_A".to_string(),
+            email: "test A".to_string(),
+            addresses: Default::default(),
+        },
+    );
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    let stored_context = store
+        .load_aggregate_from_snapshot(&id)
+        .unwrap();
+
+    assert_eq!(stored_context.version, context.version);
+    assert_eq!(stored_context.payload, context.payload);
+
+    client.database(&db_name).drop(None).unwrap();
+}
@@ -33,7 +33,7 @@ fn test_save_load_queries() {
 
     let db = client.database("test");
 
-    let mut store = ThisQueryStore::new(db);
+    let store = ThisQueryStore::new(db);
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -80,3 +80,139 @@ fn test_save_load_queries() {
 
     assert_eq!(stored_context, context);
 }
+
+#[test]
+fn test_delete_queries() {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    let client = Client::with_options(client_options).unwrap();
+
+    let db = client.database("test");
+
+    let version = uuid::Uuid::new_v4().to_string();
+    let store =
+        ThisQueryStore::new(db).with_query_version(&version);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(stored_context, context);
+
+    store.delete_queries().unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+}
+
+#[test]
+fn test_delete_query() {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    let client = Client::with_options(client_options).unwrap();
+
+    let db = client.database("test");
+
+    let version = uuid::Uuid::new_v4().to_string();
+    let store =
+        ThisQueryStore::new(db).with_query_version(&version);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(stored_context, context);
+
+    store.delete_query(&id).unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+}
+
+#[cfg(feature = "with-cbor")]
+#[test]
+fn test_binary_queries() {
+    let mut client_options =
+        ClientOptions::parse(CONNECTION_STRING).unwrap();
+
+    client_options.app_name = Some("UnitTesting".to_string());
+
+    let client = Client::with_options(client_options).unwrap();
+
+    let db = client.database("test");
+
+    let json_store = ThisQueryStore::new(db.clone());
+
+    let store = ThisQueryStore::new(db)
+        .with_serializer(crate::CborSerializer);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let mut context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    json_store
+        .save_query(context.clone())
+        .unwrap();
+
+    // the queries written as JSON still load
+    assert_eq!(store.load_query(&id).unwrap(), context);
+
+    context.version = 2;
+    context.payload.email = "test2@email.com".to_string();
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    assert_eq!(store.load_query(&id).unwrap(), context);
+}
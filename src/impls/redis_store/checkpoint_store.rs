@@ -0,0 +1,105 @@
+use log::{
+    debug,
+    trace,
+};
+
+use redis::{
+    Commands,
+    RedisResult,
+};
+
+use crate::{
+    errors::StoreError,
+    repository::ICheckpointStore,
+};
+
+use super::{
+    driver_error::driver_error,
+    pool::{
+        get_conn,
+        RedisPool,
+    },
+};
+
+/// Sync Redis checkpoint store
+pub struct CheckpointStore {
+    pool: RedisPool,
+}
+
+impl CheckpointStore {
+    /// Constructor
+    pub fn new(pool: RedisPool) -> Self {
+        let x = Self { pool };
+
+        trace!("Created new  Redis checkpoint store");
+
+        x
+    }
+}
+
+impl ICheckpointStore for CheckpointStore {
+    /// Save the last position processed by `subscription`
+    fn save_checkpoint(
+        &self,
+        subscription: &str,
+        position: i64,
+    ) -> Result<(), StoreError> {
+        debug!(
+            "storing checkpoint {} for subscription '{}'",
+            position, subscription
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<()> =
+            conn.hset("checkpoints", subscription, position);
+
+        match res {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert/update checkpoint for \
+                         subscription '{}'",
+                        subscription
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Load the last position processed by `subscription`
+    fn load_checkpoint(
+        &self,
+        subscription: &str,
+    ) -> Result<i64, StoreError> {
+        trace!(
+            "loading checkpoint for subscription '{}'",
+            subscription
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<Option<i64>> =
+            conn.hget("checkpoints", subscription);
+
+        match res {
+            Ok(x) => Ok(x.unwrap_or(0)),
+            Err(e) => {
+                Err(driver_error(
+                    format!(
+                        "unable to load checkpoints table for \
+                         subscription '{}'",
+                        subscription
+                    )
+                    .as_str(),
+                    e,
+                ))
+            },
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use redis::RedisError;
+
+use crate::errors::StoreError;
+
+/// Classifies a Redis error into a `StoreError`
+pub(super) fn driver_error(
+    message: &str,
+    e: RedisError,
+) -> StoreError {
+    if e.is_io_error() ||
+        e.is_connection_dropped() ||
+        e.is_connection_refusal()
+    {
+        return StoreError::connection(message, e);
+    }
+
+    StoreError::backend(message, e)
+}
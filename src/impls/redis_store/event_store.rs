@@ -3,28 +3,69 @@ use log::{
     trace,
 };
 use serde_json::json;
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
 
 use redis::{
     Commands,
-    Connection,
     RedisResult,
 };
 
 use cqrs_es2::{
     AggregateContext,
-    Error,
     EventContext,
     IAggregate,
     ICommand,
     IEvent,
 };
 
-use crate::repository::IEventStore;
+use crate::{
+    errors::{
+        ConflictError,
+        StoreError,
+    },
+    repository::{
+        event_type,
+        EncryptionScope,
+        EventUpcasters,
+        IEventStore,
+        IOutboxStore,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        PositionedEvent,
+        StoreSerializer,
+        StreamMetadata,
+        TimestampedEvent,
+    },
+};
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
+
+use super::{
+    driver_error::driver_error,
+    pool::{
+        get_conn,
+        RedisConnection,
+        RedisPool,
+    },
+};
+
+/// The length, first and last entries of a stream
+type StreamBounds = (i64, Option<Vec<u8>>, Option<Vec<u8>>);
 
 /// Sync Redis event store
 pub struct EventStore<C: ICommand, E: IEvent, A: IAggregate<C, E>> {
-    conn: Connection,
+    pool: RedisPool,
+    with_outbox: bool,
+    upcasters: EventUpcasters,
+    event_type: Option<fn(&E) -> String>,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A)>,
 }
 
@@ -32,9 +73,13 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
     EventStore<C, E, A>
 {
     /// Constructor
-    pub fn new(conn: Connection) -> Self {
+    pub fn new(pool: RedisPool) -> Self {
         let x = Self {
-            conn,
+            pool,
+            with_outbox: false,
+            upcasters: EventUpcasters::default(),
+            event_type: None,
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
         };
 
@@ -42,6 +87,363 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
 
         x
     }
+
+    /// Enables the transactional outbox, new events are added to
+    /// the outbox list in the same transaction for an
+    /// `OutboxRelay` to deliver
+    pub fn with_outbox(mut self) -> Self {
+        self.with_outbox = true;
+        self
+    }
+
+    /// Upcasts the stored payloads with `upcasters` before they are
+    /// deserialized
+    pub fn with_upcasters(
+        mut self,
+        upcasters: EventUpcasters,
+    ) -> Self {
+        self.upcasters = upcasters;
+        self
+    }
+
+    /// Names the event types stored with the events with
+    /// `event_type` instead of their enum variants
+    pub fn with_event_type(
+        mut self,
+        event_type: fn(&E) -> String,
+    ) -> Self {
+        self.event_type = Some(event_type);
+        self
+    }
+
+    /// Stores the event and snapshot entries with `serializer`, the
+    /// binary formats are stored as binary values
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
+
+    /// Compresses the event and snapshot entries longer than
+    /// `threshold` bytes with `compressor`, the uncompressed ones
+    /// keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
+
+    /// Encrypts the event payloads and the snapshots with
+    /// `encryption`, the events record the id of their key in their
+    /// metadata
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
+    }
+}
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
+    EventStore<C, E, A>
+{
+    /// Loads the events of entries created by `event_reference`
+    /// with a single pipeline on `conn`
+    fn load_referenced_events(
+        &self,
+        conn: &mut RedisConnection,
+        entries: &[String],
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        let mut references = Vec::new();
+        let mut pipe = redis::pipe();
+
+        for entry in entries {
+            let (aggregate_id, sequence) =
+                parse_event_reference(entry)?;
+
+            let key = format!(
+                "events;{};{}",
+                aggregate_type, &aggregate_id
+            );
+
+            // the position in the list is the sequence of the event
+            pipe.lindex(key, (sequence - 1) as isize);
+
+            references.push((aggregate_id, sequence));
+        }
+
+        if references.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let res: RedisResult<Vec<Option<Vec<u8>>>> =
+            pipe.query(&mut **conn);
+
+        let rows = match res {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to load referenced events",
+                    e,
+                ));
+            },
+        };
+
+        let mut result = Vec::new();
+
+        for ((aggregate_id, sequence), row) in
+            references.iter().zip(rows)
+        {
+            let row = match row {
+                Some(x) => x,
+                None => {
+                    return Err(StoreError::not_found(
+                        format!(
+                            "missing event {} of aggregate id '{}' \
+                             found in event reference",
+                            sequence, aggregate_id
+                        )
+                        .as_str(),
+                    ));
+                },
+            };
+
+            result.push(self.parse_event(aggregate_id, &row)?);
+        }
+
+        Ok(result)
+    }
+
+    /// Parses an entry of the events table of `aggregate_id`
+    fn parse_event(
+        &self,
+        aggregate_id: &str,
+        row: &[u8],
+    ) -> Result<TimestampedEvent<C, E>, StoreError> {
+        let v = match self.serializer.join_entry(row) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "unable to serialize entry from events table \
+                         for aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        // entries written before the types and schema versions
+        // were recorded are named after their enum variant and
+        // hold the first version of their event type
+        let event_type = v
+            .get("event_type")
+            .and_then(|x| x.as_str())
+            .unwrap_or("");
+
+        let event_version = v
+            .get("event_version")
+            .and_then(|x| x.as_i64())
+            .unwrap_or(1);
+
+        let sequence = match serde_json::from_value(entry_field(
+            &v,
+            "sequence",
+            "events",
+            aggregate_id,
+        )?) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "bad sequence found in events table for \
+                         aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let scope = EncryptionScope::Event {
+            aggregate_type: A::aggregate_type(),
+            aggregate_id,
+            sequence,
+        };
+
+        let payload =
+            entry_field(&v, "payload", "events", aggregate_id)?;
+
+        let payload = match self.serializer.decrypt(&scope, payload)
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "bad payload found in events table for \
+                         aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let payload = match self.upcasters.deserialize(
+            event_type,
+            payload,
+            event_version,
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "bad payload found in events table for \
+                         aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let metadata = match serde_json::from_value(entry_field(
+            &v,
+            "metadata",
+            "events",
+            aggregate_id,
+        )?) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "bad metadata found in events table for \
+                         aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(TimestampedEvent::new(
+            entry_timestamp(&v),
+            EventContext::new(
+                aggregate_id.to_string(),
+                sequence,
+                payload,
+                metadata,
+            ),
+        ))
+    }
+}
+
+/// Parses an entry of the events or snapshots tables
+fn parse_entry(
+    serializer: &StoreSerializer,
+    entry: &[u8],
+    key: &str,
+) -> Result<serde_json::Value, StoreError> {
+    match serializer.join_entry(entry) {
+        Ok(x) => Ok(x),
+        Err(e) => {
+            Err(StoreError::deserialization(
+                format!("bad entry found in table for key {}", key)
+                    .as_str(),
+                e,
+            ))
+        },
+    }
+}
+
+/// A field of an entry of the events or snapshots tables, the
+/// malformed entries are rejected rather than panicking
+fn entry_field(
+    entry: &serde_json::Value,
+    field: &str,
+    table: &str,
+    aggregate_id: &str,
+) -> Result<serde_json::Value, StoreError> {
+    entry.get(field).cloned().ok_or_else(|| {
+        StoreError::Deserialization {
+            message: format!(
+                "no {} found in {} table for aggregate id '{}'",
+                field, table, aggregate_id
+            ),
+            source: None,
+        }
+    })
+}
+
+/// The commit time of an entry of the events table, the entries
+/// written before the timestamps were recorded have the epoch
+fn entry_timestamp(entry: &serde_json::Value) -> SystemTime {
+    let timestamp = entry
+        .get("timestamp")
+        .and_then(|x| x.as_i64())
+        .unwrap_or(0);
+
+    UNIX_EPOCH + Duration::from_micros(timestamp as u64)
+}
+
+/// The entry referencing an event in the outbox and positions
+fn event_reference(
+    aggregate_id: &str,
+    sequence: i64,
+) -> String {
+    json!({
+        "aggregate_id": aggregate_id,
+        "sequence": sequence,
+    })
+    .to_string()
+}
+
+/// The aggregate id and sequence of an entry created by
+/// `event_reference`
+fn parse_event_reference(
+    entry: &str
+) -> Result<(String, i64), StoreError> {
+    let v: serde_json::Value = match serde_json::from_str(entry) {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(StoreError::deserialization(
+                format!("bad event reference '{}' found", entry)
+                    .as_str(),
+                e,
+            ));
+        },
+    };
+
+    match (
+        v.get("aggregate_id").and_then(|x| x.as_str()),
+        v.get("sequence").and_then(|x| x.as_i64()),
+    ) {
+        (Some(x), Some(y)) => Ok((x.to_string(), y)),
+        _ => {
+            Err(StoreError::Deserialization {
+                message: format!(
+                    "bad event reference '{}' found",
+                    entry
+                ),
+                source: None,
+            })
+        },
+    }
 }
 
 impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
@@ -49,9 +451,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 {
     /// Save new events
     fn save_events(
-        &mut self,
+        &self,
         contexts: &Vec<EventContext<C, E>>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         if contexts.len() == 0 {
             trace!("Skip saving zero contexts");
             return Ok(());
@@ -76,52 +478,222 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             aggregate_type, &aggregate_id
         );
 
+        // the commit time in microseconds since the epoch
+        let timestamp =
+            match SystemTime::now().duration_since(UNIX_EPOCH) {
+                Ok(x) => x.as_micros() as i64,
+                Err(_) => 0,
+            };
+
+        let mut entries = Vec::new();
+
         for context in contexts {
-            let r = json!({
-                "sequence": context.sequence,
-                "payload": context.payload,
-                "metadata": context.metadata
-            });
+            let payload = match serde_json::to_value(&context.payload)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to serialize the event payload \
+                             for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let event_type = match self.event_type {
+                Some(x) => x(&context.payload),
+                None => event_type(&payload),
+            };
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &aggregate_id,
+                sequence: context.sequence,
+            };
 
-            let r = match serde_json::to_string(&r) {
+            let (payload, metadata) = match self
+                .serializer
+                .encrypt_event(&scope, payload, &context.metadata)
+            {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::serialization(
                         format!(
-                            "unable to serialize the event entry for
-                              aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                            "unable to encrypt the event payload for \
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-            let res: RedisResult<()> = self.conn.rpush(&key, r);
+            let event_version =
+                self.upcasters.event_version(&event_type);
 
-            match res {
-                Ok(_) => {},
+            let r = json!({
+                "sequence": context.sequence,
+                "event_type": event_type,
+                "event_version": event_version,
+                "timestamp": timestamp,
+                "payload": payload,
+                "metadata": metadata
+            });
+
+            let r = match self.serializer.to_entry(&r) {
+                Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::serialization(
                         format!(
-                            "unable to insert new event for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                            "unable to serialize the event entry for
+                              aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
+
+            entries.push(r);
+        }
+
+        let outbox_key = format!("outbox;{}", aggregate_type);
+
+        let outbox_entries: Vec<String> = match self.with_outbox {
+            true => {
+                contexts
+                    .iter()
+                    .map(|x| event_reference(&aggregate_id, x.sequence))
+                    .collect()
+            },
+            false => Vec::new(),
+        };
+
+        let positions_key = format!("positions;{}", aggregate_type);
+        let position_key = format!("position;{}", aggregate_type);
+
+        let first_sequence = contexts.first().unwrap().sequence;
+
+        // the position in the list is the sequence of the event
+        for (i, context) in contexts.iter().enumerate() {
+            if context.sequence != first_sequence + i as i64 {
+                return Err(StoreError::Backend {
+                    message: format!(
+                        "non consecutive sequence {} found in new \
+                         events for aggregate id '{}'",
+                        context.sequence, &aggregate_id
+                    ),
+                    source: None,
+                });
+            }
         }
 
+        // all events are pushed in a single MULTI/EXEC, the list and
+        // the position counter are watched so that a concurrent
+        // writer aborts the transaction and the checks are repeated
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<bool> = redis::transaction(
+            &mut *conn,
+            &[key.as_str(), &position_key, &positions_key],
+            |conn, pipe| {
+                let len: i64 = conn.llen(&key)?;
+
+                if len + 1 != first_sequence {
+                    return Ok(Some(false));
+                }
+
+                let position: Option<i64> = conn.get(&position_key)?;
+
+                // the counters of the stores written before they were
+                // kept per aggregate type resume after the last
+                // position of the type
+                let position = match position {
+                    Some(x) => x,
+                    None => {
+                        let last: Vec<(String, i64)> = conn
+                            .zrevrange_withscores(
+                                &positions_key,
+                                0,
+                                0,
+                            )?;
+
+                        last.first().map(|x| x.1).unwrap_or(0)
+                    },
+                };
+
+                let positions: Vec<(i64, String)> = contexts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, x)| {
+                        (
+                            position + 1 + i as i64,
+                            event_reference(&aggregate_id, x.sequence),
+                        )
+                    })
+                    .collect();
+
+                pipe.rpush(&key, &entries)
+                    .ignore()
+                    .set(
+                        &position_key,
+                        position + contexts.len() as i64,
+                    )
+                    .ignore()
+                    .zadd_multiple(&positions_key, &positions)
+                    .ignore();
+
+                if !outbox_entries.is_empty() {
+                    pipe.rpush(&outbox_key, &outbox_entries)
+                        .ignore();
+                }
+
+                let res: Option<()> = pipe.query(conn)?;
+
+                Ok(res.map(|_| true))
+            },
+        );
+
+        match res {
+            Ok(true) => {},
+            Ok(false) => {
+                return Err(ConflictError::new(
+                    aggregate_type,
+                    &aggregate_id,
+                    first_sequence,
+                )
+                .into());
+            },
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert new events for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
         Ok(())
     }
 
-    /// Load all events for a particular `aggregate_id`
-    fn load_events(
-        &mut self,
+    /// Load the events for a particular `aggregate_id` within a
+    /// range of sequences along with their commit timestamps
+    fn load_timestamped_events_range(
+        &self,
         aggregate_id: &str,
-    ) -> Result<Vec<EventContext<C, E>>, Error> {
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -134,18 +706,20 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             aggregate_type, aggregate_id
         );
 
-        let res: RedisResult<bool> = self.conn.exists(&key);
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<bool> = conn.exists(&key);
 
         let res = match res {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
-                        "unable to check events table for key {} \
-                         with error: {}",
-                        &key, e
+                        "unable to check events table for key {}",
+                        &key
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -157,19 +731,32 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             true => {},
         }
 
-        let res: RedisResult<Vec<String>> =
-            self.conn.lrange(&key, 0, -1);
+        // the position in the list is the sequence of the event
+        let start = (from_sequence.max(1) - 1) as isize;
+
+        let stop = match to_sequence {
+            Some(x) => (x - 1) as isize,
+            None => -1,
+        };
+
+        if stop < -1 || (stop >= 0 && stop < start) {
+            return Ok(Vec::new());
+        }
+
+        let res: RedisResult<Vec<Vec<u8>>> =
+            conn.lrange(&key, start, stop);
 
         let rows = match res {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load events table for aggregate \
-                         id '{}' with error: {}",
-                        aggregate_id, e
+                         id '{}'",
+                        aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -177,86 +764,364 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         let mut result = Vec::new();
 
         for row in rows {
-            let v: serde_json::Value =
-                match serde_json::from_str(row.as_str()) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        return Err(Error::new(
-                            format!(
-                                "unable to serialize entry from \
-                                 events table for aggregate id '{}' \
-                                 with error: {}",
-                                aggregate_id, e
-                            )
-                            .as_str(),
-                        ));
-                    },
-                };
+            result.push(self.parse_event(aggregate_id, &row)?);
+        }
 
-            let payload = match serde_json::from_value(
-                v.get("payload").unwrap().clone(),
-            ) {
-                Ok(x) => x,
-                Err(e) => {
-                    return Err(Error::new(
-                        format!(
-                            "bad payload found in events table for \
-                             aggregate id '{}' with error: {}",
-                            aggregate_id, e
-                        )
-                        .as_str(),
-                    ));
-                },
-            };
+        Ok(result)
+    }
 
-            let metadata = match serde_json::from_value(
-                v.get("metadata").unwrap().clone(),
-            ) {
-                Ok(x) => x,
-                Err(e) => {
-                    return Err(Error::new(
-                        format!(
-                            "bad metadata found in events table for \
-                             aggregate id '{}' with error: {}",
-                            aggregate_id, e
-                        )
-                        .as_str(),
-                    ));
-                },
-            };
+    /// Load up to `limit` events of all aggregates of this type in
+    /// commit order, starting at the global position
+    /// `from_position`
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
 
-            let sequence = match serde_json::from_value(
-                v.get("sequence").unwrap().clone(),
-            ) {
-                Ok(x) => x,
-                Err(e) => {
-                    return Err(Error::new(
-                        format!(
-                            "bad sequence found in events table for \
-                             aggregate id '{}' with error: {}",
-                            aggregate_id, e
-                        )
-                        .as_str(),
-                    ));
-                },
-            };
+        trace!(
+            "reading up to '{}' events from position {}",
+            limit,
+            from_position
+        );
 
-            result.push(EventContext::new(
-                aggregate_id.to_string(),
-                sequence,
-                payload,
-                metadata,
-            ));
-        }
+        let positions_key = format!("positions;{}", aggregate_type);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<Vec<(String, i64)>> =
+            conn.zrangebyscore_limit_withscores(
+                &positions_key,
+                from_position,
+                "+inf",
+                0,
+                limit as isize,
+            );
+
+        let rows = match res {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load positions table from \
+                         position {}",
+                        from_position
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let (entries, positions): (Vec<String>, Vec<i64>) =
+            rows.into_iter().unzip();
+
+        let events =
+            self.load_referenced_events(&mut conn, &entries)?;
+
+        Ok(positions
+            .into_iter()
+            .zip(events)
+            .map(|(position, x)| {
+                PositionedEvent::new(position, x.timestamp, x.context)
+            })
+            .collect())
+    }
+
+    /// List up to `limit` ids of the aggregates of this type in
+    /// ascending order, starting after `after_aggregate_id`
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "listing up to '{}' aggregate ids after '{:?}'",
+            limit,
+            after_aggregate_id
+        );
+
+        let prefix = format!("events;{};", aggregate_type);
+
+        // SCAN returns the keys in no particular order and possibly
+        // more than once, all of them are needed to sort the page
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<redis::Iter<'_, String>> =
+            conn.scan_match(format!("{}*", &prefix));
+
+        let keys: Vec<String> = match res {
+            Ok(x) => x.collect(),
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to scan events table for keys {}*",
+                        &prefix
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let mut result: Vec<String> = keys
+            .iter()
+            .filter_map(|x| x.strip_prefix(prefix.as_str()))
+            .filter(|x| {
+                match after_aggregate_id {
+                    Some(y) => *x > y,
+                    None => true,
+                }
+            })
+            .map(|x| x.to_string())
+            .collect();
+
+        result.sort();
+        result.dedup();
+        result.truncate(limit);
 
         Ok(result)
     }
 
+    /// Load the metadata of the event stream of a particular
+    /// `aggregate_id`
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "loading stream metadata for aggregate id '{}'",
+            aggregate_id
+        );
+
+        let key = format!(
+            "events;{};{}",
+            aggregate_type, aggregate_id
+        );
+
+        let snapshot_key = format!(
+            "snapshots;{};{}",
+            aggregate_type, aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<StreamBounds> = redis::pipe()
+            .llen(&key)
+            .lindex(&key, 0)
+            .lindex(&key, -1)
+            .query(&mut *conn);
+
+        let (event_count, first, last) = match res {
+            Ok((n, Some(x), Some(y))) if n > 0 => (n, x, y),
+            Ok(_) => {
+                return Ok(None);
+            },
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load events table for key {}",
+                        &key
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let res: RedisResult<Option<Vec<u8>>> =
+            conn.get(&snapshot_key);
+
+        let snapshot = match res {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load snapshots table for key {}",
+                        &snapshot_key
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let first = parse_entry(&self.serializer, &first, &key)?;
+        let last = parse_entry(&self.serializer, &last, &key)?;
+
+        let snapshot_version = match snapshot {
+            Some(x) => {
+                parse_entry(&self.serializer, &x, &snapshot_key)?
+                    .get("version")
+                    .and_then(|x| x.as_i64())
+            },
+            None => None,
+        };
+
+        Ok(Some(StreamMetadata::new(
+            aggregate_id,
+            last.get("sequence")
+                .and_then(|x| x.as_i64())
+                .unwrap_or(event_count),
+            event_count,
+            entry_timestamp(&first),
+            entry_timestamp(&last),
+            snapshot_version,
+        )))
+    }
+
+    /// Delete all events of a particular `aggregate_id` along with
+    /// its snapshot, positions and outbox entries
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting events and snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let key =
+            format!("events;{};{}", aggregate_type, aggregate_id);
+
+        let snapshot_key =
+            format!("snapshots;{};{}", aggregate_type, aggregate_id);
+        let positions_key = format!("positions;{}", aggregate_type);
+        let outbox_key = format!("outbox;{}", aggregate_type);
+
+        // the list is watched so that an append committed between
+        // the length and the deletion aborts the transaction, which
+        // is repeated with the new length
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<()> = redis::transaction(
+            &mut *conn,
+            &[key.as_str()],
+            |conn, pipe| {
+                let len: i64 = conn.llen(&key)?;
+
+                let references: Vec<String> = (1..=len)
+                    .map(|x| event_reference(aggregate_id, x))
+                    .collect();
+
+                pipe.del(&key)
+                    .ignore()
+                    .del(&snapshot_key)
+                    .ignore();
+
+                if !references.is_empty() {
+                    pipe.zrem(&positions_key, &references)
+                        .ignore();
+
+                    for x in &references {
+                        pipe.lrem(&outbox_key, 0, x).ignore();
+                    }
+                }
+
+                pipe.query(conn)
+            },
+        );
+
+        match res {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete the events of aggregate id \
+                         '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Mark the stream of a particular `aggregate_id` as tombstoned
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!("tombstoning aggregate id '{}'", aggregate_id);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<i64> = conn.sadd(
+            format!("tombstones;{}", aggregate_type),
+            aggregate_id,
+        );
+
+        match res {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert tombstone for aggregate id \
+                         '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Check whether the stream of a particular `aggregate_id` was
+    /// tombstoned
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "checking tombstone of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<bool> = conn.sismember(
+            format!("tombstones;{}", aggregate_type),
+            aggregate_id,
+        );
+
+        match res {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                Err(driver_error(
+                    format!(
+                        "unable to load tombstones table for \
+                         aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ))
+            },
+        }
+    }
+
     /// save a new aggregate snapshot
     fn save_aggregate_snapshot(
-        &mut self,
+        &self,
         context: AggregateContext<C, E, A>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
 
         let aggregate_id = context.aggregate_id;
@@ -266,26 +1131,52 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             &aggregate_id
         );
 
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id: &aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .encrypt(&scope, &context.payload)
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::serialization(
+                    format!(
+                        "unable to encrypt the aggregate snapshot \
+                         for aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
         let r = json!({
             "version": context.version,
-            "payload": context.payload,
+            "payload": payload,
         });
 
-        let r = match serde_json::to_string(&r) {
+        let r = match self.serializer.to_entry(&r) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize the snapshot entry for
-                          aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                          aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        let res: RedisResult<()> = self.conn.set(
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<()> = conn.set(
             format!(
                 "snapshots;{};{}",
                 aggregate_type, &aggregate_id
@@ -296,13 +1187,51 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         match res {
             Ok(_) => {},
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to insert/update snapshot for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Delete the snapshot of a particular `aggregate_id`
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting the snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<i64> = conn.del(format!(
+            "snapshots;{};{}",
+            aggregate_type, aggregate_id
+        ));
+
+        match res {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete snapshot for aggregate id \
+                         '{}'",
+                        aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -312,9 +1241,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 
     /// Load aggregate at current state from snapshots
     fn load_aggregate_from_snapshot(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<AggregateContext<C, E, A>, Error> {
+    ) -> Result<AggregateContext<C, E, A>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -327,18 +1256,20 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             aggregate_type, aggregate_id
         );
 
-        let res: RedisResult<bool> = self.conn.exists(&key);
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<bool> = conn.exists(&key);
 
         let res = match res {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
-                        "unable to check snapshots table for key {} \
-                         with error: {}",
-                        &key, e
+                        "unable to check snapshots table for key {}",
+                        &key
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -360,66 +1291,93 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             },
         }
 
-        let res: RedisResult<String> = self.conn.get(&key);
+        let res: RedisResult<Vec<u8>> = conn.get(&key);
 
         let res = match res {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
-                        "unable to load snapshots table for key {} \
-                         with error: {}",
-                        &key, e
+                        "unable to load snapshots table for key {}",
+                        &key
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
         let v: serde_json::Value =
-            match serde_json::from_str(res.as_str()) {
+            match self.serializer.join_entry(&res) {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::deserialization(
                         format!(
                             "unable to serialize entry from \
-                             snapshots table for key {} with error: \
-                             {}",
-                            &key, e
+                             snapshots table for key {}",
+                            &key
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-        let payload = match serde_json::from_value(
-            v.get("payload").unwrap().clone(),
-        ) {
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id,
+        };
+
+        let payload =
+            entry_field(&v, "payload", "snapshots", aggregate_id)?;
+
+        let payload = match self.serializer.decrypt(&scope, payload)
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "unable to decrypt the payload from \
+                         snapshots table for key {}",
+                        &key
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let payload = match serde_json::from_value(payload) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in events table for key \
-                         {} with error: {}",
-                        &key, e
+                         {}",
+                        &key
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        let version = match serde_json::from_value(
-            v.get("version").unwrap().clone(),
-        ) {
+        let version = match serde_json::from_value(entry_field(
+            &v,
+            "version",
+            "snapshots",
+            aggregate_id,
+        )?) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad version found in events table for key \
-                         {} with error: {}",
-                        &key, e
+                         {}",
+                        &key
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -431,3 +1389,89 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         ))
     }
 }
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IOutboxStore<C, E, A>
+    for EventStore<C, E, A>
+{
+    /// Load up to `limit` pending events in commit order
+    fn load_pending_events(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let aggregate_type = A::aggregate_type();
+
+        trace!("loading up to '{}' pending outbox events", limit);
+
+        let outbox_key = format!("outbox;{}", aggregate_type);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<Vec<String>> = conn.lrange(
+            &outbox_key,
+            0,
+            limit as isize - 1,
+        );
+
+        let rows = match res {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to load outbox list",
+                    e,
+                ));
+            },
+        };
+
+        Ok(self
+            .load_referenced_events(&mut conn, &rows)?
+            .into_iter()
+            .map(|x| x.context)
+            .collect())
+    }
+
+    /// Mark events as delivered so that they are not loaded again
+    fn mark_events_delivered(
+        &self,
+        contexts: &[EventContext<C, E>],
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!("marking '{}' outbox events delivered", contexts.len());
+
+        let outbox_key = format!("outbox;{}", aggregate_type);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        for context in contexts {
+            let res: RedisResult<i64> = conn.lrem(
+                &outbox_key,
+                1,
+                event_reference(
+                    &context.aggregate_id,
+                    context.sequence,
+                ),
+            );
+
+            match res {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to remove outbox entry for \
+                             aggregate id '{}'",
+                            &context.aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,15 @@
 //!
 //! Redis store
 
+pub use checkpoint_store::CheckpointStore;
 pub use event_store::EventStore;
+pub use pool::RedisPool;
 pub use query_store::QueryStore;
 
+mod checkpoint_store;
+mod driver_error;
 mod event_store;
+mod pool;
 mod query_store;
 
 mod test;
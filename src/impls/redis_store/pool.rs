@@ -0,0 +1,28 @@
+use r2d2::{
+    Pool,
+    PooledConnection,
+};
+use redis::Client;
+
+use crate::errors::StoreError;
+
+/// The r2d2 pool of Redis connections shared by the stores
+pub type RedisPool = Pool<Client>;
+
+/// A connection taken from a `RedisPool`
+pub(super) type RedisConnection = PooledConnection<Client>;
+
+/// Takes a connection from the pool for a single operation
+pub(super) fn get_conn(
+    pool: &RedisPool
+) -> Result<RedisConnection, StoreError> {
+    match pool.get() {
+        Ok(x) => Ok(x),
+        Err(e) => {
+            Err(StoreError::connection(
+                "unable to get a connection from the pool",
+                e,
+            ))
+        },
+    }
+}
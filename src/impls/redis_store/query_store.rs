@@ -7,7 +7,6 @@ use std::marker::PhantomData;
 
 use redis::{
     Commands,
-    Connection,
     RedisResult,
 };
 
@@ -21,9 +20,26 @@ use cqrs_es2::{
     QueryContext,
 };
 
-use crate::repository::{
-    IEventDispatcher,
-    IQueryStore,
+use crate::{
+    errors::StoreError,
+    repository::{
+        EncryptionScope,
+        IEventDispatcher,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        IQueryStore,
+        StoreSerializer,
+    },
+};
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
+
+use super::{
+    driver_error::driver_error,
+    pool::{
+        get_conn,
+        RedisPool,
+    },
 };
 
 /// Sync Redis query store
@@ -33,7 +49,9 @@ pub struct QueryStore<
     A: IAggregate<C, E>,
     Q: IQuery<C, E>,
 > {
-    conn: Connection,
+    pool: RedisPool,
+    query_type: String,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A, Q)>,
 }
 
@@ -45,9 +63,11 @@ impl<
     > QueryStore<C, E, A, Q>
 {
     /// Constructor
-    pub fn new(conn: Connection) -> Self {
+    pub fn new(pool: RedisPool) -> Self {
         let x = Self {
-            conn,
+            pool,
+            query_type: Q::query_type().to_string(),
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
         };
 
@@ -55,6 +75,48 @@ impl<
 
         x
     }
+
+    /// Stores the queries under a versioned query type, e.g. to
+    /// rebuild a new version of the queries next to the one in use
+    pub fn with_query_version(
+        mut self,
+        version: &str,
+    ) -> Self {
+        self.query_type = format!("{}_{}", Q::query_type(), version);
+        self
+    }
+
+    /// Stores the query entries with `serializer`, the binary
+    /// formats are stored as binary values
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
+
+    /// Compresses the query entries longer than `threshold` bytes
+    /// with `compressor`, the uncompressed ones keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
+
+    /// Encrypts the queries with `encryption`, the queries stored
+    /// in clear keep loading
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
+    }
 }
 
 impl<
@@ -66,11 +128,11 @@ impl<
 {
     /// saves the updated query
     fn save_query(
-        &mut self,
+        &self,
         context: QueryContext<C, E, Q>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         let aggregate_id = context.aggregate_id;
 
@@ -79,26 +141,53 @@ impl<
             query_type, &aggregate_id
         );
 
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id: &aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .encrypt(&scope, &context.payload)
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::serialization(
+                    format!(
+                        "unable to encrypt the query for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
         let r = json!({
             "version": context.version,
-            "payload": context.payload,
+            "payload": payload,
         });
 
-        let r = match serde_json::to_string(&r) {
+        let r = match self.serializer.to_entry(&r) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize the query entry for
-                          aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                          aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        let res: RedisResult<()> = self.conn.set(
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<()> = conn.set(
             format!(
                 "queries;{};{};{}",
                 aggregate_type, aggregate_id, query_type
@@ -109,13 +198,14 @@ impl<
         match res {
             Ok(_) => {},
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to insert new query {} for \
-                         aggregate id '{}' with error: {}",
-                        query_type, &aggregate_id, e
+                         aggregate id '{}'",
+                        query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -125,11 +215,11 @@ impl<
 
     /// loads the most recent query
     fn load_query(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<QueryContext<C, E, Q>, Error> {
+    ) -> Result<QueryContext<C, E, Q>, StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         trace!(
             "loading query '{}' for aggregate id '{}'",
@@ -142,18 +232,20 @@ impl<
             aggregate_type, aggregate_id, query_type
         );
 
-        let res: RedisResult<bool> = self.conn.exists(&key);
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<bool> = conn.exists(&key);
 
         let res = match res {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
-                        "unable to check queries table for key {} \
-                         with error: {}",
-                        &key, e
+                        "unable to check queries table for key {}",
+                        &key
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -176,49 +268,73 @@ impl<
             },
         }
 
-        let res: RedisResult<String> = self.conn.get(&key);
+        let res: RedisResult<Vec<u8>> = conn.get(&key);
 
         let res = match res {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
-                        "unable to load queries table for key {} \
-                         with error: {}",
-                        &key, e
+                        "unable to load queries table for key {}",
+                        &key
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
         let v: serde_json::Value =
-            match serde_json::from_str(res.as_str()) {
+            match self.serializer.join_entry(&res) {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::deserialization(
                         format!(
                             "unable to serialize entry from queries \
-                             table for key {} with error: {}",
-                            &key, e
+                             table for key {}",
+                            &key
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-        let payload = match serde_json::from_value(
-            v.get("payload").unwrap().clone(),
-        ) {
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .decrypt(&scope, v.get("payload").unwrap().clone())
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "unable to decrypt the payload from queries \
+                         table for key {}",
+                        &key
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let payload = match serde_json::from_value(payload) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in queries table for key \
-                         {} with error: {}",
-                        &key, e
+                         {}",
+                        &key
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -228,13 +344,14 @@ impl<
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad version found in queries table for key \
-                         {} with error: {}",
-                        &key, e
+                         {}",
+                        &key
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -245,6 +362,95 @@ impl<
             payload,
         ))
     }
+    /// deletes the query of a particular `aggregate_id`
+    fn delete_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!(
+            "deleting query '{}' for aggregate id '{}'",
+            query_type, aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<()> = conn.del(format!(
+            "queries;{};{};{}",
+            aggregate_type, aggregate_id, query_type
+        ));
+
+        match res {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete query '{}' for aggregate \
+                         id '{}'",
+                        &query_type, aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// deletes all queries of the query type
+    fn delete_queries(&self) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!("deleting all queries '{}'", query_type);
+
+        let pattern =
+            format!("queries;{};*;{}", aggregate_type, query_type);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: RedisResult<redis::Iter<'_, String>> =
+            conn.scan_match(&pattern);
+
+        let keys: Vec<String> = match res {
+            Ok(x) => x.collect(),
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to scan queries table for pattern {}",
+                        &pattern
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let res: RedisResult<()> = conn.del(keys);
+
+        match res {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete queries '{}'",
+                        &query_type
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
 }
 
 impl<
@@ -255,10 +461,10 @@ impl<
     > IEventDispatcher<C, E> for QueryStore<C, E, A, Q>
 {
     fn dispatch(
-        &mut self,
+        &self,
         aggregate_id: &str,
         events: &Vec<EventContext<C, E>>,
     ) -> Result<(), Error> {
-        self.dispatch_events(aggregate_id, events)
+        Ok(self.dispatch_events(aggregate_id, events)?)
     }
 }
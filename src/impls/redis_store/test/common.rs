@@ -1 +1,17 @@
+use r2d2::Pool;
+use redis::Client;
+
+use crate::redis_store::RedisPool;
+
 pub static CONNECTION_STRING: &str = "redis://localhost:9086/";
+
+#[cfg(feature = "with-msgpack")]
+pub static BINARY_CONNECTION_STRING: &str =
+    "redis://localhost:9086/1";
+
+pub fn get_pool(uri: &str) -> RedisPool {
+    Pool::builder()
+        .max_size(2)
+        .build(Client::open(uri).unwrap())
+        .unwrap()
+}
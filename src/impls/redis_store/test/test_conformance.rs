@@ -0,0 +1,55 @@
+use cqrs_es2::{
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+};
+
+use crate::{
+    conformance::*,
+    redis_store::{
+        EventStore,
+        QueryStore,
+    },
+    IEventStore,
+    IQueryStore,
+};
+
+use super::common::*;
+
+struct StoreFactory;
+
+impl IStoreFactory for StoreFactory {
+    fn event_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IEventStore<C, E, A>> {
+        Box::new(EventStore::new(get_pool(CONNECTION_STRING)))
+    }
+
+    fn query_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+        Q: IQuery<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IQueryStore<C, E, A, Q>> {
+        Box::new(QueryStore::<C, E, A, Q>::new(get_pool(
+            CONNECTION_STRING,
+        )))
+    }
+}
+
+#[test]
+fn test_event_store_conformance() {
+    check_event_store(&mut StoreFactory);
+}
+
+#[test]
+fn test_query_store_conformance() {
+    check_query_store(&mut StoreFactory);
+}
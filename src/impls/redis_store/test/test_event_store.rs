@@ -1,16 +1,25 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    thread,
+};
 
-use redis::Client;
+use redis::Commands;
+use serde_json::json;
 
 use cqrs_es2::{
     example_impl::*,
     AggregateContext,
     EventContext,
+    IAggregate,
 };
 
 use crate::{
+    errors::StoreError,
     redis_store::EventStore,
     IEventStore,
+    IOutboxStore,
+    Repository,
 };
 
 use super::common::*;
@@ -27,11 +36,10 @@ pub fn get_metadata() -> HashMap<String, String> {
 
 #[test]
 fn test_save_load_events() {
-    let client = Client::open(CONNECTION_STRING).unwrap();
+    let pool = get_pool(CONNECTION_STRING);
 
-    let conn = client.get_connection().unwrap();
 
-    let mut store = ThisEventStore::new(conn);
+    let store = ThisEventStore::new(pool);
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -91,13 +99,111 @@ fn test_save_load_events() {
     assert_eq!(stored_events, contexts_0);
 }
 
+#[test]
+fn test_save_conflicting_events() {
+    let pool = get_pool(CONNECTION_STRING);
+
+
+    let store = ThisEventStore::new(pool);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        metadata.clone(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    let conflicting_contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::EmailUpdated(EmailUpdated {
+            new_email: "test A".to_string(),
+        }),
+        metadata,
+    )];
+
+    let err = store
+        .save_events(&conflicting_contexts)
+        .unwrap_err();
+
+    assert!(err.is_conflict());
+
+    let stored_events = store.load_events(&id).unwrap();
+    assert_eq!(stored_events, contexts);
+}
+
+#[test]
+fn test_save_events_atomically() {
+    let pool = get_pool(CONNECTION_STRING);
+
+
+    let store = ThisEventStore::new(pool);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        metadata.clone(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    let failing_contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            3,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test B".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::AddressUpdated(AddressUpdated {
+                new_address: "something else happening here"
+                    .to_string(),
+            }),
+            metadata,
+        ),
+    ];
+
+    store
+        .save_events(&failing_contexts)
+        .unwrap_err();
+
+    let stored_events = store.load_events(&id).unwrap();
+    assert_eq!(stored_events, contexts);
+}
+
 #[test]
 fn test_save_load_snapshots() {
-    let client = Client::open(CONNECTION_STRING).unwrap();
+    let pool = get_pool(CONNECTION_STRING);
 
-    let conn = client.get_connection().unwrap();
 
-    let mut store = ThisEventStore::new(conn);
+    let store = ThisEventStore::new(pool);
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -155,3 +261,249 @@ fn test_save_load_snapshots() {
 
     assert_eq!(stored_context, context);
 }
+
+#[test]
+fn test_outbox() {
+    let pool = get_pool(CONNECTION_STRING);
+
+    let store = ThisEventStore::new(pool.clone()).with_outbox();
+
+    let other_store = ThisEventStore::new(pool.clone());
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+    ];
+
+    store.save_events(&contexts).unwrap();
+
+    let other_id = uuid::Uuid::new_v4().to_string();
+
+    other_store
+        .save_events(&vec![EventContext::new(
+            other_id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_B".to_string(),
+            }),
+            metadata,
+        )]).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id || x.aggregate_id == other_id)
+        .collect();
+
+    assert_eq!(pending, contexts);
+
+    store
+        .mark_events_delivered(&pending[..1]).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id)
+        .collect();
+
+    assert_eq!(pending, contexts[1..]);
+
+    // the pending events are deleted with their stream
+    store.delete_stream(&id).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id)
+        .collect();
+
+    assert_eq!(0, pending.len());
+}
+
+// the binary entries are kept in their own database, the stores
+// reading them must use the same format
+#[cfg(feature = "with-msgpack")]
+#[test]
+fn test_binary_payloads() {
+    let pool = get_pool(BINARY_CONNECTION_STRING);
+
+    let json_store =
+        ThisEventStore::new(pool.clone());
+
+    let store =
+        ThisEventStore::new(pool.clone())
+            .with_outbox()
+            .with_serializer(crate::MsgPackSerializer);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata,
+        ),
+    ];
+
+    json_store
+        .save_events(&contexts[..1].to_vec())
+        .unwrap();
+    store
+        .save_events(&contexts[1..].to_vec())
+        .unwrap();
+
+    // the events written as JSON still load
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+    assert_eq!(
+        store
+            .read_all(0, 1_000_000)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.context)
+            .filter(|x| x.aggregate_id == id)
+            .collect::<Vec<_>>(),
+        contexts
+    );
+    assert_eq!(
+        store
+            .load_pending_events(1_000_000)
+            .unwrap()
+            .into_iter()
+            .filter(|x| x.aggregate_id == id)
+            .collect::<Vec<_>>(),
+        contexts[1..]
+    );
+
+    let context = AggregateContext::new(
+        id.to_string(),
+        2,
+        Customer {
+            customer_id: id.to_string(),
+            name: "test_event_A".to_string(),
+            email: "test A".to_string(),
+            addresses: Default::default(),
+        },
+    );
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    let stored_context = store
+        .load_aggregate_from_snapshot(&id)
+        .unwrap();
+
+    assert_eq!(stored_context.version, context.version);
+    assert_eq!(stored_context.payload, context.payload);
+
+    store.delete_stream(&id).unwrap();
+}
+
+// the stores over a pool are shared by the threads of a server
+#[test]
+fn test_shared_repository() {
+    let store = ThisEventStore::new(get_pool(CONNECTION_STRING));
+
+    let repo = Arc::new(
+        Repository::new(store, vec![], false)
+            .with_conflict_retries(3),
+    );
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let handles: Vec<_> = (0..4)
+        .map(|x| {
+            let repo = Arc::clone(&repo);
+            let id = id.clone();
+
+            thread::spawn(move || {
+                repo.execute(
+                    &id,
+                    CustomerCommand::AddAddress(AddAddress {
+                        new_address: format!("address {}", x),
+                    }),
+                )
+            })
+        })
+        .collect();
+
+    for x in handles {
+        x.join().unwrap().unwrap();
+    }
+
+    let context = repo
+        .load_aggregate_at_sequence(&id, 10)
+        .unwrap();
+
+    assert_eq!(context.version, 4);
+    assert_eq!(context.payload.addresses.len(), 4);
+}
+
+#[test]
+fn test_malformed_entries() {
+    let pool = get_pool(CONNECTION_STRING);
+
+    let store = ThisEventStore::new(pool.clone());
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let aggregate_type = Customer::aggregate_type();
+
+    let mut conn = pool.get().unwrap();
+
+    // an event without a sequence and a snapshot without a version
+    let _: () = conn
+        .rpush(
+            format!("events;{};{}", aggregate_type, id),
+            r#"{"payload": {}, "metadata": {}}"#,
+        )
+        .unwrap();
+    let _: () = conn
+        .set(
+            format!("snapshots;{};{}", aggregate_type, id),
+            json!({ "payload": Customer::default() }).to_string(),
+        )
+        .unwrap();
+
+    match store.load_events(&id) {
+        Err(StoreError::Deserialization { .. }) => {},
+        x => panic!("malformed event loaded: {:?}", x),
+    }
+
+    match store.load_aggregate_from_snapshot(&id) {
+        Err(StoreError::Deserialization { .. }) => {},
+        x => panic!("malformed snapshot loaded: {:?}", x),
+    }
+
+    store.delete_stream(&id).unwrap();
+}
@@ -1,5 +1,3 @@
-use redis::Client;
-
 use cqrs_es2::{
     example_impl::*,
     QueryContext,
@@ -21,11 +19,10 @@ type ThisQueryStore = QueryStore<
 
 #[test]
 fn test_save_load_queries() {
-    let client = Client::open(CONNECTION_STRING).unwrap();
+    let pool = get_pool(CONNECTION_STRING);
 
-    let conn = client.get_connection().unwrap();
 
-    let mut store = ThisQueryStore::new(conn);
+    let store = ThisQueryStore::new(pool);
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -72,3 +69,122 @@ fn test_save_load_queries() {
 
     assert_eq!(stored_context, context);
 }
+
+#[test]
+fn test_delete_queries() {
+    let pool = get_pool(CONNECTION_STRING);
+
+
+    let version = uuid::Uuid::new_v4().to_string();
+    let store =
+        ThisQueryStore::new(pool).with_query_version(&version);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(stored_context, context);
+
+    store.delete_queries().unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+}
+
+#[test]
+fn test_delete_query() {
+    let pool = get_pool(CONNECTION_STRING);
+
+
+    let version = uuid::Uuid::new_v4().to_string();
+    let store =
+        ThisQueryStore::new(pool).with_query_version(&version);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(stored_context, context);
+
+    store.delete_query(&id).unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+}
+
+#[cfg(feature = "with-cbor")]
+#[test]
+fn test_binary_queries() {
+    let pool = get_pool(CONNECTION_STRING);
+
+    let json_store =
+        ThisQueryStore::new(pool.clone());
+
+    let store =
+        ThisQueryStore::new(pool.clone())
+            .with_serializer(crate::CborSerializer);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let mut context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    json_store
+        .save_query(context.clone())
+        .unwrap();
+
+    // the queries written as JSON still load
+    assert_eq!(store.load_query(&id).unwrap(), context);
+
+    context.version = 2;
+    context.payload.email = "test2@email.com".to_string();
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    assert_eq!(store.load_query(&id).unwrap(), context);
+}
@@ -0,0 +1,14 @@
+/// A versioned step of the schema of the SQL stores.
+///
+/// The statements refer to the tables through the placeholders of
+/// `TableNames::sql`. Migrations are applied in the order of their
+/// versions and each version is applied only once per set of table
+/// names.
+pub(crate) struct Migration {
+    /// The version reached by applying the migration
+    pub version: i64,
+    /// A short description recorded with the version
+    pub description: &'static str,
+    /// The statements of the migration
+    pub statements: &'static [&'static str],
+}
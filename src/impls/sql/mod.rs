@@ -1,3 +1,8 @@
+pub use table_names::TableNames;
+
+mod migration;
+mod table_names;
+
 #[cfg(any(
     feature = "with-mysql",
     feature = "with-sqlite"
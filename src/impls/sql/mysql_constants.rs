@@ -1,15 +1,23 @@
 pub static INSERT_EVENT: &str = "
 INSERT INTO
-    events
+    {events}
     (
         aggregate_type, 
         aggregate_id,
         sequence,
         payload, 
-        metadata
+        metadata,
+        event_type,
+        event_version,
+        payload_bytes,
+        metadata_bytes
     )
 VALUES
     (
+        ?,
+        ?,
+        ?,
+        ?,
         ?,
         ?,
         ?,
@@ -22,23 +30,171 @@ pub static SELECT_EVENTS: &str = "
 SELECT
     sequence,
     payload,
-    metadata
+    metadata,
+    event_type,
+    event_version,
+    CAST(UNIX_TIMESTAMP(timestamp) * 1000000 AS SIGNED),
+    payload_bytes,
+    metadata_bytes
 FROM
-    events
+    {events}
 WHERE
     aggregate_type = ?
     AND
     aggregate_id = ?
+    AND
+    sequence >= ?
+    AND
+    sequence <= ?
 ORDER BY 
     sequence;
 ";
 
-pub static INSERT_SNAPSHOT: &str = "
+pub static SELECT_ALL_EVENTS: &str = "
+SELECT
+    position,
+    aggregate_id,
+    sequence,
+    payload,
+    metadata,
+    event_type,
+    event_version,
+    CAST(UNIX_TIMESTAMP(timestamp) * 1000000 AS SIGNED),
+    payload_bytes,
+    metadata_bytes
+FROM
+    {events}
+WHERE
+    aggregate_type = ?
+    AND
+    position >= ?
+ORDER BY
+    position
+LIMIT
+    ?;
+";
+
+pub static DELETE_EVENTS: &str = "
+DELETE FROM
+    {events}
+WHERE
+    aggregate_type = ?
+    AND
+    aggregate_id = ?;
+";
+
+pub static SELECT_AGGREGATE_IDS: &str = "
+SELECT
+    aggregate_id
+FROM
+    {events}
+WHERE
+    aggregate_type = ?
+    AND
+    aggregate_id > ?
+GROUP BY
+    aggregate_id
+ORDER BY
+    aggregate_id
+LIMIT
+    ?;
+";
+
+pub static SELECT_STREAM_METADATA: &str = "
+SELECT
+    COALESCE(MAX(sequence), 0),
+    COUNT(*),
+    CAST(UNIX_TIMESTAMP(MIN(timestamp)) * 1000000 AS SIGNED),
+    CAST(UNIX_TIMESTAMP(MAX(timestamp)) * 1000000 AS SIGNED),
+    (
+        SELECT
+            version
+        FROM
+            {snapshots}
+        WHERE
+            aggregate_type = ?
+            AND
+            aggregate_id = ?
+    )
+FROM
+    {events}
+WHERE
+    aggregate_type = ?
+    AND
+    aggregate_id = ?;
+";
+
+pub static INSERT_OUTBOX_EVENT: &str = "
 INSERT INTO
-    snapshots 
+    {outbox}
+    (
+        aggregate_type,
+        aggregate_id,
+        sequence
+    )
+VALUES
+    (
+        ?,
+        ?,
+        ?
+    );
+";
+
+pub static SELECT_OUTBOX_EVENTS: &str = "
+SELECT
+    {events}.aggregate_id,
+    {events}.sequence,
+    {events}.payload,
+    {events}.metadata,
+    {events}.event_type,
+    {events}.event_version,
+    {events}.payload_bytes,
+    {events}.metadata_bytes
+FROM
+    {outbox}
+JOIN
+    {events}
+ON
+    {events}.aggregate_type = {outbox}.aggregate_type
+    AND
+    {events}.aggregate_id = {outbox}.aggregate_id
+    AND
+    {events}.sequence = {outbox}.sequence
+WHERE
+    {outbox}.aggregate_type = ?
+ORDER BY
+    {outbox}.id
+LIMIT
+    ?;
+";
+
+pub static DELETE_OUTBOX_EVENT: &str = "
+DELETE FROM
+    {outbox}
+WHERE
+    aggregate_type = ?
+    AND
+    aggregate_id = ?
+    AND
+    sequence = ?;
+";
+
+pub static DELETE_AGGREGATE_OUTBOX_EVENTS: &str = "
+DELETE FROM
+    {outbox}
+WHERE
+    aggregate_type = ?
+    AND
+    aggregate_id = ?;
+";
+
+pub static UPSERT_SNAPSHOT: &str = "
+REPLACE INTO
+    {snapshots} 
     (
         version,
         payload,
+        payload_bytes,
         aggregate_type,
         aggregate_id
     )
@@ -47,28 +203,52 @@ VALUES
         ?,
         ?,
         ?,
+        ?,
         ?
     );
 ";
 
-pub static UPDATE_SNAPSHOT: &str = "
-UPDATE
-    snapshots
-SET
-    version = ?,
-    payload = ?
+pub static SELECT_SNAPSHOT: &str = "
+SELECT
+    version,
+    payload,
+    payload_bytes
+FROM
+    {snapshots}
 WHERE
     aggregate_type = ?
     AND
     aggregate_id = ?;
 ";
 
-pub static SELECT_SNAPSHOT: &str = "
+pub static DELETE_SNAPSHOT: &str = "
+DELETE FROM
+    {snapshots}
+WHERE
+    aggregate_type = ?
+    AND
+    aggregate_id = ?;
+";
+
+pub static INSERT_TOMBSTONE: &str = "
+INSERT IGNORE INTO
+    {tombstones}
+    (
+        aggregate_type,
+        aggregate_id
+    )
+VALUES
+    (
+        ?,
+        ?
+    );
+";
+
+pub static SELECT_TOMBSTONE: &str = "
 SELECT
-    version,
-    payload
+    aggregate_id
 FROM
-    snapshots
+    {tombstones}
 WHERE
     aggregate_type = ?
     AND
@@ -77,10 +257,11 @@ WHERE
 
 pub static INSERT_QUERY: &str = "
 INSERT INTO
-    queries 
+    {queries} 
     (
         version,
         payload,
+        payload_bytes,
         aggregate_type,
         aggregate_id,
         query_type
@@ -91,16 +272,18 @@ VALUES
         ?,
         ?,
         ?,
+        ?,
         ?
     );
 ";
 
 pub static UPDATE_QUERY: &str = "
 UPDATE
-    queries
+    {queries}
 SET
     version = ?,
-    payload = ?
+    payload = ?,
+    payload_bytes = ?
 WHERE
     aggregate_type = ?
     AND
@@ -112,9 +295,21 @@ WHERE
 pub static SELECT_QUERY: &str = "
 SELECT
     version,
-    payload
+    payload,
+    payload_bytes
 FROM
-    queries
+    {queries}
+WHERE
+    aggregate_type = ?
+    AND
+    aggregate_id = ?
+    AND
+    query_type = ?;
+";
+
+pub static DELETE_QUERY: &str = "
+DELETE FROM
+    {queries}
 WHERE
     aggregate_type = ?
     AND
@@ -122,3 +317,35 @@ WHERE
     AND
     query_type = ?;
 ";
+
+pub static DELETE_QUERIES: &str = "
+DELETE FROM
+    {queries}
+WHERE
+    aggregate_type = ?
+    AND
+    query_type = ?;
+";
+
+pub static UPSERT_CHECKPOINT: &str = "
+REPLACE INTO
+    {checkpoints}
+    (
+        subscription,
+        position
+    )
+VALUES
+    (
+        ?,
+        ?
+    );
+";
+
+pub static SELECT_CHECKPOINT: &str = "
+SELECT
+    position
+FROM
+    {checkpoints}
+WHERE
+    subscription = ?;
+";
@@ -0,0 +1,23 @@
+use mysql::Error;
+
+use crate::errors::StoreError;
+
+/// MySQL/MariaDB error code for missing tables
+static NO_SUCH_TABLE: u16 = 1146;
+
+/// Classifies a MySQL/MariaDB error into a `StoreError`
+pub(super) fn driver_error(
+    message: &str,
+    e: Error,
+) -> StoreError {
+    match &e {
+        Error::IoError(_) => StoreError::connection(message, e),
+        Error::MySqlError(x) if x.code == NO_SUCH_TABLE => {
+            StoreError::NotFound {
+                message: message.to_string(),
+                source: Some(Box::new(e)),
+            }
+        },
+        _ => StoreError::backend(message, e),
+    }
+}
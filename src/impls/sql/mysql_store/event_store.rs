@@ -1,142 +1,512 @@
 use log::{
     debug,
+    error,
     trace,
 };
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    time::{
+        Duration,
+        UNIX_EPOCH,
+    },
+};
 
 use mysql::{
     prelude::Queryable,
+    Error as MySqlError,
+    Pool,
     PooledConn,
     Row,
+    TxOpts,
 };
 
 use cqrs_es2::{
     AggregateContext,
-    Error,
     EventContext,
     IAggregate,
     ICommand,
     IEvent,
 };
 
-use crate::repository::IEventStore;
+use crate::{
+    errors::{
+        ConflictError,
+        StoreError,
+    },
+    repository::{
+        event_type,
+        EncryptionScope,
+        EventUpcasters,
+        IEventStore,
+        IOutboxStore,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        PositionedEvent,
+        StoreSerializer,
+        StreamMetadata,
+        TimestampedEvent,
+    },
+};
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
+
+use super::super::{
+    mysql_constants::*,
+    TableNames,
+};
+use super::{
+    driver_error::driver_error,
+    migrations::migrate,
+    pool::get_conn,
+};
+
+/// MySQL/MariaDB error code for duplicate primary keys
+static ER_DUP_ENTRY: u16 = 1062;
+
+static LOCK_EVENTS: &str = "
+SELECT
+    GET_LOCK('cqrs_es2_{events}', 60);
+";
+
+static UNLOCK_EVENTS: &str = "
+SELECT
+    RELEASE_LOCK('cqrs_es2_{events}');
+";
 
-use super::super::mysql_constants::*;
+/// The binary payload and metadata of an event
+type EventBytes = (Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// The sequence, payload, metadata, event type, event version,
+/// timestamp, binary payload and binary metadata of an event
+type EventRow = (
+    i64,
+    String,
+    String,
+    String,
+    i64,
+    i64,
+    Option<Vec<u8>>,
+    Option<Vec<u8>>,
+);
+
+/// The position, aggregate id, sequence, payload, metadata, event
+/// type, event version, timestamp, binary payload and binary
+/// metadata of an event
+type PositionedEventRow = (
+    i64,
+    String,
+    i64,
+    String,
+    String,
+    String,
+    i64,
+    i64,
+    Option<Vec<u8>>,
+    Option<Vec<u8>>,
+);
+
+/// The aggregate id, sequence, payload, metadata, event type, event
+/// version, binary payload and binary metadata of a pending event
+type OutboxEventRow = (
+    String,
+    i64,
+    String,
+    String,
+    String,
+    i64,
+    Option<Vec<u8>>,
+    Option<Vec<u8>>,
+);
+
+/// The version, event count, first and last timestamps and snapshot
+/// version of an event stream
+type StreamMetadataRow =
+    (i64, i64, Option<i64>, Option<i64>, Option<i64>);
 
 /// Sync MySql/MariaDB event store
 pub struct EventStore<C: ICommand, E: IEvent, A: IAggregate<C, E>> {
-    conn: PooledConn,
+    pool: Pool,
+    tables: TableNames,
+    with_outbox: bool,
+    upcasters: EventUpcasters,
+    event_type: Option<fn(&E) -> String>,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A)>,
 }
 
 impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
     EventStore<C, E, A>
 {
-    /// Constructor
-    pub fn new(conn: PooledConn) -> Self {
+    /// Constructor, creates or upgrades the schema of the default
+    /// tables
+    pub fn new(pool: Pool) -> Result<Self, StoreError> {
+        Self::with_tables(pool, TableNames::default())
+    }
+
+    /// Constructor using the given table names, creates or upgrades
+    /// their schema
+    pub fn with_tables(
+        pool: Pool,
+        tables: TableNames,
+    ) -> Result<Self, StoreError> {
+        migrate(&mut get_conn(&pool)?, &tables)?;
+
         let x = Self {
-            conn,
+            pool,
+            tables,
+            with_outbox: false,
+            upcasters: EventUpcasters::default(),
+            event_type: None,
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
         };
 
         trace!("Created new sync MySQL event store");
 
-        x
+        Ok(x)
     }
-}
 
-impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
-    for EventStore<C, E, A>
-{
-    /// Save new events
-    fn save_events(
-        &mut self,
-        contexts: &Vec<EventContext<C, E>>,
-    ) -> Result<(), Error> {
-        if contexts.len() == 0 {
-            trace!("Skip saving zero contexts");
-            return Ok(());
+    /// Enables the transactional outbox, new events are added to
+    /// the outbox table in the same transaction for an
+    /// `OutboxRelay` to deliver
+    pub fn with_outbox(mut self) -> Self {
+        self.with_outbox = true;
+        self
+    }
+
+    /// Upcasts the stored payloads with `upcasters` before they are
+    /// deserialized
+    pub fn with_upcasters(
+        mut self,
+        upcasters: EventUpcasters,
+    ) -> Self {
+        self.upcasters = upcasters;
+        self
+    }
+
+    /// Names the event types stored with the events with
+    /// `event_type` instead of their enum variants
+    pub fn with_event_type(
+        mut self,
+        event_type: fn(&E) -> String,
+    ) -> Self {
+        self.event_type = Some(event_type);
+        self
+    }
+
+    /// Stores the payloads, metadata and snapshots with
+    /// `serializer`, the binary formats use the blob columns
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
+
+    /// Compresses the payloads, metadata and snapshots longer than
+    /// `threshold` bytes with `compressor`, the uncompressed ones
+    /// keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
+
+    /// Encrypts the event payloads and the snapshots with
+    /// `encryption`, the events record the id of their key in their
+    /// metadata
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
+    }
+
+    fn lock_events(
+        &self,
+        conn: &mut PooledConn,
+    ) -> Result<(), StoreError> {
+        let res: Option<Option<i64>> = match conn
+            .query_first(self.tables.sql(LOCK_EVENTS))
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to lock events table",
+                    e,
+                ));
+            },
+        };
+
+        match res {
+            Some(Some(1)) => Ok(()),
+            _ => {
+                Err(StoreError::Backend {
+                    message: "unable to lock events table before \
+                              timeout"
+                        .to_string(),
+                    source: None,
+                })
+            },
         }
+    }
 
+    fn unlock_events(
+        &self,
+        conn: &mut PooledConn,
+    ) {
+        match conn.query_drop(self.tables.sql(UNLOCK_EVENTS)) {
+            Ok(_) => {},
+            Err(e) => {
+                error!(
+                    "unable to unlock events table with error: {}",
+                    e
+                );
+            },
+        };
+    }
+
+    /// Inserts the events in a single transaction
+    fn insert_events(
+        &self,
+        conn: &mut PooledConn,
+        aggregate_id: &str,
+        contexts: &[EventContext<C, E>],
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
 
-        let aggregate_id = contexts
-            .first()
-            .unwrap()
-            .aggregate_id
-            .clone();
+        let with_outbox = self.with_outbox;
 
-        debug!(
-            "storing '{}' new events for aggregate id '{}'",
-            contexts.len(),
-            &aggregate_id
-        );
+        let mut trans = match conn
+            .start_transaction(TxOpts::default()) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to start a transaction for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
 
         for context in contexts {
             let payload =
-                match serde_json::to_string(&context.payload) {
+                match serde_json::to_value(&context.payload) {
                     Ok(x) => x,
                     Err(e) => {
-                        return Err(Error::new(
+                        return Err(StoreError::serialization(
                             format!(
                                 "unable to serialize the event \
-                                 payload for aggregate id '{}' with \
-                                 error: {}",
-                                &aggregate_id, e
+                                 payload for aggregate id '{}'",
+                                &aggregate_id
                             )
                             .as_str(),
+                            e,
                         ));
                     },
                 };
 
-            let metadata =
-                match serde_json::to_string(&context.metadata) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        return Err(Error::new(
-                            format!(
-                                "unable to serialize the event \
-                                 metadata for aggregate id '{}' \
-                                 with error: {}",
-                                &aggregate_id, e
-                            )
-                            .as_str(),
-                        ));
-                    },
-                };
+            let event_type = match self.event_type {
+                Some(x) => x(&context.payload),
+                None => event_type(&payload),
+            };
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id,
+                sequence: context.sequence,
+            };
+
+            let (payload, metadata) = match self
+                .serializer
+                .encrypt_event(&scope, payload, &context.metadata)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to encrypt the event payload for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let metadata = match self.serializer.to_text(&metadata) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to serialize the event metadata \
+                             for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let event_version =
+                self.upcasters.event_version(&event_type);
 
-            match self.conn.exec_drop(
-                INSERT_EVENT,
+            let payload = match self.serializer.split_text(payload) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to serialize the event payload \
+                             for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            match trans.exec_drop(
+                self.tables.sql(INSERT_EVENT).as_str(),
                 (
                     &aggregate_type,
                     &aggregate_id,
                     context.sequence,
-                    &payload,
-                    &metadata,
+                    &payload.0,
+                    &metadata.0,
+                    &event_type,
+                    event_version,
+                    &payload.1,
+                    &metadata.1,
                 ),
             ) {
                 Ok(_) => {},
+                Err(MySqlError::MySqlError(e))
+                    if e.code == ER_DUP_ENTRY =>
+                {
+                    return Err(ConflictError::new(
+                        aggregate_type,
+                        aggregate_id,
+                        context.sequence,
+                    )
+                    .into());
+                },
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(driver_error(
                         format!(
                             "unable to insert new event for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+
+            if !with_outbox {
+                continue;
+            }
+
+            match trans.exec_drop(
+                self.tables.sql(INSERT_OUTBOX_EVENT).as_str(),
+                (&aggregate_type, &aggregate_id, context.sequence),
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to insert new outbox event for \
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             }
         }
 
+        match trans.commit() {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to commit new events for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        }
+
         Ok(())
     }
+}
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
+    for EventStore<C, E, A>
+{
+    /// Save new events
+    fn save_events(
+        &self,
+        contexts: &Vec<EventContext<C, E>>,
+    ) -> Result<(), StoreError> {
+        if contexts.len() == 0 {
+            trace!("Skip saving zero contexts");
+            return Ok(());
+        }
 
-    /// Load all events for a particular `aggregate_id`
-    fn load_events(
-        &mut self,
+        let aggregate_id = contexts
+            .first()
+            .unwrap()
+            .aggregate_id
+            .clone();
+
+        debug!(
+            "storing '{}' new events for aggregate id '{}'",
+            contexts.len(),
+            &aggregate_id
+        );
+
+        // the lock is held by the session, the events are inserted
+        // and the lock released through the same connection
+        let mut conn = get_conn(&self.pool)?;
+
+        // positions are assigned on insert, commits are serialized
+        // so that they become visible in order
+        self.lock_events(&mut conn)?;
+
+        let res =
+            self.insert_events(&mut conn, &aggregate_id, contexts);
+
+        self.unlock_events(&mut conn);
+
+        res
+    }
+
+    /// Load the events for a particular `aggregate_id` within a
+    /// range of sequences along with their commit timestamps
+    fn load_timestamped_events_range(
+        &self,
         aggregate_id: &str,
-    ) -> Result<Vec<EventContext<C, E>>, Error> {
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -144,71 +514,442 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             aggregate_id
         );
 
-        let rows: Vec<(i64, String, String)> = match self.conn.exec(
-            SELECT_EVENTS,
-            (&aggregate_type, &aggregate_id),
-        ) {
-            Ok(x) => x,
-            Err(e) => {
-                return Err(Error::new(
-                    format!(
-                        "unable to load events table for aggregate \
-                         id '{}' with error: {}",
-                        &aggregate_id, e
-                    )
-                    .as_str(),
-                ));
-            },
-        };
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows: Vec<EventRow> =
+            match conn.exec(
+                self.tables.sql(SELECT_EVENTS).as_str(),
+                (
+                    &aggregate_type,
+                    &aggregate_id,
+                    from_sequence,
+                    to_sequence.unwrap_or(i64::MAX),
+                ),
+            ) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to load events table for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
 
         let mut result = Vec::new();
 
         for row in rows {
-            let payload = match serde_json::from_str(row.1.as_str()) {
+            let bytes: EventBytes = (row.6, row.7);
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id,
+                sequence: row.0,
+            };
+
+            let payload = match self
+                .serializer
+                .deserialize_text_event(
+                    &scope,
+                    &self.upcasters,
+                    &row.3,
+                    row.1,
+                    bytes.0,
+                    row.4,
+                )
+            {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::deserialization(
                         format!(
                             "bad payload found in events table for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-            let metadata = match serde_json::from_str(row.2.as_str())
+            let metadata = match self
+                .serializer
+                .deserialize_text(row.2, bytes.1)
             {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::deserialization(
                         format!(
                             "bad metadata found in events table for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-            result.push(EventContext::new(
-                aggregate_id.to_string(),
+            result.push(TimestampedEvent::new(
+                UNIX_EPOCH + Duration::from_micros(row.5 as u64),
+                EventContext::new(
+                    aggregate_id.to_string(),
+                    row.0,
+                    payload,
+                    metadata,
+                ),
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Load up to `limit` events of all aggregates of this type in
+    /// commit order, starting at the global position
+    /// `from_position`
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "reading up to '{}' events from position {}",
+            limit,
+            from_position
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows: Vec<PositionedEventRow> =
+            match conn.exec(
+                self.tables.sql(SELECT_ALL_EVENTS).as_str(),
+                (&aggregate_type, from_position, limit as i64),
+            ) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to load events table from \
+                             position {}",
+                            from_position
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+        let mut result = Vec::new();
+
+        for row in rows {
+            let bytes: EventBytes = (row.8, row.9);
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &row.1,
+                sequence: row.2,
+            };
+
+            let payload = match self
+                .serializer
+                .deserialize_text_event(
+                    &scope,
+                    &self.upcasters,
+                    &row.5,
+                    row.3,
+                    bytes.0,
+                    row.6,
+                )
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad payload found in events table for \
+                             aggregate id '{}'",
+                            &row.1
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let metadata = match self
+                .serializer
+                .deserialize_text(row.4, bytes.1)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad metadata found in events table for \
+                             aggregate id '{}'",
+                            &row.1
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            result.push(PositionedEvent::new(
                 row.0,
-                payload,
-                metadata,
+                UNIX_EPOCH + Duration::from_micros(row.7 as u64),
+                EventContext::new(row.1, row.2, payload, metadata),
             ));
         }
 
         Ok(result)
     }
 
+    /// List up to `limit` ids of the aggregates of this type in
+    /// ascending order, starting after `after_aggregate_id`
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "listing up to '{}' aggregate ids after '{:?}'",
+            limit,
+            after_aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.exec(
+            self.tables.sql(SELECT_AGGREGATE_IDS).as_str(),
+            (
+                &aggregate_type,
+                after_aggregate_id.unwrap_or(""),
+                limit as i64,
+            ),
+        ) {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                Err(driver_error(
+                    "unable to list aggregate ids from events table",
+                    e,
+                ))
+            },
+        }
+    }
+
+    /// Load the metadata of the event stream of a particular
+    /// `aggregate_id`
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "loading stream metadata for aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let row: Option<StreamMetadataRow> =
+            match conn.exec_first(
+                self.tables
+                    .sql(SELECT_STREAM_METADATA)
+                    .as_str(),
+                (
+                    &aggregate_type,
+                    &aggregate_id,
+                    &aggregate_type,
+                    &aggregate_id,
+                ),
+            ) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to load stream metadata for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+        let row = match row {
+            Some(x) if x.1 > 0 => x,
+            _ => {
+                return Ok(None);
+            },
+        };
+
+        Ok(Some(StreamMetadata::new(
+            aggregate_id,
+            row.0,
+            row.1,
+            UNIX_EPOCH +
+                Duration::from_micros(row.2.unwrap_or(0) as u64),
+            UNIX_EPOCH +
+                Duration::from_micros(row.3.unwrap_or(0) as u64),
+            row.4,
+        )))
+    }
+
+    /// Delete all events of a particular `aggregate_id` along with
+    /// its snapshot and outbox entries
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting events and snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let mut trans = match conn
+            .start_transaction(TxOpts::default()) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to start a transaction for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        for x in [
+            DELETE_AGGREGATE_OUTBOX_EVENTS,
+            DELETE_EVENTS,
+            DELETE_SNAPSHOT,
+        ] {
+            match trans.exec_drop(
+                self.tables.sql(x).as_str(),
+                (&aggregate_type, &aggregate_id),
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to delete the stream of \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+        }
+
+        match trans.commit() {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to commit the deletion of aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Mark the stream of a particular `aggregate_id` as tombstoned
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!("tombstoning aggregate id '{}'", aggregate_id);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.exec_drop(
+            self.tables.sql(INSERT_TOMBSTONE).as_str(),
+            (&aggregate_type, &aggregate_id),
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert tombstone for aggregate id \
+                         '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Check whether the stream of a particular `aggregate_id` was
+    /// tombstoned
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "checking tombstone of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let res: Result<Option<String>, _> = conn.exec_first(
+            self.tables.sql(SELECT_TOMBSTONE).as_str(),
+            (&aggregate_type, &aggregate_id),
+        );
+
+        match res {
+            Ok(x) => Ok(x.is_some()),
+            Err(e) => {
+                Err(driver_error(
+                    format!(
+                        "unable to load tombstones table for \
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ))
+            },
+        }
+    }
+
     /// save a new aggregate snapshot
     fn save_aggregate_snapshot(
-        &mut self,
+        &self,
         context: AggregateContext<C, E, A>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
 
         let aggregate_id = context.aggregate_id;
@@ -218,43 +959,86 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             &aggregate_id
         );
 
-        let sql = match context.version {
-            1 => INSERT_SNAPSHOT,
-            _ => UPDATE_SNAPSHOT,
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id: &aggregate_id,
         };
 
-        let payload = match serde_json::to_string(&context.payload) {
+        let payload = match self
+            .serializer
+            .encrypt_to_text(&scope, &context.payload)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize aggregate snapshot for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        match self.conn.exec_drop(
-            sql,
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.exec_drop(
+            self.tables.sql(UPSERT_SNAPSHOT).as_str(),
             (
                 context.version,
-                &payload,
+                &payload.0,
+                &payload.1,
                 &aggregate_type,
                 &aggregate_id,
             ),
         ) {
             Ok(_) => {},
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to insert/update snapshot for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Delete the snapshot of a particular `aggregate_id`
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting the snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.exec_drop(
+            self.tables.sql(DELETE_SNAPSHOT).as_str(),
+            (&aggregate_type, &aggregate_id),
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete snapshot for aggregate id \
+                         '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -264,9 +1048,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 
     /// Load aggregate at current state from snapshots
     fn load_aggregate_from_snapshot(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<AggregateContext<C, E, A>, Error> {
+    ) -> Result<AggregateContext<C, E, A>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -274,19 +1058,22 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             aggregate_id
         );
 
-        let result: Option<Row> = match self.conn.exec_first(
-            SELECT_SNAPSHOT,
+        let mut conn = get_conn(&self.pool)?;
+
+        let result: Option<Row> = match conn.exec_first(
+            self.tables.sql(SELECT_SNAPSHOT).as_str(),
             (&aggregate_type, &aggregate_id),
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load snapshots table for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -310,17 +1097,27 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 
         let version: i64 = row.get(0).unwrap();
         let payload: String = row.get(1).unwrap();
+        let payload_bytes: Option<Vec<u8>> = row.get(2).unwrap();
+
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id,
+        };
 
-        let payload = match serde_json::from_str(payload.as_str()) {
+        let payload = match self
+            .serializer
+            .decrypt_text(&scope, payload, payload_bytes)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in snapshots table for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -332,3 +1129,132 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         ))
     }
 }
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IOutboxStore<C, E, A>
+    for EventStore<C, E, A>
+{
+    /// Load up to `limit` pending events in commit order
+    fn load_pending_events(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!("loading up to '{}' pending outbox events", limit);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows: Vec<OutboxEventRow> =
+            match conn.exec(
+                self.tables.sql(SELECT_OUTBOX_EVENTS).as_str(),
+                (&aggregate_type, limit as i64),
+            ) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(driver_error(
+                        "unable to load outbox table",
+                        e,
+                    ));
+                },
+            };
+
+        let mut result = Vec::new();
+
+        for row in rows {
+            let bytes: EventBytes = (row.6, row.7);
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &row.0,
+                sequence: row.1,
+            };
+
+            let payload = match self
+                .serializer
+                .deserialize_text_event(
+                    &scope,
+                    &self.upcasters,
+                    &row.4,
+                    row.2,
+                    bytes.0,
+                    row.5,
+                )
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad payload found in events table for \
+                             aggregate id '{}'",
+                            &row.0
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let metadata = match self
+                .serializer
+                .deserialize_text(row.3, bytes.1)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad metadata found in events table for \
+                             aggregate id '{}'",
+                            &row.0
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            result.push(EventContext::new(
+                row.0, row.1, payload, metadata,
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Mark events as delivered so that they are not loaded again
+    fn mark_events_delivered(
+        &self,
+        contexts: &[EventContext<C, E>],
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!("marking '{}' outbox events delivered", contexts.len());
+
+        let mut conn = get_conn(&self.pool)?;
+
+        for context in contexts {
+            match conn.exec_drop(
+                self.tables.sql(DELETE_OUTBOX_EVENT).as_str(),
+                (
+                    &aggregate_type,
+                    &context.aggregate_id,
+                    context.sequence,
+                ),
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to delete outbox event for \
+                             aggregate id '{}'",
+                            &context.aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,373 @@
+use log::{
+    debug,
+    error,
+    trace,
+};
+
+use mysql::{
+    prelude::Queryable,
+    PooledConn,
+};
+
+use crate::errors::StoreError;
+
+use super::super::{
+    migration::Migration,
+    table_names::TableNames,
+};
+use super::driver_error::driver_error;
+
+static LOCK_MIGRATIONS: &str = "
+SELECT
+    GET_LOCK('cqrs_es2_{migrations}', 60);
+";
+
+static UNLOCK_MIGRATIONS: &str = "
+SELECT
+    RELEASE_LOCK('cqrs_es2_{migrations}');
+";
+
+static CREATE_MIGRATIONS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS
+    {migrations}
+    (
+        version     bigint       NOT NULL,
+        description VARCHAR(256) NOT NULL,
+        applied_at  timestamp DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (version)
+    );
+";
+
+static SELECT_SCHEMA_VERSION: &str = "
+SELECT
+    COALESCE(MAX(version), 0)
+FROM
+    {migrations};
+";
+
+static INSERT_MIGRATION: &str = "
+INSERT INTO
+    {migrations}
+    (
+        version,
+        description
+    )
+VALUES
+    (
+        ?,
+        ?
+    );
+";
+
+/// The schema of the MySQL/MariaDB stores, the tables of the first
+/// migration are only created if they do not exist yet so that
+/// databases set up before the migrations are adopted
+pub(super) static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create the events, snapshots and queries tables",
+        statements: &[
+            "
+CREATE TABLE IF NOT EXISTS
+    {events}
+    (
+        aggregate_type VARCHAR(256)                 NOT NULL,
+        aggregate_id   VARCHAR(256)                 NOT NULL,
+        sequence       bigint CHECK (sequence >= 0) NOT NULL,
+        payload        TEXT                         NOT NULL,
+        metadata       TEXT                         NOT NULL,
+        timestamp      timestamp DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (aggregate_type, aggregate_id, sequence)
+    );
+",
+            "
+CREATE TABLE IF NOT EXISTS
+    {snapshots}
+    (
+        aggregate_type VARCHAR(256)                NOT NULL,
+        aggregate_id   VARCHAR(256)                NOT NULL,
+        version        bigint CHECK (version >= 0) NOT NULL,
+        payload        TEXT                        NOT NULL,
+        timestamp      timestamp DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (aggregate_type, aggregate_id)
+    );
+",
+            "
+CREATE TABLE IF NOT EXISTS
+    {queries}
+    (
+        aggregate_type VARCHAR(256)                NOT NULL,
+        aggregate_id   VARCHAR(256)                NOT NULL,
+        query_type     VARCHAR(256)                NOT NULL,
+        version        bigint CHECK (version >= 0) NOT NULL,
+        payload        TEXT                        NOT NULL,
+        PRIMARY KEY (aggregate_type, aggregate_id, query_type)
+    );
+",
+        ],
+    },
+    Migration {
+        version: 2,
+        description: "add the global positions of the events",
+        statements: &["
+ALTER TABLE
+    {events}
+ADD COLUMN
+    position bigint NOT NULL AUTO_INCREMENT UNIQUE;
+"],
+    },
+    Migration {
+        version: 3,
+        description: "create the outbox table",
+        statements: &["
+CREATE TABLE IF NOT EXISTS
+    {outbox}
+    (
+        id             bigint                       AUTO_INCREMENT,
+        aggregate_type VARCHAR(256)                 NOT NULL,
+        aggregate_id   VARCHAR(256)                 NOT NULL,
+        sequence       bigint CHECK (sequence >= 0) NOT NULL,
+        PRIMARY KEY (id),
+        UNIQUE (aggregate_type, aggregate_id, sequence)
+    );
+"],
+    },
+    Migration {
+        version: 4,
+        description: "create the checkpoints table",
+        statements: &["
+CREATE TABLE IF NOT EXISTS
+    {checkpoints}
+    (
+        subscription VARCHAR(256)                 NOT NULL,
+        position     bigint CHECK (position >= 0) NOT NULL,
+        PRIMARY KEY (subscription)
+    );
+"],
+    },
+    Migration {
+        version: 5,
+        description: "add the schema versions of the events",
+        statements: &["
+ALTER TABLE
+    {events}
+ADD COLUMN
+    event_version bigint NOT NULL DEFAULT 1;
+"],
+    },
+    Migration {
+        version: 6,
+        description: "add the types of the events",
+        statements: &[
+            "
+ALTER TABLE
+    {events}
+ADD COLUMN
+    event_type VARCHAR(256) NOT NULL DEFAULT '';
+",
+            "
+UPDATE
+    {events}
+SET
+    event_type = CASE JSON_TYPE(payload)
+        WHEN 'OBJECT' THEN
+            JSON_UNQUOTE(JSON_EXTRACT(JSON_KEYS(payload), '$[0]'))
+        WHEN 'STRING' THEN
+            JSON_UNQUOTE(payload)
+        ELSE ''
+    END;
+",
+            "
+CREATE INDEX
+    {events}_event_type
+ON
+    {events}
+    (
+        aggregate_type,
+        event_type
+    );
+",
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "keep the event timestamps in microseconds",
+        statements: &["
+ALTER TABLE
+    {events}
+MODIFY COLUMN
+    timestamp timestamp(6) NULL DEFAULT CURRENT_TIMESTAMP(6);
+"],
+    },
+    Migration {
+        version: 8,
+        description: "create the tombstones table",
+        statements: &["
+CREATE TABLE IF NOT EXISTS
+    {tombstones}
+    (
+        aggregate_type VARCHAR(256) NOT NULL,
+        aggregate_id   VARCHAR(256) NOT NULL,
+        timestamp      timestamp DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (aggregate_type, aggregate_id)
+    );
+"],
+    },
+    // the text columns of the binary rows are left empty
+    Migration {
+        version: 9,
+        description: "add the binary payloads",
+        statements: &[
+            "
+ALTER TABLE
+    {events}
+    ADD COLUMN payload_bytes LONGBLOB,
+    ADD COLUMN metadata_bytes LONGBLOB;
+",
+            "
+ALTER TABLE
+    {snapshots}
+ADD COLUMN
+    payload_bytes LONGBLOB;
+",
+            "
+ALTER TABLE
+    {queries}
+ADD COLUMN
+    payload_bytes LONGBLOB;
+",
+        ],
+    },
+];
+
+/// Applies the migrations missing from the tables.
+///
+/// MySQL commits schema changes implicitly, the migrations are
+/// serialized by a named lock and each version is recorded right
+/// after its statements.
+pub(super) fn migrate(
+    conn: &mut PooledConn,
+    tables: &TableNames,
+) -> Result<(), StoreError> {
+    tables.validate()?;
+
+    let res: Option<Option<i64>> =
+        match conn.query_first(tables.sql(LOCK_MIGRATIONS)) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to lock the migrations",
+                    e,
+                ));
+            },
+        };
+
+    match res {
+        Some(Some(1)) => {},
+        _ => {
+            return Err(StoreError::Backend {
+                message: "unable to lock the migrations before \
+                          timeout"
+                    .to_string(),
+                source: None,
+            });
+        },
+    }
+
+    let res = apply_migrations(conn, tables);
+
+    match conn.query_drop(tables.sql(UNLOCK_MIGRATIONS)) {
+        Ok(_) => {},
+        Err(e) => {
+            error!(
+                "unable to unlock the migrations with error: {}",
+                e
+            );
+        },
+    };
+
+    res
+}
+
+fn apply_migrations(
+    conn: &mut PooledConn,
+    tables: &TableNames,
+) -> Result<(), StoreError> {
+    match conn.query_drop(tables.sql(CREATE_MIGRATIONS_TABLE)) {
+        Ok(_) => {},
+        Err(e) => {
+            return Err(driver_error(
+                format!(
+                    "unable to create migrations table '{}'",
+                    &tables.migrations
+                )
+                .as_str(),
+                e,
+            ));
+        },
+    }
+
+    let version: Option<i64> =
+        match conn.query_first(tables.sql(SELECT_SCHEMA_VERSION)) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load migrations table '{}'",
+                        &tables.migrations
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+    let version = version.unwrap_or(0);
+
+    trace!("schema of '{}' at version {}", &tables.events, version);
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|x| x.version > version)
+    {
+        debug!(
+            "applying migration {} '{}'",
+            migration.version, migration.description
+        );
+
+        for x in migration.statements {
+            match conn.query_drop(tables.sql(x)) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to apply migration {} '{}'",
+                            migration.version, migration.description
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+        }
+
+        match conn.exec_drop(
+            tables.sql(INSERT_MIGRATION),
+            (migration.version, migration.description),
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to record migration {}",
+                        migration.version
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use mysql::{
+    Pool,
+    PooledConn,
+};
+
+use crate::errors::StoreError;
+
+use super::driver_error::driver_error;
+
+/// Takes a connection from the pool for a single operation
+pub(super) fn get_conn(
+    pool: &Pool
+) -> Result<PooledConn, StoreError> {
+    match pool.get_conn() {
+        Ok(x) => Ok(x),
+        Err(e) => {
+            Err(driver_error(
+                "unable to get a connection from the pool",
+                e,
+            ))
+        },
+    }
+}
@@ -6,7 +6,7 @@ use std::marker::PhantomData;
 
 use mysql::{
     prelude::Queryable,
-    PooledConn,
+    Pool,
     Row,
 };
 
@@ -20,12 +20,29 @@ use cqrs_es2::{
     QueryContext,
 };
 
-use crate::repository::{
-    IEventDispatcher,
-    IQueryStore,
+use crate::{
+    errors::StoreError,
+    repository::{
+        EncryptionScope,
+        IEventDispatcher,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        IQueryStore,
+        StoreSerializer,
+    },
 };
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
 
-use super::super::mysql_constants::*;
+use super::super::{
+    mysql_constants::*,
+    TableNames,
+};
+use super::{
+    driver_error::driver_error,
+    migrations::migrate,
+    pool::get_conn,
+};
 
 /// Sync MySql/MariaDB query store
 pub struct QueryStore<
@@ -34,7 +51,10 @@ pub struct QueryStore<
     A: IAggregate<C, E>,
     Q: IQuery<C, E>,
 > {
-    conn: PooledConn,
+    pool: Pool,
+    tables: TableNames,
+    query_type: String,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A, Q)>,
 }
 
@@ -45,16 +65,73 @@ impl<
         Q: IQuery<C, E>,
     > QueryStore<C, E, A, Q>
 {
-    /// Constructor
-    pub fn new(conn: PooledConn) -> Self {
+    /// Constructor, creates or upgrades the schema of the default
+    /// tables
+    pub fn new(pool: Pool) -> Result<Self, StoreError> {
+        Self::with_tables(pool, TableNames::default())
+    }
+
+    /// Constructor using the given table names, creates or upgrades
+    /// their schema
+    pub fn with_tables(
+        pool: Pool,
+        tables: TableNames,
+    ) -> Result<Self, StoreError> {
+        migrate(&mut get_conn(&pool)?, &tables)?;
+
         let x = Self {
-            conn,
+            pool,
+            tables,
+            query_type: Q::query_type().to_string(),
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
         };
 
         trace!("Created new sync MySQL query store");
 
-        x
+        Ok(x)
+    }
+
+    /// Stores the queries under a versioned query type, e.g. to
+    /// rebuild a new version of the queries next to the one in use
+    pub fn with_query_version(
+        mut self,
+        version: &str,
+    ) -> Self {
+        self.query_type = format!("{}_{}", Q::query_type(), version);
+        self
+    }
+
+    /// Stores the queries with `serializer`, the binary formats use
+    /// the blob column
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
+
+    /// Compresses the queries longer than `threshold` bytes
+    /// with `compressor`, the uncompressed ones keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
+
+    /// Encrypts the queries with `encryption`, the queries stored
+    /// in clear keep loading
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
     }
 }
 
@@ -67,11 +144,11 @@ impl<
 {
     /// saves the updated query
     fn save_query(
-        &mut self,
+        &self,
         context: QueryContext<C, E, Q>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         let aggregate_id = context.aggregate_id;
 
@@ -81,29 +158,42 @@ impl<
         );
 
         let sql = match context.version {
-            1 => INSERT_QUERY,
-            _ => UPDATE_QUERY,
+            1 => self.tables.sql(INSERT_QUERY),
+            _ => self.tables.sql(UPDATE_QUERY),
         };
 
-        let payload = match serde_json::to_string(&context.payload) {
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id: &aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .encrypt_to_text(&scope, &context.payload)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize the payload of query \
-                         '{}' with aggregate id '{}', error: {}",
-                        &query_type, &aggregate_id, e,
+                         '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        match self.conn.exec_drop(
-            sql,
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.exec_drop(
+            sql.as_str(),
             (
                 context.version,
-                &payload,
+                &payload.0,
+                &payload.1,
                 &aggregate_type,
                 &aggregate_id,
                 &query_type,
@@ -111,13 +201,14 @@ impl<
         ) {
             Ok(_) => {},
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to insert/update query for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -127,11 +218,11 @@ impl<
 
     /// loads the most recent query
     fn load_query(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<QueryContext<C, E, Q>, Error> {
+    ) -> Result<QueryContext<C, E, Q>, StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         trace!(
             "loading query '{}' for aggregate id '{}'",
@@ -139,8 +230,10 @@ impl<
             aggregate_id
         );
 
-        let result: Option<Row> = match self.conn.exec_first(
-            SELECT_QUERY,
+        let mut conn = get_conn(&self.pool)?;
+
+        let result: Option<Row> = match conn.exec_first(
+            self.tables.sql(SELECT_QUERY).as_str(),
             (
                 &aggregate_type,
                 &aggregate_id,
@@ -149,13 +242,14 @@ impl<
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load queries table for query \
-                         '{}' with aggregate id '{}', error: {}",
-                        &query_type, &aggregate_id, e,
+                         '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -180,18 +274,28 @@ impl<
 
         let version: i64 = row.get(0).unwrap();
         let payload: String = row.get(1).unwrap();
+        let payload_bytes: Option<Vec<u8>> = row.get(2).unwrap();
 
-        let payload = match serde_json::from_str(payload.as_str()) {
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .decrypt_text(&scope, payload, payload_bytes)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in queries table for \
-                         query '{}' with aggregate id '{}', error: \
-                         {}",
-                        &query_type, &aggregate_id, e,
+                         query '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -202,6 +306,70 @@ impl<
             payload,
         ))
     }
+    /// deletes the query of a particular `aggregate_id`
+    fn delete_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!(
+            "deleting query '{}' for aggregate id '{}'",
+            query_type, aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.exec_drop(
+            self.tables.sql(DELETE_QUERY).as_str(),
+            (&aggregate_type, aggregate_id, &query_type),
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete query '{}' for aggregate \
+                         id '{}'",
+                        &query_type, aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// deletes all queries of the query type
+    fn delete_queries(&self) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!("deleting all queries '{}'", query_type);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.exec_drop(
+            self.tables.sql(DELETE_QUERIES).as_str(),
+            (&aggregate_type, &query_type),
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete queries '{}'",
+                        &query_type
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
 }
 
 impl<
@@ -212,10 +380,10 @@ impl<
     > IEventDispatcher<C, E> for QueryStore<C, E, A, Q>
 {
     fn dispatch(
-        &mut self,
+        &self,
         aggregate_id: &str,
         events: &Vec<EventContext<C, E>>,
     ) -> Result<(), Error> {
-        self.dispatch_events(aggregate_id, events)
+        Ok(self.dispatch_events(aggregate_id, events)?)
     }
 }
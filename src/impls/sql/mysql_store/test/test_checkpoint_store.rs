@@ -0,0 +1,42 @@
+use mysql::{
+    Error,
+    Opts,
+    Pool,
+};
+
+use crate::{
+    mysql_store::CheckpointStore,
+    ICheckpointStore,
+};
+
+use super::common::*;
+
+fn check_save_load_checkpoints(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+    let store = CheckpointStore::new(pool).unwrap();
+
+    let subscription = uuid::Uuid::new_v4().to_string();
+
+    assert_eq!(store.load_checkpoint(&subscription).unwrap(), 0);
+
+    store
+        .save_checkpoint(&subscription, 5).unwrap();
+    assert_eq!(store.load_checkpoint(&subscription).unwrap(), 5);
+
+    store
+        .save_checkpoint(&subscription, 7).unwrap();
+    assert_eq!(store.load_checkpoint(&subscription).unwrap(), 7);
+
+    Ok(())
+}
+
+#[test]
+fn test_mariadb_save_load_checkpoints() {
+    check_save_load_checkpoints(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_save_load_checkpoints() {
+    check_save_load_checkpoints(CONNECTION_STRING_MYSQL).unwrap();
+}
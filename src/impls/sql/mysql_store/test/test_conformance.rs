@@ -0,0 +1,90 @@
+use mysql::{
+    Opts,
+    Pool,
+};
+
+use cqrs_es2::{
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+};
+
+use crate::{
+    conformance::*,
+    mysql_store::{
+        EventStore,
+        QueryStore,
+    },
+    IEventStore,
+    IQueryStore,
+};
+
+use super::common::*;
+
+struct StoreFactory {
+    pool: Pool,
+}
+
+impl StoreFactory {
+    fn new(uri: &str) -> Self {
+        let opts = Opts::from_url(uri).unwrap();
+
+        Self {
+            pool: Pool::new(opts).unwrap(),
+        }
+    }
+}
+
+impl IStoreFactory for StoreFactory {
+    fn event_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IEventStore<C, E, A>> {
+        Box::new(EventStore::new(self.pool.clone()).unwrap())
+    }
+
+    fn query_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+        Q: IQuery<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IQueryStore<C, E, A, Q>> {
+        Box::new(
+            QueryStore::<C, E, A, Q>::new(self.pool.clone()).unwrap(),
+        )
+    }
+}
+
+#[test]
+fn test_mariadb_event_store_conformance() {
+    check_event_store(&mut StoreFactory::new(
+        CONNECTION_STRING_MARIADB,
+    ));
+}
+
+#[test]
+fn test_mysql_event_store_conformance() {
+    check_event_store(&mut StoreFactory::new(
+        CONNECTION_STRING_MYSQL,
+    ));
+}
+
+#[test]
+fn test_mariadb_query_store_conformance() {
+    check_query_store(&mut StoreFactory::new(
+        CONNECTION_STRING_MARIADB,
+    ));
+}
+
+#[test]
+fn test_mysql_query_store_conformance() {
+    check_query_store(&mut StoreFactory::new(
+        CONNECTION_STRING_MYSQL,
+    ));
+}
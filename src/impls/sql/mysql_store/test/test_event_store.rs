@@ -1,6 +1,11 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    thread,
+};
 
 use mysql::{
+    prelude::Queryable,
     Error,
     Opts,
     Pool,
@@ -15,6 +20,8 @@ use cqrs_es2::{
 use crate::{
     mysql_store::EventStore,
     IEventStore,
+    IOutboxStore,
+    Repository,
 };
 
 use super::common::*;
@@ -32,9 +39,7 @@ pub fn get_metadata() -> HashMap<String, String> {
 fn check_save_load_events(uri: &str) -> Result<(), Error> {
     let opts = Opts::from_url(uri)?;
     let pool = Pool::new(opts)?;
-    let conn = pool.get_conn()?;
-
-    let mut store = ThisEventStore::new(conn);
+    let store = ThisEventStore::new(pool).unwrap();
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -96,12 +101,109 @@ fn check_save_load_events(uri: &str) -> Result<(), Error> {
     Ok(())
 }
 
-fn check_save_load_snapshots(uri: &str) -> Result<(), Error> {
+fn check_save_conflicting_events(uri: &str) -> Result<(), Error> {
     let opts = Opts::from_url(uri)?;
     let pool = Pool::new(opts)?;
-    let conn = pool.get_conn()?;
+    let store = ThisEventStore::new(pool).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        metadata.clone(),
+    )];
 
-    let mut store = ThisEventStore::new(conn);
+    store.save_events(&contexts).unwrap();
+
+    let conflicting_contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::EmailUpdated(EmailUpdated {
+            new_email: "test A".to_string(),
+        }),
+        metadata,
+    )];
+
+    let err = store
+        .save_events(&conflicting_contexts)
+        .unwrap_err();
+
+    assert!(err.is_conflict());
+
+    let stored_events = store.load_events(&id).unwrap();
+    assert_eq!(stored_events, contexts);
+
+    Ok(())
+}
+
+fn check_save_events_atomically(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+    let store = ThisEventStore::new(pool).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        metadata.clone(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    let failing_contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            3,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test B".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::AddressUpdated(AddressUpdated {
+                new_address: "something else happening here"
+                    .to_string(),
+            }),
+            metadata,
+        ),
+    ];
+
+    store
+        .save_events(&failing_contexts)
+        .unwrap_err();
+
+    let stored_events = store.load_events(&id).unwrap();
+    assert_eq!(stored_events, contexts);
+
+    Ok(())
+}
+
+fn check_save_load_snapshots(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+    let store = ThisEventStore::new(pool).unwrap();
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -162,11 +264,255 @@ fn check_save_load_snapshots(uri: &str) -> Result<(), Error> {
     Ok(())
 }
 
+fn check_save_load_late_snapshots(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+    let store = ThisEventStore::new(pool).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = AggregateContext::new(
+        id.to_string(),
+        5,
+        Customer {
+            customer_id: "customer 1".to_string(),
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            addresses: vec!["initial address".to_string()],
+        },
+    );
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    let stored_context = store
+        .load_aggregate_from_snapshot(&id)
+        .unwrap();
+
+    assert_eq!(stored_context, context);
+
+    Ok(())
+}
+
+fn check_outbox(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+
+    let store = ThisEventStore::new(pool.clone())
+        .unwrap()
+        .with_outbox();
+    let other_store =
+        ThisEventStore::new(pool.clone()).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+    ];
+
+    store.save_events(&contexts).unwrap();
+
+    let other_id = uuid::Uuid::new_v4().to_string();
+
+    other_store
+        .save_events(&vec![EventContext::new(
+            other_id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_B".to_string(),
+            }),
+            metadata,
+        )]).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id || x.aggregate_id == other_id)
+        .collect();
+
+    assert_eq!(pending, contexts);
+
+    store
+        .mark_events_delivered(&pending[..1]).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id)
+        .collect();
+
+    assert_eq!(pending, contexts[1..]);
+
+    // the pending events are deleted with their stream
+    store.delete_stream(&id).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id)
+        .collect();
+
+    assert_eq!(0, pending.len());
+
+    Ok(())
+}
+
+fn customer_event_type(event: &CustomerEvent) -> String {
+    match event {
+        CustomerEvent::NameAdded(_) => "customer.name",
+        CustomerEvent::EmailUpdated(_) => "customer.email",
+        CustomerEvent::AddressUpdated(_) => "customer.address",
+    }
+    .to_string()
+}
+
+fn stored_event_types(
+    pool: &Pool,
+    id: &str,
+) -> Result<Vec<(String, i64)>, Error> {
+    pool.get_conn()?.exec(
+        "SELECT event_type, event_version FROM events WHERE \
+         aggregate_id = ? ORDER BY sequence;",
+        (id,),
+    )
+}
+
+fn check_event_types(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+
+    let metadata = get_metadata();
+
+    let contexts = |id: &str| {
+        vec![
+            EventContext::new(
+                id.to_string(),
+                1,
+                CustomerEvent::NameAdded(NameAdded {
+                    changed_name: "test_event_A".to_string(),
+                }),
+                metadata.clone(),
+            ),
+            EventContext::new(
+                id.to_string(),
+                2,
+                CustomerEvent::EmailUpdated(EmailUpdated {
+                    new_email: "test_event_B".to_string(),
+                }),
+                metadata.clone(),
+            ),
+        ]
+    };
+
+    // named after the enum variants by default
+    let store = ThisEventStore::new(pool.clone()).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+    store.save_events(&contexts(&id)).unwrap();
+
+    assert_eq!(
+        stored_event_types(&pool, &id)?,
+        vec![
+            ("NameAdded".to_string(), 1),
+            ("EmailUpdated".to_string(), 1),
+        ]
+    );
+
+    // named by the store
+    let store = ThisEventStore::new(pool.clone())
+        .unwrap()
+        .with_event_type(customer_event_type);
+
+    let id = uuid::Uuid::new_v4().to_string();
+    store.save_events(&contexts(&id)).unwrap();
+
+    assert_eq!(
+        stored_event_types(&pool, &id)?,
+        vec![
+            ("customer.name".to_string(), 1),
+            ("customer.email".to_string(), 1),
+        ]
+    );
+    assert_eq!(store.load_events(&id).unwrap(), contexts(&id));
+
+    Ok(())
+}
+
+// the stores over a pool are shared by the threads of a server
+fn check_shared_repository(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let store = ThisEventStore::new(Pool::new(opts)?).unwrap();
+
+    let repo = Arc::new(
+        Repository::new(store, vec![], false)
+            .with_conflict_retries(3),
+    );
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let handles: Vec<_> = (0..4)
+        .map(|x| {
+            let repo = Arc::clone(&repo);
+            let id = id.clone();
+
+            thread::spawn(move || {
+                repo.execute(
+                    &id,
+                    CustomerCommand::AddAddress(AddAddress {
+                        new_address: format!("address {}", x),
+                    }),
+                )
+            })
+        })
+        .collect();
+
+    for x in handles {
+        x.join().unwrap().unwrap();
+    }
+
+    let context = repo
+        .load_aggregate_at_sequence(&id, 10)
+        .unwrap();
+
+    assert_eq!(context.version, 4);
+    assert_eq!(context.payload.addresses.len(), 4);
+
+    Ok(())
+}
+
 #[test]
 fn test_mariadb_save_load_events() {
     check_save_load_events(CONNECTION_STRING_MARIADB).unwrap();
 }
 
+#[test]
+fn test_mariadb_save_conflicting_events() {
+    check_save_conflicting_events(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mariadb_save_events_atomically() {
+    check_save_events_atomically(CONNECTION_STRING_MARIADB).unwrap();
+}
+
 #[test]
 fn test_mariadb_save_load_snapshots() {
     check_save_load_snapshots(CONNECTION_STRING_MARIADB).unwrap();
@@ -177,7 +523,57 @@ fn test_mysql_save_load_events() {
     check_save_load_events(CONNECTION_STRING_MYSQL).unwrap();
 }
 
+#[test]
+fn test_mysql_save_conflicting_events() {
+    check_save_conflicting_events(CONNECTION_STRING_MYSQL).unwrap();
+}
+
+#[test]
+fn test_mysql_save_events_atomically() {
+    check_save_events_atomically(CONNECTION_STRING_MYSQL).unwrap();
+}
+
 #[test]
 fn test_mysql_save_load_snapshots() {
     check_save_load_snapshots(CONNECTION_STRING_MYSQL).unwrap();
 }
+
+#[test]
+fn test_mariadb_save_load_late_snapshots() {
+    check_save_load_late_snapshots(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_save_load_late_snapshots() {
+    check_save_load_late_snapshots(CONNECTION_STRING_MYSQL).unwrap();
+}
+
+#[test]
+fn test_mariadb_outbox() {
+    check_outbox(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_outbox() {
+    check_outbox(CONNECTION_STRING_MYSQL).unwrap();
+}
+
+#[test]
+fn test_mariadb_event_types() {
+    check_event_types(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_event_types() {
+    check_event_types(CONNECTION_STRING_MYSQL).unwrap();
+}
+
+#[test]
+fn test_mariadb_shared_repository() {
+    check_shared_repository(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_shared_repository() {
+    check_shared_repository(CONNECTION_STRING_MYSQL).unwrap();
+}
@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+
+use mysql::{
+    prelude::Queryable,
+    Error,
+    Opts,
+    Pool,
+};
+use serde_json::{
+    json,
+    Value,
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    EventContext,
+    IAggregate,
+};
+
+use crate::{
+    errors::StoreError,
+    mysql_store::{
+        CheckpointStore,
+        EventStore,
+        QueryStore,
+    },
+    EventUpcasters,
+    IEventStore,
+    IEventUpcaster,
+    TableNames,
+};
+
+use super::super::migrations::MIGRATIONS;
+use super::common::*;
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+type ThisQueryStore = QueryStore<
+    CustomerCommand,
+    CustomerEvent,
+    Customer,
+    CustomerContactQuery,
+>;
+
+fn test_tables() -> TableNames {
+    let prefix =
+        format!("test_{}_", uuid::Uuid::new_v4().to_simple());
+
+    TableNames::default().with_prefix(&prefix)
+}
+
+fn drop_tables(
+    pool: &Pool,
+    tables: &TableNames,
+) -> Result<(), Error> {
+    pool.get_conn()?.query_drop(tables.sql(
+        "DROP TABLE IF EXISTS {events}, {snapshots}, {outbox}, \
+         {checkpoints}, {queries}, {tombstones}, {migrations};",
+    ))
+}
+
+/// Version 1 of `NameAdded` called the field `name`
+struct NameAddedV1;
+
+impl IEventUpcaster for NameAddedV1 {
+    fn event_type(&self) -> &str {
+        "NameAdded"
+    }
+
+    fn event_version(&self) -> i64 {
+        1
+    }
+
+    fn upcast(
+        &self,
+        payload: Value,
+    ) -> Value {
+        json!({
+            "NameAdded": {
+                "changed_name": payload["NameAdded"]["name"],
+            }
+        })
+    }
+}
+
+fn new_event(
+    id: &str,
+) -> EventContext<CustomerCommand, CustomerEvent> {
+    EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        HashMap::new(),
+    )
+}
+
+fn check_migrations_with_prefix(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+
+    let tables = test_tables();
+
+    let store =
+        ThisEventStore::with_tables(pool.clone(), tables.clone())
+            .unwrap();
+
+    // the other stores find the schema up to date
+    ThisQueryStore::with_tables(pool.clone(), tables.clone())
+        .unwrap();
+    CheckpointStore::with_tables(pool.clone(), tables.clone())
+        .unwrap();
+
+    let versions: Vec<i64> = pool.get_conn()?.query(
+        tables.sql(
+            "SELECT version FROM {migrations} ORDER BY version;",
+        ),
+    )?;
+
+    assert_eq!(
+        versions,
+        MIGRATIONS
+            .iter()
+            .map(|x| x.version)
+            .collect::<Vec<_>>()
+    );
+
+    // the events are only stored in the prefixed tables
+    let id = uuid::Uuid::new_v4().to_string();
+
+    store
+        .save_events(&vec![new_event(&id)])
+        .unwrap();
+
+    assert_eq!(store.load_events(&id).unwrap().len(), 1);
+
+    assert_eq!(
+        ThisEventStore::new(pool.clone())
+            .unwrap()
+            .load_events(&id)
+            .unwrap()
+            .len(),
+        0
+    );
+
+    drop_tables(&pool, &tables)
+}
+
+fn check_upgrade_unversioned_schema(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+
+    let tables = test_tables();
+
+    // a schema set up before the positions and the migrations
+    let mut conn = pool.get_conn()?;
+
+    for x in MIGRATIONS[0].statements {
+        conn.query_drop(tables.sql(x))?;
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let event = new_event(&id);
+
+    conn.exec_drop(
+        tables.sql(
+            "INSERT INTO {events} (aggregate_type, aggregate_id, \
+             sequence, payload, metadata) VALUES (?, ?, ?, ?, ?);",
+        ),
+        (
+            Customer::aggregate_type(),
+            &id,
+            event.sequence,
+            serde_json::to_string(&event.payload).unwrap(),
+            serde_json::to_string(&event.metadata).unwrap(),
+        ),
+    )?;
+
+    let store =
+        ThisEventStore::with_tables(pool.clone(), tables.clone())
+            .unwrap();
+
+    let events = store.read_all(0, 10).unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].position, 1);
+    assert_eq!(events[0].context, event);
+
+    // the types of the existing events are filled in
+    let event_type: Option<String> = pool
+        .get_conn()?
+        .query_first(tables.sql("SELECT event_type FROM {events};"))?;
+
+    assert_eq!(event_type, Some("NameAdded".to_string()));
+
+    drop_tables(&pool, &tables)
+}
+
+fn check_upcast_stored_events(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+
+    let tables = test_tables();
+
+    let store =
+        ThisEventStore::with_tables(pool.clone(), tables.clone())
+            .unwrap()
+            .with_upcasters(
+                EventUpcasters::new().with_upcaster(NameAddedV1),
+            );
+
+    // an event stored with the first version of `NameAdded`
+    let id = uuid::Uuid::new_v4().to_string();
+
+    pool.get_conn()?.exec_drop(
+        tables.sql(
+            "INSERT INTO {events} (aggregate_type, aggregate_id, \
+             sequence, payload, metadata, event_type, event_version) \
+             VALUES (?, ?, 1, ?, '{}', 'NameAdded', 1);",
+        ),
+        (
+            Customer::aggregate_type(),
+            &id,
+            json!({"NameAdded": {"name": "test_event_A"}})
+                .to_string(),
+        ),
+    )?;
+
+    let event = new_event(&id);
+
+    assert_eq!(store.load_events(&id).unwrap(), vec![event.clone()]);
+    assert_eq!(store.read_all(0, 10).unwrap()[0].context, event);
+
+    // new events are stored with the current version
+    let id = uuid::Uuid::new_v4().to_string();
+
+    store
+        .save_events(&vec![new_event(&id)])
+        .unwrap();
+
+    let event_version: Option<i64> = pool.get_conn()?.exec_first(
+        tables.sql(
+            "SELECT event_version FROM {events} WHERE aggregate_id = \
+             ?;",
+        ),
+        (&id,),
+    )?;
+
+    assert_eq!(event_version, Some(2));
+
+    drop_tables(&pool, &tables)
+}
+
+fn check_invalid_table_names(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+
+    let tables = TableNames::default().with_prefix("test; --");
+
+    match ThisEventStore::with_tables(pool.clone(), tables) {
+        Err(StoreError::Backend { .. }) => {},
+        _ => panic!("invalid table names accepted"),
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "with-msgpack")]
+fn check_switch_to_binary_payloads(uri: &str) -> Result<(), Error> {
+    use cqrs_es2::{
+        AggregateContext,
+        QueryContext,
+    };
+
+    use crate::{
+        IOutboxStore,
+        IQueryStore,
+        MsgPackSerializer,
+    };
+
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+
+    let tables = test_tables();
+
+    let json_store =
+        ThisEventStore::with_tables(pool.clone(), tables.clone())
+            .unwrap();
+
+    let store =
+        ThisEventStore::with_tables(pool.clone(), tables.clone())
+            .unwrap()
+            .with_outbox()
+            .with_serializer(MsgPackSerializer);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let mut contexts = vec![new_event(&id), new_event(&id)];
+
+    contexts[1].sequence = 2;
+    contexts[1].payload = CustomerEvent::EmailUpdated(EmailUpdated {
+        new_email: "test A".to_string(),
+    });
+    contexts[1]
+        .metadata
+        .insert("time".to_string(), "now".to_string());
+
+    json_store
+        .save_events(&contexts[..1].to_vec())
+        .unwrap();
+    store
+        .save_events(&contexts[1..].to_vec())
+        .unwrap();
+
+    // the events written as JSON still load
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+    assert_eq!(
+        store
+            .read_all(0, 10)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.context)
+            .collect::<Vec<_>>(),
+        contexts
+    );
+    assert_eq!(store.load_pending_events(10).unwrap(), contexts[1..]);
+
+    // the binary rows leave their text columns empty
+    let columns: Vec<(bool, bool)> =
+        pool.get_conn()?.query(tables.sql(
+            "SELECT payload = '', payload_bytes IS NULL FROM \
+             {events} ORDER BY sequence;",
+        ))?;
+
+    assert_eq!(columns, vec![(false, true), (true, false)]);
+
+    let context = AggregateContext::new(
+        id.to_string(),
+        2,
+        Customer {
+            customer_id: id.to_string(),
+            name: "test_event_A".to_string(),
+            email: "test A".to_string(),
+            addresses: Default::default(),
+        },
+    );
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    let stored_context = store
+        .load_aggregate_from_snapshot(&id)
+        .unwrap();
+
+    assert_eq!(stored_context.version, context.version);
+    assert_eq!(stored_context.payload, context.payload);
+
+    let json_query_store =
+        ThisQueryStore::with_tables(pool.clone(), tables.clone())
+            .unwrap();
+
+    let query_store =
+        ThisQueryStore::with_tables(pool.clone(), tables.clone())
+            .unwrap()
+            .with_serializer(MsgPackSerializer);
+
+    let mut context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    json_query_store
+        .save_query(context.clone())
+        .unwrap();
+
+    // the queries written as JSON still load
+    assert_eq!(query_store.load_query(&id).unwrap(), context);
+
+    context.version = 2;
+    context.payload.email = "test2@email.com".to_string();
+
+    query_store
+        .save_query(context.clone())
+        .unwrap();
+
+    assert_eq!(query_store.load_query(&id).unwrap(), context);
+
+    drop_tables(&pool, &tables)
+}
+
+#[test]
+fn test_mariadb_migrations_with_prefix() {
+    check_migrations_with_prefix(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_migrations_with_prefix() {
+    check_migrations_with_prefix(CONNECTION_STRING_MYSQL).unwrap();
+}
+
+#[test]
+fn test_mariadb_upgrade_unversioned_schema() {
+    check_upgrade_unversioned_schema(CONNECTION_STRING_MARIADB)
+        .unwrap();
+}
+
+#[test]
+fn test_mysql_upgrade_unversioned_schema() {
+    check_upgrade_unversioned_schema(CONNECTION_STRING_MYSQL)
+        .unwrap();
+}
+
+#[test]
+fn test_mariadb_upcast_stored_events() {
+    check_upcast_stored_events(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_upcast_stored_events() {
+    check_upcast_stored_events(CONNECTION_STRING_MYSQL).unwrap();
+}
+
+#[test]
+fn test_mariadb_invalid_table_names() {
+    check_invalid_table_names(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_invalid_table_names() {
+    check_invalid_table_names(CONNECTION_STRING_MYSQL).unwrap();
+}
+
+#[cfg(feature = "with-msgpack")]
+#[test]
+fn test_mariadb_switch_to_binary_payloads() {
+    check_switch_to_binary_payloads(CONNECTION_STRING_MARIADB)
+        .unwrap();
+}
+
+#[cfg(feature = "with-msgpack")]
+#[test]
+fn test_mysql_switch_to_binary_payloads() {
+    check_switch_to_binary_payloads(CONNECTION_STRING_MYSQL)
+        .unwrap();
+}
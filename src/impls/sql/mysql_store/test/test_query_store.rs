@@ -26,9 +26,7 @@ type ThisQueryStore = QueryStore<
 fn check_save_load_queries(uri: &str) -> Result<(), Error> {
     let opts = Opts::from_url(uri)?;
     let pool = Pool::new(opts)?;
-    let conn = pool.get_conn()?;
-
-    let mut store = ThisQueryStore::new(conn);
+    let store = ThisQueryStore::new(pool).unwrap();
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -78,6 +76,86 @@ fn check_save_load_queries(uri: &str) -> Result<(), Error> {
     Ok(())
 }
 
+fn check_delete_queries(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+    let version = uuid::Uuid::new_v4().to_string();
+    let store = ThisQueryStore::new(pool)
+        .unwrap()
+        .with_query_version(&version);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(stored_context, context);
+
+    store.delete_queries().unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+
+    Ok(())
+}
+
+fn check_delete_query(uri: &str) -> Result<(), Error> {
+    let opts = Opts::from_url(uri)?;
+    let pool = Pool::new(opts)?;
+    let version = uuid::Uuid::new_v4().to_string();
+    let store = ThisQueryStore::new(pool)
+        .unwrap()
+        .with_query_version(&version);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(stored_context, context);
+
+    store.delete_query(&id).unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_mariadb_save_load_queries() {
     check_save_load_queries(CONNECTION_STRING_MARIADB).unwrap();
@@ -87,3 +165,23 @@ fn test_mariadb_save_load_queries() {
 fn test_mysql_save_load_queries() {
     check_save_load_queries(CONNECTION_STRING_MYSQL).unwrap();
 }
+
+#[test]
+fn test_mariadb_delete_queries() {
+    check_delete_queries(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_delete_queries() {
+    check_delete_queries(CONNECTION_STRING_MYSQL).unwrap();
+}
+
+#[test]
+fn test_mariadb_delete_query() {
+    check_delete_query(CONNECTION_STRING_MARIADB).unwrap();
+}
+
+#[test]
+fn test_mysql_delete_query() {
+    check_delete_query(CONNECTION_STRING_MYSQL).unwrap();
+}
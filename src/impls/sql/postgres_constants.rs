@@ -1,12 +1,25 @@
+/// Key of the advisory lock serializing the commits so that their
+/// positions become visible in order
+pub static EVENTS_LOCK_KEY: i64 = 0x6371_7273;
+
+pub static LOCK_EVENTS: &str = "
+SELECT
+    pg_advisory_xact_lock($1);
+";
+
 pub static INSERT_EVENT: &str = "
 INSERT INTO
-    events
+    {events}
     (
         aggregate_type, 
         aggregate_id,
         sequence,
         payload, 
-        metadata
+        metadata,
+        event_type,
+        event_version,
+        payload_bytes,
+        metadata_bytes
     )
 VALUES
     (
@@ -14,7 +27,11 @@ VALUES
         $2,
         $3,
         $4,
-        $5
+        $5,
+        $6,
+        $7,
+        $8,
+        $9
     );
 ";
 
@@ -22,53 +39,240 @@ pub static SELECT_EVENTS: &str = "
 SELECT
     sequence,
     payload,
-    metadata
+    metadata,
+    event_type,
+    event_version,
+    timestamp,
+    payload_bytes,
+    metadata_bytes
 FROM
-    events
+    {events}
 WHERE
     aggregate_type = $1
     AND
     aggregate_id = $2
+    AND
+    sequence >= $3
+    AND
+    sequence <= $4
 ORDER BY 
     sequence;
 ";
 
-pub static INSERT_SNAPSHOT: &str = "
+pub static SELECT_ALL_EVENTS: &str = "
+SELECT
+    position,
+    aggregate_id,
+    sequence,
+    payload,
+    metadata,
+    event_type,
+    event_version,
+    timestamp,
+    payload_bytes,
+    metadata_bytes
+FROM
+    {events}
+WHERE
+    aggregate_type = $1
+    AND
+    position >= $2
+ORDER BY
+    position
+LIMIT
+    $3;
+";
+
+pub static DELETE_EVENTS: &str = "
+DELETE FROM
+    {events}
+WHERE
+    aggregate_type = $1
+    AND
+    aggregate_id = $2;
+";
+
+pub static SELECT_AGGREGATE_IDS: &str = "
+SELECT
+    aggregate_id
+FROM
+    {events}
+WHERE
+    aggregate_type = $1
+    AND
+    aggregate_id > $2
+GROUP BY
+    aggregate_id
+ORDER BY
+    aggregate_id
+LIMIT
+    $3;
+";
+
+pub static SELECT_STREAM_METADATA: &str = "
+SELECT
+    COALESCE(MAX(sequence), 0),
+    COUNT(*),
+    MIN(timestamp),
+    MAX(timestamp),
+    (
+        SELECT
+            version
+        FROM
+            {snapshots}
+        WHERE
+            aggregate_type = $1
+            AND
+            aggregate_id = $2
+    )
+FROM
+    {events}
+WHERE
+    aggregate_type = $1
+    AND
+    aggregate_id = $2;
+";
+
+pub static INSERT_OUTBOX_EVENT: &str = "
 INSERT INTO
-    snapshots 
+    {outbox}
     (
-        version,
-        payload,
         aggregate_type,
-        aggregate_id
+        aggregate_id,
+        sequence
     )
 VALUES
     (
         $1,
         $2,
-        $3,
-        $4
+        $3
     );
 ";
 
-pub static UPDATE_SNAPSHOT: &str = "
-UPDATE
-    snapshots
-SET
-    version = $1,
-    payload = $2
+pub static SELECT_OUTBOX_EVENTS: &str = "
+SELECT
+    {events}.aggregate_id,
+    {events}.sequence,
+    {events}.payload,
+    {events}.metadata,
+    {events}.event_type,
+    {events}.event_version,
+    {events}.payload_bytes,
+    {events}.metadata_bytes
+FROM
+    {outbox}
+JOIN
+    {events}
+ON
+    {events}.aggregate_type = {outbox}.aggregate_type
+    AND
+    {events}.aggregate_id = {outbox}.aggregate_id
+    AND
+    {events}.sequence = {outbox}.sequence
 WHERE
-    aggregate_type = $3
+    {outbox}.aggregate_type = $1
+ORDER BY
+    {outbox}.id
+LIMIT
+    $2;
+";
+
+pub static DELETE_OUTBOX_EVENT: &str = "
+DELETE FROM
+    {outbox}
+WHERE
+    aggregate_type = $1
+    AND
+    aggregate_id = $2
     AND
-    aggregate_id = $4;
+    sequence = $3;
+";
+
+pub static DELETE_AGGREGATE_OUTBOX_EVENTS: &str = "
+DELETE FROM
+    {outbox}
+WHERE
+    aggregate_type = $1
+    AND
+    aggregate_id = $2;
+";
+
+pub static UPSERT_SNAPSHOT: &str = "
+INSERT INTO
+    {snapshots} 
+    (
+        version,
+        payload,
+        aggregate_type,
+        aggregate_id,
+        payload_bytes
+    )
+VALUES
+    (
+        $1,
+        $2,
+        $3,
+        $4,
+        $5
+    )
+ON CONFLICT
+    (
+        aggregate_type,
+        aggregate_id
+    )
+DO UPDATE SET
+    version = EXCLUDED.version,
+    payload = EXCLUDED.payload,
+    payload_bytes = EXCLUDED.payload_bytes;
 ";
 
 pub static SELECT_SNAPSHOT: &str = "
 SELECT
     version,
-    payload
+    payload,
+    payload_bytes
 FROM
-    snapshots
+    {snapshots}
+WHERE
+    aggregate_type = $1
+    AND
+    aggregate_id = $2;
+";
+
+pub static DELETE_SNAPSHOT: &str = "
+DELETE FROM
+    {snapshots}
+WHERE
+    aggregate_type = $1
+    AND
+    aggregate_id = $2;
+";
+
+pub static INSERT_TOMBSTONE: &str = "
+INSERT INTO
+    {tombstones}
+    (
+        aggregate_type,
+        aggregate_id
+    )
+VALUES
+    (
+        $1,
+        $2
+    )
+ON CONFLICT
+    (
+        aggregate_type,
+        aggregate_id
+    )
+DO NOTHING;
+";
+
+pub static SELECT_TOMBSTONE: &str = "
+SELECT
+    aggregate_id
+FROM
+    {tombstones}
 WHERE
     aggregate_type = $1
     AND
@@ -77,13 +281,14 @@ WHERE
 
 pub static INSERT_QUERY: &str = "
 INSERT INTO
-    queries 
+    {queries} 
     (
         version,
         payload,
         aggregate_type,
         aggregate_id,
-        query_type
+        query_type,
+        payload_bytes
     )
 VALUES
     (
@@ -91,16 +296,18 @@ VALUES
         $2,
         $3,
         $4,
-        $5
+        $5,
+        $6
     );
 ";
 
 pub static UPDATE_QUERY: &str = "
 UPDATE
-    queries
+    {queries}
 SET
     version = $1,
-    payload = $2
+    payload = $2,
+    payload_bytes = $6
 WHERE
     aggregate_type = $3
     AND
@@ -112,9 +319,21 @@ WHERE
 pub static SELECT_QUERY: &str = "
 SELECT
     version,
-    payload
+    payload,
+    payload_bytes
 FROM
-    queries
+    {queries}
+WHERE
+    aggregate_type = $1
+    AND
+    aggregate_id = $2
+    AND
+    query_type = $3;
+";
+
+pub static DELETE_QUERY: &str = "
+DELETE FROM
+    {queries}
 WHERE
     aggregate_type = $1
     AND
@@ -122,3 +341,41 @@ WHERE
     AND
     query_type = $3;
 ";
+
+pub static DELETE_QUERIES: &str = "
+DELETE FROM
+    {queries}
+WHERE
+    aggregate_type = $1
+    AND
+    query_type = $2;
+";
+
+pub static UPSERT_CHECKPOINT: &str = "
+INSERT INTO
+    {checkpoints}
+    (
+        subscription,
+        position
+    )
+VALUES
+    (
+        $1,
+        $2
+    )
+ON CONFLICT
+    (
+        subscription
+    )
+DO UPDATE SET
+    position = EXCLUDED.position;
+";
+
+pub static SELECT_CHECKPOINT: &str = "
+SELECT
+    position
+FROM
+    {checkpoints}
+WHERE
+    subscription = $1;
+";
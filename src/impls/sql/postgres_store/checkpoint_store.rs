@@ -0,0 +1,123 @@
+use log::{
+    debug,
+    trace,
+};
+
+use crate::{
+    errors::StoreError,
+    repository::ICheckpointStore,
+};
+
+use super::super::{
+    postgres_constants::*,
+    TableNames,
+};
+use super::{
+    driver_error::driver_error,
+    migrations::migrate,
+    pool::{
+        get_conn,
+        PostgresPool,
+    },
+};
+
+/// Sync Postgres checkpoint store
+pub struct CheckpointStore {
+    pool: PostgresPool,
+    tables: TableNames,
+}
+
+impl CheckpointStore {
+    /// Constructor, creates or upgrades the schema of the default
+    /// tables
+    pub fn new(pool: PostgresPool) -> Result<Self, StoreError> {
+        Self::with_tables(pool, TableNames::default())
+    }
+
+    /// Constructor using the given table names, creates or upgrades
+    /// their schema
+    pub fn with_tables(
+        pool: PostgresPool,
+        tables: TableNames,
+    ) -> Result<Self, StoreError> {
+        migrate(&mut *get_conn(&pool)?, &tables)?;
+
+        let x = Self { pool, tables };
+
+        trace!("Created new sync Postgres checkpoint store");
+
+        Ok(x)
+    }
+}
+
+impl ICheckpointStore for CheckpointStore {
+    /// Save the last position processed by `subscription`
+    fn save_checkpoint(
+        &self,
+        subscription: &str,
+        position: i64,
+    ) -> Result<(), StoreError> {
+        debug!(
+            "storing checkpoint {} for subscription '{}'",
+            position, subscription
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(UPSERT_CHECKPOINT).as_str(),
+            &[&subscription, &position],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert/update checkpoint for \
+                         subscription '{}'",
+                        subscription
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Load the last position processed by `subscription`
+    fn load_checkpoint(
+        &self,
+        subscription: &str,
+    ) -> Result<i64, StoreError> {
+        trace!(
+            "loading checkpoint for subscription '{}'",
+            subscription
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows = match conn.query(
+            self.tables.sql(SELECT_CHECKPOINT).as_str(),
+            &[&subscription],
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load checkpoints table for \
+                         subscription '{}'",
+                        subscription
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        match rows.first() {
+            Some(row) => Ok(row.get(0)),
+            None => Ok(0),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use postgres::error::SqlState;
+
+use crate::errors::StoreError;
+
+/// Classifies a Postgres error into a `StoreError`
+pub(super) fn driver_error(
+    message: &str,
+    e: postgres::Error,
+) -> StoreError {
+    if e.is_closed() {
+        return StoreError::connection(message, e);
+    }
+
+    match e.code() {
+        Some(&SqlState::UNDEFINED_TABLE) => {
+            StoreError::NotFound {
+                message: message.to_string(),
+                source: Some(Box::new(e)),
+            }
+        },
+        _ => StoreError::backend(message, e),
+    }
+}
@@ -2,42 +2,159 @@ use log::{
     debug,
     trace,
 };
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    time::SystemTime,
+};
 
-use postgres::Client;
+use postgres::error::SqlState;
 
 use cqrs_es2::{
     AggregateContext,
-    Error,
     EventContext,
     IAggregate,
     ICommand,
     IEvent,
 };
 
-use crate::repository::IEventStore;
+use crate::{
+    errors::{
+        ConflictError,
+        StoreError,
+    },
+    repository::{
+        event_type,
+        EncryptionScope,
+        EventUpcasters,
+        IEventStore,
+        IOutboxStore,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        PositionedEvent,
+        StoreSerializer,
+        StreamMetadata,
+        TimestampedEvent,
+    },
+};
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
 
-use super::super::postgres_constants::*;
+use super::super::{
+    postgres_constants::*,
+    TableNames,
+};
+use super::{
+    driver_error::driver_error,
+    migrations::migrate,
+    pool::{
+        get_conn,
+        PostgresPool,
+    },
+};
 
 /// Sync Postgres event store
 pub struct EventStore<C: ICommand, E: IEvent, A: IAggregate<C, E>> {
-    conn: Client,
+    pool: PostgresPool,
+    tables: TableNames,
+    with_outbox: bool,
+    upcasters: EventUpcasters,
+    event_type: Option<fn(&E) -> String>,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A)>,
 }
 
 impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
     EventStore<C, E, A>
 {
-    /// Constructor
-    pub fn new(conn: Client) -> Self {
+    /// Constructor, creates or upgrades the schema of the default
+    /// tables
+    pub fn new(pool: PostgresPool) -> Result<Self, StoreError> {
+        Self::with_tables(pool, TableNames::default())
+    }
+
+    /// Constructor using the given table names, creates or upgrades
+    /// their schema
+    pub fn with_tables(
+        pool: PostgresPool,
+        tables: TableNames,
+    ) -> Result<Self, StoreError> {
+        migrate(&mut *get_conn(&pool)?, &tables)?;
+
         let x = Self {
-            conn,
+            pool,
+            tables,
+            with_outbox: false,
+            upcasters: EventUpcasters::default(),
+            event_type: None,
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
         };
 
         trace!("Created new sync Postgres event store");
 
-        x
+        Ok(x)
+    }
+
+    /// Enables the transactional outbox, new events are added to
+    /// the outbox table in the same transaction for an
+    /// `OutboxRelay` to deliver
+    pub fn with_outbox(mut self) -> Self {
+        self.with_outbox = true;
+        self
+    }
+
+    /// Upcasts the stored payloads with `upcasters` before they are
+    /// deserialized
+    pub fn with_upcasters(
+        mut self,
+        upcasters: EventUpcasters,
+    ) -> Self {
+        self.upcasters = upcasters;
+        self
+    }
+
+    /// Names the event types stored with the events with
+    /// `event_type` instead of their enum variants
+    pub fn with_event_type(
+        mut self,
+        event_type: fn(&E) -> String,
+    ) -> Self {
+        self.event_type = Some(event_type);
+        self
+    }
+
+    /// Stores the payloads, metadata and snapshots with
+    /// `serializer`, the binary formats use the bytea columns
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
+
+    /// Compresses the payloads, metadata and snapshots longer than
+    /// `threshold` bytes with `compressor`, the uncompressed ones
+    /// keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
+
+    /// Encrypts the event payloads and the snapshots with
+    /// `encryption`, the events record the id of their key in their
+    /// metadata
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
     }
 }
 
@@ -46,9 +163,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 {
     /// Save new events
     fn save_events(
-        &mut self,
+        &self,
         contexts: &Vec<EventContext<C, E>>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         if contexts.len() == 0 {
             trace!("Skip saving zero contexts");
             return Ok(());
@@ -68,64 +185,176 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             &aggregate_id
         );
 
-        let mut trans = match self.conn.transaction() {
+        let with_outbox = self.with_outbox;
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let mut trans = match conn.transaction() {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::TechnicalError(e.to_string()));
+                return Err(driver_error(
+                    format!(
+                        "unable to start a transaction for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
             },
         };
 
+        // positions are assigned on insert, commits are serialized
+        // so that they become visible in order
+        match trans.execute(LOCK_EVENTS, &[&EVENTS_LOCK_KEY]) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to lock events table for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        }
+
         for context in contexts {
             let payload = match serde_json::to_value(&context.payload)
             {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::serialization(
                         format!(
                             "unable to serialize the event payload \
-                             for aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let event_type = match self.event_type {
+                Some(x) => x(&context.payload),
+                None => event_type(&payload),
+            };
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &aggregate_id,
+                sequence: context.sequence,
+            };
+
+            let (payload, metadata) = match self
+                .serializer
+                .encrypt_event(&scope, payload, &context.metadata)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to encrypt the event payload for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let metadata = match self.serializer.to_json(&metadata) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to serialize the event metadata \
+                             for aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-            let metadata =
-                match serde_json::to_value(&context.metadata) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        return Err(Error::new(
-                            format!(
-                                "unable to serialize the event \
-                                 metadata for aggregate id '{}' \
-                                 with error: {}",
-                                &aggregate_id, e
-                            )
-                            .as_str(),
-                        ));
-                    },
-                };
+            let event_version =
+                self.upcasters.event_version(&event_type);
+
+            let payload = match self.serializer.split_json(payload) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to serialize the event payload \
+                             for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
 
             match trans.execute(
-                INSERT_EVENT,
+                self.tables.sql(INSERT_EVENT).as_str(),
                 &[
                     &aggregate_type,
                     &aggregate_id,
                     &context.sequence,
-                    &payload,
-                    &metadata,
+                    &payload.0,
+                    &metadata.0,
+                    &event_type,
+                    &event_version,
+                    &payload.1,
+                    &metadata.1,
                 ],
             ) {
                 Ok(_) => {},
                 Err(e) => {
-                    return Err(Error::new(
+                    if e.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+                        return Err(ConflictError::new(
+                            aggregate_type,
+                            &aggregate_id,
+                            context.sequence,
+                        )
+                        .into());
+                    }
+
+                    return Err(driver_error(
                         format!(
                             "unable to insert new event for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
+                    ));
+                },
+            }
+
+            if !with_outbox {
+                continue;
+            }
+
+            match trans.execute(
+                self.tables.sql(INSERT_OUTBOX_EVENT).as_str(),
+                &[&aggregate_type, &aggregate_id, &context.sequence],
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to insert new outbox event for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
                     ));
                 },
             }
@@ -134,13 +363,14 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         match trans.commit() {
             Ok(_) => {},
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to commit new event for aggregate \
-                         id '{}' with error: {}",
-                        &aggregate_id, e
+                         id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         }
@@ -148,11 +378,14 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         Ok(())
     }
 
-    /// Load all events for a particular `aggregate_id`
-    fn load_events(
-        &mut self,
+    /// Load the events for a particular `aggregate_id` within a
+    /// range of sequences along with their commit timestamps
+    fn load_timestamped_events_range(
+        &self,
         aggregate_id: &str,
-    ) -> Result<Vec<EventContext<C, E>>, Error> {
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -160,19 +393,126 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             aggregate_id
         );
 
-        let rows = match self.conn.query(
-            SELECT_EVENTS,
-            &[&aggregate_type, &aggregate_id],
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows = match conn.query(
+            self.tables.sql(SELECT_EVENTS).as_str(),
+            &[
+                &aggregate_type,
+                &aggregate_id,
+                &from_sequence,
+                &to_sequence.unwrap_or(i64::MAX),
+            ],
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load events table for aggregate \
-                         id '{}' with error: {}",
-                        &aggregate_id, e
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let mut result = Vec::new();
+
+        for row in rows {
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id,
+                sequence: row.get(0),
+            };
+
+            let payload = match self.serializer.deserialize_event(
+                &scope,
+                &self.upcasters,
+                row.get(3),
+                row.get(1),
+                row.get(6),
+                row.get(4),
+            ) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad payload found in events table for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let metadata = match self
+                .serializer
+                .deserialize_json(row.get(2), row.get(7))
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad metadata found in events table for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            result.push(TimestampedEvent::new(
+                row.get(5),
+                EventContext::new(
+                    aggregate_id.to_string(),
+                    row.get(0),
+                    payload,
+                    metadata,
+                ),
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Load up to `limit` events of all aggregates of this type in
+    /// commit order, starting at the global position
+    /// `from_position`
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "reading up to '{}' events from position {}",
+            limit,
+            from_position
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows = match conn.query(
+            self.tables.sql(SELECT_ALL_EVENTS).as_str(),
+            &[&aggregate_type, &from_position, &(limit as i64)],
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load events table from position \
+                         {}",
+                        from_position
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -180,50 +520,302 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         let mut result = Vec::new();
 
         for row in rows {
-            let payload = match serde_json::from_value(row.get(1)) {
+            let aggregate_id: String = row.get(1);
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &aggregate_id,
+                sequence: row.get(2),
+            };
+
+            let payload = match self.serializer.deserialize_event(
+                &scope,
+                &self.upcasters,
+                row.get(5),
+                row.get(3),
+                row.get(8),
+                row.get(6),
+            ) {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::deserialization(
                         format!(
                             "bad payload found in events table for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-            let metadata = match serde_json::from_value(row.get(2)) {
+            let metadata = match self
+                .serializer
+                .deserialize_json(row.get(4), row.get(9))
+            {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::deserialization(
                         format!(
                             "bad metadata found in events table for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-            result.push(EventContext::new(
-                aggregate_id.to_string(),
+            result.push(PositionedEvent::new(
                 row.get(0),
-                payload,
-                metadata,
+                row.get(7),
+                EventContext::new(
+                    aggregate_id,
+                    row.get(2),
+                    payload,
+                    metadata,
+                ),
             ));
         }
 
         Ok(result)
     }
 
+    /// List up to `limit` ids of the aggregates of this type in
+    /// ascending order, starting after `after_aggregate_id`
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "listing up to '{}' aggregate ids after '{:?}'",
+            limit,
+            after_aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows = match conn.query(
+            self.tables.sql(SELECT_AGGREGATE_IDS).as_str(),
+            &[
+                &aggregate_type,
+                &after_aggregate_id.unwrap_or(""),
+                &(limit as i64),
+            ],
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to list aggregate ids from events table",
+                    e,
+                ));
+            },
+        };
+
+        Ok(rows.iter().map(|x| x.get(0)).collect())
+    }
+
+    /// Load the metadata of the event stream of a particular
+    /// `aggregate_id`
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "loading stream metadata for aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let row = match conn.query_one(
+            self.tables
+                .sql(SELECT_STREAM_METADATA)
+                .as_str(),
+            &[&aggregate_type, &aggregate_id],
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load stream metadata for \
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let event_count: i64 = row.get(1);
+        let first_timestamp: Option<SystemTime> = row.get(2);
+        let last_timestamp: Option<SystemTime> = row.get(3);
+
+        if event_count == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(StreamMetadata::new(
+            aggregate_id,
+            row.get(0),
+            event_count,
+            first_timestamp.unwrap_or(SystemTime::UNIX_EPOCH),
+            last_timestamp.unwrap_or(SystemTime::UNIX_EPOCH),
+            row.get(4),
+        )))
+    }
+
+    /// Delete all events of a particular `aggregate_id` along with
+    /// its snapshot and outbox entries
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting events and snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let mut trans = match conn.transaction() {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to start a transaction for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        for x in [
+            DELETE_AGGREGATE_OUTBOX_EVENTS,
+            DELETE_EVENTS,
+            DELETE_SNAPSHOT,
+        ] {
+            match trans.execute(
+                self.tables.sql(x).as_str(),
+                &[&aggregate_type, &aggregate_id],
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to delete the stream of \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+        }
+
+        match trans.commit() {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to commit the deletion of aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Mark the stream of a particular `aggregate_id` as tombstoned
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!("tombstoning aggregate id '{}'", aggregate_id);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(INSERT_TOMBSTONE).as_str(),
+            &[&aggregate_type, &aggregate_id],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert tombstone for aggregate id \
+                         '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Check whether the stream of a particular `aggregate_id` was
+    /// tombstoned
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "checking tombstone of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.query(
+            self.tables.sql(SELECT_TOMBSTONE).as_str(),
+            &[&aggregate_type, &aggregate_id],
+        ) {
+            Ok(x) => Ok(!x.is_empty()),
+            Err(e) => {
+                Err(driver_error(
+                    format!(
+                        "unable to load tombstones table for \
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ))
+            },
+        }
+    }
+
     /// save a new aggregate snapshot
     fn save_aggregate_snapshot(
-        &mut self,
+        &self,
         context: AggregateContext<C, E, A>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
 
         let aggregate_id = context.aggregate_id;
@@ -233,43 +825,86 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             &aggregate_id
         );
 
-        let sql = match context.version {
-            1 => INSERT_SNAPSHOT,
-            _ => UPDATE_SNAPSHOT,
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id: &aggregate_id,
         };
 
-        let payload = match serde_json::to_value(context.payload) {
+        let payload = match self
+            .serializer
+            .encrypt_to_json(&scope, &context.payload)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize aggregate snapshot for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        match self.conn.execute(
-            sql,
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(UPSERT_SNAPSHOT).as_str(),
             &[
                 &context.version,
-                &payload,
+                &payload.0,
                 &aggregate_type,
                 &aggregate_id,
+                &payload.1,
             ],
         ) {
             Ok(_) => {},
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to insert/update snapshot for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Delete the snapshot of a particular `aggregate_id`
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting the snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(DELETE_SNAPSHOT).as_str(),
+            &[&aggregate_type, &aggregate_id],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete snapshot for aggregate id \
+                         '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -279,9 +914,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 
     /// Load aggregate at current state from snapshots
     fn load_aggregate_from_snapshot(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<AggregateContext<C, E, A>, Error> {
+    ) -> Result<AggregateContext<C, E, A>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -289,19 +924,22 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             aggregate_id
         );
 
-        let rows = match self.conn.query(
-            SELECT_SNAPSHOT,
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows = match conn.query(
+            self.tables.sql(SELECT_SNAPSHOT).as_str(),
             &[&aggregate_type, &aggregate_id],
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load snapshots table for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -323,16 +961,25 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             },
         };
 
-        let payload = match serde_json::from_value(row.get(1)) {
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .decrypt_json(&scope, row.get(1), row.get(2))
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in snapshots table for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -344,3 +991,131 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         ))
     }
 }
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IOutboxStore<C, E, A>
+    for EventStore<C, E, A>
+{
+    /// Load up to `limit` pending events in commit order
+    fn load_pending_events(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!("loading up to '{}' pending outbox events", limit);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows = match conn.query(
+            self.tables.sql(SELECT_OUTBOX_EVENTS).as_str(),
+            &[&aggregate_type, &(limit as i64)],
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to load outbox table",
+                    e,
+                ));
+            },
+        };
+
+        let mut result = Vec::new();
+
+        for row in rows {
+            let aggregate_id: String = row.get(0);
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &aggregate_id,
+                sequence: row.get(1),
+            };
+
+            let payload = match self.serializer.deserialize_event(
+                &scope,
+                &self.upcasters,
+                row.get(4),
+                row.get(2),
+                row.get(6),
+                row.get(5),
+            ) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad payload found in events table for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let metadata = match self
+                .serializer
+                .deserialize_json(row.get(3), row.get(7))
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad metadata found in events table for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            result.push(EventContext::new(
+                aggregate_id,
+                row.get(1),
+                payload,
+                metadata,
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Mark events as delivered so that they are not loaded again
+    fn mark_events_delivered(
+        &self,
+        contexts: &[EventContext<C, E>],
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!("marking '{}' outbox events delivered", contexts.len());
+
+        let mut conn = get_conn(&self.pool)?;
+
+        for context in contexts {
+            match conn.execute(
+                self.tables.sql(DELETE_OUTBOX_EVENT).as_str(),
+                &[
+                    &aggregate_type,
+                    &context.aggregate_id,
+                    &context.sequence,
+                ],
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to delete outbox event for \
+                             aggregate id '{}'",
+                            &context.aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
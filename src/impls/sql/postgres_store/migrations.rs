@@ -0,0 +1,352 @@
+use log::{
+    debug,
+    trace,
+};
+
+use postgres::Client;
+
+use crate::errors::StoreError;
+
+use super::super::{
+    migration::Migration,
+    table_names::TableNames,
+};
+use super::driver_error::driver_error;
+
+/// Key of the advisory lock serializing the migrations of
+/// concurrently constructed stores
+static MIGRATIONS_LOCK_KEY: i64 = 0x6371_7274;
+
+static LOCK_MIGRATIONS: &str = "
+SELECT
+    pg_advisory_xact_lock($1);
+";
+
+static CREATE_MIGRATIONS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS
+    {migrations}
+    (
+        version     bigint                   NOT NULL,
+        description text                     NOT NULL,
+        applied_at  timestamp with time zone DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (version)
+    );
+";
+
+static SELECT_SCHEMA_VERSION: &str = "
+SELECT
+    COALESCE(MAX(version), 0)
+FROM
+    {migrations};
+";
+
+static INSERT_MIGRATION: &str = "
+INSERT INTO
+    {migrations}
+    (
+        version,
+        description
+    )
+VALUES
+    (
+        $1,
+        $2
+    );
+";
+
+/// The schema of the Postgres stores, the tables of the first
+/// migration are only created if they do not exist yet so that
+/// databases set up before the migrations are adopted
+pub(super) static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create the events, snapshots and queries tables",
+        statements: &[
+            "
+CREATE TABLE IF NOT EXISTS
+    {events}
+    (
+        aggregate_type text                         NOT NULL,
+        aggregate_id   text                         NOT NULL,
+        sequence       bigint CHECK (sequence >= 0) NOT NULL,
+        payload        jsonb                        NOT NULL,
+        metadata       jsonb                        NOT NULL,
+        timestamp      timestamp with time zone DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (aggregate_type, aggregate_id, sequence)
+    );
+",
+            "
+CREATE TABLE IF NOT EXISTS
+    {snapshots}
+    (
+        aggregate_type text                        NOT NULL,
+        aggregate_id   text                        NOT NULL,
+        version        bigint CHECK (version >= 0) NOT NULL,
+        payload        jsonb                       NOT NULL,
+        timestamp      timestamp with time zone DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (aggregate_type, aggregate_id)
+    );
+",
+            "
+CREATE TABLE IF NOT EXISTS
+    {queries}
+    (
+        aggregate_type text                        NOT NULL,
+        aggregate_id   text                        NOT NULL,
+        query_type     text                        NOT NULL,
+        version        bigint CHECK (version >= 0) NOT NULL,
+        payload        jsonb                       NOT NULL,
+        PRIMARY KEY (aggregate_type, aggregate_id, query_type)
+    );
+",
+        ],
+    },
+    Migration {
+        version: 2,
+        description: "add the global positions of the events",
+        statements: &["
+ALTER TABLE
+    {events}
+ADD COLUMN IF NOT EXISTS
+    position bigserial NOT NULL UNIQUE;
+"],
+    },
+    Migration {
+        version: 3,
+        description: "create the outbox table",
+        statements: &["
+CREATE TABLE IF NOT EXISTS
+    {outbox}
+    (
+        id             bigserial                    PRIMARY KEY,
+        aggregate_type text                         NOT NULL,
+        aggregate_id   text                         NOT NULL,
+        sequence       bigint CHECK (sequence >= 0) NOT NULL,
+        UNIQUE (aggregate_type, aggregate_id, sequence)
+    );
+"],
+    },
+    Migration {
+        version: 4,
+        description: "create the checkpoints table",
+        statements: &["
+CREATE TABLE IF NOT EXISTS
+    {checkpoints}
+    (
+        subscription text                         NOT NULL,
+        position     bigint CHECK (position >= 0) NOT NULL,
+        PRIMARY KEY (subscription)
+    );
+"],
+    },
+    Migration {
+        version: 5,
+        description: "add the schema versions of the events",
+        statements: &["
+ALTER TABLE
+    {events}
+ADD COLUMN IF NOT EXISTS
+    event_version bigint NOT NULL DEFAULT 1;
+"],
+    },
+    Migration {
+        version: 6,
+        description: "add the types of the events",
+        statements: &[
+            "
+ALTER TABLE
+    {events}
+ADD COLUMN IF NOT EXISTS
+    event_type text NOT NULL DEFAULT '';
+",
+            "
+UPDATE
+    {events}
+SET
+    event_type = CASE jsonb_typeof(payload)
+        WHEN 'object' THEN (
+            SELECT
+                MIN(key)
+            FROM
+                jsonb_object_keys(payload) AS key
+        )
+        WHEN 'string' THEN payload #>> '{}'
+        ELSE ''
+    END;
+",
+            "
+CREATE INDEX IF NOT EXISTS
+    {events}_event_type
+ON
+    {events}
+    (
+        aggregate_type,
+        event_type
+    );
+",
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "create the tombstones table",
+        statements: &["
+CREATE TABLE IF NOT EXISTS
+    {tombstones}
+    (
+        aggregate_type text NOT NULL,
+        aggregate_id   text NOT NULL,
+        timestamp      timestamp with time zone DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (aggregate_type, aggregate_id)
+    );
+"],
+    },
+    Migration {
+        version: 8,
+        description: "add the binary payloads",
+        statements: &[
+            "
+ALTER TABLE
+    {events}
+    ADD COLUMN payload_bytes bytea,
+    ADD COLUMN metadata_bytes bytea,
+    ALTER COLUMN payload DROP NOT NULL,
+    ALTER COLUMN metadata DROP NOT NULL;
+",
+            "
+ALTER TABLE
+    {snapshots}
+    ADD COLUMN payload_bytes bytea,
+    ALTER COLUMN payload DROP NOT NULL;
+",
+            "
+ALTER TABLE
+    {queries}
+    ADD COLUMN payload_bytes bytea,
+    ALTER COLUMN payload DROP NOT NULL;
+",
+        ],
+    },
+];
+
+/// Applies the migrations missing from the tables in a single
+/// transaction
+pub(super) fn migrate(
+    conn: &mut Client,
+    tables: &TableNames,
+) -> Result<(), StoreError> {
+    tables.validate()?;
+
+    let mut trans = match conn.transaction() {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(driver_error(
+                "unable to start a transaction for the migrations",
+                e,
+            ));
+        },
+    };
+
+    match trans.execute(LOCK_MIGRATIONS, &[&MIGRATIONS_LOCK_KEY]) {
+        Ok(_) => {},
+        Err(e) => {
+            return Err(driver_error(
+                "unable to lock the migrations",
+                e,
+            ));
+        },
+    }
+
+    match trans.batch_execute(
+        tables
+            .sql(CREATE_MIGRATIONS_TABLE)
+            .as_str(),
+    ) {
+        Ok(_) => {},
+        Err(e) => {
+            return Err(driver_error(
+                format!(
+                    "unable to create migrations table '{}'",
+                    &tables.migrations
+                )
+                .as_str(),
+                e,
+            ));
+        },
+    }
+
+    let version: i64 = match trans.query_one(
+        tables
+            .sql(SELECT_SCHEMA_VERSION)
+            .as_str(),
+        &[],
+    ) {
+        Ok(x) => x.get(0),
+        Err(e) => {
+            return Err(driver_error(
+                format!(
+                    "unable to load migrations table '{}'",
+                    &tables.migrations
+                )
+                .as_str(),
+                e,
+            ));
+        },
+    };
+
+    trace!("schema of '{}' at version {}", &tables.events, version);
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|x| x.version > version)
+    {
+        debug!(
+            "applying migration {} '{}'",
+            migration.version, migration.description
+        );
+
+        for x in migration.statements {
+            match trans.batch_execute(tables.sql(x).as_str()) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to apply migration {} '{}'",
+                            migration.version, migration.description
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+        }
+
+        match trans.execute(
+            tables.sql(INSERT_MIGRATION).as_str(),
+            &[&migration.version, &migration.description],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to record migration {}",
+                        migration.version
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        }
+    }
+
+    match trans.commit() {
+        Ok(_) => {},
+        Err(e) => {
+            return Err(driver_error(
+                "unable to commit the migrations",
+                e,
+            ));
+        },
+    }
+
+    Ok(())
+}
@@ -1,9 +1,15 @@
 //! Postgres store
 
+pub use checkpoint_store::CheckpointStore;
 pub use event_store::EventStore;
+pub use pool::PostgresPool;
 pub use query_store::QueryStore;
 
+mod checkpoint_store;
+mod driver_error;
 mod event_store;
+mod migrations;
+mod pool;
 mod query_store;
 
 mod test;
@@ -0,0 +1,30 @@
+use postgres::NoTls;
+use r2d2::{
+    Pool,
+    PooledConnection,
+};
+use r2d2_postgres::PostgresConnectionManager;
+
+use crate::errors::StoreError;
+
+/// The r2d2 pool of Postgres connections shared by the stores
+pub type PostgresPool = Pool<PostgresConnectionManager<NoTls>>;
+
+/// A connection taken from a `PostgresPool`
+pub(super) type PostgresConnection =
+    PooledConnection<PostgresConnectionManager<NoTls>>;
+
+/// Takes a connection from the pool for a single operation
+pub(super) fn get_conn(
+    pool: &PostgresPool
+) -> Result<PostgresConnection, StoreError> {
+    match pool.get() {
+        Ok(x) => Ok(x),
+        Err(e) => {
+            Err(StoreError::connection(
+                "unable to get a connection from the pool",
+                e,
+            ))
+        },
+    }
+}
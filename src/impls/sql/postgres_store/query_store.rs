@@ -4,8 +4,6 @@ use log::{
 };
 use std::marker::PhantomData;
 
-use postgres::Client;
-
 use cqrs_es2::{
     Error,
     EventContext,
@@ -16,12 +14,32 @@ use cqrs_es2::{
     QueryContext,
 };
 
-use crate::repository::{
-    IEventDispatcher,
-    IQueryStore,
+use crate::{
+    errors::StoreError,
+    repository::{
+        EncryptionScope,
+        IEventDispatcher,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        IQueryStore,
+        StoreSerializer,
+    },
 };
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
 
-use super::super::postgres_constants::*;
+use super::super::{
+    postgres_constants::*,
+    TableNames,
+};
+use super::{
+    driver_error::driver_error,
+    migrations::migrate,
+    pool::{
+        get_conn,
+        PostgresPool,
+    },
+};
 
 /// Sync Postgres query store
 pub struct QueryStore<
@@ -30,7 +48,10 @@ pub struct QueryStore<
     A: IAggregate<C, E>,
     Q: IQuery<C, E>,
 > {
-    conn: Client,
+    pool: PostgresPool,
+    tables: TableNames,
+    query_type: String,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A, Q)>,
 }
 
@@ -41,16 +62,73 @@ impl<
         Q: IQuery<C, E>,
     > QueryStore<C, E, A, Q>
 {
-    /// Constructor
-    pub fn new(conn: Client) -> Self {
+    /// Constructor, creates or upgrades the schema of the default
+    /// tables
+    pub fn new(pool: PostgresPool) -> Result<Self, StoreError> {
+        Self::with_tables(pool, TableNames::default())
+    }
+
+    /// Constructor using the given table names, creates or upgrades
+    /// their schema
+    pub fn with_tables(
+        pool: PostgresPool,
+        tables: TableNames,
+    ) -> Result<Self, StoreError> {
+        migrate(&mut *get_conn(&pool)?, &tables)?;
+
         let x = Self {
-            conn,
+            pool,
+            tables,
+            query_type: Q::query_type().to_string(),
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
         };
 
         trace!("Created new sync Postgres query store");
 
-        x
+        Ok(x)
+    }
+
+    /// Stores the queries under a versioned query type, e.g. to
+    /// rebuild a new version of the queries next to the one in use
+    pub fn with_query_version(
+        mut self,
+        version: &str,
+    ) -> Self {
+        self.query_type = format!("{}_{}", Q::query_type(), version);
+        self
+    }
+
+    /// Stores the queries with `serializer`, the binary formats use
+    /// the bytea column
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
+
+    /// Compresses the queries longer than `threshold` bytes
+    /// with `compressor`, the uncompressed ones keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
+
+    /// Encrypts the queries with `encryption`, the queries stored
+    /// in clear keep loading
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
     }
 }
 
@@ -63,11 +141,11 @@ impl<
 {
     /// saves the updated query
     fn save_query(
-        &mut self,
+        &self,
         context: QueryContext<C, E, Q>,
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         let aggregate_id = context.aggregate_id;
 
@@ -77,43 +155,57 @@ impl<
         );
 
         let sql = match context.version {
-            1 => INSERT_QUERY,
-            _ => UPDATE_QUERY,
+            1 => self.tables.sql(INSERT_QUERY),
+            _ => self.tables.sql(UPDATE_QUERY),
         };
 
-        let payload = match serde_json::to_value(context.payload) {
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id: &aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .encrypt_to_json(&scope, &context.payload)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize the payload of query \
-                         '{}' with aggregate id '{}', error: {}",
-                        &query_type, &aggregate_id, e,
+                         '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        match self.conn.execute(
-            sql,
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            sql.as_str(),
             &[
                 &context.version,
-                &payload,
+                &payload.0,
                 &aggregate_type,
                 &aggregate_id,
                 &query_type,
+                &payload.1,
             ],
         ) {
             Ok(_) => {},
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to insert/update query for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -123,11 +215,11 @@ impl<
 
     /// loads the most recent query
     fn load_query(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<QueryContext<C, E, Q>, Error> {
+    ) -> Result<QueryContext<C, E, Q>, StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         trace!(
             "loading query '{}' for aggregate id '{}'",
@@ -135,8 +227,10 @@ impl<
             aggregate_id
         );
 
-        let rows = match self.conn.query(
-            SELECT_QUERY,
+        let mut conn = get_conn(&self.pool)?;
+
+        let rows = match conn.query(
+            self.tables.sql(SELECT_QUERY).as_str(),
             &[
                 &aggregate_type,
                 &aggregate_id,
@@ -145,13 +239,14 @@ impl<
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load queries table for query \
-                         '{}' with aggregate id '{}', error: {}",
-                        &query_type, &aggregate_id, e,
+                         '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -174,17 +269,26 @@ impl<
             },
         };
 
-        let payload = match serde_json::from_value(row.get(1)) {
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .decrypt_json(&scope, row.get(1), row.get(2))
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in queries table for \
-                         query '{}' with aggregate id '{}', error: \
-                         {}",
-                        &query_type, &aggregate_id, e,
+                         query '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -195,6 +299,70 @@ impl<
             payload,
         ))
     }
+    /// deletes the query of a particular `aggregate_id`
+    fn delete_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!(
+            "deleting query '{}' for aggregate id '{}'",
+            query_type, aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(DELETE_QUERY).as_str(),
+            &[&aggregate_type, &aggregate_id, &query_type],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete query '{}' for aggregate \
+                         id '{}'",
+                        &query_type, aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// deletes all queries of the query type
+    fn delete_queries(&self) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!("deleting all queries '{}'", query_type);
+
+        let mut conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(DELETE_QUERIES).as_str(),
+            &[&aggregate_type, &query_type],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete queries '{}'",
+                        &query_type
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
 }
 
 impl<
@@ -205,10 +373,10 @@ impl<
     > IEventDispatcher<C, E> for QueryStore<C, E, A, Q>
 {
     fn dispatch(
-        &mut self,
+        &self,
         aggregate_id: &str,
         events: &Vec<EventContext<C, E>>,
     ) -> Result<(), Error> {
-        self.dispatch_events(aggregate_id, events)
+        Ok(self.dispatch_events(aggregate_id, events)?)
     }
 }
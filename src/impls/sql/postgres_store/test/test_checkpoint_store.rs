@@ -0,0 +1,24 @@
+use crate::{
+    postgres_store::CheckpointStore,
+    ICheckpointStore,
+};
+
+use super::common::*;
+
+#[test]
+fn test_save_load_checkpoints() {
+    let pool = get_pool();
+    let store = CheckpointStore::new(pool).unwrap();
+
+    let subscription = uuid::Uuid::new_v4().to_string();
+
+    assert_eq!(store.load_checkpoint(&subscription).unwrap(), 0);
+
+    store
+        .save_checkpoint(&subscription, 5).unwrap();
+    assert_eq!(store.load_checkpoint(&subscription).unwrap(), 5);
+
+    store
+        .save_checkpoint(&subscription, 7).unwrap();
+    assert_eq!(store.load_checkpoint(&subscription).unwrap(), 7);
+}
@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    thread,
+};
 
 use postgres::{
     Client,
@@ -14,6 +18,8 @@ use cqrs_es2::{
 use crate::{
     postgres_store::EventStore,
     IEventStore,
+    IOutboxStore,
+    Repository,
 };
 
 use super::common::*;
@@ -30,8 +36,8 @@ pub fn get_metadata() -> HashMap<String, String> {
 
 #[test]
 fn test_save_load_events() {
-    let conn = Client::connect(CONNECTION_STRING, NoTls).unwrap();
-    let mut store = ThisEventStore::new(conn);
+    let pool = get_pool();
+    let store = ThisEventStore::new(pool).unwrap();
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -91,10 +97,105 @@ fn test_save_load_events() {
     assert_eq!(stored_events, contexts_0);
 }
 
+#[test]
+fn test_save_conflicting_events() {
+    let pool = get_pool();
+    let store = ThisEventStore::new(pool).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        metadata.clone(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    let conflicting_contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::EmailUpdated(EmailUpdated {
+            new_email: "test A".to_string(),
+        }),
+        metadata,
+    )];
+
+    let err = store
+        .save_events(&conflicting_contexts)
+        .unwrap_err();
+
+    assert!(err.is_conflict());
+
+    let stored_events = store.load_events(&id).unwrap();
+    assert_eq!(stored_events, contexts);
+}
+
+#[test]
+fn test_save_events_atomically() {
+    let pool = get_pool();
+    let store = ThisEventStore::new(pool).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        metadata.clone(),
+    )];
+
+    store.save_events(&contexts).unwrap();
+
+    let failing_contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            3,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test B".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::AddressUpdated(AddressUpdated {
+                new_address: "something else happening here"
+                    .to_string(),
+            }),
+            metadata,
+        ),
+    ];
+
+    store
+        .save_events(&failing_contexts)
+        .unwrap_err();
+
+    let stored_events = store.load_events(&id).unwrap();
+    assert_eq!(stored_events, contexts);
+}
+
 #[test]
 fn test_save_load_snapshots() {
-    let conn = Client::connect(CONNECTION_STRING, NoTls).unwrap();
-    let mut store = ThisEventStore::new(conn);
+    let pool = get_pool();
+    let store = ThisEventStore::new(pool).unwrap();
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -152,3 +253,343 @@ fn test_save_load_snapshots() {
 
     assert_eq!(stored_context, context);
 }
+
+#[test]
+fn test_save_load_late_snapshots() {
+    let pool = get_pool();
+    let store = ThisEventStore::new(pool).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = AggregateContext::new(
+        id.to_string(),
+        5,
+        Customer {
+            customer_id: "customer 1".to_string(),
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            addresses: vec!["initial address".to_string()],
+        },
+    );
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    let stored_context = store
+        .load_aggregate_from_snapshot(&id)
+        .unwrap();
+
+    assert_eq!(stored_context, context);
+}
+
+#[test]
+fn test_outbox() {
+    let pool = get_pool();
+    let store = ThisEventStore::new(pool).unwrap().with_outbox();
+
+    let pool = get_pool();
+    let other_store = ThisEventStore::new(pool).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let metadata = get_metadata();
+
+    let contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "test A".to_string(),
+            }),
+            metadata.clone(),
+        ),
+    ];
+
+    store.save_events(&contexts).unwrap();
+
+    let other_id = uuid::Uuid::new_v4().to_string();
+
+    other_store
+        .save_events(&vec![EventContext::new(
+            other_id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_B".to_string(),
+            }),
+            metadata,
+        )]).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id || x.aggregate_id == other_id)
+        .collect();
+
+    assert_eq!(pending, contexts);
+
+    store
+        .mark_events_delivered(&pending[..1]).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id)
+        .collect();
+
+    assert_eq!(pending, contexts[1..]);
+
+    // the pending events are deleted with their stream
+    store.delete_stream(&id).unwrap();
+
+    let pending: Vec<_> = store
+        .load_pending_events(1000).unwrap()
+        .into_iter()
+        .filter(|x| x.aggregate_id == id)
+        .collect();
+
+    assert_eq!(0, pending.len());
+}
+
+fn customer_event_type(event: &CustomerEvent) -> String {
+    match event {
+        CustomerEvent::NameAdded(_) => "customer.name",
+        CustomerEvent::EmailUpdated(_) => "customer.email",
+        CustomerEvent::AddressUpdated(_) => "customer.address",
+    }
+    .to_string()
+}
+
+fn stored_event_types(id: &str) -> Vec<(String, i64)> {
+    let mut conn = Client::connect(CONNECTION_STRING, NoTls).unwrap();
+
+    conn.query(
+        "SELECT event_type, event_version FROM events WHERE \
+         aggregate_id = $1 ORDER BY sequence;",
+        &[&id],
+    )
+    .unwrap()
+    .iter()
+    .map(|row| (row.get(0), row.get(1)))
+    .collect()
+}
+
+#[test]
+fn test_event_types() {
+    let metadata = get_metadata();
+
+    let contexts = |id: &str| {
+        vec![
+            EventContext::new(
+                id.to_string(),
+                1,
+                CustomerEvent::NameAdded(NameAdded {
+                    changed_name: "test_event_A".to_string(),
+                }),
+                metadata.clone(),
+            ),
+            EventContext::new(
+                id.to_string(),
+                2,
+                CustomerEvent::EmailUpdated(EmailUpdated {
+                    new_email: "test_event_B".to_string(),
+                }),
+                metadata.clone(),
+            ),
+        ]
+    };
+
+    // named after the enum variants by default
+    let pool = get_pool();
+    let store = ThisEventStore::new(pool).unwrap();
+
+    let id = uuid::Uuid::new_v4().to_string();
+    store.save_events(&contexts(&id)).unwrap();
+
+    assert_eq!(
+        stored_event_types(&id),
+        vec![
+            ("NameAdded".to_string(), 1),
+            ("EmailUpdated".to_string(), 1),
+        ]
+    );
+
+    // named by the store
+    let pool = get_pool();
+    let store = ThisEventStore::new(pool)
+        .unwrap()
+        .with_event_type(customer_event_type);
+
+    let id = uuid::Uuid::new_v4().to_string();
+    store.save_events(&contexts(&id)).unwrap();
+
+    assert_eq!(
+        stored_event_types(&id),
+        vec![
+            ("customer.name".to_string(), 1),
+            ("customer.email".to_string(), 1),
+        ]
+    );
+    assert_eq!(store.load_events(&id).unwrap(), contexts(&id));
+}
+
+#[cfg(feature = "with-encryption")]
+#[test]
+fn test_encrypt_payloads() {
+    use crate::{
+        KeyRing,
+        PayloadEncryption,
+    };
+
+    let keys = KeyRing::new("k1", &[1; 32]);
+
+    let pool = get_pool();
+    let store =
+        ThisEventStore::new(pool)
+            .unwrap()
+            .with_encryption(
+                PayloadEncryption::new(keys.clone())
+                    .with_field("/NameAdded/changed_name"),
+            );
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let mut contexts = vec![
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_A".to_string(),
+            }),
+            get_metadata(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "test_event_B".to_string(),
+            }),
+            get_metadata(),
+        ),
+    ];
+
+    store
+        .save_events(&contexts[..1].to_vec())
+        .unwrap();
+
+    keys.rotate("k2", &[2; 32]);
+
+    store
+        .save_events(&contexts[1..].to_vec())
+        .unwrap();
+
+    // the fields are stored encrypted, the metadata name the keys
+    let mut conn = Client::connect(CONNECTION_STRING, NoTls).unwrap();
+
+    let rows: Vec<(String, String)> = conn
+        .query(
+            "SELECT payload #>> '{NameAdded,changed_name}', \
+             metadata ->> 'encryption_key_id' FROM events WHERE \
+             aggregate_id = $1 ORDER BY sequence;",
+            &[&id],
+        )
+        .unwrap()
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    assert!(rows[0].0.starts_with("encrypted:k1:"));
+    assert!(rows[1].0.starts_with("encrypted:k2:"));
+    assert_eq!(rows[0].1, "k1");
+    assert_eq!(rows[1].1, "k2");
+
+    for (i, x) in ["k1", "k2"].iter().enumerate() {
+        contexts[i]
+            .metadata
+            .insert("encryption_key_id".to_string(), x.to_string());
+    }
+
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+
+    let context = AggregateContext::new(
+        id.to_string(),
+        2,
+        Customer {
+            customer_id: id.to_string(),
+            name: "test_event_B".to_string(),
+            email: "".to_string(),
+            addresses: Default::default(),
+        },
+    );
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    // the snapshots are encrypted whole
+    let payload: String = conn
+        .query_one(
+            "SELECT payload #>> '{}' FROM snapshots WHERE \
+             aggregate_id = $1;",
+            &[&id],
+        )
+        .unwrap()
+        .get(0);
+
+    assert!(payload.starts_with("encrypted:k2:"));
+    assert_eq!(
+        store
+            .load_aggregate_from_snapshot(&id)
+            .unwrap()
+            .payload,
+        context.payload
+    );
+}
+
+// the stores over a pool are shared by the threads of a server
+#[test]
+fn test_shared_repository() {
+    let store = ThisEventStore::new(get_pool()).unwrap();
+
+    let repo = Arc::new(
+        Repository::new(store, vec![], false)
+            .with_conflict_retries(3),
+    );
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let handles: Vec<_> = (0..4)
+        .map(|x| {
+            let repo = Arc::clone(&repo);
+            let id = id.clone();
+
+            thread::spawn(move || {
+                repo.execute(
+                    &id,
+                    CustomerCommand::AddAddress(AddAddress {
+                        new_address: format!("address {}", x),
+                    }),
+                )
+            })
+        })
+        .collect();
+
+    for x in handles {
+        x.join().unwrap().unwrap();
+    }
+
+    let context = repo
+        .load_aggregate_at_sequence(&id, 10)
+        .unwrap();
+
+    assert_eq!(context.version, 4);
+    assert_eq!(context.payload.addresses.len(), 4);
+}
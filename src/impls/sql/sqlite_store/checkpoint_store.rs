@@ -0,0 +1,125 @@
+use log::{
+    debug,
+    trace,
+};
+
+use rusqlite::{
+    params,
+    OptionalExtension,
+};
+
+use crate::{
+    errors::StoreError,
+    repository::ICheckpointStore,
+};
+
+use super::super::{
+    mysql_constants::*,
+    TableNames,
+};
+use super::{
+    driver_error::driver_error,
+    migrations::migrate,
+    pool::{
+        get_conn,
+        SqlitePool,
+    },
+};
+
+/// SQLite checkpoint storage
+pub struct CheckpointStore {
+    pool: SqlitePool,
+    tables: TableNames,
+}
+
+impl CheckpointStore {
+    /// Constructor, creates or upgrades the schema of the default
+    /// tables
+    pub fn new(pool: SqlitePool) -> Result<Self, StoreError> {
+        Self::with_tables(pool, TableNames::default())
+    }
+
+    /// Constructor using the given table names, creates or upgrades
+    /// their schema
+    pub fn with_tables(
+        pool: SqlitePool,
+        tables: TableNames,
+    ) -> Result<Self, StoreError> {
+        migrate(&mut *get_conn(&pool)?, &tables)?;
+
+        Ok(Self { pool, tables })
+    }
+}
+
+impl ICheckpointStore for CheckpointStore {
+    /// Save the last position processed by `subscription`
+    fn save_checkpoint(
+        &self,
+        subscription: &str,
+        position: i64,
+    ) -> Result<(), StoreError> {
+        debug!(
+            "storing checkpoint {} for subscription '{}'",
+            position, subscription
+        );
+
+        let conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(UPSERT_CHECKPOINT).as_str(),
+            params![subscription, position],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert/update checkpoint for \
+                         subscription '{}'",
+                        subscription
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Load the last position processed by `subscription`
+    fn load_checkpoint(
+        &self,
+        subscription: &str,
+    ) -> Result<i64, StoreError> {
+        trace!(
+            "loading checkpoint for subscription '{}'",
+            subscription
+        );
+
+        let conn = get_conn(&self.pool)?;
+
+        let res = match conn
+            .query_row(
+                self.tables.sql(SELECT_CHECKPOINT).as_str(),
+                params![subscription],
+                |row| row.get(0),
+            )
+            .optional()
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load checkpoints table for \
+                         subscription '{}'",
+                        subscription
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(res.unwrap_or(0))
+    }
+}
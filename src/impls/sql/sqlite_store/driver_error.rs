@@ -0,0 +1,29 @@
+use rusqlite::{
+    Error,
+    ErrorCode,
+};
+
+use crate::errors::StoreError;
+
+/// Classifies a SQLite error into a `StoreError`
+pub(super) fn driver_error(
+    message: &str,
+    e: Error,
+) -> StoreError {
+    match &e {
+        Error::SqliteFailure(x, _)
+            if x.code == ErrorCode::CannotOpen =>
+        {
+            StoreError::connection(message, e)
+        },
+        Error::SqliteFailure(_, Some(x))
+            if x.starts_with("no such table") =>
+        {
+            StoreError::NotFound {
+                message: message.to_string(),
+                source: Some(Box::new(e)),
+            }
+        },
+        _ => StoreError::backend(message, e),
+    }
+}
@@ -2,114 +2,339 @@ use log::{
     debug,
     trace,
 };
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    time::{
+        Duration,
+        UNIX_EPOCH,
+    },
+};
 
 use rusqlite::{
+    ffi::SQLITE_CONSTRAINT_PRIMARYKEY,
     params,
-    Connection,
+    Error as SqliteError,
 };
 
 use cqrs_es2::{
     AggregateContext,
-    Error,
     EventContext,
     IAggregate,
     ICommand,
     IEvent,
 };
 
-use crate::repository::IEventStore;
+use crate::{
+    errors::{
+        ConflictError,
+        StoreError,
+    },
+    repository::{
+        event_type,
+        EncryptionScope,
+        EventUpcasters,
+        IEventStore,
+        IOutboxStore,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        PositionedEvent,
+        StoreSerializer,
+        StreamMetadata,
+        TimestampedEvent,
+    },
+};
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
+
+use super::super::{
+    mysql_constants::*,
+    TableNames,
+};
+use super::{
+    driver_error::driver_error,
+    migrations::migrate,
+    pool::{
+        get_conn,
+        SqlitePool,
+    },
+};
 
-use super::super::mysql_constants::*;
+// writers are serialized by SQLite, the counter is raised in the
+// transaction of the events and never lowered by a deletion, the
+// events inserted without it are skipped over
+static TAKE_POSITION: &str = "
+UPDATE
+    {events}_positions
+SET
+    position = MAX(
+        position,
+        (
+            SELECT
+                COALESCE(MAX(position), 0)
+            FROM
+                {events}
+        )
+    ) + 1
+WHERE
+    id = 1;
+";
 
-static CREATE_EVENTS_TABLE: &str = "
-CREATE TABLE IF NOT EXISTS
-    events
+// the position is the one just taken, the timestamp is set with
+// milliseconds rather than the seconds of the column default
+static INSERT_POSITIONED_EVENT: &str = "
+INSERT INTO
+    {events}
     (
-        aggregate_type TEXT                         NOT NULL,
-        aggregate_id   TEXT                         NOT NULL,
-        sequence       bigint CHECK (sequence >= 0) NOT NULL,
-        payload        TEXT                         NOT NULL,
-        metadata       TEXT                         NOT NULL,
-        timestamp      timestamp DEFAULT (CURRENT_TIMESTAMP),
-        PRIMARY KEY (aggregate_type, aggregate_id, sequence)
+        aggregate_type,
+        aggregate_id,
+        sequence,
+        payload,
+        metadata,
+        event_type,
+        event_version,
+        payload_bytes,
+        metadata_bytes,
+        timestamp,
+        position
+    )
+VALUES
+    (
+        ?,
+        ?,
+        ?,
+        ?,
+        ?,
+        ?,
+        ?,
+        ?,
+        ?,
+        strftime('%Y-%m-%d %H:%M:%f', 'now'),
+        (
+            SELECT
+                position
+            FROM
+                {events}_positions
+            WHERE
+                id = 1
+        )
     );
 ";
 
-static CREATE_SNAPSHOT_TABLE: &str = "
-CREATE TABLE IF NOT EXISTS
-    snapshots
+// the timestamps are kept as text, they are read as microseconds
+// since the epoch
+static SELECT_SQLITE_EVENTS: &str = "
+SELECT
+    sequence,
+    payload,
+    metadata,
+    event_type,
+    event_version,
+    (
+        CAST(strftime('%s', timestamp) AS INTEGER) * 1000 +
+        CAST(substr(strftime('%f', timestamp), 4) AS INTEGER)
+    ) * 1000,
+    payload_bytes,
+    metadata_bytes
+FROM
+    {events}
+WHERE
+    aggregate_type = ?
+    AND
+    aggregate_id = ?
+    AND
+    sequence >= ?
+    AND
+    sequence <= ?
+ORDER BY
+    sequence;
+";
+
+static SELECT_ALL_SQLITE_EVENTS: &str = "
+SELECT
+    position,
+    aggregate_id,
+    sequence,
+    payload,
+    metadata,
+    event_type,
+    event_version,
+    (
+        CAST(strftime('%s', timestamp) AS INTEGER) * 1000 +
+        CAST(substr(strftime('%f', timestamp), 4) AS INTEGER)
+    ) * 1000,
+    payload_bytes,
+    metadata_bytes
+FROM
+    {events}
+WHERE
+    aggregate_type = ?
+    AND
+    position >= ?
+ORDER BY
+    position
+LIMIT
+    ?;
+";
+
+static SELECT_SQLITE_STREAM_METADATA: &str = "
+SELECT
+    COALESCE(MAX(sequence), 0),
+    COUNT(*),
+    (
+        CAST(strftime('%s', MIN(timestamp)) AS INTEGER) * 1000 +
+        CAST(substr(strftime('%f', MIN(timestamp)), 4) AS INTEGER)
+    ) * 1000,
     (
-        aggregate_type TEXT                              NOT NULL,
-        aggregate_id   TEXT                              NOT NULL,
-        version        bigint       CHECK (version >= 0) NOT NULL,
-        payload        TEXT                              NOT NULL,
-        timestamp      timestamp DEFAULT (CURRENT_TIMESTAMP),
-        PRIMARY KEY (aggregate_type, aggregate_id)
+        CAST(strftime('%s', MAX(timestamp)) AS INTEGER) * 1000 +
+        CAST(substr(strftime('%f', MAX(timestamp)), 4) AS INTEGER)
+    ) * 1000,
+    (
+        SELECT
+            version
+        FROM
+            {snapshots}
+        WHERE
+            aggregate_type = ?
+            AND
+            aggregate_id = ?
+    )
+FROM
+    {events}
+WHERE
+    aggregate_type = ?
+    AND
+    aggregate_id = ?;
+";
+
+/// The binary payload and metadata of an event
+type EventBytes = (Option<Vec<u8>>, Option<Vec<u8>>);
+
+/// The sequence, payload, metadata, event type, event version,
+/// timestamp, binary payload and binary metadata of an event
+type EventRow = (
+    i64,
+    String,
+    String,
+    String,
+    i64,
+    i64,
+    Option<Vec<u8>>,
+    Option<Vec<u8>>,
+);
+
+static INSERT_SQLITE_TOMBSTONE: &str = "
+INSERT OR IGNORE INTO
+    {tombstones}
+    (
+        aggregate_type,
+        aggregate_id
+    )
+VALUES
+    (
+        ?,
+        ?
     );
 ";
 
 /// SQLite storage
 pub struct EventStore<C: ICommand, E: IEvent, A: IAggregate<C, E>> {
-    conn: Connection,
+    pool: SqlitePool,
+    tables: TableNames,
+    with_outbox: bool,
+    upcasters: EventUpcasters,
+    event_type: Option<fn(&E) -> String>,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A)>,
 }
 
 impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
     EventStore<C, E, A>
 {
-    /// Constructor
-    pub fn new(conn: Connection) -> Self {
-        Self {
-            conn,
+    /// Constructor, creates or upgrades the schema of the default
+    /// tables
+    pub fn new(pool: SqlitePool) -> Result<Self, StoreError> {
+        Self::with_tables(pool, TableNames::default())
+    }
+
+    /// Constructor using the given table names, creates or upgrades
+    /// their schema
+    pub fn with_tables(
+        pool: SqlitePool,
+        tables: TableNames,
+    ) -> Result<Self, StoreError> {
+        migrate(&mut *get_conn(&pool)?, &tables)?;
+
+        Ok(Self {
+            pool,
+            tables,
+            with_outbox: false,
+            upcasters: EventUpcasters::default(),
+            event_type: None,
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
-        }
+        })
     }
 
-    fn create_events_table(&mut self) -> Result<(), Error> {
-        match self
-            .conn
-            .execute(CREATE_EVENTS_TABLE, [])
-        {
-            Ok(_) => {},
-            Err(e) => {
-                return Err(Error::new(
-                    format!(
-                        "unable to create events table with error: \
-                         {}",
-                        e
-                    )
-                    .as_str(),
-                ));
-            },
-        };
+    /// Enables the transactional outbox, new events are added to
+    /// the outbox table in the same transaction for an
+    /// `OutboxRelay` to deliver
+    pub fn with_outbox(mut self) -> Self {
+        self.with_outbox = true;
+        self
+    }
 
-        debug!("Created events table",);
+    /// Upcasts the stored payloads with `upcasters` before they are
+    /// deserialized
+    pub fn with_upcasters(
+        mut self,
+        upcasters: EventUpcasters,
+    ) -> Self {
+        self.upcasters = upcasters;
+        self
+    }
 
-        Ok(())
+    /// Names the event types stored with the events with
+    /// `event_type` instead of their enum variants
+    pub fn with_event_type(
+        mut self,
+        event_type: fn(&E) -> String,
+    ) -> Self {
+        self.event_type = Some(event_type);
+        self
     }
 
-    fn create_snapshot_table(&mut self) -> Result<(), Error> {
-        match self
-            .conn
-            .execute(CREATE_SNAPSHOT_TABLE, [])
-        {
-            Ok(_) => {},
-            Err(e) => {
-                return Err(Error::new(
-                    format!(
-                        "unable to create snapshots table with \
-                         error: {}",
-                        e
-                    )
-                    .as_str(),
-                ));
-            },
-        };
+    /// Stores the payloads, metadata and snapshots with
+    /// `serializer`, the binary formats use the blob columns
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
 
-        debug!("Created snapshots table",);
+    /// Compresses the payloads, metadata and snapshots longer than
+    /// `threshold` bytes with `compressor`, the uncompressed ones
+    /// keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
 
-        Ok(())
+    /// Encrypts the event payloads and the snapshots with
+    /// `encryption`, the events record the id of their key in their
+    /// metadata
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
     }
 }
 
@@ -118,11 +343,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 {
     /// Save new events
     fn save_events(
-        &mut self,
+        &self,
         contexts: &Vec<EventContext<C, E>>,
-    ) -> Result<(), Error> {
-        self.create_events_table()?;
-
+    ) -> Result<(), StoreError> {
         if contexts.len() == 0 {
             trace!("Skip saving zero contexts");
             return Ok(());
@@ -142,73 +365,210 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             &aggregate_id
         );
 
+        let with_outbox = self.with_outbox;
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let trans = match conn.transaction() {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to start a transaction for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
         for context in contexts {
             let payload =
-                match serde_json::to_string(&context.payload) {
+                match serde_json::to_value(&context.payload) {
                     Ok(x) => x,
                     Err(e) => {
-                        return Err(Error::new(
+                        return Err(StoreError::serialization(
                             format!(
                                 "unable to serialize the event \
-                                 payload for aggregate id '{}' with \
-                                 error: {}",
-                                &aggregate_id, e
+                                 payload for aggregate id '{}'",
+                                &aggregate_id
                             )
                             .as_str(),
+                            e,
                         ));
                     },
                 };
 
-            let metadata =
-                match serde_json::to_string(&context.metadata) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        return Err(Error::new(
-                            format!(
-                                "unable to serialize the event \
-                                 metadata for aggregate id '{}' \
-                                 with error: {}",
-                                &aggregate_id, e
-                            )
-                            .as_str(),
-                        ));
-                    },
-                };
+            let event_type = match self.event_type {
+                Some(x) => x(&context.payload),
+                None => event_type(&payload),
+            };
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &aggregate_id,
+                sequence: context.sequence,
+            };
 
-            match self.conn.execute(
-                INSERT_EVENT,
+            let (payload, metadata) = match self
+                .serializer
+                .encrypt_event(&scope, payload, &context.metadata)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to encrypt the event payload for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let metadata = match self.serializer.to_text(&metadata) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to serialize the event metadata \
+                             for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let event_version =
+                self.upcasters.event_version(&event_type);
+
+            let payload = match self.serializer.split_text(payload) {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::serialization(
+                        format!(
+                            "unable to serialize the event payload \
+                             for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            match trans
+                .execute(self.tables.sql(TAKE_POSITION).as_str(), [])
+            {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to take the next position for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            match trans.execute(
+                self.tables.sql(INSERT_POSITIONED_EVENT).as_str(),
                 params![
                     aggregate_type,
                     aggregate_id,
                     context.sequence,
-                    payload,
-                    metadata,
+                    payload.0,
+                    metadata.0,
+                    event_type,
+                    event_version,
+                    payload.1,
+                    metadata.1,
                 ],
             ) {
                 Ok(x) => x,
+                // only the primary key of the events is a conflict
+                Err(SqliteError::SqliteFailure(e, _))
+                    if e.extended_code ==
+                        SQLITE_CONSTRAINT_PRIMARYKEY =>
+                {
+                    return Err(ConflictError::new(
+                        aggregate_type,
+                        &aggregate_id,
+                        context.sequence,
+                    )
+                    .into());
+                },
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(driver_error(
                         format!(
                             "unable to insert new event for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
+
+            if !with_outbox {
+                continue;
+            }
+
+            match trans.execute(
+                self.tables.sql(INSERT_OUTBOX_EVENT).as_str(),
+                params![aggregate_type, aggregate_id, context.sequence],
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to insert new outbox event for \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+        }
+
+        match trans.commit() {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to commit new events for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
         }
 
         Ok(())
     }
 
-    /// Load all events for a particular `aggregate_id`
-    fn load_events(
-        &mut self,
+    /// Load the events for a particular `aggregate_id` within a
+    /// range of sequences along with their commit timestamps
+    fn load_timestamped_events_range(
+        &self,
         aggregate_id: &str,
-    ) -> Result<Vec<EventContext<C, E>>, Error> {
-        self.create_events_table()?;
-
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -216,33 +576,55 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             aggregate_id
         );
 
-        let mut sql = match self.conn.prepare(SELECT_EVENTS) {
+        let conn = get_conn(&self.pool)?;
+
+        let mut sql = match conn
+            .prepare(self.tables.sql(SELECT_SQLITE_EVENTS).as_str())
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to prepare events table for \
-                         aggregate id '{}', error: {}",
-                        &aggregate_id, e,
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
         let rows = match sql.query_map(
-            params![aggregate_type, aggregate_id],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            params![
+                aggregate_type,
+                aggregate_id,
+                from_sequence,
+                to_sequence.unwrap_or(i64::MAX),
+            ],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                    row.get(7)?,
+                ))
+            },
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load queries table for aggregate \
-                         id '{}', error: {}",
-                        &aggregate_id, e,
+                         id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -250,55 +632,501 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         let mut result = Vec::new();
 
         for row in rows {
-            let row: (i64, String, String) = row.unwrap();
+            let row: EventRow = match row {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to load next entry from events \
+                             table for aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
 
-            let payload = match serde_json::from_str(row.1.as_str()) {
+            let bytes: EventBytes = (row.6, row.7);
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id,
+                sequence: row.0,
+            };
+
+            let payload = match self
+                .serializer
+                .deserialize_text_event(
+                    &scope,
+                    &self.upcasters,
+                    &row.3,
+                    row.1,
+                    bytes.0,
+                    row.4,
+                )
+            {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::deserialization(
                         format!(
                             "bad payload found in events table for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-            let metadata = match serde_json::from_str(row.2.as_str())
+            let metadata = match self
+                .serializer
+                .deserialize_text(row.2, bytes.1)
             {
                 Ok(x) => x,
                 Err(e) => {
-                    return Err(Error::new(
+                    return Err(StoreError::deserialization(
                         format!(
                             "bad metadata found in events table for \
-                             aggregate id '{}' with error: {}",
-                            &aggregate_id, e
+                             aggregate id '{}'",
+                            &aggregate_id
                         )
                         .as_str(),
+                        e,
                     ));
                 },
             };
 
-            result.push(EventContext::new(
-                aggregate_id.to_string(),
+            result.push(TimestampedEvent::new(
+                UNIX_EPOCH + Duration::from_micros(row.5 as u64),
+                EventContext::new(
+                    aggregate_id.to_string(),
+                    row.0,
+                    payload,
+                    metadata,
+                ),
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Load up to `limit` events of all aggregates of this type in
+    /// commit order, starting at the global position
+    /// `from_position`
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "reading up to '{}' events from position {}",
+            limit,
+            from_position
+        );
+
+        let conn = get_conn(&self.pool)?;
+
+        let mut sql = match conn
+            .prepare(
+                self.tables
+                    .sql(SELECT_ALL_SQLITE_EVENTS)
+                    .as_str(),
+            )
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to prepare events table query",
+                    e,
+                ));
+            },
+        };
+
+        let rows = match sql.query_map(
+            params![aggregate_type, from_position, limit as i64],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, i64>(6)?,
+                    row.get::<_, i64>(7)?,
+                    row.get::<_, Option<Vec<u8>>>(8)?,
+                    row.get::<_, Option<Vec<u8>>>(9)?,
+                ))
+            },
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load events table from position \
+                         {}",
+                        from_position
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let rows = match rows.collect::<Result<Vec<_>, _>>() {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to load next entry from events table",
+                    e,
+                ));
+            },
+        };
+
+        let mut result = Vec::new();
+
+        for row in rows {
+            let bytes: EventBytes = (row.8, row.9);
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &row.1,
+                sequence: row.2,
+            };
+
+            let payload = match self
+                .serializer
+                .deserialize_text_event(
+                    &scope,
+                    &self.upcasters,
+                    &row.5,
+                    row.3,
+                    bytes.0,
+                    row.6,
+                )
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad payload found in events table for \
+                             aggregate id '{}'",
+                            &row.1
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let metadata = match self
+                .serializer
+                .deserialize_text(row.4, bytes.1)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad metadata found in events table for \
+                             aggregate id '{}'",
+                            &row.1
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            result.push(PositionedEvent::new(
                 row.0,
-                payload,
-                metadata,
+                UNIX_EPOCH + Duration::from_micros(row.7 as u64),
+                EventContext::new(row.1, row.2, payload, metadata),
             ));
         }
 
         Ok(result)
     }
 
+    /// List up to `limit` ids of the aggregates of this type in
+    /// ascending order, starting after `after_aggregate_id`
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "listing up to '{}' aggregate ids after '{:?}'",
+            limit,
+            after_aggregate_id
+        );
+
+        let conn = get_conn(&self.pool)?;
+
+        let mut sql = match conn
+            .prepare(self.tables.sql(SELECT_AGGREGATE_IDS).as_str())
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to prepare events table query",
+                    e,
+                ));
+            },
+        };
+
+        let rows = match sql.query_map(
+            params![
+                aggregate_type,
+                after_aggregate_id.unwrap_or(""),
+                limit as i64
+            ],
+            |row| row.get::<_, String>(0),
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to list aggregate ids from events table",
+                    e,
+                ));
+            },
+        };
+
+        match rows.collect::<Result<Vec<_>, _>>() {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                Err(driver_error(
+                    "unable to load next entry from events table",
+                    e,
+                ))
+            },
+        }
+    }
+
+    /// Load the metadata of the event stream of a particular
+    /// `aggregate_id`
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "loading stream metadata for aggregate id '{}'",
+            aggregate_id
+        );
+
+        let conn = get_conn(&self.pool)?;
+
+        let row = match conn.query_row(
+            self.tables
+                .sql(SELECT_SQLITE_STREAM_METADATA)
+                .as_str(),
+            params![
+                aggregate_type,
+                aggregate_id,
+                aggregate_type,
+                aggregate_id
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<i64>>(2)?,
+                    row.get::<_, Option<i64>>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                ))
+            },
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to load stream metadata for \
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        if row.1 == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(StreamMetadata::new(
+            aggregate_id,
+            row.0,
+            row.1,
+            UNIX_EPOCH +
+                Duration::from_micros(row.2.unwrap_or(0) as u64),
+            UNIX_EPOCH +
+                Duration::from_micros(row.3.unwrap_or(0) as u64),
+            row.4,
+        )))
+    }
+
+    /// Delete all events of a particular `aggregate_id` along with
+    /// its snapshot and outbox entries
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting events and snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let mut conn = get_conn(&self.pool)?;
+
+        let trans = match conn.transaction() {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to start a transaction for aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        for x in [
+            DELETE_AGGREGATE_OUTBOX_EVENTS,
+            DELETE_EVENTS,
+            DELETE_SNAPSHOT,
+        ] {
+            match trans.execute(
+                self.tables.sql(x).as_str(),
+                params![aggregate_type, aggregate_id],
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to delete the stream of \
+                             aggregate id '{}'",
+                            &aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+        }
+
+        match trans.commit() {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to commit the deletion of aggregate \
+                         id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Mark the stream of a particular `aggregate_id` as tombstoned
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!("tombstoning aggregate id '{}'", aggregate_id);
+
+        let conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables
+                .sql(INSERT_SQLITE_TOMBSTONE)
+                .as_str(),
+            params![aggregate_type, aggregate_id],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to insert tombstone for aggregate id \
+                         '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Check whether the stream of a particular `aggregate_id` was
+    /// tombstoned
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!(
+            "checking tombstone of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let conn = get_conn(&self.pool)?;
+
+        let mut sql = match conn
+            .prepare(self.tables.sql(SELECT_TOMBSTONE).as_str())
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to prepare tombstones table query",
+                    e,
+                ));
+            },
+        };
+
+        match sql.exists(params![aggregate_type, aggregate_id]) {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                Err(driver_error(
+                    format!(
+                        "unable to load tombstones table for \
+                         aggregate id '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ))
+            },
+        }
+    }
+
     /// save a new aggregate snapshot
     fn save_aggregate_snapshot(
-        &mut self,
+        &self,
         context: AggregateContext<C, E, A>,
-    ) -> Result<(), Error> {
-        self.create_snapshot_table()?;
-
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
 
         let aggregate_id = context.aggregate_id;
@@ -308,43 +1136,86 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             &aggregate_id
         );
 
-        let sql = match context.version {
-            1 => INSERT_SNAPSHOT,
-            _ => UPDATE_SNAPSHOT,
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id: &aggregate_id,
         };
 
-        let payload = match serde_json::to_string(&context.payload) {
+        let payload = match self
+            .serializer
+            .encrypt_to_text(&scope, &context.payload)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize aggregate snapshot for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        match self.conn.execute(
-            sql,
+        let conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(UPSERT_SNAPSHOT).as_str(),
             params![
                 context.version,
-                payload,
+                payload.0,
+                payload.1,
                 aggregate_type,
                 aggregate_id,
             ],
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to insert/update snapshot for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// Delete the snapshot of a particular `aggregate_id`
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!(
+            "deleting the snapshot of aggregate id '{}'",
+            aggregate_id
+        );
+
+        let conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(DELETE_SNAPSHOT).as_str(),
+            params![aggregate_type, aggregate_id],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete snapshot for aggregate id \
+                         '{}'",
+                        &aggregate_id
+                    )
+                    .as_str(),
+                    e,
                 ));
             },
         };
@@ -354,11 +1225,9 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 
     /// Load aggregate at current state from snapshots
     fn load_aggregate_from_snapshot(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<AggregateContext<C, E, A>, Error> {
-        self.create_snapshot_table()?;
-
+    ) -> Result<AggregateContext<C, E, A>, StoreError> {
         let aggregate_type = A::aggregate_type();
 
         trace!(
@@ -366,38 +1235,45 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
             aggregate_id
         );
 
-        let mut sql = match self.conn.prepare(SELECT_SNAPSHOT) {
+        let conn = get_conn(&self.pool)?;
+
+        let mut sql = match conn
+            .prepare(self.tables.sql(SELECT_SNAPSHOT).as_str())
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to prepare snapshots table for \
-                         aggregate id '{}', error: {}",
-                        &aggregate_id, e,
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
         let res = match sql.query_map(
             params![aggregate_type, aggregate_id],
-            |row| Ok((row.get(0)?, row.get(1)?)),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load snapshots table for \
-                         aggregate id '{}', error: {}",
-                        &aggregate_id, e,
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        let mut rows: Vec<(i64, String)> = Vec::new();
+        let mut rows: Vec<(i64, String, Option<Vec<u8>>)> =
+            Vec::new();
 
         for x in res {
             rows.push(x.unwrap());
@@ -419,16 +1295,25 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
 
         let row = rows[0].clone();
 
-        let payload = match serde_json::from_str(row.1.as_str()) {
+        let scope = EncryptionScope::Snapshot {
+            aggregate_type,
+            aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .decrypt_text(&scope, row.1, row.2)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in snapshots table for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -440,3 +1325,164 @@ impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IEventStore<C, E, A>
         ))
     }
 }
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> IOutboxStore<C, E, A>
+    for EventStore<C, E, A>
+{
+    /// Load up to `limit` pending events in commit order
+    fn load_pending_events(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        trace!("loading up to '{}' pending outbox events", limit);
+
+        let conn = get_conn(&self.pool)?;
+
+        let mut sql = match conn
+            .prepare(self.tables.sql(SELECT_OUTBOX_EVENTS).as_str())
+        {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to prepare outbox table query",
+                    e,
+                ));
+            },
+        };
+
+        let rows = match sql.query_map(
+            params![aggregate_type, limit as i64],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, i64>(5)?,
+                    row.get::<_, Option<Vec<u8>>>(6)?,
+                    row.get::<_, Option<Vec<u8>>>(7)?,
+                ))
+            },
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to load outbox table",
+                    e,
+                ));
+            },
+        };
+
+        let rows = match rows.collect::<Result<Vec<_>, _>>() {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(driver_error(
+                    "unable to load next entry from outboxtable",
+                    e,
+                ));
+            },
+        };
+
+        let mut result = Vec::new();
+
+        for row in rows {
+            let bytes: EventBytes = (row.6, row.7);
+
+            let scope = EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id: &row.0,
+                sequence: row.1,
+            };
+
+            let payload = match self
+                .serializer
+                .deserialize_text_event(
+                    &scope,
+                    &self.upcasters,
+                    &row.4,
+                    row.2,
+                    bytes.0,
+                    row.5,
+                )
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad payload found in events table for \
+                             aggregate id '{}'",
+                            &row.0
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            let metadata = match self
+                .serializer
+                .deserialize_text(row.3, bytes.1)
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    return Err(StoreError::deserialization(
+                        format!(
+                            "bad metadata found in events table for \
+                             aggregate id '{}'",
+                            &row.0
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            };
+
+            result.push(EventContext::new(
+                row.0, row.1, payload, metadata,
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// Mark events as delivered so that they are not loaded again
+    fn mark_events_delivered(
+        &self,
+        contexts: &[EventContext<C, E>],
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        debug!("marking '{}' outbox events delivered", contexts.len());
+
+        let conn = get_conn(&self.pool)?;
+
+        for context in contexts {
+            match conn.execute(
+                self.tables.sql(DELETE_OUTBOX_EVENT).as_str(),
+                params![
+                    aggregate_type,
+                    context.aggregate_id,
+                    context.sequence,
+                ],
+            ) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to delete outbox event for \
+                             aggregate id '{}'",
+                            &context.aggregate_id
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
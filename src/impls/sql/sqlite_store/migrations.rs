@@ -0,0 +1,399 @@
+use log::{
+    debug,
+    trace,
+};
+
+use rusqlite::{
+    params,
+    Connection,
+    TransactionBehavior,
+};
+
+use crate::errors::StoreError;
+
+use super::super::{
+    migration::Migration,
+    table_names::TableNames,
+};
+use super::driver_error::driver_error;
+
+static CREATE_MIGRATIONS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS
+    {migrations}
+    (
+        version     bigint NOT NULL,
+        description TEXT   NOT NULL,
+        applied_at  timestamp DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (version)
+    );
+";
+
+static SELECT_SCHEMA_VERSION: &str = "
+SELECT
+    COALESCE(MAX(version), 0)
+FROM
+    {migrations};
+";
+
+static INSERT_MIGRATION: &str = "
+INSERT INTO
+    {migrations}
+    (
+        version,
+        description
+    )
+VALUES
+    (
+        ?,
+        ?
+    );
+";
+
+/// The schema of the SQLite stores, the tables of the first
+/// migration are only created if they do not exist yet so that the
+/// databases created by previous versions are adopted
+pub(super) static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create the events, snapshots and queries tables",
+        statements: &[
+            "
+CREATE TABLE IF NOT EXISTS
+    {events}
+    (
+        aggregate_type TEXT                         NOT NULL,
+        aggregate_id   TEXT                         NOT NULL,
+        sequence       bigint CHECK (sequence >= 0) NOT NULL,
+        payload        TEXT                         NOT NULL,
+        metadata       TEXT                         NOT NULL,
+        timestamp      timestamp DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (aggregate_type, aggregate_id, sequence)
+    );
+",
+            "
+CREATE TABLE IF NOT EXISTS
+    {snapshots}
+    (
+        aggregate_type TEXT                        NOT NULL,
+        aggregate_id   TEXT                        NOT NULL,
+        version        bigint CHECK (version >= 0) NOT NULL,
+        payload        TEXT                        NOT NULL,
+        timestamp      timestamp DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (aggregate_type, aggregate_id)
+    );
+",
+            "
+CREATE TABLE IF NOT EXISTS
+    {queries}
+    (
+        aggregate_type TEXT                        NOT NULL,
+        aggregate_id   TEXT                        NOT NULL,
+        query_type     TEXT                        NOT NULL,
+        version        bigint CHECK (version >= 0) NOT NULL,
+        payload        TEXT                        NOT NULL,
+        PRIMARY KEY (aggregate_type, aggregate_id, query_type)
+    );
+",
+        ],
+    },
+    // SQLite cannot add a unique column, the existing events are
+    // numbered in insertion order and the uniqueness is enforced by
+    // an index
+    Migration {
+        version: 2,
+        description: "add the global positions of the events",
+        statements: &[
+            "
+ALTER TABLE
+    {events}
+ADD COLUMN
+    position bigint;
+",
+            "
+UPDATE
+    {events}
+SET
+    position = rowid;
+",
+            "
+CREATE UNIQUE INDEX
+    {events}_position
+ON
+    {events}
+    (
+        position
+    );
+",
+        ],
+    },
+    Migration {
+        version: 3,
+        description: "create the outbox table",
+        statements: &["
+CREATE TABLE IF NOT EXISTS
+    {outbox}
+    (
+        id             INTEGER PRIMARY KEY AUTOINCREMENT,
+        aggregate_type TEXT                         NOT NULL,
+        aggregate_id   TEXT                         NOT NULL,
+        sequence       bigint CHECK (sequence >= 0) NOT NULL,
+        UNIQUE (aggregate_type, aggregate_id, sequence)
+    );
+"],
+    },
+    Migration {
+        version: 4,
+        description: "create the checkpoints table",
+        statements: &["
+CREATE TABLE IF NOT EXISTS
+    {checkpoints}
+    (
+        subscription TEXT                         NOT NULL,
+        position     bigint CHECK (position >= 0) NOT NULL,
+        PRIMARY KEY (subscription)
+    );
+"],
+    },
+    Migration {
+        version: 5,
+        description: "add the schema versions of the events",
+        statements: &["
+ALTER TABLE
+    {events}
+ADD COLUMN
+    event_version bigint NOT NULL DEFAULT 1;
+"],
+    },
+    Migration {
+        version: 6,
+        description: "add the types of the events",
+        statements: &[
+            "
+ALTER TABLE
+    {events}
+ADD COLUMN
+    event_type TEXT NOT NULL DEFAULT '';
+",
+            "
+UPDATE
+    {events}
+SET
+    event_type = CASE json_type(payload)
+        WHEN 'object' THEN (
+            SELECT
+                MIN(key)
+            FROM
+                json_each({events}.payload)
+        )
+        WHEN 'text' THEN json_extract(payload, '$')
+        ELSE ''
+    END;
+",
+            "
+CREATE INDEX
+    {events}_event_type
+ON
+    {events}
+    (
+        aggregate_type,
+        event_type
+    );
+",
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "create the tombstones table",
+        statements: &["
+CREATE TABLE IF NOT EXISTS
+    {tombstones}
+    (
+        aggregate_type TEXT NOT NULL,
+        aggregate_id   TEXT NOT NULL,
+        timestamp      timestamp DEFAULT (CURRENT_TIMESTAMP),
+        PRIMARY KEY (aggregate_type, aggregate_id)
+    );
+"],
+    },
+    // SQLite cannot drop the NOT NULL constraints, the text columns
+    // of the binary rows are left empty
+    Migration {
+        version: 8,
+        description: "add the binary payloads",
+        statements: &[
+            "
+ALTER TABLE
+    {events}
+ADD COLUMN
+    payload_bytes BLOB;
+",
+            "
+ALTER TABLE
+    {events}
+ADD COLUMN
+    metadata_bytes BLOB;
+",
+            "
+ALTER TABLE
+    {snapshots}
+ADD COLUMN
+    payload_bytes BLOB;
+",
+            "
+ALTER TABLE
+    {queries}
+ADD COLUMN
+    payload_bytes BLOB;
+",
+        ],
+    },
+    // the last position taken is kept apart from the events, so that
+    // deleting the latest events does not hand out their positions
+    // again
+    Migration {
+        version: 9,
+        description: "create the positions counter table",
+        statements: &[
+            "
+CREATE TABLE IF NOT EXISTS
+    {events}_positions
+    (
+        id       INTEGER CHECK (id = 1)       NOT NULL,
+        position bigint CHECK (position >= 0) NOT NULL,
+        PRIMARY KEY (id)
+    );
+",
+            "
+INSERT INTO
+    {events}_positions
+    (
+        id,
+        position
+    )
+SELECT
+    1,
+    COALESCE(MAX(position), 0)
+FROM
+    {events};
+",
+        ],
+    },
+];
+
+/// Applies the migrations missing from the tables in a single
+/// transaction, which takes the write lock of the database right
+/// away
+pub(super) fn migrate(
+    conn: &mut Connection,
+    tables: &TableNames,
+) -> Result<(), StoreError> {
+    tables.validate()?;
+
+    let trans = match conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+    {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(driver_error(
+                "unable to start a transaction for the migrations",
+                e,
+            ));
+        },
+    };
+
+    match trans.execute(
+        tables
+            .sql(CREATE_MIGRATIONS_TABLE)
+            .as_str(),
+        [],
+    ) {
+        Ok(_) => {},
+        Err(e) => {
+            return Err(driver_error(
+                format!(
+                    "unable to create migrations table '{}'",
+                    &tables.migrations
+                )
+                .as_str(),
+                e,
+            ));
+        },
+    }
+
+    let version: i64 = match trans.query_row(
+        tables
+            .sql(SELECT_SCHEMA_VERSION)
+            .as_str(),
+        [],
+        |row| row.get(0),
+    ) {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(driver_error(
+                format!(
+                    "unable to load migrations table '{}'",
+                    &tables.migrations
+                )
+                .as_str(),
+                e,
+            ));
+        },
+    };
+
+    trace!("schema of '{}' at version {}", &tables.events, version);
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|x| x.version > version)
+    {
+        debug!(
+            "applying migration {} '{}'",
+            migration.version, migration.description
+        );
+
+        for x in migration.statements {
+            match trans.execute(tables.sql(x).as_str(), []) {
+                Ok(_) => {},
+                Err(e) => {
+                    return Err(driver_error(
+                        format!(
+                            "unable to apply migration {} '{}'",
+                            migration.version, migration.description
+                        )
+                        .as_str(),
+                        e,
+                    ));
+                },
+            }
+        }
+
+        match trans.execute(
+            tables.sql(INSERT_MIGRATION).as_str(),
+            params![migration.version, migration.description],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to record migration {}",
+                        migration.version
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        }
+    }
+
+    match trans.commit() {
+        Ok(_) => {},
+        Err(e) => {
+            return Err(driver_error(
+                "unable to commit the migrations",
+                e,
+            ));
+        },
+    }
+
+    Ok(())
+}
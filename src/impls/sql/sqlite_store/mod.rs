@@ -1,9 +1,15 @@
 //! SQLite store
 
+pub use checkpoint_store::*;
 pub use event_store::*;
+pub use pool::SqlitePool;
 pub use query_store::*;
 
+mod checkpoint_store;
+mod driver_error;
 mod event_store;
+mod migrations;
+mod pool;
 mod query_store;
 
 mod test;
@@ -0,0 +1,30 @@
+use r2d2::{
+    Pool,
+    PooledConnection,
+};
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::errors::StoreError;
+
+/// The r2d2 pool of SQLite connections shared by the stores, an
+/// in-memory database is private to each connection of the pool
+pub type SqlitePool = Pool<SqliteConnectionManager>;
+
+/// A connection taken from a `SqlitePool`
+pub(super) type SqliteConnection =
+    PooledConnection<SqliteConnectionManager>;
+
+/// Takes a connection from the pool for a single operation
+pub(super) fn get_conn(
+    pool: &SqlitePool
+) -> Result<SqliteConnection, StoreError> {
+    match pool.get() {
+        Ok(x) => Ok(x),
+        Err(e) => {
+            Err(StoreError::connection(
+                "unable to get a connection from the pool",
+                e,
+            ))
+        },
+    }
+}
@@ -6,7 +6,6 @@ use std::marker::PhantomData;
 
 use rusqlite::{
     params,
-    Connection,
 };
 
 use cqrs_es2::{
@@ -19,25 +18,32 @@ use cqrs_es2::{
     QueryContext,
 };
 
-use crate::repository::{
-    IEventDispatcher,
-    IQueryStore,
+use crate::{
+    errors::StoreError,
+    repository::{
+        EncryptionScope,
+        IEventDispatcher,
+        IPayloadCompressor,
+        IPayloadSerializer,
+        IQueryStore,
+        StoreSerializer,
+    },
 };
+#[cfg(feature = "with-encryption")]
+use crate::repository::PayloadEncryption;
 
-use super::super::mysql_constants::*;
-
-static CREATE_QUERY_TABLE: &str = "
-CREATE TABLE IF NOT EXISTS
-queries
-(
-    aggregate_type TEXT                        NOT NULL,
-    aggregate_id   TEXT                        NOT NULL,
-    query_type     TEXT                        NOT NULL,
-    version        bigint CHECK (version >= 0) NOT NULL,
-    payload        TEXT                        NOT NULL,
-    PRIMARY KEY (aggregate_type, aggregate_id, query_type)
-);
-";
+use super::super::{
+    mysql_constants::*,
+    TableNames,
+};
+use super::{
+    driver_error::driver_error,
+    migrations::migrate,
+    pool::{
+        get_conn,
+        SqlitePool,
+    },
+};
 
 /// SQLite storage
 pub struct QueryStore<
@@ -46,7 +52,10 @@ pub struct QueryStore<
     A: IAggregate<C, E>,
     Q: IQuery<C, E>,
 > {
-    conn: Connection,
+    pool: SqlitePool,
+    tables: TableNames,
+    query_type: String,
+    serializer: StoreSerializer,
     _phantom: PhantomData<(C, E, A, Q)>,
 }
 
@@ -57,35 +66,69 @@ impl<
         Q: IQuery<C, E>,
     > QueryStore<C, E, A, Q>
 {
-    /// Constructor
-    pub fn new(conn: Connection) -> Self {
-        Self {
-            conn,
+    /// Constructor, creates or upgrades the schema of the default
+    /// tables
+    pub fn new(pool: SqlitePool) -> Result<Self, StoreError> {
+        Self::with_tables(pool, TableNames::default())
+    }
+
+    /// Constructor using the given table names, creates or upgrades
+    /// their schema
+    pub fn with_tables(
+        pool: SqlitePool,
+        tables: TableNames,
+    ) -> Result<Self, StoreError> {
+        migrate(&mut *get_conn(&pool)?, &tables)?;
+
+        Ok(Self {
+            pool,
+            tables,
+            query_type: Q::query_type().to_string(),
+            serializer: StoreSerializer::default(),
             _phantom: PhantomData,
-        }
+        })
     }
 
-    fn create_query_table(&mut self) -> Result<(), Error> {
-        match self
-            .conn
-            .execute(CREATE_QUERY_TABLE, [])
-        {
-            Ok(_) => {},
-            Err(e) => {
-                return Err(Error::new(
-                    format!(
-                        "unable to create queries table with error: \
-                         {}",
-                        e
-                    )
-                    .as_str(),
-                ));
-            },
-        };
+    /// Stores the queries under a versioned query type, e.g. to
+    /// rebuild a new version of the queries next to the one in use
+    pub fn with_query_version(
+        mut self,
+        version: &str,
+    ) -> Self {
+        self.query_type = format!("{}_{}", Q::query_type(), version);
+        self
+    }
 
-        debug!("Created queries table");
+    /// Stores the queries with `serializer`, the binary formats use
+    /// the blob column
+    pub fn with_serializer(
+        mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) -> Self {
+        self.serializer.set_serializer(serializer);
+        self
+    }
 
-        Ok(())
+    /// Compresses the queries longer than `threshold` bytes
+    /// with `compressor`, the uncompressed ones keep loading
+    pub fn with_compression(
+        mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) -> Self {
+        self.serializer.set_compression(compressor, threshold);
+        self
+    }
+
+    /// Encrypts the queries with `encryption`, the queries stored
+    /// in clear keep loading
+    #[cfg(feature = "with-encryption")]
+    pub fn with_encryption(
+        mut self,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        self.serializer.set_encryption(encryption);
+        self
     }
 }
 
@@ -98,13 +141,11 @@ impl<
 {
     /// saves the updated query
     fn save_query(
-        &mut self,
+        &self,
         context: QueryContext<C, E, Q>,
-    ) -> Result<(), Error> {
-        self.create_query_table()?;
-
+    ) -> Result<(), StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         let aggregate_id = context.aggregate_id;
 
@@ -114,29 +155,42 @@ impl<
         );
 
         let sql = match context.version {
-            1 => INSERT_QUERY,
-            _ => UPDATE_QUERY,
+            1 => self.tables.sql(INSERT_QUERY),
+            _ => self.tables.sql(UPDATE_QUERY),
         };
 
-        let payload = match serde_json::to_string(&context.payload) {
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id: &aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .encrypt_to_text(&scope, &context.payload)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::serialization(
                     format!(
                         "unable to serialize the payload of query \
-                         '{}' with aggregate id '{}', error: {}",
-                        &query_type, &aggregate_id, e,
+                         '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        match self.conn.execute(
-            sql,
+        let conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            sql.as_str(),
             params![
                 context.version,
-                payload,
+                payload.0,
+                payload.1,
                 aggregate_type,
                 aggregate_id,
                 query_type,
@@ -144,13 +198,14 @@ impl<
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to insert/update query for \
-                         aggregate id '{}' with error: {}",
-                        &aggregate_id, e
+                         aggregate id '{}'",
+                        &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -160,13 +215,11 @@ impl<
 
     /// loads the most recent query
     fn load_query(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<QueryContext<C, E, Q>, Error> {
-        self.create_query_table()?;
-
+    ) -> Result<QueryContext<C, E, Q>, StoreError> {
         let aggregate_type = A::aggregate_type();
-        let query_type = Q::query_type();
+        let query_type = self.query_type.clone();
 
         trace!(
             "loading query '{}' for aggregate id '{}'",
@@ -174,38 +227,45 @@ impl<
             aggregate_id
         );
 
-        let mut sql = match self.conn.prepare(SELECT_QUERY) {
+        let conn = get_conn(&self.pool)?;
+
+        let mut sql = match conn
+            .prepare(self.tables.sql(SELECT_QUERY).as_str())
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to prepare queries table for query \
-                         '{}' with aggregate id '{}', error: {}",
-                        &query_type, &aggregate_id, e,
+                         '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
         let res = match sql.query_map(
             params![aggregate_type, aggregate_id, query_type],
-            |row| Ok((row.get(0)?, row.get(1)?)),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
         ) {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(driver_error(
                     format!(
                         "unable to load queries table for query \
-                         '{}' with aggregate id '{}', error: {}",
-                        &query_type, &aggregate_id, e,
+                         '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
 
-        let mut rows: Vec<(i64, String)> = Vec::new();
+        let mut rows: Vec<(i64, String, Option<Vec<u8>>)> =
+            Vec::new();
 
         for x in res {
             rows.push(x.unwrap());
@@ -227,17 +287,26 @@ impl<
 
         let row = rows[0].clone();
 
-        let payload = match serde_json::from_str(row.1.as_str()) {
+        let scope = EncryptionScope::Query {
+            aggregate_type,
+            query_type: &query_type,
+            aggregate_id,
+        };
+
+        let payload = match self
+            .serializer
+            .decrypt_text(&scope, row.1, row.2)
+        {
             Ok(x) => x,
             Err(e) => {
-                return Err(Error::new(
+                return Err(StoreError::deserialization(
                     format!(
                         "bad payload found in queries table for \
-                         query '{}' with aggregate id '{}', error: \
-                         {}",
-                        &query_type, &aggregate_id, e,
+                         query '{}' with aggregate id '{}'",
+                        &query_type, &aggregate_id
                     )
                     .as_str(),
+                    e,
                 ));
             },
         };
@@ -248,6 +317,70 @@ impl<
             payload,
         ))
     }
+    /// deletes the query of a particular `aggregate_id`
+    fn delete_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!(
+            "deleting query '{}' for aggregate id '{}'",
+            query_type, aggregate_id
+        );
+
+        let conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(DELETE_QUERY).as_str(),
+            params![aggregate_type, aggregate_id, query_type],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete query '{}' for aggregate \
+                         id '{}'",
+                        &query_type, aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+
+    /// deletes all queries of the query type
+    fn delete_queries(&self) -> Result<(), StoreError> {
+        let aggregate_type = A::aggregate_type();
+        let query_type = self.query_type.clone();
+
+        debug!("deleting all queries '{}'", query_type);
+
+        let conn = get_conn(&self.pool)?;
+
+        match conn.execute(
+            self.tables.sql(DELETE_QUERIES).as_str(),
+            params![aggregate_type, query_type],
+        ) {
+            Ok(_) => {},
+            Err(e) => {
+                return Err(driver_error(
+                    format!(
+                        "unable to delete queries '{}'",
+                        &query_type
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
 }
 
 impl<
@@ -258,10 +391,10 @@ impl<
     > IEventDispatcher<C, E> for QueryStore<C, E, A, Q>
 {
     fn dispatch(
-        &mut self,
+        &self,
         aggregate_id: &str,
         events: &Vec<EventContext<C, E>>,
     ) -> Result<(), Error> {
-        self.dispatch_events(aggregate_id, events)
+        Ok(self.dispatch_events(aggregate_id, events)?)
     }
 }
@@ -1 +1,13 @@
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+
+use crate::sqlite_store::SqlitePool;
+
 pub static DB_NAME: &str = "test.db";
+
+pub fn get_pool() -> SqlitePool {
+    Pool::builder()
+        .max_size(2)
+        .build(SqliteConnectionManager::file(DB_NAME))
+        .unwrap()
+}
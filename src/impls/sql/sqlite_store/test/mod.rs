@@ -1,8 +1,17 @@
 #[cfg(test)]
 mod common;
 
+#[cfg(test)]
+mod test_checkpoint_store;
+
+#[cfg(test)]
+mod test_conformance;
+
 #[cfg(test)]
 mod test_event_store;
 
+#[cfg(test)]
+mod test_migrations;
+
 #[cfg(test)]
 mod test_query_store;
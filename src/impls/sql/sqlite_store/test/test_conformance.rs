@@ -0,0 +1,57 @@
+use cqrs_es2::{
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+};
+
+use crate::{
+    conformance::*,
+    sqlite_store::{
+        EventStore,
+        QueryStore,
+    },
+    IEventStore,
+    IQueryStore,
+};
+
+use super::common::*;
+
+struct StoreFactory;
+
+impl IStoreFactory for StoreFactory {
+    fn event_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IEventStore<C, E, A>> {
+        let pool = get_pool();
+
+        Box::new(EventStore::new(pool).unwrap())
+    }
+
+    fn query_store<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+        Q: IQuery<C, E> + 'static,
+    >(
+        &mut self
+    ) -> Box<dyn IQueryStore<C, E, A, Q>> {
+        let pool = get_pool();
+
+        Box::new(QueryStore::<C, E, A, Q>::new(pool).unwrap())
+    }
+}
+
+#[test]
+fn test_event_store_conformance() {
+    check_event_store(&mut StoreFactory);
+}
+
+#[test]
+fn test_query_store_conformance() {
+    check_query_store(&mut StoreFactory);
+}
@@ -0,0 +1,520 @@
+use std::collections::HashMap;
+
+use rusqlite::{
+    params,
+    Connection,
+};
+use serde_json::{
+    json,
+    Value,
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    EventContext,
+    IAggregate,
+};
+
+use crate::{
+    errors::StoreError,
+    sqlite_store::{
+        CheckpointStore,
+        EventStore,
+        QueryStore,
+    },
+    EventUpcasters,
+    IEventStore,
+    IEventUpcaster,
+    TableNames,
+};
+
+use super::super::migrations::MIGRATIONS;
+use super::common::*;
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+type ThisQueryStore = QueryStore<
+    CustomerCommand,
+    CustomerEvent,
+    Customer,
+    CustomerContactQuery,
+>;
+
+fn connect() -> Connection {
+    Connection::open(DB_NAME).unwrap()
+}
+
+fn test_tables() -> TableNames {
+    let prefix =
+        format!("test_{}_", uuid::Uuid::new_v4().to_simple());
+
+    TableNames::default().with_prefix(&prefix)
+}
+
+fn drop_tables(tables: &TableNames) {
+    let conn = connect();
+
+    for x in [
+        &tables.events,
+        &tables.snapshots,
+        &tables.outbox,
+        &tables.checkpoints,
+        &tables.queries,
+        &tables.tombstones,
+        &tables.migrations,
+    ] {
+        conn.execute(format!("DROP TABLE {};", x).as_str(), [])
+            .unwrap();
+    }
+
+    conn.execute(
+        tables
+            .sql("DROP TABLE {events}_positions;")
+            .as_str(),
+        [],
+    )
+    .unwrap();
+}
+
+/// Version 1 of `NameAdded` called the field `name`
+struct NameAddedV1;
+
+impl IEventUpcaster for NameAddedV1 {
+    fn event_type(&self) -> &str {
+        "NameAdded"
+    }
+
+    fn event_version(&self) -> i64 {
+        1
+    }
+
+    fn upcast(
+        &self,
+        payload: Value,
+    ) -> Value {
+        json!({
+            "NameAdded": {
+                "changed_name": payload["NameAdded"]["name"],
+            }
+        })
+    }
+}
+
+fn new_event(
+    id: &str,
+) -> EventContext<CustomerCommand, CustomerEvent> {
+    EventContext::new(
+        id.to_string(),
+        1,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "test_event_A".to_string(),
+        }),
+        HashMap::new(),
+    )
+}
+
+#[test]
+fn test_migrations_with_prefix() {
+    let tables = test_tables();
+
+    let store =
+        ThisEventStore::with_tables(get_pool(), tables.clone())
+            .unwrap();
+
+    // the other stores find the schema up to date
+    ThisQueryStore::with_tables(get_pool(), tables.clone()).unwrap();
+    CheckpointStore::with_tables(get_pool(), tables.clone()).unwrap();
+
+    let conn = connect();
+
+    let mut sql = conn
+        .prepare(
+            tables
+                .sql(
+                    "SELECT version FROM {migrations} ORDER BY \
+                     version;",
+                )
+                .as_str(),
+        )
+        .unwrap();
+
+    let versions: Vec<i64> = sql
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    assert_eq!(
+        versions,
+        MIGRATIONS
+            .iter()
+            .map(|x| x.version)
+            .collect::<Vec<_>>()
+    );
+
+    // the events are only stored in the prefixed tables
+    let id = uuid::Uuid::new_v4().to_string();
+
+    store
+        .save_events(&vec![new_event(&id)])
+        .unwrap();
+
+    assert_eq!(store.load_events(&id).unwrap().len(), 1);
+
+    assert_eq!(
+        ThisEventStore::new(get_pool())
+            .unwrap()
+            .load_events(&id)
+            .unwrap()
+            .len(),
+        0
+    );
+
+    drop_tables(&tables);
+}
+
+#[test]
+fn test_upgrade_unversioned_schema() {
+    let tables = test_tables();
+
+    // a schema set up before the positions and the migrations
+    let conn = connect();
+
+    for x in MIGRATIONS[0].statements {
+        conn.execute(tables.sql(x).as_str(), [])
+            .unwrap();
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let event = new_event(&id);
+
+    conn.execute(
+        tables
+            .sql(
+                "INSERT INTO {events} (aggregate_type, aggregate_id, \
+                 sequence, payload, metadata) VALUES (?, ?, ?, ?, \
+                 ?);",
+            )
+            .as_str(),
+        params![
+            Customer::aggregate_type(),
+            id,
+            event.sequence,
+            serde_json::to_string(&event.payload).unwrap(),
+            serde_json::to_string(&event.metadata).unwrap(),
+        ],
+    )
+    .unwrap();
+
+    let store =
+        ThisEventStore::with_tables(get_pool(), tables.clone())
+            .unwrap();
+
+    let events = store.read_all(0, 10).unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].position, 1);
+    assert_eq!(events[0].context, event);
+
+    // the types of the existing events are filled in
+    let event_type: String = connect()
+        .query_row(
+            tables.sql("SELECT event_type FROM {events};").as_str(),
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+
+    assert_eq!(event_type, "NameAdded");
+
+    drop_tables(&tables);
+}
+
+#[test]
+fn test_upcast_stored_events() {
+    let tables = test_tables();
+
+    let store =
+        ThisEventStore::with_tables(get_pool(), tables.clone())
+            .unwrap()
+            .with_upcasters(
+                EventUpcasters::new().with_upcaster(NameAddedV1),
+            );
+
+    // an event stored with the first version of `NameAdded`
+    let id = uuid::Uuid::new_v4().to_string();
+
+    connect()
+        .execute(
+            tables
+                .sql(
+                    "INSERT INTO {events} (aggregate_type, \
+                     aggregate_id, sequence, payload, metadata, \
+                     event_type, event_version, position) VALUES \
+                     (?, ?, 1, ?, '{}', 'NameAdded', 1, 1);",
+                )
+                .as_str(),
+            params![
+                Customer::aggregate_type(),
+                id,
+                json!({"NameAdded": {"name": "test_event_A"}})
+                    .to_string(),
+            ],
+        )
+        .unwrap();
+
+    let event = new_event(&id);
+
+    assert_eq!(store.load_events(&id).unwrap(), vec![event.clone()]);
+    assert_eq!(store.read_all(0, 10).unwrap()[0].context, event);
+
+    // new events are stored with the current version
+    let id = uuid::Uuid::new_v4().to_string();
+
+    store
+        .save_events(&vec![new_event(&id)])
+        .unwrap();
+
+    let event_version: i64 = connect()
+        .query_row(
+            tables
+                .sql(
+                    "SELECT event_version FROM {events} WHERE \
+                     aggregate_id = ?;",
+                )
+                .as_str(),
+            params![id],
+            |row| row.get(0),
+        )
+        .unwrap();
+
+    assert_eq!(event_version, 2);
+
+    drop_tables(&tables);
+}
+
+#[test]
+fn test_invalid_table_names() {
+    let tables = TableNames::default().with_prefix("test; --");
+
+    match ThisEventStore::with_tables(get_pool(), tables) {
+        Err(StoreError::Backend { .. }) => {},
+        _ => panic!("invalid table names accepted"),
+    }
+}
+
+#[cfg(feature = "with-msgpack")]
+#[test]
+fn test_switch_to_binary_payloads() {
+    use cqrs_es2::{
+        AggregateContext,
+        QueryContext,
+    };
+
+    use crate::{
+        IOutboxStore,
+        IQueryStore,
+        MsgPackSerializer,
+    };
+
+    let tables = test_tables();
+
+    let json_store =
+        ThisEventStore::with_tables(get_pool(), tables.clone())
+            .unwrap();
+
+    let store =
+        ThisEventStore::with_tables(get_pool(), tables.clone())
+            .unwrap()
+            .with_outbox()
+            .with_serializer(MsgPackSerializer);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let mut contexts = vec![new_event(&id), new_event(&id)];
+
+    contexts[1].sequence = 2;
+    contexts[1].payload = CustomerEvent::EmailUpdated(EmailUpdated {
+        new_email: "test A".to_string(),
+    });
+    contexts[1]
+        .metadata
+        .insert("time".to_string(), "now".to_string());
+
+    json_store
+        .save_events(&contexts[..1].to_vec())
+        .unwrap();
+    store
+        .save_events(&contexts[1..].to_vec())
+        .unwrap();
+
+    // the events written as JSON still load
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+    assert_eq!(
+        store
+            .read_all(0, 10)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.context)
+            .collect::<Vec<_>>(),
+        contexts
+    );
+    assert_eq!(store.load_pending_events(10).unwrap(), contexts[1..]);
+
+    // the binary rows leave their text columns empty
+    let conn = connect();
+
+    let mut sql = conn
+        .prepare(
+            tables
+                .sql(
+                    "SELECT payload = '', payload_bytes IS NULL FROM \
+                     {events} ORDER BY sequence;",
+                )
+                .as_str(),
+        )
+        .unwrap();
+
+    let columns: Vec<(bool, bool)> = sql
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    assert_eq!(columns, vec![(false, true), (true, false)]);
+
+    let context = AggregateContext::new(
+        id.to_string(),
+        2,
+        Customer {
+            customer_id: id.to_string(),
+            name: "test_event_A".to_string(),
+            email: "test A".to_string(),
+            addresses: Default::default(),
+        },
+    );
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    let stored_context = store
+        .load_aggregate_from_snapshot(&id)
+        .unwrap();
+
+    assert_eq!(stored_context.version, context.version);
+    assert_eq!(stored_context.payload, context.payload);
+
+    let json_query_store =
+        ThisQueryStore::with_tables(get_pool(), tables.clone())
+            .unwrap();
+
+    let query_store =
+        ThisQueryStore::with_tables(get_pool(), tables.clone())
+            .unwrap()
+            .with_serializer(MsgPackSerializer);
+
+    let mut context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    json_query_store
+        .save_query(context.clone())
+        .unwrap();
+
+    // the queries written as JSON still load
+    assert_eq!(query_store.load_query(&id).unwrap(), context);
+
+    context.version = 2;
+    context.payload.email = "test2@email.com".to_string();
+
+    query_store
+        .save_query(context.clone())
+        .unwrap();
+
+    assert_eq!(query_store.load_query(&id).unwrap(), context);
+
+    drop_tables(&tables);
+}
+
+#[cfg(feature = "with-zstd")]
+#[test]
+fn test_compress_large_payloads() {
+    use cqrs_es2::AggregateContext;
+
+    use crate::ZstdCompressor;
+
+    let tables = test_tables();
+
+    let json_store =
+        ThisEventStore::with_tables(get_pool(), tables.clone())
+            .unwrap();
+
+    let store =
+        ThisEventStore::with_tables(get_pool(), tables.clone())
+            .unwrap()
+            .with_compression(ZstdCompressor::default(), 256);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let contexts = vec![new_event(&id)];
+
+    store.save_events(&contexts).unwrap();
+
+    let context = AggregateContext::new(
+        id.to_string(),
+        1,
+        Customer {
+            customer_id: id.to_string(),
+            name: "test_event_A".repeat(100),
+            email: "test A".to_string(),
+            addresses: Default::default(),
+        },
+    );
+
+    store
+        .save_aggregate_snapshot(context.clone())
+        .unwrap();
+
+    let conn = connect();
+
+    // the small events stay JSON, the large snapshots are compressed
+    let mut sql = conn
+        .prepare(
+            tables
+                .sql(
+                    "SELECT payload = '', payload_bytes IS NULL FROM \
+                     {events} UNION ALL SELECT payload = '', \
+                     length(payload_bytes) > 256 FROM {snapshots};",
+                )
+                .as_str(),
+        )
+        .unwrap();
+
+    let columns: Vec<(bool, bool)> = sql
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(|x| x.unwrap())
+        .collect();
+
+    assert_eq!(columns, vec![(false, true), (true, false)]);
+
+    // the compressed rows load without compression as well
+    for x in [&store, &json_store] {
+        assert_eq!(x.load_events(&id).unwrap(), contexts);
+
+        let stored_context =
+            x.load_aggregate_from_snapshot(&id).unwrap();
+
+        assert_eq!(stored_context.payload, context.payload);
+    }
+
+    drop_tables(&tables);
+}
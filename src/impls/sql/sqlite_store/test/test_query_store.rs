@@ -1,5 +1,3 @@
-use rusqlite::Connection;
-
 use cqrs_es2::{
     example_impl::*,
     QueryContext,
@@ -22,9 +20,9 @@ type ThisQueryStore = QueryStore<
 #[test]
 fn test_save_load_queries() {
     // "sqlite://demo.db"
-    let conn = Connection::open(DB_NAME).unwrap();
+    let pool = get_pool();
 
-    let mut store = ThisQueryStore::new(conn);
+    let store = ThisQueryStore::new(pool).unwrap();
 
     let id = uuid::Uuid::new_v4().to_string();
 
@@ -71,3 +69,81 @@ fn test_save_load_queries() {
 
     assert_eq!(stored_context, context);
 }
+
+#[test]
+fn test_delete_queries() {
+    let pool = get_pool();
+
+    let version = uuid::Uuid::new_v4().to_string();
+    let store = ThisQueryStore::new(pool)
+        .unwrap()
+        .with_query_version(&version);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(stored_context, context);
+
+    store.delete_queries().unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+}
+
+#[test]
+fn test_delete_query() {
+    let pool = get_pool();
+
+    let version = uuid::Uuid::new_v4().to_string();
+    let store = ThisQueryStore::new(pool)
+        .unwrap()
+        .with_query_version(&version);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let context = QueryContext::new(
+        id.to_string(),
+        1,
+        CustomerContactQuery {
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            latest_address: "one address".to_string(),
+        },
+    );
+
+    store
+        .save_query(context.clone())
+        .unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(stored_context, context);
+
+    store.delete_query(&id).unwrap();
+
+    let stored_context = store.load_query(&id).unwrap();
+
+    assert_eq!(
+        stored_context,
+        QueryContext::new(id.to_string(), 0, Default::default())
+    );
+}
@@ -0,0 +1,118 @@
+use crate::errors::StoreError;
+
+/// The names of the tables used by the SQL stores.
+///
+/// The statements of the stores refer to the tables by these
+/// names, so that several bounded contexts can share one database
+/// by giving each context its own prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableNames {
+    /// The events table, `events` by default, SQLite keeps the last
+    /// position taken next to it in `<events>_positions`
+    pub events: String,
+    /// The snapshots table, `snapshots` by default
+    pub snapshots: String,
+    /// The outbox table, `outbox` by default
+    pub outbox: String,
+    /// The checkpoints table, `checkpoints` by default
+    pub checkpoints: String,
+    /// The queries table, `queries` by default
+    pub queries: String,
+    /// The tombstones table, `tombstones` by default
+    pub tombstones: String,
+    /// The table recording the applied schema migrations,
+    /// `schema_migrations` by default
+    pub migrations: String,
+}
+
+impl Default for TableNames {
+    fn default() -> Self {
+        Self {
+            events: "events".to_string(),
+            snapshots: "snapshots".to_string(),
+            outbox: "outbox".to_string(),
+            checkpoints: "checkpoints".to_string(),
+            queries: "queries".to_string(),
+            tombstones: "tombstones".to_string(),
+            migrations: "schema_migrations".to_string(),
+        }
+    }
+}
+
+impl TableNames {
+    /// Prepends `prefix` to all table names
+    pub fn with_prefix(
+        mut self,
+        prefix: &str,
+    ) -> Self {
+        for x in self.names_mut() {
+            *x = format!("{}{}", prefix, x);
+        }
+
+        self
+    }
+
+    fn names_mut(&mut self) -> [&mut String; 7] {
+        [
+            &mut self.events,
+            &mut self.snapshots,
+            &mut self.outbox,
+            &mut self.checkpoints,
+            &mut self.queries,
+            &mut self.tombstones,
+            &mut self.migrations,
+        ]
+    }
+
+    /// Checks that all names are plain identifiers, since they are
+    /// inserted into the statements as they are
+    pub(crate) fn validate(&self) -> Result<(), StoreError> {
+        let names = [
+            &self.events,
+            &self.snapshots,
+            &self.outbox,
+            &self.checkpoints,
+            &self.queries,
+            &self.tombstones,
+            &self.migrations,
+        ];
+
+        for x in names {
+            let starts_with_letter = match x.chars().next() {
+                Some(c) => c.is_ascii_alphabetic() || c == '_',
+                None => false,
+            };
+
+            let is_identifier = x
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+            if !starts_with_letter || !is_identifier {
+                return Err(StoreError::Backend {
+                    message: format!("invalid table name '{}'", x),
+                    source: None,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the `{events}`, `{snapshots}`, `{outbox}`,
+    /// `{checkpoints}`, `{queries}`, `{tombstones}` and
+    /// `{migrations}` placeholders of a statement with the table
+    /// names
+    pub(crate) fn sql(
+        &self,
+        statement: &str,
+    ) -> String {
+        statement
+            .replace("{events}", &self.events)
+            .replace("{snapshots}", &self.snapshots)
+            .replace("{outbox}", &self.outbox)
+            .replace("{checkpoints}", &self.checkpoints)
+            .replace("{queries}", &self.queries)
+            .replace("{tombstones}", &self.tombstones)
+            .replace("{migrations}", &self.migrations)
+    }
+}
@@ -19,6 +19,7 @@
 //!   - `IEventDispatcher` - an interface for sync events listeners
 //!   - `IEventStore` - an interface for sync event stores
 //!   - `IQueryStore` - an interface for sync query stores
+//!   - `Repository` - applies commands and commits their events
 //!
 //! ## Features
 //!
@@ -31,6 +32,15 @@
 //! - `with-redis` - sync Redis store
 //! - `with-all-kv-db` - all key-value DBs drivers
 //! - `with-all-sync` - all sync drivers (default)
+//! - `with-msgpack` - MessagePack payloads
+//! - `with-cbor` - CBOR payloads
+//! - `with-bincode` - bincode payloads
+//! - `with-all-formats` - all binary payload formats
+//! - `with-zstd` - zstd payload compression
+//! - `with-gzip` - gzip payload compression
+//! - `with-encryption` - payload encryption and crypto-shredding
+//! - `with-conformance` - conformance suites for custom stores
+//! - `with-test-framework` - given/when/then repository tests
 //!
 //! ## Installation
 //!
@@ -66,8 +76,17 @@
 //!
 //! A full sync store example application is available [here](https://github.com/brgirgis/cqrs-es2-store/tree/master/examples/restful).
 
+pub use errors::*;
 pub use impls::*;
 pub use repository::*;
+#[cfg(any(test, feature = "with-test-framework"))]
+pub use test_framework::*;
 
+#[cfg(any(test, feature = "with-conformance"))]
+pub mod conformance;
+
+mod errors;
 mod impls;
 mod repository;
+#[cfg(any(test, feature = "with-test-framework"))]
+mod test_framework;
@@ -0,0 +1,109 @@
+use base64::{
+    engine::general_purpose::STANDARD,
+    Engine,
+};
+use ring::{
+    aead::{
+        Aad,
+        LessSafeKey,
+        Nonce,
+        UnboundKey,
+        AES_256_GCM,
+        NONCE_LEN,
+    },
+    rand::{
+        SecureRandom,
+        SystemRandom,
+    },
+};
+
+/// Length of the AES-256 keys
+pub(crate) const KEY_LEN: usize = 32;
+
+/// An AES-256-GCM key sealing values into the base64 of a random
+/// nonce followed by the ciphertext, shared by the payload
+/// encryption and the crypto-shredding
+pub(crate) struct AeadKey {
+    key: LessSafeKey,
+}
+
+impl AeadKey {
+    /// Constructor, `None` if `key` is not a 32 bytes key
+    pub fn new(key: &[u8]) -> Option<Self> {
+        match UnboundKey::new(&AES_256_GCM, key) {
+            Ok(x) => {
+                Some(Self {
+                    key: LessSafeKey::new(x),
+                })
+            },
+            Err(_) => None,
+        }
+    }
+
+    /// Generates a new random key
+    pub fn generate() -> Option<[u8; KEY_LEN]> {
+        let mut key = [0u8; KEY_LEN];
+
+        match SystemRandom::new().fill(&mut key) {
+            Ok(_) => Some(key),
+            Err(_) => None,
+        }
+    }
+
+    /// Encrypts `plaintext` bound to the additional data `aad`
+    pub fn seal(
+        &self,
+        aad: &str,
+        mut plaintext: Vec<u8>,
+    ) -> Option<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+
+        if SystemRandom::new().fill(&mut nonce).is_err() {
+            return None;
+        }
+
+        match self.key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(aad.as_bytes()),
+            &mut plaintext,
+        ) {
+            Ok(_) => {},
+            Err(_) => return None,
+        };
+
+        let mut sealed = nonce.to_vec();
+        sealed.append(&mut plaintext);
+
+        Some(STANDARD.encode(sealed))
+    }
+
+    /// Decrypts a value sealed with this key and the same `aad`
+    pub fn open(
+        &self,
+        aad: &str,
+        sealed: &str,
+    ) -> Option<Vec<u8>> {
+        let mut in_out = STANDARD.decode(sealed).ok()?;
+
+        if in_out.len() <= NONCE_LEN {
+            return None;
+        }
+
+        let mut ciphertext = in_out.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&in_out).ok()?;
+
+        let len = self
+            .key
+            .open_in_place(
+                nonce,
+                Aad::from(aad.as_bytes()),
+                &mut ciphertext,
+            )
+            .ok()?
+            .len();
+
+        ciphertext.truncate(len);
+
+        Some(ciphertext)
+    }
+}
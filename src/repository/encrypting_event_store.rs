@@ -0,0 +1,344 @@
+use log::trace;
+use std::marker::PhantomData;
+
+use cqrs_es2::{
+    AggregateContext,
+    EventContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+use crate::errors::StoreError;
+
+use super::{
+    encryption_scope::EncryptionScope,
+    i_event_store::IEventStore,
+    i_outbox_store::IOutboxStore,
+    payload_encryption::PayloadEncryption,
+    positioned_event::PositionedEvent,
+    store_serializer::KEY_ID_METADATA,
+    stream_metadata::StreamMetadata,
+    timestamped_event::TimestampedEvent,
+};
+
+/// An event store encrypting the events of any store with a
+/// `PayloadEncryption`, e.g. the memory store or a custom store
+/// keeping typed events.
+///
+/// The typed events only hold their encrypted values in string
+/// fields, the encryption must add the pointers of string fields
+/// `with_field()`. The events record the id of their key in their
+/// metadata as `encryption_key_id`.
+///
+/// The snapshots would hold the fields in clear, they are not saved
+/// and the aggregates are rebuilt from their events.
+pub struct EncryptingEventStore<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+    ES: IEventStore<C, E, A>,
+> {
+    store: ES,
+    encryption: PayloadEncryption,
+    _phantom: PhantomData<(C, E, A)>,
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A>,
+    > EncryptingEventStore<C, E, A, ES>
+{
+    /// Constructor
+    pub fn new(
+        store: ES,
+        encryption: PayloadEncryption,
+    ) -> Self {
+        let x = Self {
+            store,
+            encryption,
+            _phantom: PhantomData,
+        };
+
+        trace!("Created new EncryptingEventStore");
+
+        x
+    }
+
+    /// Encrypts the fields of an event
+    fn encrypt(
+        &self,
+        context: &EventContext<C, E>,
+    ) -> Result<EventContext<C, E>, StoreError> {
+        let payload = match serde_json::to_value(&context.payload) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::serialization(
+                    format!(
+                        "unable to serialize the event payload for \
+                         aggregate id '{}'",
+                        &context.aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let (payload, key_id) = self
+            .encryption
+            .encrypt(&scope::<C, E, A>(context), payload)?;
+
+        let payload = match serde_json::from_value(payload) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::serialization(
+                    format!(
+                        "unable to rebuild the encrypted event for \
+                         aggregate id '{}', only string fields can \
+                         be encrypted",
+                        &context.aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let mut metadata = context.metadata.clone();
+
+        if let Some(x) = key_id {
+            metadata.insert(KEY_ID_METADATA.to_string(), x);
+        }
+
+        Ok(EventContext::new(
+            context.aggregate_id.clone(),
+            context.sequence,
+            payload,
+            metadata,
+        ))
+    }
+
+    /// Decrypts the fields of a loaded event in place
+    fn decrypt(
+        &self,
+        context: &mut EventContext<C, E>,
+    ) -> Result<(), StoreError> {
+        let payload = match serde_json::to_value(&context.payload) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "unable to serialize the event payload for \
+                         aggregate id '{}'",
+                        &context.aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let payload = self
+            .encryption
+            .decrypt(&scope::<C, E, A>(context), payload)?;
+
+        context.payload = match serde_json::from_value(payload) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "unable to rebuild the decrypted event for \
+                         aggregate id '{}'",
+                        &context.aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        Ok(())
+    }
+}
+
+/// The encryption scope of an event
+fn scope<'a, C: ICommand, E: IEvent, A: IAggregate<C, E>>(
+    context: &'a EventContext<C, E>
+) -> EncryptionScope<'a> {
+    EncryptionScope::Event {
+        aggregate_type: A::aggregate_type(),
+        aggregate_id: &context.aggregate_id,
+        sequence: context.sequence,
+    }
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A>,
+    > IEventStore<C, E, A> for EncryptingEventStore<C, E, A, ES>
+{
+    /// Save new events with their fields encrypted
+    fn save_events(
+        &self,
+        contexts: &Vec<EventContext<C, E>>,
+    ) -> Result<(), StoreError> {
+        let mut encrypted = Vec::with_capacity(contexts.len());
+
+        for context in contexts {
+            encrypted.push(self.encrypt(context)?);
+        }
+
+        self.store.save_events(&encrypted)
+    }
+
+    /// Load the decrypted events for a particular `aggregate_id`
+    /// within a range of sequences along with their commit times
+    fn load_timestamped_events_range(
+        &self,
+        aggregate_id: &str,
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
+        let mut events = self
+            .store
+            .load_timestamped_events_range(
+                aggregate_id,
+                from_sequence,
+                to_sequence,
+            )?;
+
+        for x in events.iter_mut() {
+            self.decrypt(&mut x.context)?;
+        }
+
+        Ok(events)
+    }
+
+    /// Read the decrypted events of all aggregate instances
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError> {
+        let mut events = self
+            .store
+            .read_all(from_position, limit)?;
+
+        for x in events.iter_mut() {
+            self.decrypt(&mut x.context)?;
+        }
+
+        Ok(events)
+    }
+
+    /// List the ids of the aggregate instances
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        self.store
+            .list_aggregate_ids(after_aggregate_id, limit)
+    }
+
+    /// Load the metadata of the stream of a particular
+    /// `aggregate_id`
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        self.store.stream_metadata(aggregate_id)
+    }
+
+    /// Delete the stream of a particular `aggregate_id`
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store.delete_stream(aggregate_id)
+    }
+
+    /// Mark the stream of a particular `aggregate_id` as tombstoned
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store.tombstone_stream(aggregate_id)
+    }
+
+    /// Check whether the stream of a particular `aggregate_id` was
+    /// tombstoned
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        self.store.is_tombstoned(aggregate_id)
+    }
+
+    /// Skip the snapshot, it would hold the fields in clear
+    fn save_aggregate_snapshot(
+        &self,
+        context: AggregateContext<C, E, A>,
+    ) -> Result<(), StoreError> {
+        trace!(
+            "skip saving snapshot of encrypted aggregate id '{}'",
+            &context.aggregate_id
+        );
+
+        Ok(())
+    }
+
+    /// Delete the snapshot of a particular `aggregate_id`
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store
+            .delete_aggregate_snapshot(aggregate_id)
+    }
+
+    /// Load aggregate at current state from snapshots
+    fn load_aggregate_from_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<AggregateContext<C, E, A>, StoreError> {
+        self.store
+            .load_aggregate_from_snapshot(aggregate_id)
+    }
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A> + IOutboxStore<C, E, A>,
+    > IOutboxStore<C, E, A> for EncryptingEventStore<C, E, A, ES>
+{
+    /// Load the decrypted pending events
+    fn load_pending_events(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError> {
+        let mut contexts = self.store.load_pending_events(limit)?;
+
+        for x in contexts.iter_mut() {
+            self.decrypt(x)?;
+        }
+
+        Ok(contexts)
+    }
+
+    /// Mark events as delivered so that they are not loaded again
+    fn mark_events_delivered(
+        &self,
+        contexts: &[EventContext<C, E>],
+    ) -> Result<(), StoreError> {
+        self.store
+            .mark_events_delivered(contexts)
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(feature = "with-encryption")]
+use serde_json::json;
+
+/// The stored value an encrypted payload belongs to.
+///
+/// The ciphertexts are bound to their scope and to the JSON pointer
+/// they were encrypted at, so that they do not load when copied to
+/// another event, aggregate, query or field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncryptionScope<'a> {
+    /// The payload of an event
+    Event {
+        /// The type of the aggregate
+        aggregate_type: &'a str,
+        /// The id of the aggregate instance
+        aggregate_id: &'a str,
+        /// The sequence of the event
+        sequence: i64,
+    },
+    /// The snapshot of an aggregate, always encrypted whole
+    Snapshot {
+        /// The type of the aggregate
+        aggregate_type: &'a str,
+        /// The id of the aggregate instance
+        aggregate_id: &'a str,
+    },
+    /// The payload of a query
+    Query {
+        /// The type of the aggregate
+        aggregate_type: &'a str,
+        /// The type of the query
+        query_type: &'a str,
+        /// The id of the aggregate instance
+        aggregate_id: &'a str,
+    },
+}
+
+impl<'a> EncryptionScope<'a> {
+    /// The additional data of a value encrypted with `key_id` at
+    /// `pointer`, a JSON array so that the ids containing
+    /// separators stay unambiguous
+    #[cfg(feature = "with-encryption")]
+    pub(crate) fn aad(
+        &self,
+        key_id: &str,
+        pointer: &str,
+    ) -> String {
+        let scope = match self {
+            EncryptionScope::Event {
+                aggregate_type,
+                aggregate_id,
+                sequence,
+            } => {
+                json!([
+                    "event",
+                    aggregate_type,
+                    aggregate_id,
+                    sequence
+                ])
+            },
+            EncryptionScope::Snapshot {
+                aggregate_type,
+                aggregate_id,
+            } => json!(["snapshot", aggregate_type, aggregate_id]),
+            EncryptionScope::Query {
+                aggregate_type,
+                query_type,
+                aggregate_id,
+            } => {
+                json!([
+                    "query",
+                    aggregate_type,
+                    query_type,
+                    aggregate_id
+                ])
+            },
+        };
+
+        json!([key_id, scope, pointer]).to_string()
+    }
+}
@@ -0,0 +1,154 @@
+use log::trace;
+use std::{
+    marker::PhantomData,
+    vec::IntoIter,
+};
+
+use cqrs_es2::{
+    EventContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+use crate::errors::StoreError;
+
+use super::i_event_store::IEventStore;
+
+/// Iterates over the events of an aggregate, loading them lazily
+/// from the store in pages of consecutive sequences.
+///
+/// The sequences of a stream must be consecutive, as committed by
+/// the `Repository`: a page with fewer events than the page size is
+/// the last one, the events after a gap in the sequences are not
+/// loaded.
+///
+/// Only a single page of events is held in memory at a time. A store
+/// error is returned as the next item and ends the stream.
+pub struct EventStream<
+    'a,
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+    ES: IEventStore<C, E, A>,
+> {
+    store: &'a ES,
+    aggregate_id: String,
+    next_sequence: i64,
+    to_sequence: Option<i64>,
+    page_size: usize,
+    page: IntoIter<EventContext<C, E>>,
+    done: bool,
+    _phantom: PhantomData<A>,
+}
+
+impl<
+        'a,
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A>,
+    > EventStream<'a, C, E, A, ES>
+{
+    /// Constructor
+    pub fn new(
+        store: &'a ES,
+        aggregate_id: &str,
+        from_sequence: i64,
+    ) -> Self {
+        Self {
+            store,
+            aggregate_id: aggregate_id.to_string(),
+            next_sequence: from_sequence,
+            to_sequence: None,
+            page_size: 100,
+            page: Vec::new().into_iter(),
+            done: false,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Sets the maximum number of events loaded per page, 100 by
+    /// default
+    pub fn with_page_size(
+        mut self,
+        page_size: usize,
+    ) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Ends the stream after the event at `to_sequence`, inclusive
+    pub fn with_to_sequence(
+        mut self,
+        to_sequence: i64,
+    ) -> Self {
+        self.to_sequence = Some(to_sequence);
+        self
+    }
+
+    fn load_page(&mut self) -> Result<(), StoreError> {
+        let mut last_sequence =
+            self.next_sequence + self.page_size as i64 - 1;
+
+        if let Some(x) = self.to_sequence {
+            if x <= last_sequence {
+                last_sequence = x;
+                self.done = true;
+            }
+        }
+
+        trace!(
+            "loading events {} to {} for aggregate id '{}'",
+            self.next_sequence,
+            last_sequence,
+            &self.aggregate_id
+        );
+
+        let page = self.store.load_events_range(
+            &self.aggregate_id,
+            self.next_sequence,
+            Some(last_sequence),
+        )?;
+
+        // sequences are consecutive, a short page is the last one
+        // and saves loading an empty page
+        if page.len() < self.page_size {
+            self.done = true;
+        }
+
+        self.next_sequence = last_sequence + 1;
+        self.page = page.into_iter();
+
+        Ok(())
+    }
+}
+
+impl<
+        'a,
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A>,
+    > Iterator for EventStream<'a, C, E, A, ES>
+{
+    type Item = Result<EventContext<C, E>, StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(x) = self.page.next() {
+            return Some(Ok(x));
+        }
+
+        if self.done {
+            return None;
+        }
+
+        match self.load_page() {
+            Ok(_) => self.page.next().map(Ok),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            },
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use serde_json::Value;
+
+/// The default event type of a serialized payload, the name of its
+/// enum variant.
+///
+/// Unit variants are serialized as their name and the other
+/// variants as an object with the name as its only key, payloads of
+/// any other shape have an empty event type.
+pub(crate) fn event_type(payload: &Value) -> String {
+    match payload {
+        Value::String(x) => x.to_string(),
+        Value::Object(x) if x.len() == 1 => {
+            match x.keys().next() {
+                Some(x) => x.to_string(),
+                None => String::new(),
+            }
+        },
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,95 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::i_event_upcaster::IEventUpcaster;
+
+/// The upcasters applied by an event store to the stored payloads.
+///
+/// A payload is upcasted one version at a time until no upcaster
+/// matches its event type and version, so an old event goes through
+/// all the upcasters registered for the later versions of its type.
+#[derive(Clone, Default)]
+pub struct EventUpcasters {
+    upcasters: Vec<Arc<dyn IEventUpcaster + Send + Sync>>,
+}
+
+impl EventUpcasters {
+    /// Constructor
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an upcaster
+    pub fn with_upcaster(
+        mut self,
+        upcaster: impl IEventUpcaster + Send + Sync + 'static,
+    ) -> Self {
+        self.upcasters.push(Arc::new(upcaster));
+        self
+    }
+
+    /// The current schema version of an event type, the one
+    /// following its highest upcasted version or 1
+    pub fn event_version(
+        &self,
+        event_type: &str,
+    ) -> i64 {
+        self.upcasters
+            .iter()
+            .filter(|x| x.event_type() == event_type)
+            .map(|x| x.event_version() + 1)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Upcasts a stored payload of `event_type` and `event_version`
+    /// to the current version of its event type
+    pub fn upcast(
+        &self,
+        event_type: &str,
+        payload: Value,
+        event_version: i64,
+    ) -> Value {
+        let mut payload = payload;
+        let mut event_version = event_version;
+
+        loop {
+            let upcaster = self.upcasters.iter().find(|x| {
+                x.event_type() == event_type &&
+                    x.event_version() == event_version
+            });
+
+            match upcaster {
+                Some(x) => {
+                    payload = x.upcast(payload);
+                    event_version += 1;
+                },
+                None => {
+                    return payload;
+                },
+            }
+        }
+    }
+
+    /// Upcasts and deserializes a stored payload, the events stored
+    /// before their types were recorded are named after their enum
+    /// variant
+    pub(crate) fn deserialize<E: DeserializeOwned>(
+        &self,
+        event_type: &str,
+        payload: Value,
+        event_version: i64,
+    ) -> Result<E, serde_json::Error> {
+        let event_type = match event_type {
+            "" => super::event_type(&payload),
+            x => x.to_string(),
+        };
+
+        serde_json::from_value(self.upcast(
+            &event_type,
+            payload,
+            event_version,
+        ))
+    }
+}
@@ -0,0 +1,14 @@
+use std::time::Duration;
+
+/// A fault injected into a call of a wrapped store or dispatcher
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    /// The call fails without reaching the wrapped store
+    Failure,
+    /// The call is delayed before reaching the wrapped store
+    Latency(Duration),
+    /// Only the first events of the call reach the wrapped store,
+    /// then the call fails. It applies to `SaveEvents` and
+    /// `Dispatch`, the other operations fail as with `Failure`.
+    PartialWrite(usize),
+}
@@ -0,0 +1,30 @@
+use super::{
+    fault::Fault,
+    store_operation::StoreOperation,
+};
+
+/// A call recorded by the `Faults` of a wrapped store or dispatcher
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultCall {
+    /// The operation called
+    pub operation: StoreOperation,
+    /// The aggregate id of the call, if any
+    pub aggregate_id: Option<String>,
+    /// The faults injected into the call
+    pub faults: Vec<Fault>,
+}
+
+impl FaultCall {
+    /// Constructor
+    pub fn new(
+        operation: StoreOperation,
+        aggregate_id: Option<&str>,
+        faults: Vec<Fault>,
+    ) -> Self {
+        Self {
+            operation,
+            aggregate_id: aggregate_id.map(|x| x.to_string()),
+            faults,
+        }
+    }
+}
@@ -0,0 +1,13 @@
+/// When a fault is injected into the calls of an operation
+#[derive(Debug, Clone, PartialEq)]
+pub enum FaultTrigger {
+    /// Every call
+    Always,
+    /// Only the nth call, starting at `1`
+    OnCall(usize),
+    /// The first n calls
+    FirstCalls(usize),
+    /// Each call with a probability between `0.0` and `1.0`, drawn
+    /// from the seeded random generator of the `Faults`
+    Probability(f64),
+}
@@ -0,0 +1,210 @@
+use std::{
+    sync::{
+        Arc,
+        RwLock,
+    },
+    thread,
+};
+
+use crate::errors::StoreError;
+
+use super::{
+    fault::Fault,
+    fault_call::FaultCall,
+    fault_trigger::FaultTrigger,
+    store_operation::StoreOperation,
+};
+
+/// A fault of an operation along with the number of calls it saw
+struct FaultRule {
+    operation: StoreOperation,
+    trigger: FaultTrigger,
+    fault: Fault,
+    calls: usize,
+}
+
+/// The rules, random generator state and call log of the `Faults`
+struct FaultState {
+    rules: Vec<FaultRule>,
+    rng: u64,
+    calls: Vec<FaultCall>,
+}
+
+/// The faults injected by the `FaultyEventStore`, `FaultyQueryStore`
+/// and `FaultyDispatcher` wrapping a store or a dispatcher, along
+/// with the log of their calls.
+///
+/// The clones of a `Faults` share its rules and log, so that a test
+/// keeping a clone checks the calls of the wrappers it was given to.
+/// Every fault whose trigger fires is injected, the latencies first.
+/// The probabilities are drawn from a random generator seeded
+/// `with_seed()`, `0` by default, so that the same calls get the
+/// same faults on every run.
+#[derive(Clone)]
+pub struct Faults {
+    state: Arc<RwLock<FaultState>>,
+}
+
+impl Faults {
+    /// Constructor, without any fault
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(RwLock::new(FaultState {
+                rules: Vec::new(),
+                rng: 0,
+                calls: Vec::new(),
+            })),
+        }
+    }
+
+    /// Seeds the random generator of the `Probability` triggers
+    #[must_use]
+    pub fn with_seed(
+        self,
+        seed: u64,
+    ) -> Self {
+        self.state.write().unwrap().rng = seed;
+        self
+    }
+
+    /// Injects `fault` into the calls of `operation` selected by
+    /// `trigger`
+    #[must_use]
+    pub fn with_fault(
+        self,
+        operation: StoreOperation,
+        trigger: FaultTrigger,
+        fault: Fault,
+    ) -> Self {
+        self.state
+            .write()
+            .unwrap()
+            .rules
+            .push(FaultRule {
+                operation,
+                trigger,
+                fault,
+                calls: 0,
+            });
+
+        self
+    }
+
+    /// The calls recorded so far, in call order
+    pub fn calls(&self) -> Vec<FaultCall> {
+        self.state.read().unwrap().calls.clone()
+    }
+
+    /// Forgets the calls recorded so far
+    pub fn clear_calls(&self) {
+        self.state.write().unwrap().calls.clear();
+    }
+
+    /// Records a call and sleeps its latencies, returns the failure
+    /// or partial write to inject into it, if any
+    pub(crate) fn inject(
+        &self,
+        operation: StoreOperation,
+        aggregate_id: Option<&str>,
+    ) -> Option<Fault> {
+        let faults = {
+            let mut state = self.state.write().unwrap();
+            let state = &mut *state;
+
+            let mut faults = Vec::new();
+
+            for rule in &mut state.rules {
+                if rule.operation != operation {
+                    continue;
+                }
+
+                rule.calls += 1;
+
+                let fires = match rule.trigger {
+                    FaultTrigger::Always => true,
+                    FaultTrigger::OnCall(x) => rule.calls == x,
+                    FaultTrigger::FirstCalls(x) => rule.calls <= x,
+                    FaultTrigger::Probability(x) => {
+                        next_f64(&mut state.rng) < x
+                    },
+                };
+
+                if fires {
+                    faults.push(rule.fault.clone());
+                }
+            }
+
+            state.calls.push(FaultCall::new(
+                operation,
+                aggregate_id,
+                faults.clone(),
+            ));
+
+            faults
+        };
+
+        for x in &faults {
+            if let Fault::Latency(x) = x {
+                thread::sleep(*x);
+            }
+        }
+
+        faults
+            .into_iter()
+            .find(|x| !matches!(x, Fault::Latency(_)))
+    }
+
+    /// Records a call and sleeps its latencies, fails it if a
+    /// failure or partial write is injected
+    pub(crate) fn check(
+        &self,
+        operation: StoreOperation,
+        aggregate_id: Option<&str>,
+    ) -> Result<(), StoreError> {
+        match self.inject(operation, aggregate_id) {
+            Some(_) => Err(Self::error(operation, aggregate_id)),
+            None => Ok(()),
+        }
+    }
+
+    /// The error of an injected failure
+    pub(crate) fn error(
+        operation: StoreOperation,
+        aggregate_id: Option<&str>,
+    ) -> StoreError {
+        let message = match aggregate_id {
+            Some(x) => {
+                format!(
+                    "injected failure of '{:?}' for aggregate id \
+                     '{}'",
+                    operation, x
+                )
+            },
+            None => format!("injected failure of '{:?}'", operation),
+        };
+
+        StoreError::Backend {
+            message,
+            source: None,
+        }
+    }
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The next number of the splitmix64 generator, between `0.0` and
+/// `1.0`
+fn next_f64(state: &mut u64) -> f64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,91 @@
+use log::trace;
+use std::marker::PhantomData;
+
+use cqrs_es2::{
+    Error,
+    EventContext,
+    ICommand,
+    IEvent,
+};
+
+use super::{
+    fault::Fault,
+    faults::Faults,
+    i_event_dispatcher::IEventDispatcher,
+    store_operation::StoreOperation,
+};
+
+/// A dispatcher injecting the failures, latencies and partial
+/// writes of its `Faults` into the dispatches of the wrapped
+/// dispatcher, for resilience testing.
+///
+/// A partial write dispatches the first events only before failing.
+pub struct FaultyDispatcher<
+    C: ICommand,
+    E: IEvent,
+    D: IEventDispatcher<C, E>,
+> {
+    dispatcher: D,
+    faults: Faults,
+    _phantom: PhantomData<(C, E)>,
+}
+
+impl<C: ICommand, E: IEvent, D: IEventDispatcher<C, E>>
+    FaultyDispatcher<C, E, D>
+{
+    /// Constructor
+    pub fn new(
+        dispatcher: D,
+        faults: Faults,
+    ) -> Self {
+        let x = Self {
+            dispatcher,
+            faults,
+            _phantom: PhantomData,
+        };
+
+        trace!("Created new FaultyDispatcher");
+
+        x
+    }
+
+    /// The wrapped dispatcher
+    pub fn inner(&self) -> &D {
+        &self.dispatcher
+    }
+}
+
+impl<C: ICommand, E: IEvent, D: IEventDispatcher<C, E>>
+    IEventDispatcher<C, E> for FaultyDispatcher<C, E, D>
+{
+    fn dispatch(
+        &self,
+        aggregate_id: &str,
+        events: &Vec<EventContext<C, E>>,
+    ) -> Result<(), Error> {
+        let operation = StoreOperation::Dispatch;
+
+        let fault = match self
+            .faults
+            .inject(operation, Some(aggregate_id))
+        {
+            Some(x) => x,
+            None => {
+                return self.dispatcher.dispatch(aggregate_id, events);
+            },
+        };
+
+        if let Fault::PartialWrite(x) = fault {
+            let events = events
+                .iter()
+                .take(x)
+                .cloned()
+                .collect();
+
+            self.dispatcher
+                .dispatch(aggregate_id, &events)?;
+        }
+
+        Err(Faults::error(operation, Some(aggregate_id)).into())
+    }
+}
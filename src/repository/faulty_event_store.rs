@@ -0,0 +1,223 @@
+use log::trace;
+use std::marker::PhantomData;
+
+use cqrs_es2::{
+    AggregateContext,
+    EventContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+use crate::errors::StoreError;
+
+use super::{
+    fault::Fault,
+    faults::Faults,
+    i_event_store::IEventStore,
+    positioned_event::PositionedEvent,
+    store_operation::StoreOperation,
+    stream_metadata::StreamMetadata,
+    timestamped_event::TimestampedEvent,
+};
+
+/// An event store injecting the failures, latencies and partial
+/// writes of its `Faults` into the calls of the wrapped store, for
+/// resilience testing.
+///
+/// A partial write of `save_events` commits the first events of the
+/// batch to the wrapped store before failing, like a store without
+/// atomic commits would.
+pub struct FaultyEventStore<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+    ES: IEventStore<C, E, A>,
+> {
+    store: ES,
+    faults: Faults,
+    _phantom: PhantomData<(C, E, A)>,
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A>,
+    > FaultyEventStore<C, E, A, ES>
+{
+    /// Constructor
+    pub fn new(
+        store: ES,
+        faults: Faults,
+    ) -> Self {
+        let x = Self {
+            store,
+            faults,
+            _phantom: PhantomData,
+        };
+
+        trace!("Created new FaultyEventStore");
+
+        x
+    }
+
+    /// The wrapped store
+    pub fn inner(&self) -> &ES {
+        &self.store
+    }
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A>,
+    > IEventStore<C, E, A> for FaultyEventStore<C, E, A, ES>
+{
+    fn save_events(
+        &self,
+        contexts: &Vec<EventContext<C, E>>,
+    ) -> Result<(), StoreError> {
+        let operation = StoreOperation::SaveEvents;
+
+        let aggregate_id = contexts
+            .first()
+            .map(|x| x.aggregate_id.as_str());
+
+        match self.faults.inject(operation, aggregate_id) {
+            None => self.store.save_events(contexts),
+            Some(Fault::PartialWrite(x)) => {
+                let contexts = contexts
+                    .iter()
+                    .take(x)
+                    .cloned()
+                    .collect();
+
+                self.store.save_events(&contexts)?;
+
+                Err(Faults::error(operation, aggregate_id))
+            },
+            Some(_) => Err(Faults::error(operation, aggregate_id)),
+        }
+    }
+
+    fn load_timestamped_events_range(
+        &self,
+        aggregate_id: &str,
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
+        self.faults
+            .check(StoreOperation::LoadEvents, Some(aggregate_id))?;
+
+        self.store.load_timestamped_events_range(
+            aggregate_id,
+            from_sequence,
+            to_sequence,
+        )
+    }
+
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError> {
+        self.faults
+            .check(StoreOperation::ReadAll, None)?;
+
+        self.store.read_all(from_position, limit)
+    }
+
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        self.faults
+            .check(StoreOperation::ListAggregateIds, None)?;
+
+        self.store
+            .list_aggregate_ids(after_aggregate_id, limit)
+    }
+
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        self.faults.check(
+            StoreOperation::StreamMetadata,
+            Some(aggregate_id),
+        )?;
+
+        self.store.stream_metadata(aggregate_id)
+    }
+
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.faults
+            .check(StoreOperation::DeleteStream, Some(aggregate_id))?;
+
+        self.store.delete_stream(aggregate_id)
+    }
+
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.faults.check(
+            StoreOperation::TombstoneStream,
+            Some(aggregate_id),
+        )?;
+
+        self.store.tombstone_stream(aggregate_id)
+    }
+
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        self.faults
+            .check(StoreOperation::IsTombstoned, Some(aggregate_id))?;
+
+        self.store.is_tombstoned(aggregate_id)
+    }
+
+    fn save_aggregate_snapshot(
+        &self,
+        context: AggregateContext<C, E, A>,
+    ) -> Result<(), StoreError> {
+        self.faults.check(
+            StoreOperation::SaveSnapshot,
+            Some(&context.aggregate_id),
+        )?;
+
+        self.store.save_aggregate_snapshot(context)
+    }
+
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.faults.check(
+            StoreOperation::DeleteSnapshot,
+            Some(aggregate_id),
+        )?;
+
+        self.store
+            .delete_aggregate_snapshot(aggregate_id)
+    }
+
+    fn load_aggregate_from_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<AggregateContext<C, E, A>, StoreError> {
+        self.faults
+            .check(StoreOperation::LoadSnapshot, Some(aggregate_id))?;
+
+        self.store
+            .load_aggregate_from_snapshot(aggregate_id)
+    }
+}
@@ -0,0 +1,155 @@
+use log::trace;
+use std::marker::PhantomData;
+
+use cqrs_es2::{
+    Error,
+    EventContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+    QueryContext,
+};
+
+use crate::errors::StoreError;
+
+use super::{
+    fault::Fault,
+    faults::Faults,
+    i_event_dispatcher::IEventDispatcher,
+    i_query_store::IQueryStore,
+    store_operation::StoreOperation,
+};
+
+/// A query store injecting the failures, latencies and partial
+/// writes of its `Faults` into the calls of the wrapped store, for
+/// resilience testing.
+///
+/// Its dispatches go through its own `load_query` and `save_query`,
+/// so that their faults apply as well. A partial write of `dispatch`
+/// updates the query with the first events only before failing.
+pub struct FaultyQueryStore<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+    Q: IQuery<C, E>,
+    QS: IQueryStore<C, E, A, Q>,
+> {
+    store: QS,
+    faults: Faults,
+    _phantom: PhantomData<(C, E, A, Q)>,
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        Q: IQuery<C, E>,
+        QS: IQueryStore<C, E, A, Q>,
+    > FaultyQueryStore<C, E, A, Q, QS>
+{
+    /// Constructor
+    pub fn new(
+        store: QS,
+        faults: Faults,
+    ) -> Self {
+        let x = Self {
+            store,
+            faults,
+            _phantom: PhantomData,
+        };
+
+        trace!("Created new FaultyQueryStore");
+
+        x
+    }
+
+    /// The wrapped store
+    pub fn inner(&self) -> &QS {
+        &self.store
+    }
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        Q: IQuery<C, E>,
+        QS: IQueryStore<C, E, A, Q>,
+    > IQueryStore<C, E, A, Q> for FaultyQueryStore<C, E, A, Q, QS>
+{
+    fn save_query(
+        &self,
+        context: QueryContext<C, E, Q>,
+    ) -> Result<(), StoreError> {
+        self.faults.check(
+            StoreOperation::SaveQuery,
+            Some(&context.aggregate_id),
+        )?;
+
+        self.store.save_query(context)
+    }
+
+    fn load_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<QueryContext<C, E, Q>, StoreError> {
+        self.faults
+            .check(StoreOperation::LoadQuery, Some(aggregate_id))?;
+
+        self.store.load_query(aggregate_id)
+    }
+
+    fn delete_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.faults
+            .check(StoreOperation::DeleteQuery, Some(aggregate_id))?;
+
+        self.store.delete_query(aggregate_id)
+    }
+
+    fn delete_queries(&self) -> Result<(), StoreError> {
+        self.faults
+            .check(StoreOperation::DeleteQueries, None)?;
+
+        self.store.delete_queries()
+    }
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        Q: IQuery<C, E>,
+        QS: IQueryStore<C, E, A, Q>,
+    > IEventDispatcher<C, E> for FaultyQueryStore<C, E, A, Q, QS>
+{
+    fn dispatch(
+        &self,
+        aggregate_id: &str,
+        events: &Vec<EventContext<C, E>>,
+    ) -> Result<(), Error> {
+        let operation = StoreOperation::Dispatch;
+
+        let fault = match self
+            .faults
+            .inject(operation, Some(aggregate_id))
+        {
+            Some(x) => x,
+            None => {
+                self.dispatch_events(aggregate_id, events)?;
+                return Ok(());
+            },
+        };
+
+        if let Fault::PartialWrite(x) = fault {
+            let count = x.min(events.len());
+
+            self.dispatch_events(aggregate_id, &events[..count])?;
+        }
+
+        Err(Faults::error(operation, Some(aggregate_id)).into())
+    }
+}
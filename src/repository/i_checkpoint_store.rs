@@ -0,0 +1,19 @@
+use crate::errors::StoreError;
+
+/// The abstract central source for loading and committing the
+/// positions processed by `Subscription`s.
+pub trait ICheckpointStore {
+    /// Save the last position processed by `subscription`
+    fn save_checkpoint(
+        &self,
+        subscription: &str,
+        position: i64,
+    ) -> Result<(), StoreError>;
+
+    /// Load the last position processed by `subscription`, 0 if it
+    /// did not process any events yet
+    fn load_checkpoint(
+        &self,
+        subscription: &str,
+    ) -> Result<i64, StoreError>;
+}
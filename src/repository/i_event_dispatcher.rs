@@ -34,7 +34,7 @@ use cqrs_es2::{
 ///     for CustomerEventDispatcher
 /// {
 ///     fn dispatch(
-///         &mut self,
+///         &self,
 ///         aggregate_id: &str,
 ///         events: &Vec<
 ///             EventContext<CustomerCommand, CustomerEvent>,
@@ -51,7 +51,7 @@ pub trait IEventDispatcher<C: ICommand, E: IEvent> {
     /// Events will be dispatched here immediately after being
     /// committed for the downstream queries to be updated.
     fn dispatch(
-        &mut self,
+        &self,
         aggregate_id: &str,
         events: &Vec<EventContext<C, E>>,
     ) -> Result<(), Error>;
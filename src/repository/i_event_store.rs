@@ -1,36 +1,158 @@
 use cqrs_es2::{
     AggregateContext,
-    Error,
     EventContext,
     IAggregate,
     ICommand,
     IEvent,
 };
 
+use crate::errors::StoreError;
+
+use super::{
+    event_stream::EventStream,
+    positioned_event::PositionedEvent,
+    stream_metadata::StreamMetadata,
+    timestamped_event::TimestampedEvent,
+};
+
 /// The abstract central source for loading past events and committing
 /// new events.
+///
+/// The methods take `&self`, the stores take a connection from
+/// their pool or lock their maps for every call so that they can be
+/// shared between threads.
 pub trait IEventStore<C: ICommand, E: IEvent, A: IAggregate<C, E>> {
-    /// Save new events
+    /// Save new events, either all of them are committed or none.
+    /// Events whose sequence was already committed are rejected
+    /// with a `StoreError::Conflict`.
     fn save_events(
-        &mut self,
+        &self,
         contexts: &Vec<EventContext<C, E>>,
-    ) -> Result<(), Error>;
+    ) -> Result<(), StoreError>;
 
     /// Load all events for a particular `aggregate_id`
     fn load_events(
-        &mut self,
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError> {
+        self.load_events_range(aggregate_id, 0, None)
+    }
+
+    /// Stream the events for a particular `aggregate_id` starting at
+    /// `from_sequence`, the events are loaded lazily page by page
+    /// until a page is short of events
+    fn stream_events(
+        &self,
+        aggregate_id: &str,
+        from_sequence: i64,
+    ) -> EventStream<'_, C, E, A, Self>
+    where
+        Self: Sized, {
+        EventStream::new(self, aggregate_id, from_sequence)
+    }
+
+    /// Load the events for a particular `aggregate_id` starting at
+    /// `from_sequence` and, if given, ending at `to_sequence`, both
+    /// inclusive
+    fn load_events_range(
+        &self,
+        aggregate_id: &str,
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError> {
+        Ok(self
+            .load_timestamped_events_range(
+                aggregate_id,
+                from_sequence,
+                to_sequence,
+            )?
+            .into_iter()
+            .map(|x| x.context)
+            .collect())
+    }
+
+    /// Load the events for a particular `aggregate_id` like
+    /// `load_events_range`, along with their commit timestamps
+    fn load_timestamped_events_range(
+        &self,
         aggregate_id: &str,
-    ) -> Result<Vec<EventContext<C, E>>, Error>;
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError>;
+
+    /// Load up to `limit` events of all aggregates of this type in
+    /// commit order, starting at the global position
+    /// `from_position`
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError>;
+
+    /// List up to `limit` ids of the aggregates of this type that
+    /// have committed events, in ascending order and starting after
+    /// `after_aggregate_id` if given
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError>;
+
+    /// Check whether any event was committed for a particular
+    /// `aggregate_id`
+    fn stream_exists(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        Ok(self
+            .stream_metadata(aggregate_id)?
+            .is_some())
+    }
+
+    /// Load the metadata of the event stream of a particular
+    /// `aggregate_id`, `None` if no event was committed for it
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError>;
+
+    /// Delete all events of a particular `aggregate_id` along with
+    /// its snapshot and outbox entries, a tombstone is kept
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError>;
+
+    /// Mark the stream of a particular `aggregate_id` as
+    /// tombstoned, the `Repository` refuses further commands for it
+    /// while its events are kept
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError>;
+
+    /// Check whether the stream of a particular `aggregate_id` was
+    /// tombstoned
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError>;
 
     /// save a new aggregate snapshot
     fn save_aggregate_snapshot(
-        &mut self,
+        &self,
         context: AggregateContext<C, E, A>,
-    ) -> Result<(), Error>;
+    ) -> Result<(), StoreError>;
+
+    /// Delete the snapshot of a particular `aggregate_id`, if any
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError>;
 
     /// Load aggregate at current state from snapshots
     fn load_aggregate_from_snapshot(
-        &mut self,
+        &self,
         aggregate_id: &str,
-    ) -> Result<AggregateContext<C, E, A>, Error>;
+    ) -> Result<AggregateContext<C, E, A>, StoreError>;
 }
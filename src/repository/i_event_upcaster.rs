@@ -0,0 +1,59 @@
+use serde_json::Value;
+
+/// Transforms the stored payloads of an event type from one schema
+/// version to the next, before they are deserialized.
+///
+/// Upcasters are keyed by the event type stored with the payload,
+/// the name of its enum variant unless the store names the types
+/// itself. New events are stored with the version following the
+/// highest upcasted version of their type.
+///
+/// # Example
+///
+/// For illustration only:
+///
+/// ```rust
+/// use serde_json::{
+///     json,
+///     Value,
+/// };
+///
+/// use cqrs_es2_store::IEventUpcaster;
+///
+/// // version 1 of `NameAdded` called the field `name`
+/// pub struct NameAddedV1;
+///
+/// impl IEventUpcaster for NameAddedV1 {
+///     fn event_type(&self) -> &str {
+///         "NameAdded"
+///     }
+///
+///     fn event_version(&self) -> i64 {
+///         1
+///     }
+///
+///     fn upcast(
+///         &self,
+///         payload: Value,
+///     ) -> Value {
+///         json!({
+///             "NameAdded": {
+///                 "changed_name": payload["NameAdded"]["name"],
+///             }
+///         })
+///     }
+/// }
+/// ```
+pub trait IEventUpcaster {
+    /// The event type of the upcasted payloads
+    fn event_type(&self) -> &str;
+
+    /// The schema version of the upcasted payloads
+    fn event_version(&self) -> i64;
+
+    /// Transforms a payload of `event_version` into the next version
+    fn upcast(
+        &self,
+        payload: Value,
+    ) -> Value;
+}
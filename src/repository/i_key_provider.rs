@@ -0,0 +1,19 @@
+use crate::errors::StoreError;
+
+/// The abstract source of the AES-256-GCM keys encrypting the
+/// payloads of the stores created `with_encryption()`.
+///
+/// The new values are encrypted with the current key and carry its
+/// id, rotating the keys only changes the current one: the values
+/// encrypted before keep loading as long as their key can be loaded.
+pub trait IKeyProvider {
+    /// The id of the key encrypting the new values
+    fn current_key_id(&self) -> Result<String, StoreError>;
+
+    /// Load the 32 bytes key of a particular `key_id`, `None` if it
+    /// is unknown
+    fn load_key(
+        &self,
+        key_id: &str,
+    ) -> Result<Option<Vec<u8>>, StoreError>;
+}
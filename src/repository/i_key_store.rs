@@ -0,0 +1,43 @@
+use crate::errors::StoreError;
+
+/// The abstract central source for the encryption keys of the
+/// aggregates, one key per aggregate instance.
+///
+/// Destroying the key of an aggregate makes the fields encrypted
+/// with it unreadable, wherever the events were copied to. Losing
+/// the keys does the same, the only store of this crate is the
+/// memory `KeyStore` for tests, its keys are gone with the process.
+pub trait IKeyStore {
+    /// Save the key of a particular `aggregate_id`, replacing the
+    /// existing one
+    fn save_key(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        key: &[u8],
+    ) -> Result<(), StoreError>;
+
+    /// Save the key of a particular `aggregate_id` unless it already
+    /// has one, atomically for all writers, and return the key kept
+    fn save_key_if_absent(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        key: &[u8],
+    ) -> Result<Vec<u8>, StoreError>;
+
+    /// Load the key of a particular `aggregate_id`, `None` if it was
+    /// never created or was deleted
+    fn load_key(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+    ) -> Result<Option<Vec<u8>>, StoreError>;
+
+    /// Delete the key of a particular `aggregate_id`
+    fn delete_key(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError>;
+}
@@ -0,0 +1,27 @@
+use cqrs_es2::{
+    EventContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+use crate::errors::StoreError;
+
+/// The outbox of an event store with the events that were committed
+/// but not delivered to the dispatchers yet.
+///
+/// Stores write the outbox entries in the same commit as the events
+/// once their outbox is enabled, the `OutboxRelay` delivers them.
+pub trait IOutboxStore<C: ICommand, E: IEvent, A: IAggregate<C, E>> {
+    /// Load up to `limit` pending events in commit order
+    fn load_pending_events(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError>;
+
+    /// Mark events as delivered so that they are not loaded again
+    fn mark_events_delivered(
+        &self,
+        contexts: &[EventContext<C, E>],
+    ) -> Result<(), StoreError>;
+}
@@ -0,0 +1,27 @@
+use std::error::Error;
+
+/// Compresses the stored payloads, metadata, snapshots and queries
+/// above the threshold of a store.
+///
+/// The compressed values are stored in the binary columns behind a
+/// marker naming the codec, the uncompressed rows and entries keep
+/// loading after compression is turned on or off. Values that do not
+/// get smaller are stored uncompressed.
+pub trait IPayloadCompressor {
+    /// The byte naming the codec in the marker of the compressed
+    /// values, `1` for zstd and `2` for gzip. Custom codecs should
+    /// use values above `127`.
+    fn codec(&self) -> u8;
+
+    /// Compresses a serialized value
+    fn compress(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+
+    /// Decompresses a value written by `compress`
+    fn decompress(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+}
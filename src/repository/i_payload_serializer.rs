@@ -0,0 +1,33 @@
+use serde_json::Value;
+use std::error::Error;
+
+/// Converts the payloads, metadata, snapshots and queries of the
+/// stores to and from their storage format.
+///
+/// The values go through `serde_json::Value`, so the upcasters apply
+/// whatever the format. JSON is the only text format: the stores
+/// keep their JSON columns and entries for it. The output of binary
+/// formats is stored in BYTEA/BLOB columns, MongoDB binary fields
+/// and binary Redis values, the Redis values starting with `{` are
+/// read as JSON. The rows written as JSON keep loading after a store
+/// switches to a binary format, the binary rows only load with the
+/// format they were written with.
+pub trait IPayloadSerializer {
+    /// The name of the format, e.g. `json`
+    fn format(&self) -> &str;
+
+    /// Whether the format is binary, `false` for JSON only
+    fn is_binary(&self) -> bool;
+
+    /// Serializes a value
+    fn serialize(
+        &self,
+        value: &Value,
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>;
+
+    /// Deserializes a value written by `serialize`
+    fn deserialize(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Value, Box<dyn Error + Send + Sync>>;
+}
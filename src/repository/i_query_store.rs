@@ -1,5 +1,4 @@
 use cqrs_es2::{
-    Error,
     EventContext,
     IAggregate,
     ICommand,
@@ -8,10 +7,15 @@ use cqrs_es2::{
     QueryContext,
 };
 
+use crate::errors::StoreError;
+
 use super::i_event_dispatcher::IEventDispatcher;
 
 /// The abstract central source for loading and committing
 /// queries.
+///
+/// Like the event stores, the query stores can be shared between
+/// threads through `&self`.
 pub trait IQueryStore<
     C: ICommand,
     E: IEvent,
@@ -20,22 +24,33 @@ pub trait IQueryStore<
 >: IEventDispatcher<C, E> {
     /// saves the updated query
     fn save_query(
-        &mut self,
+        &self,
         context: QueryContext<C, E, Q>,
-    ) -> Result<(), Error>;
+    ) -> Result<(), StoreError>;
 
     /// loads the most recent query
     fn load_query(
-        &mut self,
+        &self,
+        aggregate_id: &str,
+    ) -> Result<QueryContext<C, E, Q>, StoreError>;
+
+    /// deletes the query of a particular `aggregate_id`, e.g. when
+    /// its stream is deleted
+    fn delete_query(
+        &self,
         aggregate_id: &str,
-    ) -> Result<QueryContext<C, E, Q>, Error>;
+    ) -> Result<(), StoreError>;
+
+    /// deletes all queries of the query type, e.g. before they are
+    /// rebuilt from the events
+    fn delete_queries(&self) -> Result<(), StoreError>;
 
     /// used as a default implementation for dispatching
     fn dispatch_events(
-        &mut self,
+        &self,
         aggregate_id: &str,
         events: &[EventContext<C, E>],
-    ) -> Result<(), Error> {
+    ) -> Result<(), StoreError> {
         let mut context = match self.load_query(aggregate_id) {
             Ok(x) => x,
             Err(e) => {
@@ -0,0 +1,80 @@
+use cqrs_es2::{
+    AggregateContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+/// Decides when the `Repository` takes a new aggregate snapshot.
+///
+/// Closures taking the version of the latest snapshot and the
+/// aggregate context after the commit are policies as well.
+///
+/// # Example
+///
+/// For illustration only:
+///
+/// ```rust
+/// use cqrs_es2::{
+///     example_impl::{
+///         Customer,
+///         CustomerCommand,
+///         CustomerEvent,
+///     },
+///     AggregateContext,
+/// };
+///
+/// use cqrs_es2_store::ISnapshotPolicy;
+///
+/// pub struct EveryTenEvents;
+///
+/// impl ISnapshotPolicy<CustomerCommand, CustomerEvent, Customer>
+///     for EveryTenEvents
+/// {
+///     fn should_snapshot(
+///         &self,
+///         snapshot_version: i64,
+///         context: &AggregateContext<
+///             CustomerCommand,
+///             CustomerEvent,
+///             Customer,
+///         >,
+///     ) -> bool {
+///         context.version - snapshot_version >= 10
+///     }
+/// }
+/// ```
+pub trait ISnapshotPolicy<C: ICommand, E: IEvent, A: IAggregate<C, E>> {
+    /// Called after new events are committed with the version of
+    /// the latest snapshot (0 if none) and the updated aggregate.
+    fn should_snapshot(
+        &self,
+        snapshot_version: i64,
+        context: &AggregateContext<C, E, A>,
+    ) -> bool;
+
+    /// Called once the snapshot at `version` was saved, it is not
+    /// called when the save fails.
+    fn snapshot_saved(
+        &self,
+        _aggregate_id: &str,
+        _version: i64,
+    ) {
+    }
+}
+
+impl<C, E, A, F> ISnapshotPolicy<C, E, A> for F
+where
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+    F: Fn(i64, &AggregateContext<C, E, A>) -> bool,
+{
+    fn should_snapshot(
+        &self,
+        snapshot_version: i64,
+        context: &AggregateContext<C, E, A>,
+    ) -> bool {
+        self(snapshot_version, context)
+    }
+}
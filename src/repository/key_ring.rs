@@ -0,0 +1,87 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        RwLock,
+    },
+};
+
+use crate::errors::StoreError;
+
+use super::i_key_provider::IKeyProvider;
+
+/// The current key id and the keys by id
+type Keys = (String, HashMap<String, Vec<u8>>);
+
+/// An in-memory key provider.
+///
+/// The clones of a key ring share its keys, so that a key ring kept
+/// by the application rotates the keys of the stores it was given
+/// to.
+#[derive(Debug, Clone)]
+pub struct KeyRing {
+    keys: Arc<RwLock<Keys>>,
+}
+
+impl KeyRing {
+    /// Constructor, `key` becomes the current key
+    pub fn new(
+        key_id: &str,
+        key: &[u8],
+    ) -> Self {
+        let mut keys = HashMap::new();
+        keys.insert(key_id.to_string(), key.to_vec());
+
+        Self {
+            keys: Arc::new(RwLock::new((key_id.to_string(), keys))),
+        }
+    }
+
+    /// Keeps a previous key to load the values encrypted with it
+    pub fn with_key(
+        self,
+        key_id: &str,
+        key: &[u8],
+    ) -> Self {
+        self.keys
+            .write()
+            .unwrap()
+            .1
+            .insert(key_id.to_string(), key.to_vec());
+
+        self
+    }
+
+    /// Encrypts the new values with `key`, the previous keys keep
+    /// loading the values encrypted with them
+    pub fn rotate(
+        &self,
+        key_id: &str,
+        key: &[u8],
+    ) {
+        let mut keys = self.keys.write().unwrap();
+
+        keys.0 = key_id.to_string();
+        keys.1
+            .insert(key_id.to_string(), key.to_vec());
+    }
+}
+
+impl IKeyProvider for KeyRing {
+    fn current_key_id(&self) -> Result<String, StoreError> {
+        Ok(self.keys.read().unwrap().0.clone())
+    }
+
+    fn load_key(
+        &self,
+        key_id: &str,
+    ) -> Result<Option<Vec<u8>>, StoreError> {
+        Ok(self
+            .keys
+            .read()
+            .unwrap()
+            .1
+            .get(key_id)
+            .cloned())
+    }
+}
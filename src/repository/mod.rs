@@ -1,12 +1,115 @@
+#[cfg(feature = "with-encryption")]
+pub use encrypting_event_store::EncryptingEventStore;
+pub use encryption_scope::EncryptionScope;
+pub use event_stream::EventStream;
+pub(crate) use event_type::event_type;
+pub use event_upcasters::EventUpcasters;
+pub use fault::Fault;
+pub use fault_call::FaultCall;
+pub use fault_trigger::FaultTrigger;
+pub use faults::Faults;
+pub use faulty_dispatcher::FaultyDispatcher;
+pub use faulty_event_store::FaultyEventStore;
+pub use faulty_query_store::FaultyQueryStore;
+pub use i_checkpoint_store::ICheckpointStore;
 pub use i_event_dispatcher::IEventDispatcher;
 pub use i_event_store::IEventStore;
+pub use i_event_upcaster::IEventUpcaster;
+#[cfg(feature = "with-encryption")]
+pub use i_key_provider::IKeyProvider;
+#[cfg(feature = "with-encryption")]
+pub use i_key_store::IKeyStore;
+pub use i_outbox_store::IOutboxStore;
+pub use i_payload_compressor::IPayloadCompressor;
+pub use i_payload_serializer::IPayloadSerializer;
 pub use i_query_store::IQueryStore;
+pub use i_snapshot_policy::ISnapshotPolicy;
+#[cfg(feature = "with-encryption")]
+pub use key_ring::KeyRing;
+pub use outbox_relay::OutboxRelay;
+#[cfg(feature = "with-gzip")]
+pub use payload_compressors::GzipCompressor;
+#[cfg(feature = "with-zstd")]
+pub use payload_compressors::ZstdCompressor;
+#[cfg(feature = "with-encryption")]
+pub use payload_encryption::PayloadEncryption;
+#[cfg(feature = "with-bincode")]
+pub use payload_serializers::BincodeSerializer;
+#[cfg(feature = "with-cbor")]
+pub use payload_serializers::CborSerializer;
+pub use payload_serializers::JsonSerializer;
+#[cfg(feature = "with-msgpack")]
+pub use payload_serializers::MsgPackSerializer;
+pub use positioned_event::PositionedEvent;
+pub use query_replay::QueryReplay;
+pub use replay_progress::ReplayProgress;
 pub use repository::Repository;
+#[cfg(any(test, feature = "with-test-framework"))]
+pub(crate) use repository::{
+    Dispatchers,
+    SnapshotPolicy,
+};
+#[cfg(feature = "with-encryption")]
+pub use shredding_event_store::ShreddingEventStore;
+pub use snapshot_policies::{
+    EventCountSnapshotPolicy,
+    IntervalSnapshotPolicy,
+    SizeSnapshotPolicy,
+};
+pub use store_operation::StoreOperation;
+pub(crate) use store_serializer::StoreSerializer;
+pub use stream_metadata::StreamMetadata;
+pub use subscription::Subscription;
+pub use timestamped_event::TimestampedEvent;
 
+#[cfg(feature = "with-encryption")]
+mod aead_key;
+#[cfg(feature = "with-encryption")]
+mod encrypting_event_store;
+mod encryption_scope;
+mod event_stream;
+mod event_type;
+mod event_upcasters;
+mod fault;
+mod fault_call;
+mod fault_trigger;
+mod faults;
+mod faulty_dispatcher;
+mod faulty_event_store;
+mod faulty_query_store;
+mod i_checkpoint_store;
 mod i_event_dispatcher;
 mod i_event_store;
+mod i_event_upcaster;
+#[cfg(feature = "with-encryption")]
+mod i_key_provider;
+#[cfg(feature = "with-encryption")]
+mod i_key_store;
+mod i_outbox_store;
+mod i_payload_compressor;
+mod i_payload_serializer;
 mod i_query_store;
+mod i_snapshot_policy;
+#[cfg(feature = "with-encryption")]
+mod key_ring;
+mod outbox_relay;
+#[cfg(any(feature = "with-zstd", feature = "with-gzip"))]
+mod payload_compressors;
+#[cfg(feature = "with-encryption")]
+mod payload_encryption;
+mod payload_serializers;
+mod positioned_event;
+mod query_replay;
+mod replay_progress;
 mod repository;
+#[cfg(feature = "with-encryption")]
+mod shredding_event_store;
+mod snapshot_policies;
+mod store_operation;
+mod store_serializer;
+mod stream_metadata;
+mod subscription;
+mod timestamped_event;
 
 #[cfg(test)]
 mod test;
@@ -0,0 +1,140 @@
+use log::{
+    debug,
+    error,
+    trace,
+};
+use std::marker::PhantomData;
+
+use cqrs_es2::{
+    Error,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+use super::{
+    i_event_dispatcher::IEventDispatcher,
+    i_outbox_store::IOutboxStore,
+};
+
+/// Delivers the pending events of an outbox to the dispatchers.
+///
+/// Events are marked as delivered only after all dispatchers
+/// accepted them, so an event is delivered at least once. A failed
+/// or interrupted delivery is repeated by the next relay, including
+/// for the dispatchers that already handled the events.
+pub struct OutboxRelay<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+    OS: IOutboxStore<C, E, A>,
+> {
+    outbox: OS,
+    dispatchers: Vec<Box<dyn IEventDispatcher<C, E>>>,
+    batch_size: usize,
+    _phantom: PhantomData<A>,
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        OS: IOutboxStore<C, E, A>,
+    > OutboxRelay<C, E, A, OS>
+{
+    /// Constructor
+    pub fn new(
+        outbox: OS,
+        dispatchers: Vec<Box<dyn IEventDispatcher<C, E>>>,
+    ) -> Self {
+        let x = Self {
+            outbox,
+            dispatchers,
+            batch_size: 100,
+            _phantom: PhantomData,
+        };
+
+        trace!("Created new OutboxRelay");
+
+        x
+    }
+
+    /// Sets the maximum number of events loaded per batch, 100 by
+    /// default
+    pub fn with_batch_size(
+        mut self,
+        batch_size: usize,
+    ) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Delivers a single batch of pending events and returns the
+    /// number of delivered events.
+    ///
+    /// # Error
+    /// The first dispatcher or outbox error is returned, the
+    /// undelivered events stay pending.
+    pub fn relay(&mut self) -> Result<usize, Error> {
+        let contexts = self
+            .outbox
+            .load_pending_events(self.batch_size)?;
+
+        let mut delivered = 0;
+
+        // consecutive events of the same aggregate are dispatched
+        // together, the way the `Repository` dispatches a commit
+        while delivered < contexts.len() {
+            let aggregate_id = &contexts[delivered].aggregate_id;
+
+            let group: Vec<_> = contexts[delivered..]
+                .iter()
+                .take_while(|x| &x.aggregate_id == aggregate_id)
+                .cloned()
+                .collect();
+
+            for x in &mut self.dispatchers {
+                match x.dispatch(aggregate_id, &group) {
+                    Ok(_) => {},
+                    Err(e) => {
+                        error!(
+                            "dispatcher returned error '{}' for \
+                             aggregate id '{}'",
+                            e, aggregate_id
+                        );
+                        return Err(e);
+                    },
+                }
+            }
+
+            self.outbox
+                .mark_events_delivered(&group)?;
+
+            delivered += group.len();
+        }
+
+        debug!("relayed '{}' pending events", delivered);
+
+        Ok(delivered)
+    }
+
+    /// Delivers batches until no pending events are left and
+    /// returns the number of delivered events.
+    ///
+    /// # Error
+    /// The first dispatcher or outbox error is returned, the
+    /// undelivered events stay pending.
+    pub fn relay_all(&mut self) -> Result<usize, Error> {
+        let mut delivered = 0;
+
+        loop {
+            let x = self.relay()?;
+
+            delivered += x;
+
+            if x == 0 || x < self.batch_size {
+                return Ok(delivered);
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::error::Error;
+
+#[cfg(feature = "with-gzip")]
+use std::io::{
+    Read,
+    Write,
+};
+
+use super::i_payload_compressor::IPayloadCompressor;
+
+/// The zstd codec
+#[cfg(feature = "with-zstd")]
+#[derive(Debug, Clone)]
+pub struct ZstdCompressor {
+    level: i32,
+}
+
+#[cfg(feature = "with-zstd")]
+impl ZstdCompressor {
+    /// Constructor, `level` goes from `1` to `22`
+    pub fn new(level: i32) -> Self {
+        Self { level }
+    }
+}
+
+#[cfg(feature = "with-zstd")]
+impl Default for ZstdCompressor {
+    fn default() -> Self {
+        Self::new(zstd::DEFAULT_COMPRESSION_LEVEL)
+    }
+}
+
+#[cfg(feature = "with-zstd")]
+impl IPayloadCompressor for ZstdCompressor {
+    fn codec(&self) -> u8 {
+        1
+    }
+
+    fn compress(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        Ok(zstd::encode_all(bytes, self.level)?)
+    }
+
+    fn decompress(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        Ok(zstd::decode_all(bytes)?)
+    }
+}
+
+/// The gzip codec
+#[cfg(feature = "with-gzip")]
+#[derive(Debug, Clone)]
+pub struct GzipCompressor {
+    level: u32,
+}
+
+#[cfg(feature = "with-gzip")]
+impl GzipCompressor {
+    /// Constructor, `level` goes from `0` to `9`
+    pub fn new(level: u32) -> Self {
+        Self { level }
+    }
+}
+
+#[cfg(feature = "with-gzip")]
+impl Default for GzipCompressor {
+    fn default() -> Self {
+        Self::new(6)
+    }
+}
+
+#[cfg(feature = "with-gzip")]
+impl IPayloadCompressor for GzipCompressor {
+    fn codec(&self) -> u8 {
+        2
+    }
+
+    fn compress(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut encoder = flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::new(self.level),
+        );
+
+        encoder.write_all(bytes)?;
+
+        Ok(encoder.finish()?)
+    }
+
+    fn decompress(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let mut res = Vec::new();
+
+        flate2::read::GzDecoder::new(bytes).read_to_end(&mut res)?;
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,267 @@
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+
+use crate::errors::StoreError;
+
+use super::{
+    aead_key::AeadKey,
+    encryption_scope::EncryptionScope,
+    i_key_provider::IKeyProvider,
+};
+
+/// Prefix of the encrypted values, followed by the key id and the
+/// base64 nonce and ciphertext
+static ENCRYPTED_PREFIX: &str = "encrypted:";
+
+/// The encryption of the payloads of a store, created
+/// `with_encryption()`.
+///
+/// The payloads are encrypted whole by default, or only at the JSON
+/// pointers added `with_field()`, e.g. `/NameAdded/changed_name`.
+/// The pointers missing from a payload are skipped. The encrypted
+/// values are AES-256-GCM ciphertexts of their JSON stored as
+/// strings naming their key, the events record it in their metadata
+/// as `encryption_key_id`. The snapshots are encrypted whole.
+///
+/// The ciphertexts are bound to their `EncryptionScope` and pointer.
+/// Only the whole payloads and the values at the pointers are
+/// decrypted, the values stored in clear before the encryption keep
+/// loading.
+///
+/// Custom stores serializing their payloads call `encrypt` before
+/// writing them and `decrypt` after reading them, the stores keeping
+/// typed events are wrapped in an `EncryptingEventStore`.
+#[derive(Clone)]
+pub struct PayloadEncryption {
+    keys: Arc<dyn IKeyProvider + Send + Sync>,
+    fields: Vec<String>,
+}
+
+impl PayloadEncryption {
+    /// Constructor
+    pub fn new(
+        keys: impl IKeyProvider + Send + Sync + 'static
+    ) -> Self {
+        Self {
+            keys: Arc::new(keys),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Encrypts the field at `pointer` instead of the whole payloads
+    pub fn with_field(
+        mut self,
+        pointer: &str,
+    ) -> Self {
+        self.fields.push(pointer.to_string());
+        self
+    }
+
+    /// Encrypts a payload of `scope`, whole or at the fields, along
+    /// with the id of the key used if anything was encrypted
+    pub fn encrypt(
+        &self,
+        scope: &EncryptionScope<'_>,
+        mut value: Value,
+    ) -> Result<(Value, Option<String>), StoreError> {
+        let key_id = self.keys.current_key_id()?;
+        let key = self.key(&key_id)?;
+
+        let whole = match scope {
+            EncryptionScope::Snapshot { .. } => true,
+            _ => self.fields.is_empty(),
+        };
+
+        if whole {
+            let value = seal(scope, &key_id, &key, "", &value)?;
+
+            return Ok((value, Some(key_id)));
+        }
+
+        let mut encrypted = false;
+
+        for pointer in &self.fields {
+            let field = match value.pointer_mut(pointer) {
+                Some(Value::Null) | None => continue,
+                Some(x) => x,
+            };
+
+            *field = seal(scope, &key_id, &key, pointer, field)?;
+            encrypted = true;
+        }
+
+        match encrypted {
+            true => Ok((value, Some(key_id))),
+            false => Ok((value, None)),
+        }
+    }
+
+    /// Decrypts a payload of `scope` encrypted whole or at the
+    /// fields, whatever the key it was encrypted with
+    pub fn decrypt(
+        &self,
+        scope: &EncryptionScope<'_>,
+        mut value: Value,
+    ) -> Result<Value, StoreError> {
+        let mut keys = HashMap::new();
+
+        if is_sealed(&value) {
+            return self.open(&mut keys, scope, "", &value);
+        }
+
+        for pointer in &self.fields {
+            let field = match value.pointer_mut(pointer) {
+                Some(x) if is_sealed(x) => x,
+                _ => continue,
+            };
+
+            *field = self.open(&mut keys, scope, pointer, field)?;
+        }
+
+        Ok(value)
+    }
+
+    /// The AES-256-GCM key of a particular `key_id`
+    fn key(
+        &self,
+        key_id: &str,
+    ) -> Result<AeadKey, StoreError> {
+        let key = match self.keys.load_key(key_id)? {
+            Some(x) => x,
+            None => {
+                return Err(StoreError::not_found(
+                    format!("no key found for key id '{}'", key_id)
+                        .as_str(),
+                ));
+            },
+        };
+
+        match AeadKey::new(&key) {
+            Some(x) => Ok(x),
+            None => {
+                Err(StoreError::Backend {
+                    message: format!(
+                        "bad key found for key id '{}'",
+                        key_id
+                    ),
+                    source: None,
+                })
+            },
+        }
+    }
+
+    /// Decrypts an encrypted value found at `pointer`
+    fn open(
+        &self,
+        keys: &mut HashMap<String, AeadKey>,
+        scope: &EncryptionScope<'_>,
+        pointer: &str,
+        value: &Value,
+    ) -> Result<Value, StoreError> {
+        let sealed = value
+            .as_str()
+            .and_then(|x| x.strip_prefix(ENCRYPTED_PREFIX))
+            .unwrap_or_default();
+
+        let (key_id, sealed) = match sealed.rsplit_once(':') {
+            Some(x) => x,
+            None => {
+                return Err(StoreError::Deserialization {
+                    message: format!(
+                        "bad encrypted value found at '{}'",
+                        pointer
+                    ),
+                    source: None,
+                });
+            },
+        };
+
+        if !keys.contains_key(key_id) {
+            keys.insert(key_id.to_string(), self.key(key_id)?);
+        }
+
+        let opened = match keys[key_id]
+            .open(&scope.aad(key_id, pointer), sealed)
+        {
+            Some(x) => x,
+            None => {
+                return Err(StoreError::Deserialization {
+                    message: format!(
+                        "unable to decrypt the value at '{}' with \
+                         key id '{}'",
+                        pointer, key_id
+                    ),
+                    source: None,
+                });
+            },
+        };
+
+        match serde_json::from_slice(&opened) {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                Err(StoreError::deserialization(
+                    format!(
+                        "bad decrypted value found at '{}'",
+                        pointer
+                    )
+                    .as_str(),
+                    e,
+                ))
+            },
+        }
+    }
+}
+
+/// Whether a value is an encrypted string
+fn is_sealed(value: &Value) -> bool {
+    match value {
+        Value::String(x) => x.starts_with(ENCRYPTED_PREFIX),
+        _ => false,
+    }
+}
+
+/// Encrypts a value at `pointer` into a string naming its key, the
+/// ciphertext is bound to the key id, the scope and the pointer
+fn seal(
+    scope: &EncryptionScope<'_>,
+    key_id: &str,
+    key: &AeadKey,
+    pointer: &str,
+    value: &Value,
+) -> Result<Value, StoreError> {
+    let plaintext = match serde_json::to_vec(value) {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(StoreError::serialization(
+                format!(
+                    "unable to serialize the value at '{}'",
+                    pointer
+                )
+                .as_str(),
+                e,
+            ));
+        },
+    };
+
+    match key.seal(&scope.aad(key_id, pointer), plaintext) {
+        Some(x) => {
+            Ok(Value::String(format!(
+                "{}{}:{}",
+                ENCRYPTED_PREFIX, key_id, x
+            )))
+        },
+        None => {
+            Err(StoreError::Serialization {
+                message: format!(
+                    "unable to encrypt the value at '{}' with key id \
+                     '{}'",
+                    pointer, key_id
+                ),
+                source: None,
+            })
+        },
+    }
+}
@@ -0,0 +1,199 @@
+use serde_json::Value;
+use std::error::Error;
+
+#[cfg(feature = "with-bincode")]
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::i_payload_serializer::IPayloadSerializer;
+
+/// The default JSON format, stored as `jsonb`, text and JSON strings
+#[derive(Debug, Clone, Default)]
+pub struct JsonSerializer;
+
+impl IPayloadSerializer for JsonSerializer {
+    fn format(&self) -> &str {
+        "json"
+    }
+
+    fn is_binary(&self) -> bool {
+        false
+    }
+
+    fn serialize(
+        &self,
+        value: &Value,
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        Ok(serde_json::to_vec(value)?)
+    }
+
+    fn deserialize(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
+
+/// The MessagePack format, maps keep their field names
+#[cfg(feature = "with-msgpack")]
+#[derive(Debug, Clone, Default)]
+pub struct MsgPackSerializer;
+
+#[cfg(feature = "with-msgpack")]
+impl IPayloadSerializer for MsgPackSerializer {
+    fn format(&self) -> &str {
+        "msgpack"
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn serialize(
+        &self,
+        value: &Value,
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        Ok(rmp_serde::to_vec(value)?)
+    }
+
+    fn deserialize(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+/// The CBOR format
+#[cfg(feature = "with-cbor")]
+#[derive(Debug, Clone, Default)]
+pub struct CborSerializer;
+
+#[cfg(feature = "with-cbor")]
+impl IPayloadSerializer for CborSerializer {
+    fn format(&self) -> &str {
+        "cbor"
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn serialize(
+        &self,
+        value: &Value,
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        Ok(serde_cbor::to_vec(value)?)
+    }
+
+    fn deserialize(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        Ok(serde_cbor::from_slice(bytes)?)
+    }
+}
+
+/// The bincode format.
+///
+/// Bincode does not describe the types of its values, they are
+/// written as a tagged copy of the JSON value so that any payload
+/// reads back without its Rust type.
+#[cfg(feature = "with-bincode")]
+#[derive(Debug, Clone, Default)]
+pub struct BincodeSerializer;
+
+#[cfg(feature = "with-bincode")]
+#[derive(Serialize, Deserialize)]
+enum BincodeValue {
+    Null,
+    Bool(bool),
+    PosInt(u64),
+    NegInt(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<BincodeValue>),
+    Object(Vec<(String, BincodeValue)>),
+}
+
+#[cfg(feature = "with-bincode")]
+impl From<&Value> for BincodeValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(x) => Self::Bool(*x),
+            Value::Number(x) => {
+                match (x.as_u64(), x.as_i64()) {
+                    (Some(x), _) => Self::PosInt(x),
+                    (None, Some(x)) => Self::NegInt(x),
+                    _ => Self::Float(x.as_f64().unwrap_or_default()),
+                }
+            },
+            Value::String(x) => Self::String(x.clone()),
+            Value::Array(x) => {
+                Self::Array(x.iter().map(Self::from).collect())
+            },
+            Value::Object(x) => {
+                Self::Object(
+                    x.iter()
+                        .map(|(k, v)| (k.clone(), Self::from(v)))
+                        .collect(),
+                )
+            },
+        }
+    }
+}
+
+#[cfg(feature = "with-bincode")]
+impl From<BincodeValue> for Value {
+    fn from(value: BincodeValue) -> Self {
+        match value {
+            BincodeValue::Null => Value::Null,
+            BincodeValue::Bool(x) => Value::Bool(x),
+            BincodeValue::PosInt(x) => Value::from(x),
+            BincodeValue::NegInt(x) => Value::from(x),
+            BincodeValue::Float(x) => Value::from(x),
+            BincodeValue::String(x) => Value::String(x),
+            BincodeValue::Array(x) => {
+                Value::Array(x.into_iter().map(Value::from).collect())
+            },
+            BincodeValue::Object(x) => {
+                Value::Object(
+                    x.into_iter()
+                        .map(|(k, v)| (k, Value::from(v)))
+                        .collect(),
+                )
+            },
+        }
+    }
+}
+
+#[cfg(feature = "with-bincode")]
+impl IPayloadSerializer for BincodeSerializer {
+    fn format(&self) -> &str {
+        "bincode"
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn serialize(
+        &self,
+        value: &Value,
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        Ok(bincode::serialize(&BincodeValue::from(value))?)
+    }
+
+    fn deserialize(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let value: BincodeValue = bincode::deserialize(bytes)?;
+
+        Ok(value.into())
+    }
+}
@@ -0,0 +1,39 @@
+use std::time::SystemTime;
+
+use cqrs_es2::{
+    EventContext,
+    ICommand,
+    IEvent,
+};
+
+/// An event along with the global position and the timestamp
+/// assigned by the store when it was committed.
+///
+/// Positions increase in commit order across all aggregates, they
+/// are unique but not necessarily consecutive.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PositionedEvent<C: ICommand, E: IEvent> {
+    /// The global position of the event.
+    pub position: i64,
+
+    /// The commit time of the event.
+    pub timestamp: SystemTime,
+
+    /// The event itself.
+    pub context: EventContext<C, E>,
+}
+
+impl<C: ICommand, E: IEvent> PositionedEvent<C, E> {
+    /// Constructor
+    pub fn new(
+        position: i64,
+        timestamp: SystemTime,
+        context: EventContext<C, E>,
+    ) -> Self {
+        Self {
+            position,
+            timestamp,
+            context,
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use log::{
+    debug,
+    error,
+    trace,
+};
+use std::marker::PhantomData;
+
+use cqrs_es2::{
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+};
+
+use crate::errors::StoreError;
+
+use super::{
+    i_checkpoint_store::ICheckpointStore,
+    i_event_store::IEventStore,
+    i_query_store::IQueryStore,
+    replay_progress::ReplayProgress,
+};
+
+type ProgressCallback = Box<dyn FnMut(&ReplayProgress)>;
+
+/// Rebuilds the queries of a query store from all events of the
+/// aggregate type, e.g. after a bug in `IQuery::update` corrupted
+/// them.
+///
+/// A replay starting from scratch deletes the queries of the query
+/// type first, a query store created with a new query version can
+/// be rebuilt next to the queries in use instead. The position of
+/// the replay is saved as a checkpoint after the events of each
+/// aggregate were applied, so an interrupted replay resumes where it
+/// stopped. The events of the aggregate being applied during an
+/// interruption can be applied twice.
+pub struct QueryReplay<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+    Q: IQuery<C, E>,
+    ES: IEventStore<C, E, A>,
+    QS: IQueryStore<C, E, A, Q>,
+    CS: ICheckpointStore,
+> {
+    name: String,
+    store: ES,
+    queries: QS,
+    checkpoints: CS,
+    batch_size: usize,
+    progress: Option<ProgressCallback>,
+    _phantom: PhantomData<(C, E, A, Q)>,
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        Q: IQuery<C, E>,
+        ES: IEventStore<C, E, A>,
+        QS: IQueryStore<C, E, A, Q>,
+        CS: ICheckpointStore,
+    > QueryReplay<C, E, A, Q, ES, QS, CS>
+{
+    /// Constructor
+    pub fn new(
+        name: &str,
+        store: ES,
+        queries: QS,
+        checkpoints: CS,
+    ) -> Self {
+        let x = Self {
+            name: name.to_string(),
+            store,
+            queries,
+            checkpoints,
+            batch_size: 100,
+            progress: None,
+            _phantom: PhantomData,
+        };
+
+        trace!("Created new QueryReplay '{}'", name);
+
+        x
+    }
+
+    /// Sets the maximum number of events read per batch, 100 by
+    /// default
+    pub fn with_batch_size(
+        mut self,
+        batch_size: usize,
+    ) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets a callback receiving the progress after each batch
+    pub fn with_progress(
+        mut self,
+        progress: impl FnMut(&ReplayProgress) + 'static,
+    ) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Replays all events after the checkpoint of the replay and
+    /// returns the final progress. The queries are deleted first
+    /// when no checkpoint was saved yet.
+    ///
+    /// A completed replay keeps its checkpoint, replaying again
+    /// only applies the events committed since.
+    ///
+    /// # Error
+    /// The first store error is returned, the checkpoint stays at
+    /// the last fully applied aggregate.
+    pub fn replay(&mut self) -> Result<ReplayProgress, StoreError> {
+        let checkpoint = self
+            .checkpoints
+            .load_checkpoint(&self.name)?;
+
+        if checkpoint == 0 {
+            debug!(
+                "replay '{}' deleting queries '{}'",
+                &self.name,
+                Q::query_type()
+            );
+
+            self.queries.delete_queries()?;
+        }
+
+        let mut progress = ReplayProgress::new(checkpoint, 0);
+
+        loop {
+            let events = self
+                .store
+                .read_all(progress.position + 1, self.batch_size)?;
+
+            let mut applied = 0;
+
+            while applied < events.len() {
+                let aggregate_id =
+                    &events[applied].context.aggregate_id;
+
+                let group: Vec<_> = events[applied..]
+                    .iter()
+                    .map(|x| &x.context)
+                    .take_while(|x| &x.aggregate_id == aggregate_id)
+                    .cloned()
+                    .collect();
+
+                match self
+                    .queries
+                    .dispatch_events(aggregate_id, &group)
+                {
+                    Ok(_) => {},
+                    Err(e) => {
+                        error!(
+                            "replay '{}' failed for aggregate id \
+                             '{}' with error '{}'",
+                            &self.name, aggregate_id, e
+                        );
+                        return Err(e);
+                    },
+                };
+
+                applied += group.len();
+
+                progress.position = events[applied - 1].position;
+
+                self.checkpoints
+                    .save_checkpoint(&self.name, progress.position)?;
+            }
+
+            progress.replayed += applied;
+
+            debug!(
+                "replay '{}' applied '{}' events up to position {}",
+                &self.name, progress.replayed, progress.position
+            );
+
+            if let Some(x) = &mut self.progress {
+                x(&progress);
+            }
+
+            if applied < self.batch_size {
+                return Ok(progress);
+            }
+        }
+    }
+
+    /// Drops the checkpoint, the next replay deletes the queries and
+    /// starts over from the first event
+    pub fn reset(&mut self) -> Result<(), StoreError> {
+        debug!("resetting replay '{}'", &self.name);
+
+        self.checkpoints
+            .save_checkpoint(&self.name, 0)
+    }
+}
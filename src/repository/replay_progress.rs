@@ -0,0 +1,18 @@
+/// The progress of a `QueryReplay`, reported after each batch
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReplayProgress {
+    /// position of the last replayed event
+    pub position: i64,
+    /// number of events replayed by the current run
+    pub replayed: usize,
+}
+
+impl ReplayProgress {
+    /// Constructor
+    pub fn new(
+        position: i64,
+        replayed: usize,
+    ) -> Self {
+        Self { position, replayed }
+    }
+}
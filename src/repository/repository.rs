@@ -6,6 +6,7 @@ use log::{
 use std::{
     collections::HashMap,
     marker::PhantomData,
+    time::SystemTime,
 };
 
 use cqrs_es2::{
@@ -17,11 +18,30 @@ use cqrs_es2::{
     IEvent,
 };
 
+use crate::errors::{
+    ConflictError,
+    TombstonedError,
+};
+
 use super::{
     i_event_dispatcher::IEventDispatcher,
     i_event_store::IEventStore,
+    i_snapshot_policy::ISnapshotPolicy,
+    snapshot_policies::EventCountSnapshotPolicy,
 };
 
+/// The number of events loaded at a time while rebuilding an
+/// aggregate at a point in time
+const TIME_TRAVEL_PAGE_SIZE: i64 = 100;
+
+/// The dispatchers of a `Repository`
+pub(crate) type Dispatchers<C, E> =
+    Vec<Box<dyn IEventDispatcher<C, E> + Send + Sync>>;
+
+/// The snapshot policy of a `Repository`
+pub(crate) type SnapshotPolicy<C, E, A> =
+    Box<dyn ISnapshotPolicy<C, E, A> + Send + Sync>;
+
 /// This is the base framework for applying commands to produce
 /// events.
 ///
@@ -35,6 +55,15 @@ use super::{
 /// 4. persisting any generated events or rolling back on an error
 ///
 /// To manage these tasks we use a `Repository`.
+///
+/// With snapshots enabled, the aggregate is loaded from its latest
+/// snapshot and only the events committed after it are applied. New
+/// snapshots are taken as decided by the configured
+/// `ISnapshotPolicy`.
+///
+/// The commands are executed through `&self`, a `Repository` over a
+/// pooled store is `Send + Sync` and can be shared between the
+/// threads of a server in an `Arc`.
 pub struct Repository<
     C: ICommand,
     E: IEvent,
@@ -42,8 +71,9 @@ pub struct Repository<
     ES: IEventStore<C, E, A>,
 > {
     store: ES,
-    dispatchers: Vec<Box<dyn IEventDispatcher<C, E>>>,
-    with_snapshots: bool,
+    dispatchers: Dispatchers<C, E>,
+    snapshot_policy: Option<SnapshotPolicy<C, E, A>>,
+    conflict_retries: usize,
     _phantom: PhantomData<A>,
 }
 
@@ -56,15 +86,27 @@ impl<
 {
     /// Creates new framework for dispatching commands using the
     /// provided elements.
+    ///
+    /// If `with_snapshots` is set, a snapshot is taken after every
+    /// command, use `with_snapshot_policy` to snapshot less often.
     pub fn new(
         store: ES,
-        dispatchers: Vec<Box<dyn IEventDispatcher<C, E>>>,
+        dispatchers: Dispatchers<C, E>,
         with_snapshots: bool,
     ) -> Self {
+        let snapshot_policy: Option<SnapshotPolicy<C, E, A>> =
+            match with_snapshots {
+                true => {
+                    Some(Box::new(EventCountSnapshotPolicy::new(1)))
+                },
+                false => None,
+            };
+
         let x = Self {
             store,
             dispatchers,
-            with_snapshots,
+            snapshot_policy,
+            conflict_retries: 0,
             _phantom: PhantomData,
         };
 
@@ -73,6 +115,31 @@ impl<
         x
     }
 
+    /// Enables snapshots and takes them as decided by `policy`
+    /// instead of after every command.
+    pub fn with_snapshot_policy(
+        mut self,
+        policy: SnapshotPolicy<C, E, A>,
+    ) -> Self {
+        self.snapshot_policy = Some(policy);
+        self
+    }
+
+    /// Enables retrying commands whose events were rejected by the
+    /// store with a `StoreError::Conflict`.
+    ///
+    /// On each retry the aggregate is reloaded and the command is
+    /// handled again against the fresh state. Once the retries are
+    /// exhausted the conflict error is returned to the caller.
+    /// Retries are disabled by default.
+    pub fn with_conflict_retries(
+        mut self,
+        retries: usize,
+    ) -> Self {
+        self.conflict_retries = retries;
+        self
+    }
+
     /// This applies a command to an aggregate. Executing a command
     /// in this way is the only way to make any change to
     /// the state of an aggregate.
@@ -87,7 +154,7 @@ impl<
     /// If an error is generated while processing the command this
     /// will be returned.
     pub fn execute(
-        &mut self,
+        &self,
         aggregate_id: &str,
         command: C,
     ) -> Result<(), Error> {
@@ -105,19 +172,23 @@ impl<
     /// A `Hashmap<String,String>` is supplied with any contextual
     /// information that should be associated with this change.
     /// This metadata will be attached to any produced events and is
-    /// meant to assist in debugging and auditing. Common information
+    /// meant to assist in debugging and auditing, the stores
+    /// record the time of commit themselves. Common information
     /// might include:
-    /// - time of commit
     /// - user making the change
     /// - application version
     ///
     /// An error while processing will result in no events committed
-    /// and an Error being returned.
+    /// and an Error being returned. Commits rejected with a
+    /// `StoreError::Conflict` are retried as configured by
+    /// `with_conflict_retries`. Commands for tombstoned aggregates
+    /// are refused with a `TombstonedError`, before the first
+    /// attempt and before every retry.
     ///
     /// If successful the events produced will be applied to the
     /// configured `QueryProcessor`s.
     pub fn execute_with_metadata(
-        &mut self,
+        &self,
         aggregate_id: &str,
         command: C,
         metadata: HashMap<String, String>,
@@ -130,56 +201,105 @@ impl<
             &metadata
         );
 
-        let stored_context = match self.load_aggregate(&aggregate_id)
-        {
-            Ok(x) => x,
-            Err(e) => {
-                error!(
-                    "Loading aggregate '{}' returned error '{}'",
-                    &aggregate_id,
-                    e.to_string()
-                );
-                return Err(e);
-            },
-        };
+        let mut retries = 0;
 
-        let events = match stored_context
-            .payload
-            .handle(command.clone())
-        {
-            Ok(x) => x,
-            Err(e) => {
-                error!(
-                    "Handling command '{:?}' for aggregate '{}' \
-                     returned error '{}'",
-                    &command,
-                    &aggregate_id,
-                    e.to_string()
-                );
-                return Err(e);
-            },
-        };
+        let event_contexts = loop {
+            // checked on every attempt, the aggregate may have been
+            // tombstoned by the writer it conflicted with
+            match self.store.is_tombstoned(aggregate_id) {
+                Ok(false) => {},
+                Ok(true) => {
+                    error!(
+                        "Refusing command '{:?}' for tombstoned \
+                         aggregate '{}'",
+                        &command, &aggregate_id
+                    );
+                    return Err(TombstonedError::new(
+                        A::aggregate_type(),
+                        aggregate_id,
+                    )
+                    .into());
+                },
+                Err(e) => {
+                    error!(
+                        "Checking tombstone of aggregate '{}' \
+                         returned error '{}'",
+                        &aggregate_id, e
+                    );
+                    return Err(e.into());
+                },
+            };
 
-        if events.len() == 0 {
-            return Ok(());
-        }
+            let (stored_context, snapshot_version) =
+                match self.load_aggregate(&aggregate_id) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        error!(
+                            "Loading aggregate '{}' returned error \
+                             '{}'",
+                            &aggregate_id,
+                            e.to_string()
+                        );
+                        return Err(e);
+                    },
+                };
+
+            let events = match stored_context
+                .payload
+                .handle(command.clone())
+            {
+                Ok(x) => x,
+                Err(e) => {
+                    error!(
+                        "Handling command '{:?}' for aggregate '{}' \
+                         returned error '{}'",
+                        &command,
+                        &aggregate_id,
+                        e.to_string()
+                    );
+                    return Err(e);
+                },
+            };
 
-        let event_contexts = match self.save_events(
-            events,
-            stored_context,
-            metadata,
-        ) {
-            Ok(x) => x,
-            Err(e) => {
-                error!(
-                    "Committing events returned error '{}'",
-                    e.to_string()
-                );
-                return Err(e);
-            },
+            if events.len() == 0 {
+                return Ok(());
+            }
+
+            match self.save_events(
+                events,
+                stored_context,
+                snapshot_version,
+                metadata.clone(),
+            ) {
+                Ok(x) => break x,
+                Err(e) => {
+                    if ConflictError::is_conflict(&e) &&
+                        retries < self.conflict_retries
+                    {
+                        retries += 1;
+
+                        debug!(
+                            "Retrying command '{:?}' for aggregate \
+                             '{}' after conflict, attempt {} of {}",
+                            &command,
+                            &aggregate_id,
+                            retries,
+                            self.conflict_retries
+                        );
+
+                        continue;
+                    }
+
+                    error!(
+                        "Committing events returned error '{}'",
+                        e.to_string()
+                    );
+                    return Err(e);
+                },
+            };
         };
 
-        for x in &mut self.dispatchers {
+        for x in &self.dispatchers {
             match x.dispatch(&aggregate_id, &event_contexts) {
                 Ok(_) => {},
                 Err(e) => {
@@ -200,23 +320,142 @@ impl<
         Ok(())
     }
 
-    fn load_aggregate(
-        &mut self,
+    /// Tombstones an aggregate, further commands for it are refused
+    /// with a `TombstonedError` while its events are kept.
+    pub fn tombstone_aggregate(
+        &self,
         aggregate_id: &str,
+    ) -> Result<(), Error> {
+        debug!("Tombstoning aggregate '{}'", &aggregate_id);
+
+        Ok(self.store.tombstone_stream(aggregate_id)?)
+    }
+
+    /// Rebuilds the aggregate as it was after the event at
+    /// `sequence` was applied.
+    ///
+    /// The events are folded from the start of the stream, the
+    /// snapshots are neither used nor updated. The version of the
+    /// returned context is the sequence of the last applied event,
+    /// 0 if the aggregate had no events at that point.
+    pub fn load_aggregate_at_sequence(
+        &self,
+        aggregate_id: &str,
+        sequence: i64,
     ) -> Result<AggregateContext<C, E, A>, Error> {
-        match self.with_snapshots {
-            true => {
+        trace!(
+            "Loading aggregate '{}' at sequence {}",
+            &aggregate_id,
+            sequence
+        );
+
+        let mut context = AggregateContext::new(
+            aggregate_id.to_string(),
+            0,
+            A::default(),
+        );
+
+        if sequence < 1 {
+            return Ok(context);
+        }
+
+        for x in self
+            .store
+            .stream_events(aggregate_id, 1)
+            .with_to_sequence(sequence)
+        {
+            let x = x?;
+
+            context.payload.apply(&x.payload);
+            context.version = x.sequence;
+        }
+
+        Ok(context)
+    }
+
+    /// Rebuilds the aggregate as it was at `timestamp`, with the
+    /// events committed up to and including that time applied.
+    ///
+    /// The events are folded from the start of the stream and the
+    /// snapshots are neither used nor updated. The fold stops at the
+    /// first event committed after `timestamp`, the version of the
+    /// returned context is the sequence of the last applied event.
+    pub fn load_aggregate_at_time(
+        &self,
+        aggregate_id: &str,
+        timestamp: SystemTime,
+    ) -> Result<AggregateContext<C, E, A>, Error> {
+        trace!(
+            "Loading aggregate '{}' at time {:?}",
+            &aggregate_id,
+            &timestamp
+        );
+
+        let mut context = AggregateContext::new(
+            aggregate_id.to_string(),
+            0,
+            A::default(),
+        );
+
+        loop {
+            let from_sequence = context.version + 1;
+
+            let page = self.store.load_timestamped_events_range(
+                aggregate_id,
+                from_sequence,
+                Some(from_sequence + TIME_TRAVEL_PAGE_SIZE - 1),
+            )?;
+
+            let page_len = page.len() as i64;
+
+            for x in page {
+                if x.timestamp > timestamp {
+                    return Ok(context);
+                }
+
+                context.payload.apply(&x.context.payload);
+                context.version = x.context.sequence;
+            }
+
+            // sequences are consecutive, a short page is the last one
+            if page_len < TIME_TRAVEL_PAGE_SIZE {
+                return Ok(context);
+            }
+        }
+    }
+
+    /// Loads the aggregate at its current state along with the
+    /// version of the snapshot it was loaded from
+    fn load_aggregate(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(AggregateContext<C, E, A>, i64), Error> {
+        let context = match self.snapshot_policy {
+            Some(_) => {
                 self.store
-                    .load_aggregate_from_snapshot(aggregate_id)
+                    .load_aggregate_from_snapshot(aggregate_id)?
             },
-            false => self.load_aggregate_from_events(aggregate_id),
-        }
+            None => {
+                AggregateContext::new(
+                    aggregate_id.to_string(),
+                    0,
+                    A::default(),
+                )
+            },
+        };
+
+        let snapshot_version = context.version;
+
+        let context = self.apply_new_events(context)?;
+
+        Ok((context, snapshot_version))
     }
 
     fn save_events(
-        &mut self,
+        &self,
         events: Vec<E>,
         stored_context: AggregateContext<C, E, A>,
+        snapshot_version: i64,
         metadata: HashMap<String, String>,
     ) -> Result<Vec<EventContext<C, E>>, Error> {
         let aggregate_id = stored_context.aggregate_id;
@@ -235,36 +474,55 @@ impl<
                     "save events returned error '{}'",
                     e.to_string()
                 );
-                return Err(e);
+                return Err(e.into());
             },
         };
 
-        if self.with_snapshots {
-            let mut aggregate = stored_context.payload;
+        let policy = match &self.snapshot_policy {
+            Some(x) => x,
+            None => {
+                return Ok(contexts);
+            },
+        };
 
-            contexts
-                .iter()
-                .map(|x| &x.payload)
-                .for_each(|x| aggregate.apply(&x));
+        let mut aggregate = stored_context.payload;
 
-            match self.store.save_aggregate_snapshot(
-                AggregateContext::new(
-                    aggregate_id,
-                    contexts.last().unwrap().sequence,
-                    aggregate,
-                ),
-            ) {
-                Ok(_) => {},
-                Err(e) => {
-                    error!(
-                        "save aggregate snapshot returned error '{}'",
-                        e.to_string()
-                    );
-                    return Err(e);
-                },
-            };
+        contexts
+            .iter()
+            .map(|x| &x.payload)
+            .for_each(|x| aggregate.apply(&x));
+
+        let context = AggregateContext::new(
+            aggregate_id,
+            contexts.last().unwrap().sequence,
+            aggregate,
+        );
+
+        if !policy.should_snapshot(snapshot_version, &context) {
+            return Ok(contexts);
         }
 
+        let aggregate_id = context.aggregate_id.clone();
+        let version = context.version;
+
+        // the events are committed at this point and the next load
+        // catches up from the previous snapshot, so a failed
+        // snapshot is not reported to the caller
+        match self
+            .store
+            .save_aggregate_snapshot(context)
+        {
+            Ok(_) => {
+                policy.snapshot_saved(&aggregate_id, version);
+            },
+            Err(e) => {
+                error!(
+                    "save aggregate snapshot returned error '{}'",
+                    e.to_string()
+                );
+            },
+        };
+
         Ok(contexts)
     }
 
@@ -295,32 +553,26 @@ impl<
         result
     }
 
-    /// Load aggregate at current state from events stream
-    fn load_aggregate_from_events(
-        &mut self,
-        aggregate_id: &str,
+    /// Brings the aggregate up to date with the events committed
+    /// after its version
+    fn apply_new_events(
+        &self,
+        mut context: AggregateContext<C, E, A>,
     ) -> Result<AggregateContext<C, E, A>, Error> {
-        let contexts = self.store.load_events(&aggregate_id)?;
+        let aggregate_id = context.aggregate_id.clone();
 
-        if contexts.len() == 0 {
-            return Ok(AggregateContext::new(
-                aggregate_id.to_string(),
-                0,
-                A::default(),
-            ));
-        }
-
-        let mut aggregate = A::default();
+        // folds over the stream so that long event histories are
+        // never held in memory at once
+        for x in self
+            .store
+            .stream_events(&aggregate_id, context.version + 1)
+        {
+            let x = x?;
 
-        contexts
-            .iter()
-            .map(|x| &x.payload)
-            .for_each(|x| aggregate.apply(&x));
+            context.payload.apply(&x.payload);
+            context.version = x.sequence;
+        }
 
-        Ok(AggregateContext::new(
-            aggregate_id.to_string(),
-            contexts.last().unwrap().sequence,
-            aggregate,
-        ))
+        Ok(context)
     }
 }
@@ -0,0 +1,603 @@
+use log::{
+    debug,
+    trace,
+};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+};
+
+use cqrs_es2::{
+    AggregateContext,
+    EventContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+use crate::errors::StoreError;
+
+use super::{
+    aead_key::AeadKey,
+    i_event_store::IEventStore,
+    i_key_store::IKeyStore,
+    i_outbox_store::IOutboxStore,
+    positioned_event::PositionedEvent,
+    stream_metadata::StreamMetadata,
+    timestamped_event::TimestampedEvent,
+};
+
+/// Prefix of the encrypted fields
+static ENCRYPTED_PREFIX: &str = "shredded:";
+
+/// An event store encrypting personal data fields of the events with
+/// a key per aggregate instance, the keys are kept in an `IKeyStore`.
+///
+/// The encrypted fields are string fields addressed by JSON pointers
+/// into the serialized events, e.g. `/NameAdded/changed_name`. They
+/// are stored as AES-256-GCM ciphertexts bound to the aggregate, the
+/// pointers missing from an event are skipped.
+///
+/// `shred` destroys the key of an aggregate and its snapshot, which
+/// holds the fields in clear, and tombstones its stream. From then on
+/// the encrypted fields load as the erased value while the rest of
+/// the events is untouched. The queries are not encrypted, they have
+/// to be deleted from their query stores.
+///
+/// The keys have to outlive the events, the memory `KeyStore` is
+/// for tests only since a restart shreds all aggregates.
+pub struct ShreddingEventStore<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+    ES: IEventStore<C, E, A>,
+> {
+    store: ES,
+    keys: Box<dyn IKeyStore + Send + Sync>,
+    fields: Vec<String>,
+    erased_value: String,
+    _phantom: PhantomData<(C, E, A)>,
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A>,
+    > ShreddingEventStore<C, E, A, ES>
+{
+    /// Constructor
+    pub fn new(
+        store: ES,
+        keys: Box<dyn IKeyStore + Send + Sync>,
+    ) -> Self {
+        let x = Self {
+            store,
+            keys,
+            fields: Vec::new(),
+            erased_value: String::new(),
+            _phantom: PhantomData,
+        };
+
+        trace!("Created new ShreddingEventStore");
+
+        x
+    }
+
+    /// Encrypts the string field at `pointer` in the serialized
+    /// events
+    pub fn with_field(
+        mut self,
+        pointer: &str,
+    ) -> Self {
+        self.fields.push(pointer.to_string());
+        self
+    }
+
+    /// Sets the value of the fields whose key was destroyed, an
+    /// empty string by default
+    pub fn with_erased_value(
+        mut self,
+        erased_value: &str,
+    ) -> Self {
+        self.erased_value = erased_value.to_string();
+        self
+    }
+
+    /// Destroys the key and the snapshot of a particular
+    /// `aggregate_id`, its encrypted fields cannot be read anymore.
+    ///
+    /// The stream is tombstoned first so that no new key is created
+    /// for the aggregate.
+    pub fn shred(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        debug!("shredding aggregate id '{}'", aggregate_id);
+
+        self.store.tombstone_stream(aggregate_id)?;
+
+        self.keys
+            .delete_key(A::aggregate_type(), aggregate_id)?;
+
+        self.store
+            .delete_aggregate_snapshot(aggregate_id)
+    }
+
+    /// The key of a particular `aggregate_id`, created if missing
+    /// unless the aggregate was shredded
+    fn create_key(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<AeadKey, StoreError> {
+        let aggregate_type = A::aggregate_type();
+
+        if let Some(x) =
+            self.keys
+                .load_key(aggregate_type, aggregate_id)?
+        {
+            return aead_key(&x, aggregate_id);
+        }
+
+        if self.store.is_tombstoned(aggregate_id)? {
+            return Err(StoreError::Serialization {
+                message: format!(
+                    "unable to encrypt the events of tombstoned \
+                     aggregate id '{}'",
+                    aggregate_id
+                ),
+                source: None,
+            });
+        }
+
+        debug!("creating a key for aggregate id '{}'", aggregate_id);
+
+        let key = match AeadKey::generate() {
+            Some(x) => x,
+            None => {
+                return Err(StoreError::Backend {
+                    message: format!(
+                        "unable to generate a key for aggregate id \
+                         '{}'",
+                        aggregate_id
+                    ),
+                    source: None,
+                });
+            },
+        };
+
+        // the key of a concurrent writer wins over this one, all the
+        // events of the aggregate are encrypted with the same key
+        let key = self.keys.save_key_if_absent(
+            aggregate_type,
+            aggregate_id,
+            &key,
+        )?;
+
+        aead_key(&key, aggregate_id)
+    }
+
+    /// The keys of the aggregates of `contexts`, `None` for the
+    /// shredded ones
+    fn load_keys<'a>(
+        &self,
+        contexts: impl Iterator<Item = &'a EventContext<C, E>>,
+    ) -> Result<HashMap<String, Option<AeadKey>>, StoreError>
+    where
+        C: 'a,
+        E: 'a, {
+        let mut keys = HashMap::new();
+
+        for context in contexts {
+            if keys.contains_key(&context.aggregate_id) {
+                continue;
+            }
+
+            let key = match self.keys.load_key(
+                A::aggregate_type(),
+                &context.aggregate_id,
+            )? {
+                Some(x) => Some(aead_key(&x, &context.aggregate_id)?),
+                None => None,
+            };
+
+            keys.insert(context.aggregate_id.clone(), key);
+        }
+
+        Ok(keys)
+    }
+
+    /// Encrypts the fields of an event payload, the key of the
+    /// aggregate is only needed if it has any of the fields
+    fn encrypt(
+        &self,
+        keys: &mut HashMap<String, AeadKey>,
+        aggregate_id: &str,
+        payload: &E,
+    ) -> Result<E, StoreError> {
+        let mut v = match serde_json::to_value(payload) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::serialization(
+                    format!(
+                        "unable to serialize the event payload for \
+                         aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let aad = aad(A::aggregate_type(), aggregate_id);
+
+        for pointer in &self.fields.clone() {
+            let field = match v.pointer_mut(pointer) {
+                Some(Value::String(x)) => x,
+                Some(Value::Null) | None => continue,
+                Some(_) => {
+                    return Err(StoreError::Serialization {
+                        message: format!(
+                            "non string field '{}' found in event of \
+                             aggregate id '{}'",
+                            pointer, aggregate_id
+                        ),
+                        source: None,
+                    });
+                },
+            };
+
+            if !keys.contains_key(aggregate_id) {
+                let key = self.create_key(aggregate_id)?;
+                keys.insert(aggregate_id.to_string(), key);
+            }
+
+            let sealed = match keys[aggregate_id]
+                .seal(&aad, field.as_bytes().to_vec())
+            {
+                Some(x) => x,
+                None => {
+                    return Err(StoreError::Serialization {
+                        message: format!(
+                            "unable to encrypt field '{}' for \
+                             aggregate id '{}'",
+                            pointer, aggregate_id
+                        ),
+                        source: None,
+                    });
+                },
+            };
+
+            *field = format!("{}{}", ENCRYPTED_PREFIX, sealed);
+        }
+
+        match serde_json::from_value(v) {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                Err(StoreError::serialization(
+                    format!(
+                        "unable to rebuild the encrypted event for \
+                         aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ))
+            },
+        }
+    }
+
+    /// Decrypts the fields of an event payload, they are replaced by
+    /// the erased value without `key`
+    fn decrypt(
+        &self,
+        key: Option<&AeadKey>,
+        aggregate_id: &str,
+        payload: &E,
+    ) -> Result<E, StoreError> {
+        let mut v = match serde_json::to_value(payload) {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(StoreError::deserialization(
+                    format!(
+                        "unable to serialize the event payload for \
+                         aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ));
+            },
+        };
+
+        let aad = aad(A::aggregate_type(), aggregate_id);
+
+        for pointer in &self.fields {
+            let field = match v.pointer_mut(pointer) {
+                Some(Value::String(x)) => x,
+                _ => continue,
+            };
+
+            // the fields stored before the encryption are in clear
+            let sealed = match field.strip_prefix(ENCRYPTED_PREFIX) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            let key = match key {
+                Some(x) => x,
+                None => {
+                    *field = self.erased_value.clone();
+                    continue;
+                },
+            };
+
+            let opened = key
+                .open(&aad, sealed)
+                .and_then(|x| String::from_utf8(x).ok());
+
+            *field = match opened {
+                Some(x) => x,
+                None => {
+                    return Err(StoreError::Deserialization {
+                        message: format!(
+                            "unable to decrypt field '{}' for \
+                             aggregate id '{}'",
+                            pointer, aggregate_id
+                        ),
+                        source: None,
+                    });
+                },
+            };
+        }
+
+        match serde_json::from_value(v) {
+            Ok(x) => Ok(x),
+            Err(e) => {
+                Err(StoreError::deserialization(
+                    format!(
+                        "unable to rebuild the decrypted event for \
+                         aggregate id '{}'",
+                        aggregate_id
+                    )
+                    .as_str(),
+                    e,
+                ))
+            },
+        }
+    }
+
+    /// Decrypts the fields of loaded events
+    fn decrypt_contexts<'a>(
+        &self,
+        contexts: impl Iterator<Item = &'a mut EventContext<C, E>>,
+    ) -> Result<(), StoreError>
+    where
+        C: 'a,
+        E: 'a, {
+        let contexts: Vec<&mut EventContext<C, E>> =
+            contexts.collect();
+
+        let keys = self.load_keys(contexts.iter().map(|x| &**x))?;
+
+        for context in contexts {
+            let key = keys
+                .get(&context.aggregate_id)
+                .and_then(|x| x.as_ref());
+
+            context.payload = self.decrypt(
+                key,
+                &context.aggregate_id,
+                &context.payload,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The AES-256-GCM key of an aggregate
+fn aead_key(
+    key: &[u8],
+    aggregate_id: &str,
+) -> Result<AeadKey, StoreError> {
+    match AeadKey::new(key) {
+        Some(x) => Ok(x),
+        None => {
+            Err(StoreError::Deserialization {
+                message: format!(
+                    "bad key found for aggregate id '{}'",
+                    aggregate_id
+                ),
+                source: None,
+            })
+        },
+    }
+}
+
+/// The additional data binding the ciphertexts to their aggregate
+fn aad(
+    aggregate_type: &str,
+    aggregate_id: &str,
+) -> String {
+    format!("{};{}", aggregate_type, aggregate_id)
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A>,
+    > IEventStore<C, E, A> for ShreddingEventStore<C, E, A, ES>
+{
+    /// Save new events with their fields encrypted
+    fn save_events(
+        &self,
+        contexts: &Vec<EventContext<C, E>>,
+    ) -> Result<(), StoreError> {
+        let mut encrypted = Vec::with_capacity(contexts.len());
+        let mut keys: HashMap<String, AeadKey> = HashMap::new();
+
+        for context in contexts {
+            let payload = self.encrypt(
+                &mut keys,
+                &context.aggregate_id,
+                &context.payload,
+            )?;
+
+            encrypted.push(EventContext::new(
+                context.aggregate_id.clone(),
+                context.sequence,
+                payload,
+                context.metadata.clone(),
+            ));
+        }
+
+        self.store.save_events(&encrypted)
+    }
+
+    /// Load the decrypted events for a particular `aggregate_id`
+    /// within a range of sequences along with their commit times
+    fn load_timestamped_events_range(
+        &self,
+        aggregate_id: &str,
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<Vec<TimestampedEvent<C, E>>, StoreError> {
+        let mut events = self
+            .store
+            .load_timestamped_events_range(
+                aggregate_id,
+                from_sequence,
+                to_sequence,
+            )?;
+
+        self.decrypt_contexts(
+            events.iter_mut().map(|x| &mut x.context),
+        )?;
+
+        Ok(events)
+    }
+
+    /// Read the decrypted events of all aggregate instances
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<Vec<PositionedEvent<C, E>>, StoreError> {
+        let mut events = self
+            .store
+            .read_all(from_position, limit)?;
+
+        self.decrypt_contexts(
+            events.iter_mut().map(|x| &mut x.context),
+        )?;
+
+        Ok(events)
+    }
+
+    /// List the ids of the aggregate instances
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        self.store
+            .list_aggregate_ids(after_aggregate_id, limit)
+    }
+
+    /// Load the metadata of the stream of a particular
+    /// `aggregate_id`
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        self.store.stream_metadata(aggregate_id)
+    }
+
+    /// Delete the stream and the key of a particular `aggregate_id`
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store.delete_stream(aggregate_id)?;
+
+        self.keys
+            .delete_key(A::aggregate_type(), aggregate_id)
+    }
+
+    /// Mark the stream of a particular `aggregate_id` as tombstoned
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store.tombstone_stream(aggregate_id)
+    }
+
+    /// Check whether the stream of a particular `aggregate_id` was
+    /// tombstoned
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        self.store.is_tombstoned(aggregate_id)
+    }
+
+    /// save a new aggregate snapshot
+    fn save_aggregate_snapshot(
+        &self,
+        context: AggregateContext<C, E, A>,
+    ) -> Result<(), StoreError> {
+        self.store
+            .save_aggregate_snapshot(context)
+    }
+
+    /// Delete the snapshot of a particular `aggregate_id`
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store
+            .delete_aggregate_snapshot(aggregate_id)
+    }
+
+    /// Load aggregate at current state from snapshots
+    fn load_aggregate_from_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<AggregateContext<C, E, A>, StoreError> {
+        self.store
+            .load_aggregate_from_snapshot(aggregate_id)
+    }
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A> + IOutboxStore<C, E, A>,
+    > IOutboxStore<C, E, A> for ShreddingEventStore<C, E, A, ES>
+{
+    /// Load the decrypted pending events
+    fn load_pending_events(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<EventContext<C, E>>, StoreError> {
+        let mut contexts = self.store.load_pending_events(limit)?;
+
+        self.decrypt_contexts(contexts.iter_mut())?;
+
+        Ok(contexts)
+    }
+
+    /// Mark events as delivered so that they are not loaded again
+    fn mark_events_delivered(
+        &self,
+        contexts: &[EventContext<C, E>],
+    ) -> Result<(), StoreError> {
+        self.store
+            .mark_events_delivered(contexts)
+    }
+}
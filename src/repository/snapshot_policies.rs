@@ -0,0 +1,171 @@
+use log::error;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        RwLock,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use cqrs_es2::{
+    AggregateContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+use super::i_snapshot_policy::ISnapshotPolicy;
+
+/// Takes a snapshot once at least `events` events were committed
+/// since the latest snapshot.
+#[derive(Debug, Clone)]
+pub struct EventCountSnapshotPolicy {
+    events: i64,
+}
+
+impl EventCountSnapshotPolicy {
+    /// Constructor
+    pub fn new(events: i64) -> Self {
+        Self { events }
+    }
+}
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
+    ISnapshotPolicy<C, E, A> for EventCountSnapshotPolicy
+{
+    fn should_snapshot(
+        &self,
+        snapshot_version: i64,
+        context: &AggregateContext<C, E, A>,
+    ) -> bool {
+        context.version - snapshot_version >= self.events
+    }
+}
+
+/// Takes a snapshot when the serialized aggregate is larger than
+/// `bytes`, smaller aggregates are cheap enough to replay.
+#[derive(Debug, Clone)]
+pub struct SizeSnapshotPolicy {
+    bytes: usize,
+}
+
+impl SizeSnapshotPolicy {
+    /// Constructor
+    pub fn new(bytes: usize) -> Self {
+        Self { bytes }
+    }
+}
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
+    ISnapshotPolicy<C, E, A> for SizeSnapshotPolicy
+{
+    fn should_snapshot(
+        &self,
+        _snapshot_version: i64,
+        context: &AggregateContext<C, E, A>,
+    ) -> bool {
+        match serde_json::to_vec(&context.payload) {
+            Ok(x) => x.len() > self.bytes,
+            Err(e) => {
+                error!(
+                    "unable to measure aggregate id '{}' with error \
+                     '{}'",
+                    &context.aggregate_id, e
+                );
+                false
+            },
+        }
+    }
+}
+
+/// Takes a snapshot when `interval` has passed since the last
+/// snapshot of the same aggregate saved with this policy, a failed
+/// save is retried on the next commit.
+///
+/// The time of the last snapshot is only tracked in memory, so the
+/// first commit of an aggregate after startup always takes one. The
+/// clones of a policy share the times of the last snapshots.
+///
+/// The times of at most `capacity` aggregates are kept, the expired
+/// ones are dropped first and then the oldest ones, whose aggregates
+/// take their next snapshot early.
+#[derive(Debug, Clone)]
+pub struct IntervalSnapshotPolicy {
+    interval: Duration,
+    capacity: usize,
+    last_snapshots: Arc<RwLock<HashMap<String, Instant>>>,
+}
+
+impl IntervalSnapshotPolicy {
+    /// Constructor
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            capacity: 10_000,
+            last_snapshots: Default::default(),
+        }
+    }
+
+    /// Sets the maximum number of aggregates whose last snapshot
+    /// time is kept, 10000 by default
+    pub fn with_capacity(
+        mut self,
+        capacity: usize,
+    ) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+}
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>>
+    ISnapshotPolicy<C, E, A> for IntervalSnapshotPolicy
+{
+    fn should_snapshot(
+        &self,
+        _snapshot_version: i64,
+        context: &AggregateContext<C, E, A>,
+    ) -> bool {
+        let last_snapshots = self.last_snapshots.read().unwrap();
+
+        match last_snapshots.get(&context.aggregate_id) {
+            Some(x) => x.elapsed() >= self.interval,
+            None => true,
+        }
+    }
+
+    fn snapshot_saved(
+        &self,
+        aggregate_id: &str,
+        _version: i64,
+    ) {
+        let now = Instant::now();
+
+        let mut last_snapshots = self.last_snapshots.write().unwrap();
+
+        if !last_snapshots.contains_key(aggregate_id) &&
+            last_snapshots.len() >= self.capacity
+        {
+            // the expired times would take a snapshot anyway
+            last_snapshots.retain(|_, x| {
+                now.duration_since(*x) < self.interval
+            });
+
+            if last_snapshots.len() >= self.capacity {
+                let oldest = last_snapshots
+                    .iter()
+                    .min_by_key(|(_, x)| **x)
+                    .map(|(k, _)| k.clone());
+
+                if let Some(x) = oldest {
+                    last_snapshots.remove(&x);
+                }
+            }
+        }
+
+        last_snapshots.insert(aggregate_id.to_string(), now);
+    }
+}
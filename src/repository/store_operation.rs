@@ -0,0 +1,37 @@
+/// The operations of the stores and dispatchers wrapped by the
+/// `FaultyEventStore`, `FaultyQueryStore` and `FaultyDispatcher`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StoreOperation {
+    /// `IEventStore::save_events`
+    SaveEvents,
+    /// `IEventStore::load_events` and the range variants
+    LoadEvents,
+    /// `IEventStore::read_all`
+    ReadAll,
+    /// `IEventStore::list_aggregate_ids`
+    ListAggregateIds,
+    /// `IEventStore::stream_metadata` and `stream_exists`
+    StreamMetadata,
+    /// `IEventStore::delete_stream`
+    DeleteStream,
+    /// `IEventStore::tombstone_stream`
+    TombstoneStream,
+    /// `IEventStore::is_tombstoned`
+    IsTombstoned,
+    /// `IEventStore::save_aggregate_snapshot`
+    SaveSnapshot,
+    /// `IEventStore::delete_aggregate_snapshot`
+    DeleteSnapshot,
+    /// `IEventStore::load_aggregate_from_snapshot`
+    LoadSnapshot,
+    /// `IQueryStore::save_query`
+    SaveQuery,
+    /// `IQueryStore::load_query`
+    LoadQuery,
+    /// `IQueryStore::delete_query`
+    DeleteQuery,
+    /// `IQueryStore::delete_queries`
+    DeleteQueries,
+    /// `IEventDispatcher::dispatch`
+    Dispatch,
+}
@@ -0,0 +1,456 @@
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::Arc,
+};
+
+use super::{
+    encryption_scope::EncryptionScope,
+    event_upcasters::EventUpcasters,
+    i_payload_compressor::IPayloadCompressor,
+    i_payload_serializer::IPayloadSerializer,
+    payload_serializers::JsonSerializer,
+};
+
+#[cfg(feature = "with-encryption")]
+use super::payload_encryption::PayloadEncryption;
+#[cfg(feature = "with-gzip")]
+use super::payload_compressors::GzipCompressor;
+#[cfg(feature = "with-zstd")]
+use super::payload_compressors::ZstdCompressor;
+
+type BoxedError = Box<dyn Error + Send + Sync>;
+
+/// The metadata entry recording the key id of an encrypted event
+pub(crate) static KEY_ID_METADATA: &str = "encryption_key_id";
+
+type Compressor = Arc<dyn IPayloadCompressor + Send + Sync>;
+
+/// The first bytes of the compressed values, followed by the codec
+/// and the format of the compressed value. No JSON text or binary
+/// format output starts with them.
+const COMPRESSION_MARKER: [u8; 2] = [0, b'Z'];
+
+/// The format of a compressed JSON value
+const JSON_FORMAT: u8 = b'j';
+
+/// The format of a compressed value of the store format
+const STORE_FORMAT: u8 = b'b';
+
+/// The serializer of a store, splits the stored values between the
+/// JSON columns and the binary ones.
+///
+/// The binary value of a row wins over its JSON value so that the
+/// rows written before and after a change of format both load. The
+/// values above the compression threshold are compressed into the
+/// binary column. The payloads are encrypted before their
+/// serialization and decrypted after their deserialization, bound
+/// to the `EncryptionScope` of the event, snapshot or query.
+#[derive(Clone)]
+pub(crate) struct StoreSerializer {
+    serializer: Arc<dyn IPayloadSerializer + Send + Sync>,
+    compression: Option<(Compressor, usize)>,
+    #[cfg(feature = "with-encryption")]
+    encryption: Option<PayloadEncryption>,
+}
+
+impl Default for StoreSerializer {
+    fn default() -> Self {
+        Self::new(JsonSerializer)
+    }
+}
+
+impl StoreSerializer {
+    /// Constructor
+    pub fn new(
+        serializer: impl IPayloadSerializer + Send + Sync + 'static
+    ) -> Self {
+        Self {
+            serializer: Arc::new(serializer),
+            compression: None,
+            #[cfg(feature = "with-encryption")]
+            encryption: None,
+        }
+    }
+
+    /// Changes the format of the values
+    pub fn set_serializer(
+        &mut self,
+        serializer: impl IPayloadSerializer + Send + Sync + 'static,
+    ) {
+        self.serializer = Arc::new(serializer);
+    }
+
+    /// Compresses the values longer than `threshold` bytes
+    pub fn set_compression(
+        &mut self,
+        compressor: impl IPayloadCompressor + Send + Sync + 'static,
+        threshold: usize,
+    ) {
+        self.compression = Some((Arc::new(compressor), threshold));
+    }
+
+    /// Encrypts the payloads with `encryption`
+    #[cfg(feature = "with-encryption")]
+    pub fn set_encryption(
+        &mut self,
+        encryption: PayloadEncryption,
+    ) {
+        self.encryption = Some(encryption);
+    }
+
+    /// Encrypts an event payload of `scope`, the id of its key is
+    /// recorded in the returned metadata
+    pub fn encrypt_event(
+        &self,
+        scope: &EncryptionScope<'_>,
+        payload: Value,
+        metadata: &HashMap<String, String>,
+    ) -> Result<(Value, HashMap<String, String>), BoxedError> {
+        let mut metadata = metadata.clone();
+
+        let (payload, key_id) = self.encrypt_value(scope, payload)?;
+
+        if let Some(x) = key_id {
+            metadata.insert(KEY_ID_METADATA.to_string(), x);
+        }
+
+        Ok((payload, metadata))
+    }
+
+    /// Encrypts a query payload or an aggregate snapshot of `scope`
+    pub fn encrypt<T: Serialize>(
+        &self,
+        scope: &EncryptionScope<'_>,
+        x: &T,
+    ) -> Result<Value, BoxedError> {
+        let payload = serde_json::to_value(x)?;
+
+        Ok(self.encrypt_value(scope, payload)?.0)
+    }
+
+    /// Encrypts a query payload or an aggregate snapshot of `scope`
+    /// and splits it between a JSON and a binary column
+    pub fn encrypt_to_json<T: Serialize>(
+        &self,
+        scope: &EncryptionScope<'_>,
+        x: &T,
+    ) -> Result<(Option<Value>, Option<Vec<u8>>), BoxedError> {
+        self.split_json(self.encrypt(scope, x)?)
+    }
+
+    /// Encrypts a query payload or an aggregate snapshot of `scope`
+    /// and splits it between a text and a binary column
+    pub fn encrypt_to_text<T: Serialize>(
+        &self,
+        scope: &EncryptionScope<'_>,
+        x: &T,
+    ) -> Result<(String, Option<Vec<u8>>), BoxedError> {
+        self.split_text(self.encrypt(scope, x)?)
+    }
+
+    /// Decrypts a loaded value of `scope`
+    pub fn decrypt(
+        &self,
+        scope: &EncryptionScope<'_>,
+        value: Value,
+    ) -> Result<Value, BoxedError> {
+        #[cfg(feature = "with-encryption")]
+        if let Some(x) = &self.encryption {
+            return Ok(x.decrypt(scope, value)?);
+        }
+
+        #[cfg(not(feature = "with-encryption"))]
+        let _ = scope;
+
+        Ok(value)
+    }
+
+    /// Encrypts a value of `scope` along with the id of the key
+    /// used if anything was encrypted
+    fn encrypt_value(
+        &self,
+        scope: &EncryptionScope<'_>,
+        value: Value,
+    ) -> Result<(Value, Option<String>), BoxedError> {
+        #[cfg(feature = "with-encryption")]
+        if let Some(x) = &self.encryption {
+            return Ok(x.encrypt(scope, value)?);
+        }
+
+        #[cfg(not(feature = "with-encryption"))]
+        let _ = scope;
+
+        Ok((value, None))
+    }
+
+    /// Compresses a serialized value of `format` above the threshold
+    /// when it gets smaller
+    fn compress(
+        &self,
+        bytes: Vec<u8>,
+        format: u8,
+    ) -> Result<Vec<u8>, BoxedError> {
+        let compressor = match &self.compression {
+            Some((x, threshold)) if bytes.len() > *threshold => x,
+            _ => return Ok(bytes),
+        };
+
+        let compressed = compressor.compress(&bytes)?;
+
+        if compressed.len() + 4 >= bytes.len() {
+            return Ok(bytes);
+        }
+
+        let mut res = Vec::with_capacity(compressed.len() + 4);
+        res.extend_from_slice(&COMPRESSION_MARKER);
+        res.push(compressor.codec());
+        res.push(format);
+        res.extend_from_slice(&compressed);
+
+        Ok(res)
+    }
+
+    /// Deserializes a binary value, compressed or not
+    fn deserialize(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Value, BoxedError> {
+        if !bytes.starts_with(&COMPRESSION_MARKER) {
+            return self.serializer.deserialize(bytes);
+        }
+
+        let (codec, format) = match bytes.get(2..4) {
+            Some(x) => (x[0], x[1]),
+            None => return Err("truncated compressed value".into()),
+        };
+
+        let bytes = match self.compressor(codec) {
+            Some(x) => x.decompress(&bytes[4..])?,
+            None => {
+                return Err(format!(
+                    "no compressor found for codec {}",
+                    codec
+                )
+                .into());
+            },
+        };
+
+        match format {
+            JSON_FORMAT => Ok(serde_json::from_slice(&bytes)?),
+            _ => self.serializer.deserialize(&bytes),
+        }
+    }
+
+    /// The compressor of a codec, the store one or one of the
+    /// built-in ones so that the values compressed before a change
+    /// of codec still load
+    fn compressor(
+        &self,
+        codec: u8,
+    ) -> Option<Compressor> {
+        if let Some((x, _)) = &self.compression {
+            if x.codec() == codec {
+                return Some(x.clone());
+            }
+        }
+
+        match codec {
+            #[cfg(feature = "with-zstd")]
+            1 => Some(Arc::new(ZstdCompressor::default())),
+            #[cfg(feature = "with-gzip")]
+            2 => Some(Arc::new(GzipCompressor::default())),
+            _ => None,
+        }
+    }
+
+    /// Splits a value between a JSON and a binary column
+    pub fn to_json<T: Serialize>(
+        &self,
+        x: &T,
+    ) -> Result<(Option<Value>, Option<Vec<u8>>), BoxedError> {
+        self.split_json(serde_json::to_value(x)?)
+    }
+
+    /// Splits a JSON value between a JSON and a binary column
+    pub fn split_json(
+        &self,
+        value: Value,
+    ) -> Result<(Option<Value>, Option<Vec<u8>>), BoxedError> {
+        if self.serializer.is_binary() {
+            let bytes = self.serializer.serialize(&value)?;
+            let bytes = self.compress(bytes, STORE_FORMAT)?;
+
+            return Ok((None, Some(bytes)));
+        }
+
+        if self.compression.is_none() {
+            return Ok((Some(value), None));
+        }
+
+        let bytes = serde_json::to_vec(&value)?;
+        let len = bytes.len();
+
+        match self.compress(bytes, JSON_FORMAT)? {
+            x if x.len() < len => Ok((None, Some(x))),
+            _ => Ok((Some(value), None)),
+        }
+    }
+
+    /// Splits a value between a text and a binary column, the text
+    /// of a binary value is left empty
+    pub fn to_text<T: Serialize>(
+        &self,
+        x: &T,
+    ) -> Result<(String, Option<Vec<u8>>), BoxedError> {
+        self.split_text(serde_json::to_value(x)?)
+    }
+
+    /// Splits a JSON value between a text and a binary column, the
+    /// text of a binary value is left empty
+    pub fn split_text(
+        &self,
+        value: Value,
+    ) -> Result<(String, Option<Vec<u8>>), BoxedError> {
+        match self.split_json(value)? {
+            (Some(x), _) => Ok((serde_json::to_string(&x)?, None)),
+            (None, x) => Ok((String::new(), x)),
+        }
+    }
+
+    /// Loads a value from its JSON and binary columns
+    pub fn join_json(
+        &self,
+        json: Option<Value>,
+        bytes: Option<Vec<u8>>,
+    ) -> Result<Value, BoxedError> {
+        match (bytes, json) {
+            (Some(x), _) => self.deserialize(&x),
+            (None, Some(x)) => Ok(x),
+            (None, None) => Err("no stored value found".into()),
+        }
+    }
+
+    /// Loads a typed value from its JSON and binary columns
+    pub fn deserialize_json<T: DeserializeOwned>(
+        &self,
+        json: Option<Value>,
+        bytes: Option<Vec<u8>>,
+    ) -> Result<T, BoxedError> {
+        Ok(serde_json::from_value(self.join_json(json, bytes)?)?)
+    }
+
+    /// Loads and decrypts a typed value of `scope` from its JSON and
+    /// binary columns
+    pub fn decrypt_json<T: DeserializeOwned>(
+        &self,
+        scope: &EncryptionScope<'_>,
+        json: Option<Value>,
+        bytes: Option<Vec<u8>>,
+    ) -> Result<T, BoxedError> {
+        let value = self.join_json(json, bytes)?;
+
+        Ok(serde_json::from_value(self.decrypt(scope, value)?)?)
+    }
+
+    /// Loads and decrypts an event of `scope` from its JSON and
+    /// binary columns, upcasting its payload with `upcasters`
+    pub fn deserialize_event<E: DeserializeOwned>(
+        &self,
+        scope: &EncryptionScope<'_>,
+        upcasters: &EventUpcasters,
+        event_type: &str,
+        json: Option<Value>,
+        bytes: Option<Vec<u8>>,
+        event_version: i64,
+    ) -> Result<E, BoxedError> {
+        let payload = self.join_json(json, bytes)?;
+        let payload = self.decrypt(scope, payload)?;
+
+        Ok(upcasters.deserialize(event_type, payload, event_version)?)
+    }
+
+    /// Loads a value from its text and binary columns
+    pub fn join_text(
+        &self,
+        text: String,
+        bytes: Option<Vec<u8>>,
+    ) -> Result<Value, BoxedError> {
+        let json = match &bytes {
+            Some(_) => None,
+            None => Some(serde_json::from_str(&text)?),
+        };
+
+        self.join_json(json, bytes)
+    }
+
+    /// Loads a typed value from its text and binary columns
+    pub fn deserialize_text<T: DeserializeOwned>(
+        &self,
+        text: String,
+        bytes: Option<Vec<u8>>,
+    ) -> Result<T, BoxedError> {
+        Ok(serde_json::from_value(self.join_text(text, bytes)?)?)
+    }
+
+    /// Loads and decrypts a typed value of `scope` from its text and
+    /// binary columns
+    pub fn decrypt_text<T: DeserializeOwned>(
+        &self,
+        scope: &EncryptionScope<'_>,
+        text: String,
+        bytes: Option<Vec<u8>>,
+    ) -> Result<T, BoxedError> {
+        let value = self.join_text(text, bytes)?;
+
+        Ok(serde_json::from_value(self.decrypt(scope, value)?)?)
+    }
+
+    /// Loads and decrypts an event of `scope` from its text and
+    /// binary columns, upcasting its payload with `upcasters`
+    pub fn deserialize_text_event<E: DeserializeOwned>(
+        &self,
+        scope: &EncryptionScope<'_>,
+        upcasters: &EventUpcasters,
+        event_type: &str,
+        text: String,
+        bytes: Option<Vec<u8>>,
+        event_version: i64,
+    ) -> Result<E, BoxedError> {
+        let payload = self.join_text(text, bytes)?;
+        let payload = self.decrypt(scope, payload)?;
+
+        Ok(upcasters.deserialize(event_type, payload, event_version)?)
+    }
+
+    /// Serializes a whole entry, uncompressed JSON entries stay JSON
+    /// objects
+    pub fn to_entry(
+        &self,
+        value: &Value,
+    ) -> Result<Vec<u8>, BoxedError> {
+        let format = match self.serializer.is_binary() {
+            true => STORE_FORMAT,
+            false => JSON_FORMAT,
+        };
+
+        self.compress(self.serializer.serialize(value)?, format)
+    }
+
+    /// Loads an entry written by `to_entry`, entries starting with
+    /// `{` are uncompressed JSON objects whatever the current format
+    pub fn join_entry(
+        &self,
+        entry: &[u8],
+    ) -> Result<Value, BoxedError> {
+        match entry.first() {
+            Some(b'{') => Ok(serde_json::from_slice(entry)?),
+            _ => self.deserialize(entry),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::time::SystemTime;
+
+/// A summary of the event stream of an aggregate, as returned by
+/// `IEventStore::stream_metadata`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StreamMetadata {
+    /// The id of the aggregate.
+    pub aggregate_id: String,
+
+    /// The sequence of the last committed event.
+    pub version: i64,
+
+    /// The number of committed events.
+    pub event_count: i64,
+
+    /// The commit time of the first event.
+    pub first_timestamp: SystemTime,
+
+    /// The commit time of the last event.
+    pub last_timestamp: SystemTime,
+
+    /// The version of the latest snapshot, if any.
+    pub snapshot_version: Option<i64>,
+}
+
+impl StreamMetadata {
+    /// Constructor
+    pub fn new(
+        aggregate_id: &str,
+        version: i64,
+        event_count: i64,
+        first_timestamp: SystemTime,
+        last_timestamp: SystemTime,
+        snapshot_version: Option<i64>,
+    ) -> Self {
+        Self {
+            aggregate_id: aggregate_id.to_string(),
+            version,
+            event_count,
+            first_timestamp,
+            last_timestamp,
+            snapshot_version,
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use log::{
+    debug,
+    error,
+    trace,
+};
+use std::{
+    marker::PhantomData,
+    sync::atomic::{
+        AtomicBool,
+        Ordering,
+    },
+    thread,
+    time::Duration,
+};
+
+use cqrs_es2::{
+    Error,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+use super::{
+    i_checkpoint_store::ICheckpointStore,
+    i_event_dispatcher::IEventDispatcher,
+    i_event_store::IEventStore,
+};
+
+/// Feeds the events of an aggregate type to dispatchers in commit
+/// order, starting after the checkpoint of the subscription.
+///
+/// The checkpoint is saved after each batch, so a subscription that
+/// was stopped or is new catches up from where it left off. Events
+/// of a batch that was interrupted before its checkpoint was saved
+/// are delivered again.
+pub struct Subscription<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+    ES: IEventStore<C, E, A>,
+    CS: ICheckpointStore,
+> {
+    name: String,
+    store: ES,
+    checkpoints: CS,
+    dispatchers: Vec<Box<dyn IEventDispatcher<C, E>>>,
+    batch_size: usize,
+    poll_interval: Duration,
+    _phantom: PhantomData<A>,
+}
+
+impl<
+        C: ICommand,
+        E: IEvent,
+        A: IAggregate<C, E>,
+        ES: IEventStore<C, E, A>,
+        CS: ICheckpointStore,
+    > Subscription<C, E, A, ES, CS>
+{
+    /// Constructor
+    pub fn new(
+        name: &str,
+        store: ES,
+        checkpoints: CS,
+        dispatchers: Vec<Box<dyn IEventDispatcher<C, E>>>,
+    ) -> Self {
+        let x = Self {
+            name: name.to_string(),
+            store,
+            checkpoints,
+            dispatchers,
+            batch_size: 100,
+            poll_interval: Duration::from_secs(1),
+            _phantom: PhantomData,
+        };
+
+        trace!("Created new Subscription '{}'", name);
+
+        x
+    }
+
+    /// Sets the maximum number of events read per batch, 100 by
+    /// default
+    pub fn with_batch_size(
+        mut self,
+        batch_size: usize,
+    ) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets the time waited for new events once caught up, 1 second
+    /// by default
+    pub fn with_poll_interval(
+        mut self,
+        poll_interval: Duration,
+    ) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Dispatches a single batch of events after the checkpoint and
+    /// returns the number of dispatched events.
+    ///
+    /// # Error
+    /// The first store or dispatcher error is returned, the
+    /// checkpoint is not moved in that case.
+    pub fn poll(&mut self) -> Result<usize, Error> {
+        let checkpoint = self
+            .checkpoints
+            .load_checkpoint(&self.name)?;
+
+        let events = self
+            .store
+            .read_all(checkpoint + 1, self.batch_size)?;
+
+        let last_position = match events.last() {
+            Some(x) => x.position,
+            None => {
+                return Ok(0);
+            },
+        };
+
+        let mut dispatched = 0;
+
+        // consecutive events of the same aggregate are dispatched
+        // together, the way the `Repository` dispatches a commit
+        while dispatched < events.len() {
+            let aggregate_id =
+                &events[dispatched].context.aggregate_id;
+
+            let group: Vec<_> = events[dispatched..]
+                .iter()
+                .map(|x| &x.context)
+                .take_while(|x| &x.aggregate_id == aggregate_id)
+                .cloned()
+                .collect();
+
+            for x in &mut self.dispatchers {
+                match x.dispatch(aggregate_id, &group) {
+                    Ok(_) => {},
+                    Err(e) => {
+                        error!(
+                            "subscription '{}' dispatcher returned \
+                             error '{}' for aggregate id '{}'",
+                            &self.name, e, aggregate_id
+                        );
+                        return Err(e);
+                    },
+                }
+            }
+
+            dispatched += group.len();
+        }
+
+        self.checkpoints
+            .save_checkpoint(&self.name, last_position)?;
+
+        debug!(
+            "subscription '{}' dispatched '{}' events up to position \
+             {}",
+            &self.name, dispatched, last_position
+        );
+
+        Ok(dispatched)
+    }
+
+    /// Dispatches batches until no new events are left and returns
+    /// the number of dispatched events.
+    ///
+    /// # Error
+    /// The first store or dispatcher error is returned.
+    pub fn catch_up(&mut self) -> Result<usize, Error> {
+        let mut dispatched = 0;
+
+        loop {
+            let x = self.poll()?;
+
+            dispatched += x;
+
+            if x == 0 || x < self.batch_size {
+                return Ok(dispatched);
+            }
+        }
+    }
+
+    /// Catches up and then keeps polling for new events every poll
+    /// interval until `stop` is set.
+    ///
+    /// # Error
+    /// The first store or dispatcher error stops the subscription
+    /// and is returned.
+    pub fn run(
+        &mut self,
+        stop: &AtomicBool,
+    ) -> Result<(), Error> {
+        loop {
+            let x = self.catch_up()?;
+
+            if stop.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+
+            if x == 0 {
+                thread::sleep(self.poll_interval);
+            }
+        }
+    }
+}
@@ -1,6 +1,14 @@
-use std::sync::{
-    Arc,
-    RwLock,
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{
+            AtomicBool,
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+        RwLock,
+    },
 };
 
 use cqrs_es2::{
@@ -9,7 +17,14 @@ use cqrs_es2::{
     EventContext,
 };
 
-use crate::IEventDispatcher;
+use crate::{
+    memory_store::EventStore,
+    IEventDispatcher,
+    IEventStore,
+};
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
 
 pub struct CustomDispatcher {
     events: Arc<
@@ -31,7 +46,7 @@ impl IEventDispatcher<CustomerCommand, CustomerEvent>
     for CustomDispatcher
 {
     fn dispatch(
-        &mut self,
+        &self,
         _aggregate_id: &str,
         events: &Vec<EventContext<CustomerCommand, CustomerEvent>>,
     ) -> Result<(), Error> {
@@ -43,3 +58,102 @@ impl IEventDispatcher<CustomerCommand, CustomerEvent>
         Ok(())
     }
 }
+
+/// A dispatcher that fails the next `failures` dispatches and
+/// records the events of the successful ones
+pub struct FailingDispatcher {
+    events: Arc<
+        RwLock<Vec<EventContext<CustomerCommand, CustomerEvent>>>,
+    >,
+    failures: AtomicUsize,
+}
+
+impl FailingDispatcher {
+    pub fn new(
+        events: Arc<
+            RwLock<Vec<EventContext<CustomerCommand, CustomerEvent>>>,
+        >,
+        failures: usize,
+    ) -> Self {
+        Self {
+            events,
+            failures: AtomicUsize::new(failures),
+        }
+    }
+}
+
+impl IEventDispatcher<CustomerCommand, CustomerEvent>
+    for FailingDispatcher
+{
+    fn dispatch(
+        &self,
+        aggregate_id: &str,
+        events: &Vec<EventContext<CustomerCommand, CustomerEvent>>,
+    ) -> Result<(), Error> {
+        if self.failures.load(Ordering::SeqCst) > 0 {
+            self.failures.fetch_sub(1, Ordering::SeqCst);
+
+            return Err(Error::new(
+                format!(
+                    "dispatch failed for aggregate id '{}'",
+                    aggregate_id
+                )
+                .as_str(),
+            ));
+        }
+
+        let mut event_list = self.events.write().unwrap();
+        event_list.extend(events.iter().cloned());
+
+        Ok(())
+    }
+}
+
+/// A dispatcher that commits a new event for the aggregate of the
+/// first dispatch and raises `stop` once that event was dispatched
+/// as well
+pub struct TailingDispatcher {
+    store: ThisEventStore,
+    stop: Arc<AtomicBool>,
+    dispatches: AtomicUsize,
+}
+
+impl TailingDispatcher {
+    pub fn new(
+        store: ThisEventStore,
+        stop: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            store,
+            stop,
+            dispatches: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl IEventDispatcher<CustomerCommand, CustomerEvent>
+    for TailingDispatcher
+{
+    fn dispatch(
+        &self,
+        aggregate_id: &str,
+        events: &Vec<EventContext<CustomerCommand, CustomerEvent>>,
+    ) -> Result<(), Error> {
+        if self.dispatches.fetch_add(1, Ordering::SeqCst) > 0 {
+            self.stop.store(true, Ordering::SeqCst);
+            return Ok(());
+        }
+
+        self.store
+            .save_events(&vec![EventContext::new(
+                aggregate_id.to_string(),
+                events.last().unwrap().sequence + 1,
+                CustomerEvent::AddressUpdated(AddressUpdated {
+                    new_address: "tailed address".to_string(),
+                }),
+                HashMap::new(),
+            )])?;
+
+        Ok(())
+    }
+}
@@ -1,3 +1,20 @@
 mod dispatchers;
+mod stores;
 
+#[cfg(feature = "with-encryption")]
+mod test_encrypting_event_store;
+mod test_event_stream;
+mod test_event_upcasters;
+mod test_faults;
+mod test_outbox_relay;
+#[cfg(any(feature = "with-zstd", feature = "with-gzip"))]
+mod test_payload_compressors;
+#[cfg(feature = "with-encryption")]
+mod test_payload_encryption;
+mod test_payload_serializers;
+mod test_query_replay;
 mod test_repository;
+#[cfg(feature = "with-encryption")]
+mod test_shredding_event_store;
+mod test_snapshot_policies;
+mod test_subscription;
@@ -0,0 +1,609 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+        RwLock,
+    },
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    AggregateContext,
+    Error,
+    EventContext,
+    QueryContext,
+};
+
+use crate::{
+    errors::StoreError,
+    memory_store::{
+        EventStore,
+        QueryStore,
+    },
+    IEventDispatcher,
+    IEventStore,
+    IOutboxStore,
+    IQueryStore,
+    PositionedEvent,
+    StreamMetadata,
+    TimestampedEvent,
+};
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+type ThisQueryStore = QueryStore<
+    CustomerCommand,
+    CustomerEvent,
+    Customer,
+    CustomerContactQuery,
+>;
+
+/// A memory event store that lets a competing writer commit an
+/// event right before each of the next `races` commits, and
+/// tombstone the stream if created `with_tombstone()`
+pub struct RacingEventStore {
+    store: ThisEventStore,
+    races: AtomicUsize,
+    tombstone: bool,
+}
+
+impl RacingEventStore {
+    pub fn new(
+        store: ThisEventStore,
+        races: usize,
+    ) -> Self {
+        Self {
+            store,
+            races: AtomicUsize::new(races),
+            tombstone: false,
+        }
+    }
+
+    pub fn with_tombstone(mut self) -> Self {
+        self.tombstone = true;
+        self
+    }
+}
+
+impl IEventStore<CustomerCommand, CustomerEvent, Customer>
+    for RacingEventStore
+{
+    fn save_events(
+        &self,
+        contexts: &Vec<EventContext<CustomerCommand, CustomerEvent>>,
+    ) -> Result<(), StoreError> {
+        if self.races.load(Ordering::SeqCst) > 0 {
+            self.races.fetch_sub(1, Ordering::SeqCst);
+
+            let first = contexts.first().unwrap();
+
+            self.store
+                .save_events(&vec![EventContext::new(
+                    first.aggregate_id.clone(),
+                    first.sequence,
+                    CustomerEvent::AddressUpdated(AddressUpdated {
+                        new_address: "competing address".to_string(),
+                    }),
+                    HashMap::new(),
+                )])?;
+
+            if self.tombstone {
+                self.store
+                    .tombstone_stream(&first.aggregate_id)?;
+            }
+        }
+
+        self.store.save_events(contexts)
+    }
+
+    fn load_timestamped_events_range(
+        &self,
+        aggregate_id: &str,
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<
+        Vec<TimestampedEvent<CustomerCommand, CustomerEvent>>,
+        StoreError,
+    > {
+        self.store.load_timestamped_events_range(
+            aggregate_id,
+            from_sequence,
+            to_sequence,
+        )
+    }
+
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<
+        Vec<PositionedEvent<CustomerCommand, CustomerEvent>>,
+        StoreError,
+    > {
+        self.store.read_all(from_position, limit)
+    }
+
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        self.store
+            .list_aggregate_ids(after_aggregate_id, limit)
+    }
+
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        self.store.stream_metadata(aggregate_id)
+    }
+
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store.delete_stream(aggregate_id)
+    }
+
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store.tombstone_stream(aggregate_id)
+    }
+
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        self.store.is_tombstoned(aggregate_id)
+    }
+
+    fn save_aggregate_snapshot(
+        &self,
+        context: AggregateContext<
+            CustomerCommand,
+            CustomerEvent,
+            Customer,
+        >,
+    ) -> Result<(), StoreError> {
+        self.store
+            .save_aggregate_snapshot(context)
+    }
+
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store
+            .delete_aggregate_snapshot(aggregate_id)
+    }
+
+    fn load_aggregate_from_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<
+        AggregateContext<CustomerCommand, CustomerEvent, Customer>,
+        StoreError,
+    > {
+        self.store
+            .load_aggregate_from_snapshot(aggregate_id)
+    }
+}
+
+/// A memory event store that records the sequence ranges loaded
+/// through it
+pub struct PagingEventStore {
+    store: ThisEventStore,
+    pub ranges: RwLock<Vec<(i64, Option<i64>)>>,
+}
+
+impl PagingEventStore {
+    pub fn new(store: ThisEventStore) -> Self {
+        Self {
+            store,
+            ranges: Default::default(),
+        }
+    }
+}
+
+impl IEventStore<CustomerCommand, CustomerEvent, Customer>
+    for PagingEventStore
+{
+    fn save_events(
+        &self,
+        contexts: &Vec<EventContext<CustomerCommand, CustomerEvent>>,
+    ) -> Result<(), StoreError> {
+        self.store.save_events(contexts)
+    }
+
+    fn load_timestamped_events_range(
+        &self,
+        aggregate_id: &str,
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<
+        Vec<TimestampedEvent<CustomerCommand, CustomerEvent>>,
+        StoreError,
+    > {
+        self.ranges
+            .write()
+            .unwrap()
+            .push((from_sequence, to_sequence));
+
+        self.store.load_timestamped_events_range(
+            aggregate_id,
+            from_sequence,
+            to_sequence,
+        )
+    }
+
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<
+        Vec<PositionedEvent<CustomerCommand, CustomerEvent>>,
+        StoreError,
+    > {
+        self.store.read_all(from_position, limit)
+    }
+
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        self.store
+            .list_aggregate_ids(after_aggregate_id, limit)
+    }
+
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        self.store.stream_metadata(aggregate_id)
+    }
+
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store.delete_stream(aggregate_id)
+    }
+
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store.tombstone_stream(aggregate_id)
+    }
+
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        self.store.is_tombstoned(aggregate_id)
+    }
+
+    fn save_aggregate_snapshot(
+        &self,
+        context: AggregateContext<
+            CustomerCommand,
+            CustomerEvent,
+            Customer,
+        >,
+    ) -> Result<(), StoreError> {
+        self.store
+            .save_aggregate_snapshot(context)
+    }
+
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store
+            .delete_aggregate_snapshot(aggregate_id)
+    }
+
+    fn load_aggregate_from_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<
+        AggregateContext<CustomerCommand, CustomerEvent, Customer>,
+        StoreError,
+    > {
+        self.store
+            .load_aggregate_from_snapshot(aggregate_id)
+    }
+}
+
+type LockedEventContexts = Arc<
+    RwLock<Vec<EventContext<CustomerCommand, CustomerEvent>>>,
+>;
+
+/// A memory event store that keeps the committed events in a shared
+/// outbox until they are marked as delivered
+#[derive(Clone)]
+pub struct OutboxEventStore {
+    store: Arc<RwLock<ThisEventStore>>,
+    pending: LockedEventContexts,
+}
+
+impl OutboxEventStore {
+    pub fn new(store: ThisEventStore) -> Self {
+        Self {
+            store: Arc::new(RwLock::new(store)),
+            pending: Default::default(),
+        }
+    }
+}
+
+impl IEventStore<CustomerCommand, CustomerEvent, Customer>
+    for OutboxEventStore
+{
+    fn save_events(
+        &self,
+        contexts: &Vec<EventContext<CustomerCommand, CustomerEvent>>,
+    ) -> Result<(), StoreError> {
+        self.store
+            .write()
+            .unwrap()
+            .save_events(contexts)?;
+
+        self.pending
+            .write()
+            .unwrap()
+            .extend(contexts.iter().cloned());
+
+        Ok(())
+    }
+
+    fn load_timestamped_events_range(
+        &self,
+        aggregate_id: &str,
+        from_sequence: i64,
+        to_sequence: Option<i64>,
+    ) -> Result<
+        Vec<TimestampedEvent<CustomerCommand, CustomerEvent>>,
+        StoreError,
+    > {
+        self.store
+            .write()
+            .unwrap()
+            .load_timestamped_events_range(
+                aggregate_id,
+                from_sequence,
+                to_sequence,
+            )
+    }
+
+    fn read_all(
+        &self,
+        from_position: i64,
+        limit: usize,
+    ) -> Result<
+        Vec<PositionedEvent<CustomerCommand, CustomerEvent>>,
+        StoreError,
+    > {
+        self.store
+            .write()
+            .unwrap()
+            .read_all(from_position, limit)
+    }
+
+    fn list_aggregate_ids(
+        &self,
+        after_aggregate_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<String>, StoreError> {
+        self.store
+            .write()
+            .unwrap()
+            .list_aggregate_ids(after_aggregate_id, limit)
+    }
+
+    fn stream_metadata(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<Option<StreamMetadata>, StoreError> {
+        self.store
+            .write()
+            .unwrap()
+            .stream_metadata(aggregate_id)
+    }
+
+    fn delete_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store
+            .write()
+            .unwrap()
+            .delete_stream(aggregate_id)
+    }
+
+    fn tombstone_stream(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store
+            .write()
+            .unwrap()
+            .tombstone_stream(aggregate_id)
+    }
+
+    fn is_tombstoned(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<bool, StoreError> {
+        self.store
+            .write()
+            .unwrap()
+            .is_tombstoned(aggregate_id)
+    }
+
+    fn save_aggregate_snapshot(
+        &self,
+        context: AggregateContext<
+            CustomerCommand,
+            CustomerEvent,
+            Customer,
+        >,
+    ) -> Result<(), StoreError> {
+        self.store
+            .write()
+            .unwrap()
+            .save_aggregate_snapshot(context)
+    }
+
+    fn delete_aggregate_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store
+            .write()
+            .unwrap()
+            .delete_aggregate_snapshot(aggregate_id)
+    }
+
+    fn load_aggregate_from_snapshot(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<
+        AggregateContext<CustomerCommand, CustomerEvent, Customer>,
+        StoreError,
+    > {
+        self.store
+            .write()
+            .unwrap()
+            .load_aggregate_from_snapshot(aggregate_id)
+    }
+}
+
+impl IOutboxStore<CustomerCommand, CustomerEvent, Customer>
+    for OutboxEventStore
+{
+    fn load_pending_events(
+        &self,
+        limit: usize,
+    ) -> Result<
+        Vec<EventContext<CustomerCommand, CustomerEvent>>,
+        StoreError,
+    > {
+        Ok(self
+            .pending
+            .read()
+            .unwrap()
+            .iter()
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    fn mark_events_delivered(
+        &self,
+        contexts: &[EventContext<CustomerCommand, CustomerEvent>],
+    ) -> Result<(), StoreError> {
+        self.pending
+            .write()
+            .unwrap()
+            .retain(|x| !contexts.contains(x));
+
+        Ok(())
+    }
+}
+
+/// A memory query store that saves the next `successes` queries and
+/// fails all saves after them
+pub struct FailingQueryStore {
+    store: ThisQueryStore,
+    successes: AtomicUsize,
+}
+
+impl FailingQueryStore {
+    pub fn new(
+        store: ThisQueryStore,
+        successes: usize,
+    ) -> Self {
+        Self {
+            store,
+            successes: AtomicUsize::new(successes),
+        }
+    }
+}
+
+impl
+    IQueryStore<
+        CustomerCommand,
+        CustomerEvent,
+        Customer,
+        CustomerContactQuery,
+    > for FailingQueryStore
+{
+    fn save_query(
+        &self,
+        context: QueryContext<
+            CustomerCommand,
+            CustomerEvent,
+            CustomerContactQuery,
+        >,
+    ) -> Result<(), StoreError> {
+        if self.successes.load(Ordering::SeqCst) == 0 {
+            return Err(StoreError::Backend {
+                message: format!(
+                    "save failed for aggregate id '{}'",
+                    context.aggregate_id
+                ),
+                source: None,
+            });
+        }
+
+        self.successes.fetch_sub(1, Ordering::SeqCst);
+
+        self.store.save_query(context)
+    }
+
+    fn load_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<
+        QueryContext<
+            CustomerCommand,
+            CustomerEvent,
+            CustomerContactQuery,
+        >,
+        StoreError,
+    > {
+        self.store.load_query(aggregate_id)
+    }
+
+    fn delete_query(
+        &self,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.store.delete_query(aggregate_id)
+    }
+
+    fn delete_queries(&self) -> Result<(), StoreError> {
+        self.store.delete_queries()
+    }
+}
+
+impl IEventDispatcher<CustomerCommand, CustomerEvent>
+    for FailingQueryStore
+{
+    fn dispatch(
+        &self,
+        aggregate_id: &str,
+        events: &Vec<EventContext<CustomerCommand, CustomerEvent>>,
+    ) -> Result<(), Error> {
+        Ok(self.dispatch_events(aggregate_id, events)?)
+    }
+}
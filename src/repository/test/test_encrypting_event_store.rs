@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use cqrs_es2::{
+    example_impl::*,
+    AggregateContext,
+    EventContext,
+};
+
+use crate::{
+    memory_store::EventStore,
+    EncryptingEventStore,
+    IEventStore,
+    IOutboxStore,
+    KeyRing,
+    PayloadEncryption,
+};
+
+use super::stores::OutboxEventStore;
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+type ThisEncryptingEventStore = EncryptingEventStore<
+    CustomerCommand,
+    CustomerEvent,
+    Customer,
+    OutboxEventStore,
+>;
+
+fn get_contexts(
+    id: &str
+) -> Vec<EventContext<CustomerCommand, CustomerEvent>> {
+    vec![
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "John Doe".to_string(),
+            }),
+            HashMap::new(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "john.doe@example.com".to_string(),
+            }),
+            HashMap::new(),
+        ),
+    ]
+}
+
+fn get_encryption() -> PayloadEncryption {
+    PayloadEncryption::new(KeyRing::new("k1", &[1; 32]))
+        .with_field("/NameAdded/changed_name")
+}
+
+#[test]
+fn test_encrypt_fields() {
+    let inner = OutboxEventStore::new(ThisEventStore::default());
+
+    let store = ThisEncryptingEventStore::new(
+        inner.clone(),
+        get_encryption(),
+    );
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    store
+        .save_events(&get_contexts(&id))
+        .unwrap();
+
+    let mut contexts = get_contexts(&id);
+    contexts[0]
+        .metadata
+        .insert("encryption_key_id".to_string(), "k1".to_string());
+
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+    assert_eq!(store.load_pending_events(100).unwrap(), contexts);
+    assert_eq!(
+        store
+            .read_all(0, 100)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.context)
+            .collect::<Vec<_>>(),
+        contexts
+    );
+
+    let stored_contexts = inner.load_events(&id).unwrap();
+
+    match &stored_contexts[0].payload {
+        CustomerEvent::NameAdded(x) => {
+            assert!(x.changed_name.starts_with("encrypted:k1:"));
+            assert!(!x.changed_name.contains("John Doe"));
+        },
+        _ => panic!("unexpected event"),
+    }
+
+    // the fields of other events are left in clear
+    assert_eq!(stored_contexts[1], contexts[1]);
+
+    // the snapshots are not kept
+    store
+        .save_aggregate_snapshot(AggregateContext::new(
+            id.clone(),
+            2,
+            Customer {
+                customer_id: Default::default(),
+                name: "John Doe".to_string(),
+                email: "john.doe@example.com".to_string(),
+                addresses: Default::default(),
+            },
+        ))
+        .unwrap();
+
+    assert_eq!(
+        inner
+            .load_aggregate_from_snapshot(&id)
+            .unwrap()
+            .version,
+        0
+    );
+}
+
+#[test]
+fn test_reject_whole_payloads() {
+    let store = ThisEncryptingEventStore::new(
+        OutboxEventStore::new(ThisEventStore::default()),
+        PayloadEncryption::new(KeyRing::new("k1", &[1; 32])),
+    );
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    // whole payloads cannot be rebuilt into events
+    assert!(store
+        .save_events(&get_contexts(&id))
+        .is_err());
+    assert_eq!(store.load_events(&id).unwrap().len(), 0);
+}
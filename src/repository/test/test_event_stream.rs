@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use cqrs_es2::{
+    example_impl::*,
+    EventContext,
+};
+
+use crate::{
+    memory_store::EventStore,
+    IEventStore,
+};
+
+use super::stores::PagingEventStore;
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+fn commit_events(
+    store: &PagingEventStore,
+    count: i64,
+) -> Vec<EventContext<CustomerCommand, CustomerEvent>> {
+    let contexts: Vec<_> = (1..=count)
+        .map(|i| {
+            EventContext::new(
+                "test_id_A".to_string(),
+                i,
+                CustomerEvent::AddressUpdated(AddressUpdated {
+                    new_address: format!("address {}", i),
+                }),
+                HashMap::new(),
+            )
+        })
+        .collect();
+
+    store.save_events(&contexts).unwrap();
+
+    contexts
+}
+
+#[test]
+fn test_stream_events_in_pages() {
+    let store = PagingEventStore::new(ThisEventStore::default());
+
+    let contexts = commit_events(&store, 5);
+
+    let mut stream = store
+        .stream_events("test_id_A", 1)
+        .with_page_size(2);
+
+    // the second page is only loaded once the first one is consumed
+    assert_eq!(stream.next().unwrap().unwrap(), contexts[0]);
+    assert_eq!(stream.next().unwrap().unwrap(), contexts[1]);
+    assert_eq!(stream.next().unwrap().unwrap(), contexts[2]);
+
+    let rest: Vec<_> = stream.map(|x| x.unwrap()).collect();
+
+    assert_eq!(rest, contexts[3..].to_vec());
+
+    // the short page is the last one
+    assert_eq!(
+        *store.ranges.read().unwrap(),
+        vec![(1, Some(2)), (3, Some(4)), (5, Some(6))]
+    );
+}
+
+#[test]
+fn test_stream_events_range() {
+    let store = PagingEventStore::new(ThisEventStore::default());
+
+    let contexts = commit_events(&store, 5);
+
+    let streamed: Vec<_> = store
+        .stream_events("test_id_A", 2)
+        .with_page_size(2)
+        .with_to_sequence(4)
+        .map(|x| x.unwrap())
+        .collect();
+
+    assert_eq!(streamed, contexts[1..4].to_vec());
+
+    assert_eq!(
+        *store.ranges.read().unwrap(),
+        vec![(2, Some(3)), (4, Some(4))]
+    );
+
+    let streamed: Vec<_> = store
+        .stream_events("test_id_B", 1)
+        .map(|x| x.unwrap())
+        .collect();
+
+    assert_eq!(streamed, Vec::new());
+}
@@ -0,0 +1,155 @@
+use serde_json::{
+    json,
+    Value,
+};
+
+use cqrs_es2::example_impl::*;
+
+use crate::{
+    repository::event_type,
+    EventUpcasters,
+    IEventUpcaster,
+};
+
+/// Version 1 of `NameAdded` called the field `name`
+pub struct NameAddedV1;
+
+impl IEventUpcaster for NameAddedV1 {
+    fn event_type(&self) -> &str {
+        "NameAdded"
+    }
+
+    fn event_version(&self) -> i64 {
+        1
+    }
+
+    fn upcast(
+        &self,
+        payload: Value,
+    ) -> Value {
+        json!({
+            "NameAdded": {
+                "full_name": payload["NameAdded"]["name"],
+            }
+        })
+    }
+}
+
+/// Version 2 of `NameAdded` called the field `full_name`
+pub struct NameAddedV2;
+
+impl IEventUpcaster for NameAddedV2 {
+    fn event_type(&self) -> &str {
+        "NameAdded"
+    }
+
+    fn event_version(&self) -> i64 {
+        2
+    }
+
+    fn upcast(
+        &self,
+        payload: Value,
+    ) -> Value {
+        json!({
+            "NameAdded": {
+                "changed_name": payload["NameAdded"]["full_name"],
+            }
+        })
+    }
+}
+
+fn upcasters() -> EventUpcasters {
+    EventUpcasters::new()
+        .with_upcaster(NameAddedV2)
+        .with_upcaster(NameAddedV1)
+}
+
+#[test]
+fn test_event_version() {
+    let upcasters = upcasters();
+
+    assert_eq!(upcasters.event_version("NameAdded"), 3);
+    assert_eq!(upcasters.event_version("EmailUpdated"), 1);
+    assert_eq!(EventUpcasters::new().event_version("NameAdded"), 1);
+}
+
+#[test]
+fn test_upcast_all_versions() {
+    let upcasters = upcasters();
+
+    let v1 = json!({"NameAdded": {"name": "John Doe"}});
+    let v2 = json!({"NameAdded": {"full_name": "John Doe"}});
+    let v3 = json!({"NameAdded": {"changed_name": "John Doe"}});
+
+    assert_eq!(upcasters.upcast("NameAdded", v1.clone(), 1), v3);
+    assert_eq!(upcasters.upcast("NameAdded", v2, 2), v3);
+    assert_eq!(upcasters.upcast("NameAdded", v3.clone(), 3), v3);
+
+    let event: CustomerEvent =
+        upcasters.deserialize("NameAdded", v1, 1).unwrap();
+
+    assert_eq!(
+        event,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "John Doe".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_upcast_other_event_types() {
+    let upcasters = upcasters();
+
+    let payload =
+        json!({"EmailUpdated": {"new_email": "john@doe.com"}});
+
+    assert_eq!(
+        upcasters.upcast("EmailUpdated", payload.clone(), 1),
+        payload
+    );
+
+    let event: CustomerEvent = upcasters
+        .deserialize("EmailUpdated", payload, 1)
+        .unwrap();
+
+    assert_eq!(
+        event,
+        CustomerEvent::EmailUpdated(EmailUpdated {
+            new_email: "john@doe.com".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_upcast_untyped_events() {
+    let upcasters = upcasters();
+
+    // named after their enum variant
+    let payload = json!({"NameAdded": {"name": "John Doe"}});
+
+    let event: CustomerEvent =
+        upcasters.deserialize("", payload, 1).unwrap();
+
+    assert_eq!(
+        event,
+        CustomerEvent::NameAdded(NameAdded {
+            changed_name: "John Doe".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_default_event_type() {
+    let payload = serde_json::to_value(CustomerEvent::NameAdded(
+        NameAdded {
+            changed_name: "John Doe".to_string(),
+        },
+    ))
+    .unwrap();
+
+    assert_eq!(event_type(&payload), "NameAdded");
+    assert_eq!(event_type(&json!("Closed")), "Closed");
+    assert_eq!(event_type(&json!({"a": 1, "b": 2})), "");
+    assert_eq!(event_type(&json!(1)), "");
+}
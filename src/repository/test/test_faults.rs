@@ -0,0 +1,376 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    EventContext,
+    QueryContext,
+};
+
+use crate::{
+    memory_store::{
+        EventStore,
+        QueryStore,
+    },
+    Fault,
+    FaultCall,
+    FaultTrigger,
+    Faults,
+    FaultyDispatcher,
+    FaultyEventStore,
+    FaultyQueryStore,
+    IEventDispatcher,
+    IEventStore,
+    IQueryStore,
+    IntervalSnapshotPolicy,
+    Repository,
+    StoreOperation,
+};
+
+use super::dispatchers::CustomDispatcher;
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+type ThisQueryStore = QueryStore<
+    CustomerCommand,
+    CustomerEvent,
+    Customer,
+    CustomerContactQuery,
+>;
+
+fn get_events(
+    id: &str,
+    count: i64,
+) -> Vec<EventContext<CustomerCommand, CustomerEvent>> {
+    (1..=count)
+        .map(|x| {
+            EventContext::new(
+                id.to_string(),
+                x,
+                CustomerEvent::EmailUpdated(EmailUpdated {
+                    new_email: format!("email {}", x),
+                }),
+                HashMap::new(),
+            )
+        })
+        .collect()
+}
+
+fn stream_key(id: &str) -> (String, String) {
+    ("customer".to_string(), id.to_string())
+}
+
+fn add_address(address: &str) -> CustomerCommand {
+    CustomerCommand::AddAddress(AddAddress {
+        new_address: address.to_string(),
+    })
+}
+
+#[test]
+fn test_dispatch_fails_after_commit() {
+    let events = Default::default();
+    let dispatched_events = Default::default();
+
+    let faults = Faults::new().with_fault(
+        StoreOperation::Dispatch,
+        FaultTrigger::OnCall(1),
+        Fault::Failure,
+    );
+
+    let repo = Repository::new(
+        FaultyEventStore::new(
+            ThisEventStore::new(
+                Arc::clone(&events),
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ),
+            faults.clone(),
+        ),
+        vec![Box::new(FaultyDispatcher::new(
+            CustomDispatcher::new(Arc::clone(&dispatched_events)),
+            faults.clone(),
+        ))],
+        false,
+    );
+
+    let id = "test_id_A";
+
+    let err = repo
+        .execute(id, add_address("first address"))
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("injected failure of 'Dispatch'"));
+
+    // the events were committed but never dispatched
+    assert_eq!(events.read().unwrap()[&stream_key(id)].len(), 1);
+    assert!(dispatched_events.read().unwrap().is_empty());
+
+    repo.execute(id, add_address("second address"))
+        .unwrap();
+
+    assert_eq!(events.read().unwrap()[&stream_key(id)].len(), 2);
+    assert_eq!(dispatched_events.read().unwrap().len(), 1);
+
+    let operations: Vec<_> = faults
+        .calls()
+        .into_iter()
+        .map(|x| x.operation)
+        .collect();
+
+    assert_eq!(operations, vec![
+        StoreOperation::IsTombstoned,
+        StoreOperation::LoadEvents,
+        StoreOperation::SaveEvents,
+        StoreOperation::Dispatch,
+        StoreOperation::IsTombstoned,
+        StoreOperation::LoadEvents,
+        StoreOperation::SaveEvents,
+        StoreOperation::Dispatch,
+    ]);
+
+    assert_eq!(
+        faults.calls()[3],
+        FaultCall::new(StoreOperation::Dispatch, Some(id), vec![
+            Fault::Failure
+        ])
+    );
+}
+
+#[test]
+fn test_snapshot_fails_after_commit() {
+    let events = Default::default();
+    let snapshots = Default::default();
+
+    let faults = Faults::new().with_fault(
+        StoreOperation::SaveSnapshot,
+        FaultTrigger::Always,
+        Fault::Failure,
+    );
+
+    let repo = Repository::new(
+        FaultyEventStore::new(
+            ThisEventStore::new(
+                Arc::clone(&events),
+                Arc::clone(&snapshots),
+                Default::default(),
+                Default::default(),
+            ),
+            faults.clone(),
+        ),
+        Vec::new(),
+        true,
+    );
+
+    let id = "test_id_A";
+
+    // the failed snapshots are not reported to the caller
+    repo.execute(id, add_address("first address"))
+        .unwrap();
+    repo.execute(id, add_address("second address"))
+        .unwrap();
+
+    assert_eq!(events.read().unwrap()[&stream_key(id)].len(), 2);
+    assert!(snapshots.read().unwrap().is_empty());
+
+    let failures = faults
+        .calls()
+        .into_iter()
+        .filter(|x| !x.faults.is_empty())
+        .count();
+
+    assert_eq!(failures, 2);
+}
+
+#[test]
+fn test_snapshot_retried_after_failure() {
+    let snapshots = Default::default();
+
+    let faults = Faults::new().with_fault(
+        StoreOperation::SaveSnapshot,
+        FaultTrigger::FirstCalls(1),
+        Fault::Failure,
+    );
+
+    let repo = Repository::new(
+        FaultyEventStore::new(
+            ThisEventStore::new(
+                Default::default(),
+                Arc::clone(&snapshots),
+                Default::default(),
+                Default::default(),
+            ),
+            faults,
+        ),
+        Vec::new(),
+        false,
+    )
+    .with_snapshot_policy(Box::new(IntervalSnapshotPolicy::new(
+        Duration::from_secs(60),
+    )));
+
+    let id = "test_id_A";
+
+    repo.execute(id, add_address("first address"))
+        .unwrap();
+
+    assert!(snapshots.read().unwrap().is_empty());
+
+    // the failed snapshot does not wait for the next interval
+    repo.execute(id, add_address("second address"))
+        .unwrap();
+
+    assert_eq!(snapshots.read().unwrap()[&stream_key(id)].version, 2);
+}
+
+#[test]
+fn test_partial_writes() {
+    let events = Default::default();
+
+    let faults = Faults::new()
+        .with_fault(
+            StoreOperation::SaveEvents,
+            FaultTrigger::FirstCalls(1),
+            Fault::PartialWrite(2),
+        )
+        .with_fault(
+            StoreOperation::Dispatch,
+            FaultTrigger::Always,
+            Fault::PartialWrite(1),
+        );
+
+    let store = FaultyEventStore::new(
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        ),
+        faults.clone(),
+    );
+
+    let id = "test_id_A";
+    let contexts = get_events(id, 3);
+
+    assert!(store.save_events(&contexts).is_err());
+    assert_eq!(store.load_events(id).unwrap(), contexts[..2]);
+
+    let query_store = FaultyQueryStore::new(
+        ThisQueryStore::new(Default::default()),
+        faults.clone(),
+    );
+
+    assert!(query_store
+        .dispatch(id, &contexts)
+        .is_err());
+
+    let context = query_store.load_query(id).unwrap();
+
+    assert_eq!(context.version, 1);
+    assert_eq!(context.payload.email, "email 1");
+}
+
+#[test]
+fn test_triggers() {
+    let faults = Faults::new()
+        .with_fault(
+            StoreOperation::LoadQuery,
+            FaultTrigger::OnCall(2),
+            Fault::Failure,
+        )
+        .with_fault(
+            StoreOperation::DeleteQueries,
+            FaultTrigger::FirstCalls(2),
+            Fault::Failure,
+        );
+
+    let store = FaultyQueryStore::new(
+        ThisQueryStore::new(Default::default()),
+        faults.clone(),
+    );
+
+    let id = "test_id_A";
+
+    let results: Vec<_> = (0..3)
+        .map(|_| store.load_query(id).is_ok())
+        .collect();
+
+    assert_eq!(results, vec![true, false, true]);
+
+    let results: Vec<_> = (0..3)
+        .map(|_| store.delete_queries().is_ok())
+        .collect();
+
+    assert_eq!(results, vec![false, false, true]);
+
+    // the operations without faults reach the wrapped store
+    store
+        .save_query(QueryContext::new(
+            id.to_string(),
+            1,
+            Default::default(),
+        ))
+        .unwrap();
+
+    assert_eq!(faults.calls().len(), 7);
+
+    faults.clear_calls();
+
+    assert!(faults.calls().is_empty());
+}
+
+#[test]
+fn test_seeded_probabilities() {
+    let run = |seed: u64| {
+        let faults = Faults::new().with_seed(seed).with_fault(
+            StoreOperation::IsTombstoned,
+            FaultTrigger::Probability(0.3),
+            Fault::Failure,
+        );
+
+        let store =
+            FaultyEventStore::new(ThisEventStore::default(), faults);
+
+        (0..200)
+            .map(|_| store.is_tombstoned("test_id_A").is_ok())
+            .collect::<Vec<_>>()
+    };
+
+    let results = run(42);
+
+    assert_eq!(results, run(42));
+    assert_ne!(results, run(43));
+
+    let failures = results.iter().filter(|x| !**x).count();
+
+    assert!(failures > 30 && failures < 90, "{} failures", failures);
+}
+
+#[test]
+fn test_latency() {
+    let faults = Faults::new().with_fault(
+        StoreOperation::LoadSnapshot,
+        FaultTrigger::Always,
+        Fault::Latency(Duration::from_millis(20)),
+    );
+
+    let store =
+        FaultyEventStore::new(ThisEventStore::default(), faults);
+
+    let start = Instant::now();
+
+    store
+        .load_aggregate_from_snapshot("test_id_A")
+        .unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(20));
+}
@@ -0,0 +1,151 @@
+use std::sync::{
+    Arc,
+    RwLock,
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    EventContext,
+};
+
+use crate::{
+    memory_store::EventStore,
+    IOutboxStore,
+    OutboxRelay,
+    Repository,
+};
+
+use super::{
+    dispatchers::{
+        CustomDispatcher,
+        FailingDispatcher,
+    },
+    stores::OutboxEventStore,
+};
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+type DispatchedEvents = Arc<
+    RwLock<Vec<EventContext<CustomerCommand, CustomerEvent>>>,
+>;
+
+fn commit_events(outbox: &OutboxEventStore) -> Vec<String> {
+    let repo =
+        Repository::new(outbox.clone(), Vec::new(), false);
+
+    let ids = vec![
+        uuid::Uuid::new_v4().to_string(),
+        uuid::Uuid::new_v4().to_string(),
+    ];
+
+    for id in &ids {
+        repo.execute(
+            id,
+            CustomerCommand::AddCustomerName(AddCustomerName {
+                changed_name: "John Doe".to_string(),
+            }),
+        )
+        .unwrap();
+
+        repo.execute(
+            id,
+            CustomerCommand::UpdateEmail(UpdateEmail {
+                new_email: "john.doe@example.com".to_string(),
+            }),
+        )
+        .unwrap();
+    }
+
+    ids
+}
+
+#[test]
+fn test_relay_pending_events() {
+    let outbox = OutboxEventStore::new(ThisEventStore::default());
+
+    let ids = commit_events(&outbox);
+
+    let dispatched_events: DispatchedEvents = Default::default();
+
+    let mut relay = OutboxRelay::new(
+        outbox.clone(),
+        vec![Box::new(CustomDispatcher::new(Arc::clone(
+            &dispatched_events,
+        )))],
+    )
+    .with_batch_size(3);
+
+    assert_eq!(relay.relay().unwrap(), 3);
+    assert_eq!(relay.relay_all().unwrap(), 1);
+    assert_eq!(relay.relay_all().unwrap(), 0);
+
+    let dispatched_events = dispatched_events.read().unwrap();
+
+    assert_eq!(dispatched_events.len(), 4);
+
+    for (i, x) in dispatched_events.iter().enumerate() {
+        assert_eq!(x.aggregate_id, ids[i / 2]);
+        assert_eq!(x.sequence, (i % 2 + 1) as i64);
+    }
+
+    assert_eq!(outbox.load_pending_events(10).unwrap().len(), 0);
+}
+
+#[test]
+fn test_relay_with_zero_batch_size() {
+    let outbox = OutboxEventStore::new(ThisEventStore::default());
+
+    commit_events(&outbox);
+
+    let dispatched_events: DispatchedEvents = Default::default();
+
+    // a batch size of zero delivers one event per batch
+    let mut relay = OutboxRelay::new(
+        outbox.clone(),
+        vec![Box::new(CustomDispatcher::new(Arc::clone(
+            &dispatched_events,
+        )))],
+    )
+    .with_batch_size(0);
+
+    assert_eq!(relay.relay().unwrap(), 1);
+    assert_eq!(relay.relay_all().unwrap(), 3);
+    assert_eq!(dispatched_events.read().unwrap().len(), 4);
+}
+
+#[test]
+fn test_relay_failed_dispatch() {
+    let outbox = OutboxEventStore::new(ThisEventStore::default());
+
+    commit_events(&outbox);
+
+    let dispatched_events: DispatchedEvents = Default::default();
+    let failed_events: DispatchedEvents = Default::default();
+
+    let mut relay = OutboxRelay::new(
+        outbox.clone(),
+        vec![
+            Box::new(CustomDispatcher::new(Arc::clone(
+                &dispatched_events,
+            ))),
+            Box::new(FailingDispatcher::new(
+                Arc::clone(&failed_events),
+                1,
+            )),
+        ],
+    );
+
+    relay.relay_all().unwrap_err();
+
+    assert_eq!(dispatched_events.read().unwrap().len(), 2);
+    assert_eq!(failed_events.read().unwrap().len(), 0);
+    assert_eq!(outbox.load_pending_events(10).unwrap().len(), 4);
+
+    assert_eq!(relay.relay_all().unwrap(), 4);
+
+    // the first dispatcher receives the failed events again
+    assert_eq!(dispatched_events.read().unwrap().len(), 6);
+    assert_eq!(failed_events.read().unwrap().len(), 4);
+    assert_eq!(outbox.load_pending_events(10).unwrap().len(), 0);
+}
@@ -0,0 +1,147 @@
+use serde_json::{
+    json,
+    Value,
+};
+
+use crate::{
+    repository::StoreSerializer,
+    IPayloadCompressor,
+};
+
+fn get_value(len: usize) -> Value {
+    json!({
+        "NameAdded": {
+            "changed_name": "a".repeat(len),
+        }
+    })
+}
+
+fn check_round_trip(compressor: impl IPayloadCompressor) {
+    let bytes = get_value(1000).to_string().into_bytes();
+
+    let compressed = compressor.compress(&bytes).unwrap();
+
+    assert!(compressed.len() < bytes.len());
+    assert_eq!(compressor.decompress(&compressed).unwrap(), bytes);
+}
+
+fn check_store_serializer(
+    compressor: impl IPayloadCompressor + Send + Sync + 'static
+) {
+    let json = StoreSerializer::default();
+    let mut compressed = StoreSerializer::default();
+
+    compressed.set_compression(compressor, 100);
+
+    // the values below the threshold stay JSON
+    let value = get_value(10);
+
+    assert_eq!(
+        compressed.to_json(&value).unwrap(),
+        (Some(value.clone()), None)
+    );
+
+    let value = get_value(1000);
+
+    let (text, bytes) = compressed.to_json(&value).unwrap();
+
+    assert_eq!(text, None);
+    assert!(bytes.as_ref().unwrap().len() < 100);
+    assert_eq!(
+        compressed
+            .join_json(None, bytes.clone())
+            .unwrap(),
+        value
+    );
+
+    // the compressed values load without compression
+    assert_eq!(json.join_json(None, bytes).unwrap(), value);
+
+    let (text, bytes) = compressed.to_text(&value).unwrap();
+
+    assert_eq!(text, "");
+    assert_eq!(compressed.join_text(text, bytes).unwrap(), value);
+
+    // the uncompressed values still load
+    let (text, bytes) = json.to_text(&value).unwrap();
+
+    assert_eq!(compressed.join_text(text, bytes).unwrap(), value);
+
+    let entry = json.to_entry(&value).unwrap();
+
+    assert_eq!(compressed.join_entry(&entry).unwrap(), value);
+
+    let entry = compressed.to_entry(&value).unwrap();
+
+    assert_ne!(entry.first(), Some(&b'{'));
+    assert_eq!(compressed.join_entry(&entry).unwrap(), value);
+    assert_eq!(json.join_entry(&entry).unwrap(), value);
+}
+
+#[cfg(feature = "with-zstd")]
+#[test]
+fn test_zstd_compressor() {
+    check_round_trip(crate::ZstdCompressor::default());
+    check_store_serializer(crate::ZstdCompressor::default());
+}
+
+#[cfg(feature = "with-gzip")]
+#[test]
+fn test_gzip_compressor() {
+    check_round_trip(crate::GzipCompressor::default());
+    check_store_serializer(crate::GzipCompressor::default());
+}
+
+#[cfg(all(feature = "with-zstd", feature = "with-msgpack"))]
+#[test]
+fn test_compressed_binary_format() {
+    let mut serializer = StoreSerializer::default();
+
+    serializer.set_serializer(crate::MsgPackSerializer);
+    serializer.set_compression(crate::ZstdCompressor::default(), 100);
+
+    let value = get_value(1000);
+
+    let (_, bytes) = serializer.to_json(&value).unwrap();
+
+    assert!(bytes.as_ref().unwrap().len() < 100);
+    assert_eq!(serializer.join_json(None, bytes).unwrap(), value);
+
+    // the values below the threshold keep the binary format
+    let value = get_value(10);
+
+    let (_, bytes) = serializer.to_json(&value).unwrap();
+
+    assert_eq!(
+        bytes,
+        Some(rmp_serde::to_vec(&value).unwrap())
+    );
+}
+
+#[cfg(all(feature = "with-zstd", feature = "with-gzip"))]
+#[test]
+fn test_switch_codec() {
+    let mut gzip = StoreSerializer::default();
+    let mut zstd = StoreSerializer::default();
+
+    gzip.set_compression(crate::GzipCompressor::default(), 100);
+    zstd.set_compression(crate::ZstdCompressor::default(), 100);
+
+    let value = get_value(1000);
+
+    let (_, bytes) = gzip.to_json(&value).unwrap();
+
+    assert_eq!(zstd.join_json(None, bytes).unwrap(), value);
+}
+
+#[test]
+fn test_unknown_codec() {
+    let serializer = StoreSerializer::default();
+
+    let mut bytes = vec![0, b'Z', 200, b'j'];
+    bytes.extend_from_slice(b"not compressed");
+
+    assert!(serializer
+        .join_json(None, Some(bytes))
+        .is_err());
+}
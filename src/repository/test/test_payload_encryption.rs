@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+
+use serde_json::{
+    json,
+    Value,
+};
+
+use crate::{
+    repository::StoreSerializer,
+    EncryptionScope,
+    KeyRing,
+    PayloadEncryption,
+};
+
+fn get_value() -> Value {
+    json!({
+        "NameAdded": {
+            "changed_name": "John Doe",
+            "count": 3,
+            "nickname": null,
+        }
+    })
+}
+
+fn get_scope(sequence: i64) -> EncryptionScope<'static> {
+    EncryptionScope::Event {
+        aggregate_type: "Customer",
+        aggregate_id: "c-1",
+        sequence,
+    }
+}
+
+fn get_serializer(encryption: PayloadEncryption) -> StoreSerializer {
+    let mut serializer = StoreSerializer::default();
+
+    serializer.set_encryption(encryption);
+    serializer
+}
+
+fn is_encrypted(
+    value: &Value,
+    key_id: &str,
+) -> bool {
+    value
+        .as_str()
+        .unwrap_or_default()
+        .starts_with(format!("encrypted:{}:", key_id).as_str())
+}
+
+#[test]
+fn test_encrypt_whole_payloads() {
+    let serializer = get_serializer(PayloadEncryption::new(
+        KeyRing::new("k1", &[1; 32]),
+    ));
+
+    let value = get_value();
+
+    let (payload, metadata) = serializer
+        .encrypt_event(&get_scope(1), value.clone(), &HashMap::new())
+        .unwrap();
+
+    assert!(is_encrypted(&payload, "k1"));
+    assert_eq!(metadata["encryption_key_id"], "k1");
+
+    let (json, bytes) = serializer.split_json(payload).unwrap();
+    let payload = serializer.join_json(json, bytes).unwrap();
+
+    assert_eq!(
+        serializer
+            .decrypt(&get_scope(1), payload)
+            .unwrap(),
+        value
+    );
+
+    // the values stored in clear keep loading
+    assert_eq!(
+        serializer
+            .decrypt(&get_scope(1), value.clone())
+            .unwrap(),
+        value
+    );
+}
+
+#[test]
+fn test_encrypt_fields() {
+    let serializer = get_serializer(
+        PayloadEncryption::new(KeyRing::new("k1", &[1; 32]))
+            .with_field("/NameAdded/changed_name")
+            .with_field("/NameAdded/count")
+            .with_field("/NameAdded/nickname")
+            .with_field("/EmailUpdated/new_email"),
+    );
+
+    let value = get_value();
+
+    let (payload, metadata) = serializer
+        .encrypt_event(&get_scope(1), value.clone(), &HashMap::new())
+        .unwrap();
+
+    let fields = &payload["NameAdded"];
+
+    assert!(is_encrypted(&fields["changed_name"], "k1"));
+    assert!(is_encrypted(&fields["count"], "k1"));
+    assert_eq!(fields["nickname"], Value::Null);
+    assert_eq!(metadata["encryption_key_id"], "k1");
+
+    let (text, bytes) = serializer.split_text(payload).unwrap();
+    let payload = serializer.join_text(text, bytes).unwrap();
+
+    assert_eq!(
+        serializer
+            .decrypt(&get_scope(1), payload)
+            .unwrap(),
+        value
+    );
+
+    // the payloads without any of the fields are left untouched
+    let value = json!({"AddressUpdated": {"new_address": "here"}});
+
+    let (payload, metadata) = serializer
+        .encrypt_event(&get_scope(2), value.clone(), &HashMap::new())
+        .unwrap();
+
+    assert_eq!(payload, value);
+    assert!(metadata.is_empty());
+
+    // the snapshots are encrypted whole
+    let scope = EncryptionScope::Snapshot {
+        aggregate_type: "Customer",
+        aggregate_id: "c-1",
+    };
+
+    let payload = serializer
+        .encrypt(&scope, &value)
+        .unwrap();
+
+    assert!(is_encrypted(&payload, "k1"));
+    assert_eq!(serializer.decrypt(&scope, payload).unwrap(), value);
+}
+
+#[test]
+fn test_decrypt_only_fields() {
+    let encryption =
+        PayloadEncryption::new(KeyRing::new("k1", &[1; 32]))
+            .with_field("/NameAdded/changed_name");
+
+    let (sealed, _) = encryption
+        .encrypt(&get_scope(1), get_value())
+        .unwrap();
+
+    let sealed = sealed["NameAdded"]["changed_name"].clone();
+
+    // the encrypted looking strings outside of the fields are data
+    let value = json!({
+        "NameAdded": {
+            "changed_name": "John Doe",
+            "nickname": sealed,
+        }
+    });
+
+    assert_eq!(
+        encryption
+            .decrypt(&get_scope(1), value.clone())
+            .unwrap(),
+        value
+    );
+}
+
+#[test]
+fn test_bind_ciphertexts() {
+    let encryption =
+        PayloadEncryption::new(KeyRing::new("k1", &[1; 32]))
+            .with_field("/NameAdded/changed_name")
+            .with_field("/NameAdded/nickname");
+
+    let value = json!({
+        "NameAdded": {
+            "changed_name": "John Doe",
+            "nickname": "Johnny",
+        }
+    });
+
+    let (payload, _) = encryption
+        .encrypt(&get_scope(1), value.clone())
+        .unwrap();
+
+    assert_eq!(
+        encryption
+            .decrypt(&get_scope(1), payload.clone())
+            .unwrap(),
+        value
+    );
+
+    // copied to another event
+    assert!(encryption
+        .decrypt(&get_scope(2), payload.clone())
+        .is_err());
+
+    let other = EncryptionScope::Event {
+        aggregate_type: "Customer",
+        aggregate_id: "c-2",
+        sequence: 1,
+    };
+
+    assert!(encryption
+        .decrypt(&other, payload.clone())
+        .is_err());
+
+    // copied to another field
+    let mut swapped = payload.clone();
+    swapped["NameAdded"]["nickname"] =
+        payload["NameAdded"]["changed_name"].clone();
+
+    assert!(encryption
+        .decrypt(&get_scope(1), swapped)
+        .is_err());
+}
+
+#[test]
+fn test_rotate_keys() {
+    let keys = KeyRing::new("k1", &[1; 32]);
+
+    let serializer =
+        get_serializer(PayloadEncryption::new(keys.clone()));
+
+    let value = get_value();
+
+    let (old_payload, _) = serializer
+        .encrypt_event(&get_scope(1), value.clone(), &HashMap::new())
+        .unwrap();
+
+    keys.rotate("k2", &[2; 32]);
+
+    let (payload, metadata) = serializer
+        .encrypt_event(&get_scope(1), value.clone(), &HashMap::new())
+        .unwrap();
+
+    assert!(is_encrypted(&payload, "k2"));
+    assert_eq!(metadata["encryption_key_id"], "k2");
+
+    for x in [old_payload.clone(), payload] {
+        assert_eq!(
+            serializer
+                .decrypt(&get_scope(1), x)
+                .unwrap(),
+            value
+        );
+    }
+
+    // the values encrypted with unknown keys do not load
+    let serializer = get_serializer(PayloadEncryption::new(
+        KeyRing::new("k2", &[2; 32]),
+    ));
+
+    assert!(serializer
+        .decrypt(&get_scope(1), old_payload.clone())
+        .is_err());
+
+    // the key id is bound to the ciphertext
+    let serializer = get_serializer(PayloadEncryption::new(
+        KeyRing::new("k2", &[1; 32]),
+    ));
+
+    let tampered = old_payload
+        .as_str()
+        .unwrap()
+        .replace("encrypted:k1:", "encrypted:k2:");
+
+    assert!(serializer
+        .decrypt(&get_scope(1), Value::String(tampered))
+        .is_err());
+}
+
+#[test]
+fn test_bad_keys() {
+    let serializer = get_serializer(PayloadEncryption::new(
+        KeyRing::new("k1", &[1; 16]),
+    ));
+
+    assert!(serializer
+        .encrypt_event(&get_scope(1), get_value(), &HashMap::new())
+        .is_err());
+}
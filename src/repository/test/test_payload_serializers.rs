@@ -0,0 +1,121 @@
+use serde_json::{
+    json,
+    Value,
+};
+
+use crate::{
+    repository::StoreSerializer,
+    IPayloadSerializer,
+    JsonSerializer,
+};
+
+fn get_value() -> Value {
+    json!({
+        "NameAdded": {
+            "changed_name": "John Doe",
+            "count": 3,
+            "balance": -12,
+            "ratio": 0.5,
+            "active": true,
+            "nickname": null,
+            "tags": ["a", "b"],
+        }
+    })
+}
+
+fn check_round_trip(serializer: impl IPayloadSerializer) {
+    let value = get_value();
+
+    let bytes = serializer.serialize(&value).unwrap();
+
+    assert_eq!(serializer.deserialize(&bytes).unwrap(), value);
+}
+
+#[cfg(any(
+    feature = "with-msgpack",
+    feature = "with-cbor",
+    feature = "with-bincode"
+))]
+fn check_store_serializer(
+    serializer: impl IPayloadSerializer + Send + Sync + 'static
+) {
+    let value = get_value();
+
+    let json = StoreSerializer::default();
+    let binary = StoreSerializer::new(serializer);
+
+    let (text, bytes) = binary.to_json(&value).unwrap();
+
+    assert_eq!(text, None);
+    assert_eq!(
+        binary
+            .join_json(None, bytes.clone())
+            .unwrap(),
+        value
+    );
+
+    let (text, bytes) = binary.to_text(&value).unwrap();
+
+    assert_eq!(text, "");
+    assert_eq!(binary.join_text(text, bytes).unwrap(), value);
+
+    // the values written as JSON still load
+    let (text, bytes) = json.to_text(&value).unwrap();
+
+    assert_eq!(bytes, None);
+    assert_eq!(binary.join_text(text, bytes).unwrap(), value);
+
+    let entry = json.to_entry(&value).unwrap();
+
+    assert_eq!(binary.join_entry(&entry).unwrap(), value);
+
+    let entry = binary.to_entry(&value).unwrap();
+
+    assert_eq!(binary.join_entry(&entry).unwrap(), value);
+}
+
+#[test]
+fn test_json_serializer() {
+    check_round_trip(JsonSerializer);
+
+    let value = get_value();
+
+    let serializer = StoreSerializer::default();
+
+    assert_eq!(
+        serializer.to_json(&value).unwrap(),
+        (Some(value.clone()), None)
+    );
+    assert_eq!(
+        serializer.to_text(&value).unwrap(),
+        (value.to_string(), None)
+    );
+    assert_eq!(
+        serializer
+            .to_entry(&value)
+            .unwrap(),
+        value.to_string().into_bytes()
+    );
+    assert!(serializer.join_json(None, None).is_err());
+}
+
+#[cfg(feature = "with-msgpack")]
+#[test]
+fn test_msgpack_serializer() {
+    check_round_trip(crate::MsgPackSerializer);
+    check_store_serializer(crate::MsgPackSerializer);
+}
+
+#[cfg(feature = "with-cbor")]
+#[test]
+fn test_cbor_serializer() {
+    check_round_trip(crate::CborSerializer);
+    check_store_serializer(crate::CborSerializer);
+}
+
+#[cfg(feature = "with-bincode")]
+#[test]
+fn test_bincode_serializer() {
+    check_round_trip(crate::BincodeSerializer);
+    check_store_serializer(crate::BincodeSerializer);
+}
@@ -0,0 +1,247 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::Arc,
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    QueryContext,
+};
+
+use crate::{
+    memory_store::{
+        CheckpointStore,
+        EventStore,
+        QueryStore,
+    },
+    IQueryStore,
+    QueryReplay,
+    ReplayProgress,
+    Repository,
+};
+
+use super::stores::FailingQueryStore;
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+type ThisQueryStore = QueryStore<
+    CustomerCommand,
+    CustomerEvent,
+    Customer,
+    CustomerContactQuery,
+>;
+
+fn commit_events(
+    store: ThisEventStore,
+    ids: &[&str],
+) {
+    let repo = Repository::new(store, Vec::new(), false);
+
+    for id in ids {
+        repo.execute(
+            id,
+            CustomerCommand::AddCustomerName(AddCustomerName {
+                changed_name: format!("{} name", id),
+            }),
+        )
+        .unwrap();
+
+        repo.execute(
+            id,
+            CustomerCommand::UpdateEmail(UpdateEmail {
+                new_email: format!("{}@example.com", id),
+            }),
+        )
+        .unwrap();
+    }
+}
+
+fn check_query(
+    store: &ThisQueryStore,
+    id: &str,
+) {
+    assert_eq!(
+        store.load_query(id).unwrap().payload,
+        CustomerContactQuery {
+            name: format!("{} name", id),
+            email: format!("{}@example.com", id),
+            latest_address: "".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_replay_queries() {
+    let events = Arc::default();
+    let positions = Arc::default();
+    let snapshots = Arc::default();
+    let queries = Arc::default();
+
+    let store = || {
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Arc::clone(&snapshots),
+            Default::default(),
+            Arc::clone(&positions),
+        )
+    };
+
+    commit_events(store(), &["test_id_A", "test_id_B"]);
+
+    let query_store = ThisQueryStore::new(Arc::clone(&queries));
+
+    // a query corrupted by a faulty update
+    query_store
+        .save_query(QueryContext::new(
+            "test_id_C".to_string(),
+            1,
+            Default::default(),
+        ))
+        .unwrap();
+
+    let reported: Rc<RefCell<Vec<ReplayProgress>>> =
+        Default::default();
+
+    let mut replay = QueryReplay::new(
+        "test_replay",
+        store(),
+        ThisQueryStore::new(Arc::clone(&queries)),
+        CheckpointStore::default(),
+    )
+    .with_batch_size(3)
+    .with_progress({
+        let reported = Rc::clone(&reported);
+        move |x| reported.borrow_mut().push(x.clone())
+    });
+
+    assert_eq!(
+        replay.replay().unwrap(),
+        ReplayProgress::new(4, 4)
+    );
+
+    assert_eq!(
+        *reported.borrow(),
+        vec![
+            ReplayProgress::new(3, 3),
+            ReplayProgress::new(4, 4),
+        ]
+    );
+
+    check_query(&query_store, "test_id_A");
+    check_query(&query_store, "test_id_B");
+
+    assert_eq!(
+        query_store
+            .load_query("test_id_C")
+            .unwrap()
+            .version,
+        0
+    );
+
+    // a completed replay only applies the new events
+    commit_events(store(), &["test_id_C"]);
+
+    assert_eq!(
+        replay.replay().unwrap(),
+        ReplayProgress::new(6, 2)
+    );
+
+    check_query(&query_store, "test_id_A");
+    check_query(&query_store, "test_id_C");
+
+    // a reset replay starts over
+    replay.reset().unwrap();
+
+    assert_eq!(
+        replay.replay().unwrap(),
+        ReplayProgress::new(6, 6)
+    );
+
+    check_query(&query_store, "test_id_C");
+}
+
+#[test]
+fn test_replay_with_zero_batch_size() {
+    let events = Arc::default();
+    let positions = Arc::default();
+    let queries = Arc::default();
+
+    let store = || {
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Default::default(),
+            Default::default(),
+            Arc::clone(&positions),
+        )
+    };
+
+    commit_events(store(), &["test_id_A"]);
+
+    // a batch size of zero reads one event per batch
+    let mut replay = QueryReplay::new(
+        "test_replay",
+        store(),
+        ThisQueryStore::new(Arc::clone(&queries)),
+        CheckpointStore::default(),
+    )
+    .with_batch_size(0);
+
+    assert_eq!(
+        replay.replay().unwrap(),
+        ReplayProgress::new(2, 2)
+    );
+
+    check_query(&ThisQueryStore::new(queries), "test_id_A");
+}
+
+#[test]
+fn test_resume_interrupted_replay() {
+    let events = Arc::default();
+    let positions = Arc::default();
+    let snapshots = Arc::default();
+    let queries = Arc::default();
+    let checkpoints = Arc::default();
+
+    let store = || {
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Arc::clone(&snapshots),
+            Default::default(),
+            Arc::clone(&positions),
+        )
+    };
+
+    commit_events(store(), &["test_id_A", "test_id_B"]);
+
+    let mut replay = QueryReplay::new(
+        "test_replay",
+        store(),
+        FailingQueryStore::new(
+            ThisQueryStore::new(Arc::clone(&queries)),
+            1,
+        ),
+        CheckpointStore::new(Arc::clone(&checkpoints)),
+    );
+
+    replay.replay().unwrap_err();
+
+    let mut replay = QueryReplay::new(
+        "test_replay",
+        store(),
+        ThisQueryStore::new(Arc::clone(&queries)),
+        CheckpointStore::new(Arc::clone(&checkpoints)),
+    );
+
+    // the queries of the first aggregate are kept
+    assert_eq!(
+        replay.replay().unwrap(),
+        ReplayProgress::new(4, 2)
+    );
+
+    let query_store = ThisQueryStore::new(Arc::clone(&queries));
+
+    check_query(&query_store, "test_id_A");
+    check_query(&query_store, "test_id_B");
+}
@@ -1,6 +1,11 @@
 use std::{
     collections::HashMap,
     sync::Arc,
+    thread,
+    time::{
+        Duration,
+        SystemTime,
+    },
 };
 
 use cqrs_es2::{
@@ -16,10 +21,16 @@ use crate::{
         EventStore,
         QueryStore,
     },
+    ConflictError,
+    EventCountSnapshotPolicy,
     Repository,
+    TombstonedError,
 };
 
-use super::dispatchers::CustomDispatcher;
+use super::{
+    dispatchers::CustomDispatcher,
+    stores::RacingEventStore,
+};
 
 type ThisEventStore =
     EventStore<CustomerCommand, CustomerEvent, Customer>;
@@ -31,6 +42,18 @@ type ThisQueryStore = QueryStore<
     CustomerContactQuery,
 >;
 
+fn stream_key(id: &str) -> (String, String) {
+    ("customer".to_string(), id.to_string())
+}
+
+fn query_key(id: &str) -> (String, String, String) {
+    (
+        "customer".to_string(),
+        id.to_string(),
+        "customer_contact_query".to_string(),
+    )
+}
+
 fn get_metadata() -> HashMap<String, String> {
     let now = "2021-03-18T12:32:45.930Z".to_string();
     let mut metadata = HashMap::new();
@@ -47,12 +70,14 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
     let event_store = ThisEventStore::new(
         Arc::clone(&events),
         Arc::clone(&snapshots),
+        Default::default(),
+        Default::default(),
     );
     let query_store = ThisQueryStore::new(Arc::clone(&queries));
     let custom_dispatcher =
         CustomDispatcher::new(Arc::clone(&dispatched_events));
 
-    let mut repo = Repository::new(
+    let repo = Repository::new(
         event_store,
         vec![
             Box::new(query_store),
@@ -88,9 +113,11 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
         events
             .read()
             .unwrap()
-            .get(&id)
+            .get(&stream_key(&id))
             .unwrap()
-            .clone(),
+            .iter()
+            .map(|x| x.context.clone())
+            .collect::<Vec<_>>(),
         events_context_0.clone()
     );
 
@@ -99,7 +126,7 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
             snapshots
                 .read()
                 .unwrap()
-                .get(&id)
+                .get(&stream_key(&id))
                 .unwrap()
                 .clone(),
             AggregateContext::new(
@@ -119,7 +146,7 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
         queries
             .read()
             .unwrap()
-            .get(&id)
+            .get(&query_key(&id))
             .unwrap()
             .clone(),
         QueryContext::new(
@@ -186,9 +213,11 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
         events
             .read()
             .unwrap()
-            .get(&id)
+            .get(&stream_key(&id))
             .unwrap()
-            .clone(),
+            .iter()
+            .map(|x| x.context.clone())
+            .collect::<Vec<_>>(),
         events_context_0.clone()
     );
 
@@ -197,7 +226,7 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
             snapshots
                 .read()
                 .unwrap()
-                .get(&id)
+                .get(&stream_key(&id))
                 .unwrap()
                 .clone(),
             AggregateContext::new(
@@ -217,7 +246,7 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
         queries
             .read()
             .unwrap()
-            .get(&id)
+            .get(&query_key(&id))
             .unwrap()
             .clone(),
         QueryContext::new(
@@ -258,9 +287,11 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
         events
             .read()
             .unwrap()
-            .get(&id)
+            .get(&stream_key(&id))
             .unwrap()
-            .clone(),
+            .iter()
+            .map(|x| x.context.clone())
+            .collect::<Vec<_>>(),
         events_context_0.clone()
     );
 
@@ -269,7 +300,7 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
             snapshots
                 .read()
                 .unwrap()
-                .get(&id)
+                .get(&stream_key(&id))
                 .unwrap()
                 .clone(),
             AggregateContext::new(
@@ -289,7 +320,7 @@ fn check_execute(with_snapshots: bool) -> Result<(), Error> {
         queries
             .read()
             .unwrap()
-            .get(&id)
+            .get(&query_key(&id))
             .unwrap()
             .clone(),
         QueryContext::new(
@@ -323,3 +354,528 @@ fn test_execute_no_snapshots() {
 fn test_execute_with_snapshots() {
     check_execute(true).unwrap();
 }
+
+fn check_execute_with_conflicts(
+    races: usize,
+    retries: usize,
+) -> Result<(), Error> {
+    let events = Default::default();
+    let dispatched_events = Default::default();
+
+    let event_store = RacingEventStore::new(
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        ),
+        races,
+    );
+    let custom_dispatcher =
+        CustomDispatcher::new(Arc::clone(&dispatched_events));
+
+    let repo = Repository::new(
+        event_store,
+        vec![Box::new(custom_dispatcher)],
+        false,
+    )
+    .with_conflict_retries(retries);
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let metadata = get_metadata();
+
+    let result = repo.execute_with_metadata(
+        &id,
+        CustomerCommand::AddAddress(AddAddress {
+            new_address: "one new address".to_string(),
+        }),
+        metadata.clone(),
+    );
+
+    let mut events_context: Vec<
+        EventContext<CustomerCommand, CustomerEvent>,
+    > = (1..=races)
+        .map(|x| {
+            EventContext::new(
+                id.clone(),
+                x as i64,
+                CustomerEvent::AddressUpdated(AddressUpdated {
+                    new_address: "competing address".to_string(),
+                }),
+                HashMap::new(),
+            )
+        })
+        .collect();
+
+    if races > retries {
+        assert!(ConflictError::is_conflict(&result.unwrap_err()));
+
+        assert_eq!(
+            events
+                .read()
+                .unwrap()
+                .get(&stream_key(&id))
+                .unwrap()
+                .iter()
+                .map(|x| x.context.clone())
+                .collect::<Vec<_>>(),
+            events_context
+        );
+
+        assert!(dispatched_events
+            .read()
+            .unwrap()
+            .is_empty());
+
+        return Ok(());
+    }
+
+    result.unwrap();
+
+    let committed_context = EventContext::new(
+        id.clone(),
+        races as i64 + 1,
+        CustomerEvent::AddressUpdated(AddressUpdated {
+            new_address: "one new address".to_string(),
+        }),
+        metadata.clone(),
+    );
+
+    events_context.push(committed_context.clone());
+
+    assert_eq!(
+        events
+            .read()
+            .unwrap()
+            .get(&stream_key(&id))
+            .unwrap()
+            .iter()
+            .map(|x| x.context.clone())
+            .collect::<Vec<_>>(),
+        events_context
+    );
+
+    assert_eq!(
+        dispatched_events
+            .read()
+            .unwrap()
+            .clone(),
+        vec![committed_context]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_execute_conflict_no_retries() {
+    check_execute_with_conflicts(1, 0).unwrap();
+}
+
+#[test]
+fn test_execute_conflict_with_retries() {
+    check_execute_with_conflicts(2, 2).unwrap();
+}
+
+#[test]
+fn test_execute_conflict_retries_exhausted() {
+    check_execute_with_conflicts(3, 2).unwrap();
+}
+
+#[test]
+fn test_execute_with_snapshot_policy() {
+    let events = Default::default();
+    let snapshots = Default::default();
+
+    let event_store = ThisEventStore::new(
+        Arc::clone(&events),
+        Arc::clone(&snapshots),
+        Default::default(),
+        Default::default(),
+    );
+
+    let repo = Repository::new(event_store, vec![], false)
+        .with_snapshot_policy(Box::new(
+            EventCountSnapshotPolicy::new(2),
+        ));
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let metadata = get_metadata();
+
+    repo.execute_with_metadata(
+        &id,
+        CustomerCommand::AddAddress(AddAddress {
+            new_address: "one new address".to_string(),
+        }),
+        metadata.clone(),
+    )
+    .unwrap();
+
+    assert!(snapshots
+        .read()
+        .unwrap()
+        .get(&stream_key(&id))
+        .is_none());
+
+    repo.execute_with_metadata(
+        &id,
+        CustomerCommand::UpdateEmail(UpdateEmail {
+            new_email: "e@mail.com".to_string(),
+        }),
+        metadata.clone(),
+    )
+    .unwrap();
+
+    repo.execute_with_metadata(
+        &id,
+        CustomerCommand::AddCustomerName(AddCustomerName {
+            changed_name: "some name".to_string(),
+        }),
+        metadata.clone(),
+    )
+    .unwrap();
+
+    let snapshot = AggregateContext::new(
+        id.clone(),
+        2,
+        Customer {
+            customer_id: Default::default(),
+            name: Default::default(),
+            email: "e@mail.com".to_string(),
+            addresses: vec!["one new address".to_string()],
+        },
+    );
+
+    assert_eq!(
+        snapshots
+            .read()
+            .unwrap()
+            .get(&stream_key(&id))
+            .unwrap()
+            .clone(),
+        snapshot
+    );
+
+    // the name is only known from the event after the snapshot
+    let err = repo
+        .execute_with_metadata(
+            &id,
+            CustomerCommand::AddCustomerName(AddCustomerName {
+                changed_name: "another name".to_string(),
+            }),
+            metadata.clone(),
+        )
+        .unwrap_err();
+
+    assert_eq!(
+        Error::new("a name has already been added for this customer"),
+        err
+    );
+
+    assert_eq!(
+        events
+            .read()
+            .unwrap()
+            .get(&stream_key(&id))
+            .unwrap()
+            .len(),
+        3
+    );
+
+    assert_eq!(
+        snapshots
+            .read()
+            .unwrap()
+            .get(&stream_key(&id))
+            .unwrap()
+            .clone(),
+        snapshot
+    );
+}
+
+#[test]
+fn test_execute_tombstoned() {
+    let repo =
+        Repository::new(ThisEventStore::default(), vec![], false);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    repo.execute(
+        &id,
+        CustomerCommand::AddCustomerName(AddCustomerName {
+            changed_name: "John Doe".to_string(),
+        }),
+    )
+    .unwrap();
+
+    repo.tombstone_aggregate(&id).unwrap();
+
+    let err = repo
+        .execute(
+            &id,
+            CustomerCommand::UpdateEmail(UpdateEmail {
+                new_email: "john.doe@example.com".to_string(),
+            }),
+        )
+        .unwrap_err();
+
+    assert!(TombstonedError::is_tombstoned(&err));
+
+    // the events are kept
+    assert_eq!(
+        repo.load_aggregate_at_sequence(&id, 10)
+            .unwrap()
+            .version,
+        1
+    );
+}
+
+#[test]
+fn test_execute_tombstoned_while_retrying() {
+    let events = Default::default();
+
+    let event_store = RacingEventStore::new(
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        ),
+        1,
+    )
+    .with_tombstone();
+
+    let repo = Repository::new(event_store, vec![], false)
+        .with_conflict_retries(2);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let err = repo
+        .execute(
+            &id,
+            CustomerCommand::AddCustomerName(AddCustomerName {
+                changed_name: "John Doe".to_string(),
+            }),
+        )
+        .unwrap_err();
+
+    assert!(TombstonedError::is_tombstoned(&err));
+
+    // only the competing event was committed
+    assert_eq!(
+        events
+            .read()
+            .unwrap()
+            .get(&stream_key(&id))
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_execute_from_threads() {
+    let repo = Arc::new(
+        Repository::new(ThisEventStore::default(), vec![], false)
+            .with_conflict_retries(3),
+    );
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let handles: Vec<_> = (0..4)
+        .map(|x| {
+            let repo = Arc::clone(&repo);
+            let id = id.clone();
+
+            thread::spawn(move || {
+                repo.execute(
+                    &id,
+                    CustomerCommand::AddAddress(AddAddress {
+                        new_address: format!("address {}", x),
+                    }),
+                )
+            })
+        })
+        .collect();
+
+    for x in handles {
+        x.join().unwrap().unwrap();
+    }
+
+    // the conflicting commands were retried, none got lost
+    let context = repo
+        .load_aggregate_at_sequence(&id, 10)
+        .unwrap();
+
+    assert_eq!(context.version, 4);
+    assert_eq!(context.payload.addresses.len(), 4);
+}
+
+fn execute_customer_commands(
+    repo: &Repository<
+        CustomerCommand,
+        CustomerEvent,
+        Customer,
+        ThisEventStore,
+    >,
+    id: &str,
+) -> Vec<SystemTime> {
+    let commands = vec![
+        CustomerCommand::AddAddress(AddAddress {
+            new_address: "one new address".to_string(),
+        }),
+        CustomerCommand::UpdateEmail(UpdateEmail {
+            new_email: "e@mail.com".to_string(),
+        }),
+        CustomerCommand::AddCustomerName(AddCustomerName {
+            changed_name: "some name".to_string(),
+        }),
+    ];
+
+    let mut times = Vec::new();
+
+    for x in commands {
+        thread::sleep(Duration::from_millis(5));
+        repo.execute(id, x).unwrap();
+        thread::sleep(Duration::from_millis(5));
+        times.push(SystemTime::now());
+    }
+
+    times
+}
+
+#[test]
+fn test_load_aggregate_at_sequence() {
+    let events = Default::default();
+    let snapshots = Default::default();
+
+    let event_store = ThisEventStore::new(
+        Arc::clone(&events),
+        Arc::clone(&snapshots),
+        Default::default(),
+        Default::default(),
+    );
+
+    let repo = Repository::new(event_store, vec![], true);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    execute_customer_commands(&repo, &id);
+
+    let snapshot = snapshots
+        .read()
+        .unwrap()
+        .get(&stream_key(&id))
+        .cloned();
+
+    assert_eq!(
+        repo.load_aggregate_at_sequence(&id, 0).unwrap(),
+        AggregateContext::new(id.clone(), 0, Customer::default())
+    );
+
+    assert_eq!(
+        repo.load_aggregate_at_sequence(&id, 2).unwrap(),
+        AggregateContext::new(
+            id.clone(),
+            2,
+            Customer {
+                customer_id: Default::default(),
+                name: Default::default(),
+                email: "e@mail.com".to_string(),
+                addresses: vec!["one new address".to_string()],
+            },
+        )
+    );
+
+    let current = AggregateContext::new(
+        id.clone(),
+        3,
+        Customer {
+            customer_id: Default::default(),
+            name: "some name".to_string(),
+            email: "e@mail.com".to_string(),
+            addresses: vec!["one new address".to_string()],
+        },
+    );
+
+    assert_eq!(
+        repo.load_aggregate_at_sequence(&id, 3).unwrap(),
+        current
+    );
+    assert_eq!(
+        repo.load_aggregate_at_sequence(&id, 10).unwrap(),
+        current
+    );
+
+    // the snapshot is left untouched
+    assert_eq!(
+        snapshots
+            .read()
+            .unwrap()
+            .get(&stream_key(&id))
+            .cloned(),
+        snapshot
+    );
+}
+
+#[test]
+fn test_load_aggregate_at_time() {
+    let events = Default::default();
+    let snapshots = Default::default();
+
+    let event_store = ThisEventStore::new(
+        Arc::clone(&events),
+        Arc::clone(&snapshots),
+        Default::default(),
+        Default::default(),
+    );
+
+    let repo = Repository::new(event_store, vec![], false);
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let before = SystemTime::now();
+
+    let times = execute_customer_commands(&repo, &id);
+
+    assert_eq!(
+        repo.load_aggregate_at_time(&id, before)
+            .unwrap(),
+        AggregateContext::new(id.clone(), 0, Customer::default())
+    );
+
+    assert_eq!(
+        repo.load_aggregate_at_time(&id, times[0])
+            .unwrap(),
+        AggregateContext::new(
+            id.clone(),
+            1,
+            Customer {
+                customer_id: Default::default(),
+                name: Default::default(),
+                email: Default::default(),
+                addresses: vec!["one new address".to_string()],
+            },
+        )
+    );
+
+    assert_eq!(
+        repo.load_aggregate_at_time(&id, times[2])
+            .unwrap(),
+        AggregateContext::new(
+            id.clone(),
+            3,
+            Customer {
+                customer_id: Default::default(),
+                name: "some name".to_string(),
+                email: "e@mail.com".to_string(),
+                addresses: vec!["one new address".to_string()],
+            },
+        )
+    );
+
+    assert!(snapshots
+        .read()
+        .unwrap()
+        .get(&stream_key(&id))
+        .is_none());
+}
@@ -0,0 +1,309 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    },
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    AggregateContext,
+    EventContext,
+    IAggregate,
+};
+
+use crate::{
+    errors::StoreError,
+    memory_store::{
+        EventStore,
+        KeyStore,
+    },
+    IEventStore,
+    IKeyStore,
+    IOutboxStore,
+    Repository,
+    ShreddingEventStore,
+    TombstonedError,
+};
+
+use super::stores::OutboxEventStore;
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+type ThisShreddingEventStore = ShreddingEventStore<
+    CustomerCommand,
+    CustomerEvent,
+    Customer,
+    OutboxEventStore,
+>;
+
+/// A memory key store where a competing writer saves its key for
+/// an aggregate right after its first lookup
+struct RacingKeyStore {
+    keys: KeyStore,
+    raced: AtomicBool,
+}
+
+impl IKeyStore for RacingKeyStore {
+    fn save_key(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        key: &[u8],
+    ) -> Result<(), StoreError> {
+        self.keys
+            .save_key(aggregate_type, aggregate_id, key)
+    }
+
+    fn save_key_if_absent(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+        key: &[u8],
+    ) -> Result<Vec<u8>, StoreError> {
+        self.keys
+            .save_key_if_absent(aggregate_type, aggregate_id, key)
+    }
+
+    fn load_key(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+    ) -> Result<Option<Vec<u8>>, StoreError> {
+        let key = self
+            .keys
+            .load_key(aggregate_type, aggregate_id)?;
+
+        if !self.raced.swap(true, Ordering::SeqCst) {
+            self.keys.save_key_if_absent(
+                aggregate_type,
+                aggregate_id,
+                &[7; 32],
+            )?;
+        }
+
+        Ok(key)
+    }
+
+    fn delete_key(
+        &self,
+        aggregate_type: &str,
+        aggregate_id: &str,
+    ) -> Result<(), StoreError> {
+        self.keys
+            .delete_key(aggregate_type, aggregate_id)
+    }
+}
+
+fn get_contexts(
+    id: &str
+) -> Vec<EventContext<CustomerCommand, CustomerEvent>> {
+    vec![
+        EventContext::new(
+            id.to_string(),
+            1,
+            CustomerEvent::NameAdded(NameAdded {
+                changed_name: "John Doe".to_string(),
+            }),
+            HashMap::new(),
+        ),
+        EventContext::new(
+            id.to_string(),
+            2,
+            CustomerEvent::EmailUpdated(EmailUpdated {
+                new_email: "john.doe@example.com".to_string(),
+            }),
+            HashMap::new(),
+        ),
+    ]
+}
+
+#[test]
+fn test_encrypt_fields() {
+    let inner = OutboxEventStore::new(ThisEventStore::default());
+
+    let store = ThisShreddingEventStore::new(
+        inner.clone(),
+        Box::new(KeyStore::default()),
+    )
+    .with_field("/NameAdded/changed_name");
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let contexts = get_contexts(&id);
+
+    store.save_events(&contexts).unwrap();
+
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+    assert_eq!(store.load_pending_events(100).unwrap(), contexts);
+    assert_eq!(
+        store
+            .read_all(0, 100)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.context)
+            .collect::<Vec<_>>(),
+        contexts
+    );
+
+    let stored_contexts = inner.clone().load_events(&id).unwrap();
+
+    match &stored_contexts[0].payload {
+        CustomerEvent::NameAdded(x) => {
+            assert!(x.changed_name.starts_with("shredded:"));
+            assert!(!x.changed_name.contains("John Doe"));
+        },
+        _ => panic!("unexpected event"),
+    }
+
+    // the fields of other events are left in clear
+    assert_eq!(stored_contexts[1], contexts[1]);
+}
+
+#[test]
+fn test_shred() {
+    let keys = Default::default();
+
+    let store = ThisShreddingEventStore::new(
+        OutboxEventStore::new(ThisEventStore::default()),
+        Box::new(KeyStore::new(Arc::clone(&keys))),
+    )
+    .with_field("/NameAdded/changed_name")
+    .with_erased_value("<erased>");
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let other_id = uuid::Uuid::new_v4().to_string();
+
+    store.save_events(&get_contexts(&id)).unwrap();
+    store
+        .save_events(&get_contexts(&other_id))
+        .unwrap();
+
+    store
+        .save_aggregate_snapshot(AggregateContext::new(
+            id.clone(),
+            2,
+            Customer {
+                customer_id: Default::default(),
+                name: "John Doe".to_string(),
+                email: "john.doe@example.com".to_string(),
+                addresses: Default::default(),
+            },
+        ))
+        .unwrap();
+
+    assert_eq!(keys.read().unwrap().len(), 2);
+
+    store.shred(&id).unwrap();
+
+    assert_eq!(keys.read().unwrap().len(), 1);
+
+    assert_eq!(
+        store
+            .load_aggregate_from_snapshot(&id)
+            .unwrap()
+            .version,
+        0
+    );
+
+    let mut contexts = get_contexts(&id);
+    contexts[0].payload = CustomerEvent::NameAdded(NameAdded {
+        changed_name: "<erased>".to_string(),
+    });
+
+    assert_eq!(store.load_events(&id).unwrap(), contexts);
+    assert_eq!(
+        store.load_events(&other_id).unwrap(),
+        get_contexts(&other_id)
+    );
+
+    // the aggregate is rebuilt from the remaining fields
+    let repo = Repository::new(store, Vec::new(), false);
+
+    assert_eq!(
+        repo.load_aggregate_at_sequence(&id, 2)
+            .unwrap()
+            .payload,
+        Customer {
+            customer_id: Default::default(),
+            name: "<erased>".to_string(),
+            email: "john.doe@example.com".to_string(),
+            addresses: Default::default(),
+        }
+    );
+
+    let err = repo
+        .execute(
+            &id,
+            CustomerCommand::AddCustomerName(AddCustomerName {
+                changed_name: "Jane Doe".to_string(),
+            }),
+        )
+        .unwrap_err();
+
+    assert!(TombstonedError::is_tombstoned(&err));
+}
+
+#[test]
+fn test_delete_stream_key() {
+    let keys = Default::default();
+
+    let store = ThisShreddingEventStore::new(
+        OutboxEventStore::new(ThisEventStore::default()),
+        Box::new(KeyStore::new(Arc::clone(&keys))),
+    )
+    .with_field("/NameAdded/changed_name");
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    store.save_events(&get_contexts(&id)).unwrap();
+
+    assert_eq!(keys.read().unwrap().len(), 1);
+
+    store.delete_stream(&id).unwrap();
+
+    assert_eq!(keys.read().unwrap().len(), 0);
+    assert_eq!(store.load_events(&id).unwrap().len(), 0);
+}
+
+#[test]
+fn test_keep_concurrent_key() {
+    let keys = Arc::default();
+    let inner = OutboxEventStore::new(ThisEventStore::default());
+
+    let store = ThisShreddingEventStore::new(
+        inner.clone(),
+        Box::new(RacingKeyStore {
+            keys: KeyStore::new(Arc::clone(&keys)),
+            raced: AtomicBool::new(false),
+        }),
+    )
+    .with_field("/NameAdded/changed_name");
+
+    let id = uuid::Uuid::new_v4().to_string();
+
+    store.save_events(&get_contexts(&id)).unwrap();
+
+    // the key of the competing writer is kept and used
+    let key = KeyStore::new(Arc::clone(&keys))
+        .load_key(Customer::aggregate_type(), &id)
+        .unwrap();
+
+    assert_eq!(key, Some(vec![7; 32]));
+
+    let other_store = ThisShreddingEventStore::new(
+        inner,
+        Box::new(KeyStore::new(keys)),
+    )
+    .with_field("/NameAdded/changed_name");
+
+    assert_eq!(
+        other_store.load_events(&id).unwrap(),
+        get_contexts(&id)
+    );
+}
@@ -0,0 +1,147 @@
+use std::{
+    thread,
+    time::Duration,
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    AggregateContext,
+};
+
+use crate::{
+    EventCountSnapshotPolicy,
+    ISnapshotPolicy,
+    IntervalSnapshotPolicy,
+    SizeSnapshotPolicy,
+};
+
+type ThisAggregateContext =
+    AggregateContext<CustomerCommand, CustomerEvent, Customer>;
+
+fn get_context(
+    id: &str,
+    version: i64,
+) -> ThisAggregateContext {
+    AggregateContext::new(
+        id.to_string(),
+        version,
+        Customer {
+            customer_id: "customer 1".to_string(),
+            name: "test name".to_string(),
+            email: "test@email.com".to_string(),
+            addresses: vec!["initial address".to_string()],
+        },
+    )
+}
+
+fn should_snapshot(
+    policy: &dyn ISnapshotPolicy<
+        CustomerCommand,
+        CustomerEvent,
+        Customer,
+    >,
+    snapshot_version: i64,
+    context: &ThisAggregateContext,
+) -> bool {
+    policy.should_snapshot(snapshot_version, context)
+}
+
+/// Asks for a snapshot and saves it when due, like the `Repository`
+fn take_snapshot(
+    policy: &dyn ISnapshotPolicy<
+        CustomerCommand,
+        CustomerEvent,
+        Customer,
+    >,
+    snapshot_version: i64,
+    context: &ThisAggregateContext,
+) -> bool {
+    let due = policy.should_snapshot(snapshot_version, context);
+
+    if due {
+        policy.snapshot_saved(&context.aggregate_id, context.version);
+    }
+
+    due
+}
+
+#[test]
+fn test_event_count_policy() {
+    let policy = EventCountSnapshotPolicy::new(3);
+
+    assert!(!should_snapshot(&policy, 0, &get_context("A", 2)));
+    assert!(should_snapshot(&policy, 0, &get_context("A", 3)));
+    assert!(!should_snapshot(&policy, 3, &get_context("A", 5)));
+    assert!(should_snapshot(&policy, 3, &get_context("A", 7)));
+}
+
+#[test]
+fn test_size_policy() {
+    let context = get_context("A", 1);
+
+    let size = serde_json::to_vec(&context.payload)
+        .unwrap()
+        .len();
+
+    let policy = SizeSnapshotPolicy::new(size);
+    assert!(!should_snapshot(&policy, 0, &context));
+
+    let policy = SizeSnapshotPolicy::new(size - 1);
+    assert!(should_snapshot(&policy, 0, &context));
+}
+
+#[test]
+fn test_interval_policy() {
+    let policy =
+        IntervalSnapshotPolicy::new(Duration::from_millis(50));
+
+    assert!(take_snapshot(&policy, 0, &get_context("A", 1)));
+    assert!(!take_snapshot(&policy, 1, &get_context("A", 2)));
+    assert!(take_snapshot(&policy, 0, &get_context("B", 1)));
+
+    thread::sleep(Duration::from_millis(60));
+
+    assert!(take_snapshot(&policy, 1, &get_context("A", 3)));
+    assert!(!take_snapshot(&policy, 3, &get_context("A", 4)));
+}
+
+#[test]
+fn test_interval_policy_failed_save() {
+    let policy = IntervalSnapshotPolicy::new(Duration::from_secs(60));
+
+    // the snapshot was not saved, it is still due
+    assert!(should_snapshot(&policy, 0, &get_context("A", 1)));
+    assert!(take_snapshot(&policy, 0, &get_context("A", 2)));
+    assert!(!should_snapshot(&policy, 2, &get_context("A", 3)));
+}
+
+#[test]
+fn test_interval_policy_capacity() {
+    let policy = IntervalSnapshotPolicy::new(Duration::from_secs(60))
+        .with_capacity(2);
+
+    assert!(take_snapshot(&policy, 0, &get_context("A", 1)));
+    assert!(take_snapshot(&policy, 0, &get_context("B", 1)));
+    assert!(take_snapshot(&policy, 0, &get_context("C", 1)));
+
+    // the oldest time was dropped
+    assert!(take_snapshot(&policy, 1, &get_context("A", 2)));
+    assert!(!take_snapshot(&policy, 1, &get_context("A", 3)));
+    assert!(!take_snapshot(&policy, 1, &get_context("C", 2)));
+}
+
+#[test]
+fn test_custom_policy() {
+    let policy = |_: i64, context: &ThisAggregateContext| {
+        context.payload.addresses.len() > 1
+    };
+
+    let mut context = get_context("A", 1);
+    assert!(!should_snapshot(&policy, 0, &context));
+
+    context
+        .payload
+        .addresses
+        .push("second address".to_string());
+    assert!(should_snapshot(&policy, 0, &context));
+}
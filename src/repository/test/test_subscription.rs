@@ -0,0 +1,247 @@
+use std::{
+    sync::{
+        atomic::AtomicBool,
+        Arc,
+        RwLock,
+    },
+    time::Duration,
+};
+
+use cqrs_es2::{
+    example_impl::*,
+    EventContext,
+};
+
+use crate::{
+    memory_store::{
+        CheckpointStore,
+        EventStore,
+    },
+    ICheckpointStore,
+    Repository,
+    Subscription,
+};
+
+use super::dispatchers::{
+    CustomDispatcher,
+    FailingDispatcher,
+    TailingDispatcher,
+};
+
+type ThisEventStore =
+    EventStore<CustomerCommand, CustomerEvent, Customer>;
+
+type DispatchedEvents = Arc<
+    RwLock<Vec<EventContext<CustomerCommand, CustomerEvent>>>,
+>;
+
+fn commit_events(
+    store: ThisEventStore,
+    ids: &[&str],
+) {
+    let repo = Repository::new(store, Vec::new(), false);
+
+    for id in ids {
+        repo.execute(
+            id,
+            CustomerCommand::AddCustomerName(AddCustomerName {
+                changed_name: "John Doe".to_string(),
+            }),
+        )
+        .unwrap();
+
+        repo.execute(
+            id,
+            CustomerCommand::UpdateEmail(UpdateEmail {
+                new_email: "john.doe@example.com".to_string(),
+            }),
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn test_subscription_catch_up() {
+    let events = Arc::default();
+    let positions = Arc::default();
+    let snapshots = Arc::default();
+    let checkpoints = Arc::default();
+
+    let store = || {
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Arc::clone(&snapshots),
+            Default::default(),
+            Arc::clone(&positions),
+        )
+    };
+
+    commit_events(store(), &["test_id_A", "test_id_B"]);
+
+    let dispatched_events: DispatchedEvents = Default::default();
+
+    let mut subscription = Subscription::new(
+        "test_subscription",
+        store(),
+        CheckpointStore::new(Arc::clone(&checkpoints)),
+        vec![Box::new(CustomDispatcher::new(Arc::clone(
+            &dispatched_events,
+        )))],
+    )
+    .with_batch_size(3);
+
+    assert_eq!(subscription.catch_up().unwrap(), 4);
+    assert_eq!(subscription.catch_up().unwrap(), 0);
+
+    assert_eq!(
+        CheckpointStore::new(Arc::clone(&checkpoints))
+            .load_checkpoint("test_subscription")
+            .unwrap(),
+        4
+    );
+
+    {
+        let dispatched_events = dispatched_events.read().unwrap();
+
+        assert_eq!(dispatched_events.len(), 4);
+
+        for (i, x) in dispatched_events.iter().enumerate() {
+            assert_eq!(
+                x.aggregate_id,
+                ["test_id_A", "test_id_B"][i / 2]
+            );
+            assert_eq!(x.sequence, (i % 2 + 1) as i64);
+        }
+    }
+
+    commit_events(store(), &["test_id_C"]);
+
+    // a new subscription resumes from the stored checkpoint
+    let mut subscription = Subscription::new(
+        "test_subscription",
+        store(),
+        CheckpointStore::new(Arc::clone(&checkpoints)),
+        vec![Box::new(CustomDispatcher::new(Arc::clone(
+            &dispatched_events,
+        )))],
+    );
+
+    assert_eq!(subscription.catch_up().unwrap(), 2);
+    assert_eq!(dispatched_events.read().unwrap().len(), 6);
+}
+
+#[test]
+fn test_subscription_with_zero_batch_size() {
+    let events = Arc::default();
+    let positions = Arc::default();
+
+    let store = || {
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Default::default(),
+            Default::default(),
+            Arc::clone(&positions),
+        )
+    };
+
+    commit_events(store(), &["test_id_A", "test_id_B"]);
+
+    let dispatched_events: DispatchedEvents = Default::default();
+
+    // a batch size of zero reads one event per batch
+    let mut subscription = Subscription::new(
+        "test_subscription",
+        store(),
+        CheckpointStore::default(),
+        vec![Box::new(CustomDispatcher::new(Arc::clone(
+            &dispatched_events,
+        )))],
+    )
+    .with_batch_size(0);
+
+    assert_eq!(subscription.catch_up().unwrap(), 4);
+    assert_eq!(dispatched_events.read().unwrap().len(), 4);
+}
+
+#[test]
+fn test_subscription_failed_dispatch() {
+    let events = Arc::default();
+    let positions = Arc::default();
+    let snapshots = Arc::default();
+
+    let store = || {
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Arc::clone(&snapshots),
+            Default::default(),
+            Arc::clone(&positions),
+        )
+    };
+
+    commit_events(store(), &["test_id_A", "test_id_B"]);
+
+    let dispatched_events: DispatchedEvents = Default::default();
+
+    let mut subscription = Subscription::new(
+        "test_subscription",
+        store(),
+        CheckpointStore::default(),
+        vec![Box::new(FailingDispatcher::new(
+            Arc::clone(&dispatched_events),
+            1,
+        ))],
+    );
+
+    subscription.catch_up().unwrap_err();
+    assert_eq!(dispatched_events.read().unwrap().len(), 0);
+
+    assert_eq!(subscription.catch_up().unwrap(), 4);
+    assert_eq!(dispatched_events.read().unwrap().len(), 4);
+}
+
+#[test]
+fn test_subscription_run() {
+    let events = Arc::default();
+    let positions = Arc::default();
+    let snapshots = Arc::default();
+
+    let store = || {
+        ThisEventStore::new(
+            Arc::clone(&events),
+            Arc::clone(&snapshots),
+            Default::default(),
+            Arc::clone(&positions),
+        )
+    };
+
+    commit_events(store(), &["test_id_A"]);
+
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let dispatched_events: DispatchedEvents = Default::default();
+
+    let mut subscription = Subscription::new(
+        "test_subscription",
+        store(),
+        CheckpointStore::default(),
+        vec![
+            Box::new(TailingDispatcher::new(
+                store(),
+                Arc::clone(&stop),
+            )),
+            Box::new(CustomDispatcher::new(Arc::clone(
+                &dispatched_events,
+            ))),
+        ],
+    )
+    .with_poll_interval(Duration::from_millis(10));
+
+    // the event committed while dispatching is picked up by polling
+    // and stops the subscription
+    subscription.run(&stop).unwrap();
+
+    let dispatched_events = dispatched_events.read().unwrap();
+
+    assert_eq!(dispatched_events.len(), 3);
+    assert_eq!(dispatched_events[2].sequence, 3);
+}
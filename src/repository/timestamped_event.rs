@@ -0,0 +1,31 @@
+use std::time::SystemTime;
+
+use cqrs_es2::{
+    EventContext,
+    ICommand,
+    IEvent,
+};
+
+/// An event along with the time its commit was recorded by the
+/// store.
+///
+/// The SQL stores take the timestamps from the database clock, the
+/// other stores from the clock of the committing process.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TimestampedEvent<C: ICommand, E: IEvent> {
+    /// The commit time of the event.
+    pub timestamp: SystemTime,
+
+    /// The event itself.
+    pub context: EventContext<C, E>,
+}
+
+impl<C: ICommand, E: IEvent> TimestampedEvent<C, E> {
+    /// Constructor
+    pub fn new(
+        timestamp: SystemTime,
+        context: EventContext<C, E>,
+    ) -> Self {
+        Self { timestamp, context }
+    }
+}
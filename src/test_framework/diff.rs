@@ -0,0 +1,69 @@
+use std::fmt::Debug;
+
+/// A line diff of the pretty debug output of two values, the lines
+/// only expected start with `-` and the lines only found with `+`
+pub(super) fn diff<T: Debug>(
+    expected: &T,
+    actual: &T,
+) -> String {
+    let expected = format!("{:#?}", expected);
+    let actual = format!("{:#?}", actual);
+
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // lengths of the longest common subsequences of the suffixes
+    let mut lengths =
+        vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = match expected[i] == actual[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    let mut res = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() &&
+            j < actual.len() &&
+            expected[i] == actual[j]
+        {
+            res.push(format!("  {}", expected[i]));
+            i += 1;
+            j += 1;
+        }
+        else if j == actual.len() ||
+            (i < expected.len() &&
+                lengths[i + 1][j] >= lengths[i][j + 1])
+        {
+            res.push(format!("- {}", expected[i]));
+            i += 1;
+        }
+        else {
+            res.push(format!("+ {}", actual[j]));
+            j += 1;
+        }
+    }
+
+    res.join("\n")
+}
+
+/// Panics with the diff of two values if they differ
+pub(super) fn assert_same<T: Debug + PartialEq>(
+    what: &str,
+    expected: &T,
+    actual: &T,
+) {
+    if expected != actual {
+        panic!(
+            "unexpected {} (- expected, + found):\n{}",
+            what,
+            diff(expected, actual)
+        );
+    }
+}
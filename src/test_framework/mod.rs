@@ -0,0 +1,12 @@
+pub use repository_result_validator::RepositoryResultValidator;
+pub use repository_test_executor::RepositoryTestExecutor;
+pub use repository_tester::RepositoryTester;
+
+mod diff;
+mod recording_dispatcher;
+mod repository_result_validator;
+mod repository_test_executor;
+mod repository_tester;
+
+#[cfg(test)]
+mod test;
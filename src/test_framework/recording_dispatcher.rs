@@ -0,0 +1,45 @@
+use std::sync::{
+    Arc,
+    RwLock,
+};
+
+use cqrs_es2::{
+    Error,
+    EventContext,
+    ICommand,
+    IEvent,
+};
+
+use crate::repository::IEventDispatcher;
+
+/// The dispatched events shared with a `RecordingDispatcher`
+pub(super) type DispatchedEvents<C, E> =
+    Arc<RwLock<Vec<EventContext<C, E>>>>;
+
+/// A dispatcher recording the events dispatched by the `Repository`
+pub(super) struct RecordingDispatcher<C: ICommand, E: IEvent> {
+    events: DispatchedEvents<C, E>,
+}
+
+impl<C: ICommand, E: IEvent> RecordingDispatcher<C, E> {
+    pub(super) fn new(events: DispatchedEvents<C, E>) -> Self {
+        Self { events }
+    }
+}
+
+impl<C: ICommand, E: IEvent> IEventDispatcher<C, E>
+    for RecordingDispatcher<C, E>
+{
+    fn dispatch(
+        &self,
+        _aggregate_id: &str,
+        events: &Vec<EventContext<C, E>>,
+    ) -> Result<(), Error> {
+        self.events
+            .write()
+            .unwrap()
+            .extend(events.iter().cloned());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use std::any::TypeId;
+
+use cqrs_es2::{
+    AggregateContext,
+    Error,
+    EventContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+};
+
+use crate::{
+    memory_store::{
+        EventStore,
+        QueryStore,
+    },
+    repository::{
+        IEventStore,
+        IQueryStore,
+    },
+};
+
+use super::{
+    diff::assert_same,
+    recording_dispatcher::DispatchedEvents,
+    repository_tester::QueryStores,
+};
+
+/// Validation object for the `RepositoryTester`, the failed
+/// validations panic with a diff of the expected and found values
+pub struct RepositoryResultValidator<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+> {
+    result: Result<(), Error>,
+    aggregate_id: String,
+    given: Vec<EventContext<C, E>>,
+    store: EventStore<C, E, A>,
+    dispatched: DispatchedEvents<C, E>,
+    query_stores: QueryStores,
+}
+
+impl<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+    > RepositoryResultValidator<C, E, A>
+{
+    pub(super) fn new(
+        result: Result<(), Error>,
+        aggregate_id: String,
+        given: Vec<EventContext<C, E>>,
+        store: EventStore<C, E, A>,
+        dispatched: DispatchedEvents<C, E>,
+        query_stores: QueryStores,
+    ) -> Self {
+        Self {
+            result,
+            aggregate_id,
+            given,
+            store,
+            dispatched,
+            query_stores,
+        }
+    }
+
+    /// Verifies that the expected events have been committed by the
+    /// command
+    pub fn then_expect_events(
+        self,
+        expected: Vec<E>,
+    ) -> Self {
+        self.expect_success();
+
+        let events: Vec<_> = self
+            .new_events()
+            .into_iter()
+            .map(|x| x.payload)
+            .collect();
+
+        assert_same("committed events", &expected, &events);
+        self
+    }
+
+    /// Verifies that the committed events carry the expected
+    /// metadata along with their payloads
+    pub fn then_expect_event_contexts(
+        self,
+        expected: Vec<EventContext<C, E>>,
+    ) -> Self {
+        self.expect_success();
+
+        let events = self.new_events();
+
+        assert_same("committed events", &expected, &events);
+        self
+    }
+
+    /// Verifies that the expected events have been dispatched by the
+    /// `Repository`
+    pub fn then_expect_dispatched(
+        self,
+        expected: Vec<E>,
+    ) -> Self {
+        self.expect_success();
+
+        let events: Vec<_> = self
+            .dispatched
+            .read()
+            .unwrap()
+            .iter()
+            .map(|x| x.payload.clone())
+            .collect();
+
+        assert_same("dispatched events", &expected, &events);
+        self
+    }
+
+    /// Verifies the latest snapshot of the aggregate, at version `0`
+    /// with the default aggregate if none was taken
+    pub fn then_expect_snapshot(
+        self,
+        version: i64,
+        expected: A,
+    ) -> Self {
+        self.expect_success();
+
+        let context = match self
+            .store
+            .load_aggregate_from_snapshot(&self.aggregate_id)
+        {
+            Ok(x) => x,
+            Err(e) => {
+                panic!("unable to load the snapshot: '{}'", e);
+            },
+        };
+
+        assert_same(
+            "snapshot",
+            &AggregateContext::new(
+                self.aggregate_id.clone(),
+                version,
+                expected,
+            ),
+            &context,
+        );
+        self
+    }
+
+    /// Verifies the state of the query of a store wired
+    /// `with_query()`, updated with the given events and then by the
+    /// `Repository`
+    pub fn then_expect_query<Q: IQuery<C, E> + 'static>(
+        self,
+        expected: Q,
+    ) -> Self {
+        self.expect_success();
+
+        let store = match self
+            .query_stores
+            .get(&TypeId::of::<Q>())
+            .and_then(|x| x.downcast_ref::<QueryStore<C, E, A, Q>>())
+        {
+            Some(x) => x,
+            None => {
+                panic!(
+                    "no query store of type '{}' wired with_query()",
+                    Q::query_type()
+                );
+            },
+        };
+
+        let context = match store.load_query(&self.aggregate_id) {
+            Ok(x) => x,
+            Err(e) => {
+                panic!("unable to load the query: '{}'", e);
+            },
+        };
+
+        assert_same("query", &expected, &context.payload);
+        self
+    }
+
+    /// Verifies that an `Error` with the expected message is
+    /// returned for the command, without any event being committed
+    /// or dispatched
+    pub fn then_expect_error(
+        self,
+        error_message: &str,
+    ) {
+        match &self.result {
+            Ok(_) => {
+                panic!(
+                    "expected error, committed events: '{:?}'",
+                    self.new_events()
+                );
+            },
+            Err(Error::TechnicalError(e)) => {
+                panic!(
+                    "expected user error but found technical error: \
+                     {}",
+                    e
+                );
+            },
+            Err(Error::UserError(e)) => {
+                assert_same(
+                    "error message",
+                    &Some(error_message.to_string()),
+                    &e.message,
+                );
+            },
+        }
+
+        assert_same(
+            "committed events",
+            &Vec::new(),
+            &self.new_events(),
+        );
+        assert_same(
+            "dispatched events",
+            &Vec::new(),
+            &*self.dispatched.read().unwrap(),
+        );
+    }
+
+    /// Panics if the command failed
+    fn expect_success(&self) {
+        if let Err(e) = &self.result {
+            panic!("expected success, received error: '{}'", e);
+        }
+    }
+
+    /// The events committed by the command
+    fn new_events(&self) -> Vec<EventContext<C, E>> {
+        let from_sequence = self.given.len() as i64 + 1;
+
+        match self.store.load_events_range(
+            &self.aggregate_id,
+            from_sequence,
+            None,
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                panic!(
+                    "unable to load the committed events: '{}'",
+                    e
+                );
+            },
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+
+use cqrs_es2::{
+    EventContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+};
+
+use crate::{
+    memory_store::EventStore,
+    repository::{
+        Dispatchers,
+        Repository,
+        SnapshotPolicy,
+    },
+};
+
+use super::{
+    recording_dispatcher::RecordingDispatcher,
+    repository_result_validator::RepositoryResultValidator,
+    repository_tester::QueryStores,
+};
+
+/// Holds the initial event state and accepts a command
+pub struct RepositoryTestExecutor<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+> {
+    aggregate_id: String,
+    given: Vec<EventContext<C, E>>,
+    store: EventStore<C, E, A>,
+    reader: EventStore<C, E, A>,
+    dispatchers: Dispatchers<C, E>,
+    query_stores: QueryStores,
+    snapshot_policy: Option<SnapshotPolicy<C, E, A>>,
+}
+
+impl<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+    > RepositoryTestExecutor<C, E, A>
+{
+    pub(super) fn new(
+        aggregate_id: String,
+        given: Vec<EventContext<C, E>>,
+        store: EventStore<C, E, A>,
+        reader: EventStore<C, E, A>,
+        dispatchers: Dispatchers<C, E>,
+        query_stores: QueryStores,
+        snapshot_policy: Option<SnapshotPolicy<C, E, A>>,
+    ) -> Self {
+        Self {
+            aggregate_id,
+            given,
+            store,
+            reader,
+            dispatchers,
+            query_stores,
+            snapshot_policy,
+        }
+    }
+
+    /// Executes a command through the `Repository` and provides a
+    /// validator object to test against
+    #[must_use]
+    pub fn when(
+        self,
+        command: C,
+    ) -> RepositoryResultValidator<C, E, A> {
+        self.when_with_metadata(command, HashMap::new())
+    }
+
+    /// Executes a command along with its metadata through the
+    /// `Repository` and provides a validator object to test against
+    #[must_use]
+    pub fn when_with_metadata(
+        self,
+        command: C,
+        metadata: HashMap<String, String>,
+    ) -> RepositoryResultValidator<C, E, A> {
+        let dispatched = Arc::default();
+
+        let mut dispatchers = self.dispatchers;
+        dispatchers.push(Box::new(RecordingDispatcher::new(
+            Arc::clone(&dispatched),
+        )));
+
+        let mut repo =
+            Repository::new(self.store, dispatchers, false);
+
+        if let Some(x) = self.snapshot_policy {
+            repo = repo.with_snapshot_policy(x);
+        }
+
+        let result = repo.execute_with_metadata(
+            &self.aggregate_id,
+            command,
+            metadata,
+        );
+
+        RepositoryResultValidator::new(
+            result,
+            self.aggregate_id,
+            self.given,
+            self.reader,
+            dispatched,
+            self.query_stores,
+        )
+    }
+}
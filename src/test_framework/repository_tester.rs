@@ -0,0 +1,242 @@
+use std::{
+    any::{
+        Any,
+        TypeId,
+    },
+    collections::HashMap,
+    sync::Arc,
+};
+
+use cqrs_es2::{
+    EventContext,
+    IAggregate,
+    ICommand,
+    IEvent,
+    IQuery,
+};
+
+use crate::{
+    memory_store::{
+        EventStore,
+        QueryStore,
+    },
+    repository::{
+        Dispatchers,
+        IEventDispatcher,
+        IEventStore,
+        SnapshotPolicy,
+    },
+};
+
+use super::repository_test_executor::RepositoryTestExecutor;
+
+/// The memory query stores wired into the `Repository`, by query
+/// type
+pub(super) type QueryStores = HashMap<TypeId, Box<dyn Any>>;
+
+/// `RepositoryTester` provides a consistent way to test commands
+/// going through a `Repository` over the memory store, along with
+/// its snapshots, dispatchers and queries
+///
+/// # Examples
+/// ```rust
+/// use cqrs_es2::example_impl::{
+///     AddCustomerName,
+///     Customer,
+///     CustomerCommand,
+///     CustomerContactQuery,
+///     CustomerEvent,
+///     EmailUpdated,
+///     NameAdded,
+///     UpdateEmail,
+/// };
+///
+/// use cqrs_es2_store::RepositoryTester;
+///
+/// type CustomTester =
+///     RepositoryTester<CustomerCommand, CustomerEvent, Customer>;
+///
+/// CustomTester::default()
+///     .with_query::<CustomerContactQuery>()
+///     .given(vec![CustomerEvent::NameAdded(NameAdded {
+///         changed_name: "John Doe".to_string(),
+///     })])
+///     .when(CustomerCommand::UpdateEmail(UpdateEmail {
+///         new_email: "john@example.com".to_string(),
+///     }))
+///     .then_expect_events(vec![CustomerEvent::EmailUpdated(
+///         EmailUpdated {
+///             new_email: "john@example.com".to_string(),
+///         },
+///     )])
+///     .then_expect_query(CustomerContactQuery {
+///         name: "John Doe".to_string(),
+///         email: "john@example.com".to_string(),
+///         latest_address: "".to_string(),
+///     });
+///
+/// CustomTester::default()
+///     .given(vec![CustomerEvent::NameAdded(NameAdded {
+///         changed_name: "John Doe".to_string(),
+///     })])
+///     .when(CustomerCommand::AddCustomerName(AddCustomerName {
+///         changed_name: "John Doe".to_string(),
+///     }))
+///     .then_expect_error(
+///         "a name has already been added for this customer",
+///     );
+/// ```
+pub struct RepositoryTester<
+    C: ICommand,
+    E: IEvent,
+    A: IAggregate<C, E>,
+> {
+    aggregate_id: String,
+    dispatchers: Dispatchers<C, E>,
+    query_dispatchers: Dispatchers<C, E>,
+    query_stores: QueryStores,
+    snapshot_policy: Option<SnapshotPolicy<C, E, A>>,
+}
+
+impl<
+        C: ICommand + 'static,
+        E: IEvent + 'static,
+        A: IAggregate<C, E> + 'static,
+    > RepositoryTester<C, E, A>
+{
+    /// Runs the command against the aggregate of `aggregate_id`
+    /// instead of `test_aggregate_id`
+    #[must_use]
+    pub fn with_aggregate_id(
+        mut self,
+        aggregate_id: &str,
+    ) -> Self {
+        self.aggregate_id = aggregate_id.to_string();
+        self
+    }
+
+    /// Adds a dispatcher of the `Repository`, next to the one
+    /// recording the dispatched events
+    #[must_use]
+    pub fn with_dispatcher(
+        mut self,
+        dispatcher: Box<dyn IEventDispatcher<C, E> + Send + Sync>,
+    ) -> Self {
+        self.dispatchers.push(dispatcher);
+        self
+    }
+
+    /// Adds a memory query store of `Q` as a dispatcher of the
+    /// `Repository`, updated with the given events first, for
+    /// `then_expect_query`
+    #[must_use]
+    pub fn with_query<Q: IQuery<C, E> + 'static>(mut self) -> Self {
+        let queries = Arc::default();
+
+        self.query_dispatchers
+            .push(Box::new(QueryStore::<C, E, A, Q>::new(
+                Arc::clone(&queries),
+            )));
+
+        self.query_stores.insert(
+            TypeId::of::<Q>(),
+            Box::new(QueryStore::<C, E, A, Q>::new(queries)),
+        );
+        self
+    }
+
+    /// Enables the snapshots of the `Repository`, taken as decided
+    /// by `policy`
+    #[must_use]
+    pub fn with_snapshot_policy(
+        mut self,
+        policy: SnapshotPolicy<C, E, A>,
+    ) -> Self {
+        self.snapshot_policy = Some(policy);
+        self
+    }
+
+    /// Initiates a repository test with no previous events
+    #[must_use]
+    pub fn given_no_previous_events(
+        self
+    ) -> RepositoryTestExecutor<C, E, A> {
+        self.given(Vec::new())
+    }
+
+    /// Initiates a repository test with a collection of previous
+    /// events, committed with the sequences `1` to `events.len()`
+    /// and only dispatched to the query stores
+    #[must_use]
+    pub fn given(
+        mut self,
+        events: Vec<E>,
+    ) -> RepositoryTestExecutor<C, E, A> {
+        let events_map = Arc::default();
+        let snapshots = Arc::default();
+        let tombstones = Arc::default();
+        let positions = Arc::default();
+
+        let store = EventStore::new(
+            Arc::clone(&events_map),
+            Arc::clone(&snapshots),
+            Arc::clone(&tombstones),
+            Arc::clone(&positions),
+        );
+
+        let contexts: Vec<_> = events
+            .into_iter()
+            .zip(1..)
+            .map(|(x, sequence)| {
+                EventContext::new(
+                    self.aggregate_id.clone(),
+                    sequence,
+                    x,
+                    HashMap::new(),
+                )
+            })
+            .collect();
+
+        if let Err(e) = store.save_events(&contexts) {
+            panic!("unable to commit the given events: '{}'", e);
+        }
+
+        for x in self.query_dispatchers {
+            if !contexts.is_empty() {
+                let res = x.dispatch(&self.aggregate_id, &contexts);
+
+                if let Err(e) = res {
+                    panic!("unable to update the queries: '{}'", e);
+                }
+            }
+
+            self.dispatchers.push(x);
+        }
+
+        RepositoryTestExecutor::new(
+            self.aggregate_id,
+            contexts,
+            store,
+            EventStore::new(
+                events_map, snapshots, tombstones, positions,
+            ),
+            self.dispatchers,
+            self.query_stores,
+            self.snapshot_policy,
+        )
+    }
+}
+
+impl<C: ICommand, E: IEvent, A: IAggregate<C, E>> Default
+    for RepositoryTester<C, E, A>
+{
+    fn default() -> Self {
+        Self {
+            aggregate_id: "test_aggregate_id".to_string(),
+            dispatchers: Vec::new(),
+            query_dispatchers: Vec::new(),
+            query_stores: HashMap::new(),
+            snapshot_policy: None,
+        }
+    }
+}
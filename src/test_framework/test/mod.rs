@@ -0,0 +1 @@
+mod test_repository_tester;
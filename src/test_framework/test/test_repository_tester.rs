@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use cqrs_es2::{
+    example_impl::*,
+    EventContext,
+};
+
+use crate::{
+    EventCountSnapshotPolicy,
+    RepositoryTester,
+};
+
+use super::super::diff::diff;
+
+type ThisTester =
+    RepositoryTester<CustomerCommand, CustomerEvent, Customer>;
+
+fn name_added(name: &str) -> CustomerEvent {
+    CustomerEvent::NameAdded(NameAdded {
+        changed_name: name.to_string(),
+    })
+}
+
+fn email_updated(email: &str) -> CustomerEvent {
+    CustomerEvent::EmailUpdated(EmailUpdated {
+        new_email: email.to_string(),
+    })
+}
+
+#[test]
+fn test_expect_events() {
+    ThisTester::default()
+        .with_query::<CustomerContactQuery>()
+        .given(vec![name_added("John Doe")])
+        .when(CustomerCommand::UpdateEmail(UpdateEmail {
+            new_email: "john@example.com".to_string(),
+        }))
+        .then_expect_events(vec![email_updated("john@example.com")])
+        .then_expect_dispatched(vec![email_updated(
+            "john@example.com",
+        )])
+        .then_expect_query(CustomerContactQuery {
+            name: "John Doe".to_string(),
+            email: "john@example.com".to_string(),
+            latest_address: "".to_string(),
+        })
+        .then_expect_snapshot(0, Customer::default());
+
+    ThisTester::default()
+        .given_no_previous_events()
+        .when(CustomerCommand::AddCustomerName(AddCustomerName {
+            changed_name: "John Doe".to_string(),
+        }))
+        .then_expect_events(vec![name_added("John Doe")]);
+}
+
+#[test]
+fn test_expect_event_contexts() {
+    let mut metadata = HashMap::new();
+    metadata.insert("user".to_string(), "admin".to_string());
+
+    ThisTester::default()
+        .with_aggregate_id("customer_A")
+        .given(vec![name_added("John Doe")])
+        .when_with_metadata(
+            CustomerCommand::UpdateEmail(UpdateEmail {
+                new_email: "john@example.com".to_string(),
+            }),
+            metadata.clone(),
+        )
+        .then_expect_event_contexts(vec![EventContext::new(
+            "customer_A".to_string(),
+            2,
+            email_updated("john@example.com"),
+            metadata,
+        )]);
+}
+
+#[test]
+fn test_expect_snapshot() {
+    ThisTester::default()
+        .with_snapshot_policy(Box::new(
+            EventCountSnapshotPolicy::new(1),
+        ))
+        .given(vec![name_added("John Doe")])
+        .when(CustomerCommand::AddAddress(AddAddress {
+            new_address: "one new address".to_string(),
+        }))
+        .then_expect_snapshot(2, Customer {
+            customer_id: "".to_string(),
+            name: "John Doe".to_string(),
+            email: "".to_string(),
+            addresses: vec!["one new address".to_string()],
+        });
+}
+
+#[test]
+fn test_expect_error() {
+    ThisTester::default()
+        .given(vec![name_added("John Doe")])
+        .when(CustomerCommand::AddCustomerName(AddCustomerName {
+            changed_name: "John Doe".to_string(),
+        }))
+        .then_expect_error(
+            "a name has already been added for this customer",
+        );
+}
+
+#[test]
+#[should_panic(expected = "unexpected committed events")]
+fn test_expect_events_failure() {
+    ThisTester::default()
+        .given_no_previous_events()
+        .when(CustomerCommand::UpdateEmail(UpdateEmail {
+            new_email: "john@example.com".to_string(),
+        }))
+        .then_expect_events(vec![email_updated("jane@example.com")]);
+}
+
+#[test]
+#[should_panic(expected = "no query store of type")]
+fn test_expect_query_failure() {
+    ThisTester::default()
+        .given(vec![name_added("John Doe")])
+        .when(CustomerCommand::UpdateEmail(UpdateEmail {
+            new_email: "john@example.com".to_string(),
+        }))
+        .then_expect_query(CustomerContactQuery::default());
+}
+
+#[test]
+#[should_panic(expected = "expected error")]
+fn test_expect_error_failure() {
+    ThisTester::default()
+        .given_no_previous_events()
+        .when(CustomerCommand::UpdateEmail(UpdateEmail {
+            new_email: "john@example.com".to_string(),
+        }))
+        .then_expect_error("no email expected");
+}
+
+#[test]
+fn test_diff() {
+    let res = diff(
+        &vec![name_added("A"), email_updated("B")],
+        &vec![name_added("A"), email_updated("C")],
+    );
+
+    let changes: Vec<_> = res
+        .lines()
+        .filter(|x| !x.starts_with(' '))
+        .map(|x| x.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+
+    assert_eq!(changes, vec![
+        "- new_email: \"B\",",
+        "+ new_email: \"C\",",
+    ]);
+    assert!(res.contains("changed_name: \"A\","));
+}